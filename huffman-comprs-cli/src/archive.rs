@@ -0,0 +1,202 @@
+use std::{
+    cmp::Reverse,
+    collections::BTreeSet,
+    fs,
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use glob::Pattern;
+use huffman_comprs::{ArchiveBuilder, Entry, RZArchive};
+
+use crate::{atomic::AtomicFile, error::Error};
+
+/// Expands each of `paths` that doesn't exist as a glob, such as `logs/*.log`,
+/// so patterns the shell didn't expand still match. Fails if a pattern
+/// matches nothing
+pub fn expand(paths: &[&str]) -> Result<Vec<PathBuf>, Error> {
+    let mut expanded = Vec::new();
+    for path in paths {
+        if Path::new(path).exists() {
+            expanded.push(PathBuf::from(path));
+            continue;
+        }
+
+        let matches = glob::glob(path)
+            .map(|matches| matches.filter_map(Result::ok).collect::<Vec<_>>())
+            .unwrap_or_default();
+        if matches.is_empty() {
+            return Err(Error::Usage(format!(
+                "{} doesn't exist, and matches no files",
+                path
+            )));
+        }
+        expanded.extend(matches);
+    }
+    Ok(expanded)
+}
+
+/// Adds each of `paths` to `builder`, with every file under any directories,
+/// named by their paths as given
+pub fn add_paths(mut builder: ArchiveBuilder, paths: &[PathBuf]) -> Result<ArchiveBuilder, Error> {
+    for path in paths {
+        builder = if path.is_dir() {
+            add_dir(builder, Path::new(""), path)?
+        } else {
+            builder.add(entry_name(path)?, fs::read(path).map_err(Error::io(path))?)
+        };
+    }
+    Ok(builder)
+}
+
+/// Adds every file under `dir` to `builder`, named by its path relative to
+/// `root`, with `/` between directories
+pub fn add_dir(
+    mut builder: ArchiveBuilder,
+    root: &Path,
+    dir: &Path,
+) -> Result<ArchiveBuilder, Error> {
+    let mut entries = fs::read_dir(dir)
+        .and_then(Iterator::collect::<io::Result<Vec<_>>>)
+        .map_err(Error::io(dir))?;
+    // Sorted, so the same directory always makes the same archive
+    entries.sort_by_key(fs::DirEntry::path);
+
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type().map_err(Error::io(&path))?.is_dir() {
+            builder = add_dir(builder, root, &path)?;
+            continue;
+        }
+
+        // Every path found under `root` starts with it
+        let name = entry_name(path.strip_prefix(root).unwrap())?;
+        builder = builder.add(name, fs::read(&path).map_err(Error::io(&path))?);
+    }
+    Ok(builder)
+}
+
+/// Removes each file of `archive`, built from `root` by
+/// [`add_dir`](fn.add_dir.html), then any directories, `root` included, that
+/// removing them leaves empty. Anything added to `root` since the archive was
+/// built is left in place
+pub fn remove_archived(root: &Path, archive: &RZArchive) -> Result<(), Error> {
+    let mut dirs = BTreeSet::new();
+    for entry in archive.entries() {
+        let path = root.join(entry.name());
+        fs::remove_file(&path).map_err(Error::io(&path))?;
+        dirs.extend(
+            path.ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(root))
+                .map(Path::to_path_buf),
+        );
+    }
+    // Deepest first, so a directory is only checked once those under it are gone
+    let mut dirs: Vec<PathBuf> = dirs.into_iter().collect();
+    dirs.sort_by_key(|dir| Reverse(dir.components().count()));
+
+    for dir in dirs {
+        let empty = fs::read_dir(&dir)
+            .map_err(Error::io(&dir))?
+            .next()
+            .is_none();
+        if empty {
+            fs::remove_dir(&dir).map_err(Error::io(&dir))?;
+        }
+    }
+    Ok(())
+}
+
+/// The name `path` is stored as, with `/` between directories. As with tar,
+/// any leading `/`, `.` or `..` is left out, so extracting it can't leave the
+/// directory it's extracted to
+fn entry_name(path: &Path) -> Result<String, Error> {
+    let parts = path
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_str().ok_or_else(|| {
+                Error::Format(path.to_path_buf(), "the path isn't valid UTF-8".to_string())
+            })),
+            _ => None,
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(parts.join("/"))
+}
+
+/// Prints the size and name of each file in `archive`, one per line
+pub fn list(archive: &RZArchive) {
+    for entry in archive.entries() {
+        println!("{:>12}  {}", entry.len(), entry.name());
+    }
+}
+
+/// The files in `archive` with a name matching any of `patterns`, or every
+/// file if there are none. Fails if a pattern matches nothing
+pub fn select<'a>(archive: &'a RZArchive, patterns: &[&str]) -> Result<Vec<&'a Entry>, Error> {
+    if patterns.is_empty() {
+        return Ok(archive.entries().iter().collect());
+    }
+
+    let mut selected: Vec<&Entry> = Vec::new();
+    for pattern in patterns {
+        let compiled = Pattern::new(pattern).ok();
+        let matches: Vec<&Entry> = archive
+            .entries()
+            .iter()
+            .filter(|entry| {
+                entry.name() == *pattern
+                    || compiled.as_ref().is_some_and(|p| p.matches(entry.name()))
+            })
+            .collect();
+        if matches.is_empty() {
+            return Err(Error::Usage(format!(
+                "{} matches no files in the archive",
+                pattern
+            )));
+        }
+
+        for entry in matches {
+            if !selected.contains(&entry) {
+                selected.push(entry);
+            }
+        }
+    }
+    Ok(selected)
+}
+
+/// Extracts each of `entries` from `archive`, read from `input`, into `dir`,
+/// creating any directories needed. Existing files aren't replaced unless
+/// `force` is set
+pub fn extract(
+    archive: &RZArchive,
+    input: &Path,
+    entries: &[&Entry],
+    dir: &Path,
+    force: bool,
+) -> Result<(), Error> {
+    for entry in entries {
+        let name = Path::new(entry.name());
+        if !name
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(Error::Format(
+                input.to_path_buf(),
+                format!("{} isn't a safe path to extract to", entry.name()),
+            ));
+        }
+
+        let path = dir.join(name);
+        crate::check_clobber(&path, force)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(Error::io(parent))?;
+        }
+        let contents = archive.extract_entry(entry).map_err(Error::format(input))?;
+
+        let mut file = AtomicFile::create(&path).map_err(Error::io(&path))?;
+        file.write_all(&contents).map_err(Error::io(&path))?;
+        file.persist().map_err(Error::io(&path))?;
+    }
+    Ok(())
+}
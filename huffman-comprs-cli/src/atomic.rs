@@ -0,0 +1,108 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+/// A file written beside where it's going, then renamed into place once it's
+/// complete, so a failed run never leaves part of a file behind. The partial
+/// file is removed if this is dropped, or fails, before being persisted
+#[derive(Debug)]
+pub struct AtomicFile {
+    temp: PathBuf,
+    dest: PathBuf,
+    writer: Option<BufWriter<File>>,
+    persisted: bool,
+}
+
+impl AtomicFile {
+    /// Starts writing a file that will replace `dest`
+    pub fn create(dest: &Path) -> io::Result<Self> {
+        let temp = temp_path(dest);
+        let writer = BufWriter::new(File::create(&temp)?);
+        Ok(Self {
+            temp,
+            dest: dest.to_path_buf(),
+            writer: Some(writer),
+            persisted: false,
+        })
+    }
+    /// Starts writing a file that will replace `dest`, starting with a copy
+    /// of what `dest` holds now, if it exists
+    pub fn append(dest: &Path) -> io::Result<Self> {
+        let mut file = Self::create(dest)?;
+        match File::open(dest) {
+            Ok(mut existing) => {
+                io::copy(&mut existing, &mut file)?;
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        Ok(file)
+    }
+    /// Writes the file to disk, then moves it into place. If any step fails,
+    /// the partial file is removed when this is dropped
+    pub fn persist(mut self) -> io::Result<()> {
+        let file = self.writer.take().unwrap().into_inner()?;
+        file.sync_all()?;
+        drop(file);
+        fs::rename(&self.temp, &self.dest)?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Write for AtomicFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.as_mut().unwrap().write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.as_mut().unwrap().flush()
+    }
+}
+
+impl Drop for AtomicFile {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let _ = fs::remove_file(&self.temp);
+        }
+    }
+}
+
+/// Creates the directory `dest` by filling in a directory beside it with
+/// `fill`, then renaming it into place, replacing anything already there. If
+/// `fill` fails, the partial directory is removed
+pub fn create_dir_with<F>(dest: &Path, fill: F) -> io::Result<()>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    let temp = temp_path(dest);
+    fs::create_dir(&temp)?;
+    if let Err(e) = fill(&temp) {
+        let _ = fs::remove_dir_all(&temp);
+        return Err(e);
+    }
+
+    if dest.is_dir() {
+        fs::remove_dir_all(dest)?;
+    }
+    fs::rename(&temp, dest)
+}
+
+/// A hidden path beside `dest`, unique to this process
+fn temp_path(dest: &Path) -> PathBuf {
+    let name = dest.file_name().map_or_else(
+        || "output".into(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    dest.with_file_name(format!(".{}.{}.tmp", name, std::process::id()))
+}
+
+/// Whether `path` is a temporary path made by this process, for a file still
+/// being written
+pub fn is_temp(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| {
+        let name = name.to_string_lossy();
+        name.starts_with('.') && name.ends_with(&format!(".{}.tmp", std::process::id()))
+    })
+}
@@ -0,0 +1,98 @@
+use std::{
+    convert::TryFrom,
+    time::{Duration, Instant},
+};
+
+use huffman_comprs::{Huffman, RZFile};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::model::{self, Model};
+
+/// How well, and how quickly, a file was compressed
+#[derive(Clone, Copy, Debug)]
+pub struct Report {
+    /// The size of the whole `.rz` file, including the tree
+    pub compressed_len: usize,
+    /// The fastest compression, including splitting the input into symbols
+    /// and building the tree
+    pub compress: Duration,
+    /// The fastest decompression, including parsing the file and joining the
+    /// symbols back into bytes
+    pub decompress: Duration,
+}
+
+/// Compresses and decompresses `input` with `model`, `iterations` times,
+/// keeping the fastest times. Returns `None` if `model` needs UTF-8, and
+/// `input` isn't
+pub fn run(input: &[u8], model: Model, iterations: u32) -> Option<Report> {
+    match model {
+        Model::Byte => Some(measure(input, iterations, <[u8]>::to_vec, |bytes| bytes)),
+        Model::Char => std::str::from_utf8(input).ok().map(|_| {
+            measure(
+                input,
+                iterations,
+                |input| text(input).chars().collect(),
+                model::join_chars,
+            )
+        }),
+        Model::Word => std::str::from_utf8(input).ok().map(|_| {
+            measure(
+                input,
+                iterations,
+                |input| model::words(text(input)),
+                model::join_words,
+            )
+        }),
+    }
+}
+
+/// `input` as text, which has already been checked to be UTF-8
+fn text(input: &[u8]) -> &str {
+    std::str::from_utf8(input).unwrap()
+}
+
+fn measure<T, S, J>(input: &[u8], iterations: u32, split: S, join: J) -> Report
+where
+    T: Serialize + DeserializeOwned + Ord + Clone + 'static,
+    S: Fn(&[u8]) -> Vec<T>,
+    J: Fn(Vec<T>) -> Vec<u8>,
+{
+    let mut report = Report {
+        compressed_len: 0,
+        compress: Duration::MAX,
+        decompress: Duration::MAX,
+    };
+
+    for _ in 0..iterations.max(1) {
+        let start = Instant::now();
+        let symbols = split(input);
+        let file = RZFile::compress(Huffman::from(&symbols), &symbols).unwrap();
+        let mut buf = Vec::new();
+        file.write_to(&mut buf).unwrap();
+        report.compress = report.compress.min(start.elapsed());
+
+        let start = Instant::now();
+        let file: RZFile<T> = RZFile::try_from(buf.as_slice()).unwrap();
+        let output = join(file.decompress().unwrap());
+        report.decompress = report.decompress.min(start.elapsed());
+
+        assert_eq!(input, output.as_slice(), "the input didn't round trip");
+        report.compressed_len = buf.len();
+    }
+    report
+}
+
+/// Prints a row of the table of reports, for `len` bytes of input
+pub fn print(model: Model, len: usize, report: Option<Report>) {
+    let throughput = |time: Duration| len as f64 / time.as_secs_f64() / 1_000_000.0;
+    match report {
+        Some(report) => println!(
+            "{:<6} {:>7.2}% {:>10.2} MB/s {:>10.2} MB/s",
+            model.name(),
+            100.0 * report.compressed_len as f64 / len.max(1) as f64,
+            throughput(report.compress),
+            throughput(report.decompress),
+        ),
+        None => println!("{:<6} skipped, as the file isn't UTF-8", model.name()),
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    convert::TryFrom,
+    io::{self, Read},
+};
+
+use huffman_comprs::{DecodeOptions, Extension, Huffman, RZFile, RzError};
+use serde::{Deserialize, Serialize};
+
+/// The tag of the extension frame describing the columns of a CSV file
+/// compressed by `csv-compress`, being "CSVC" in ASCII
+pub const TAG: u32 = 0x4353_5643;
+
+/// The header, and how each column is compressed, stored in the extension
+/// frame before the columns
+#[derive(Debug, Serialize, Deserialize)]
+struct Layout {
+    /// The header row's fields, exactly as written, quotes and all, and the
+    /// line ending after them, or `None` if the file is empty
+    header: Option<(Vec<String>, String)>,
+    columns: Vec<Column>,
+}
+
+/// How a column's fields are compressed
+#[derive(Debug, Serialize, Deserialize)]
+enum Column {
+    /// With a tree over whole fields, so a column of a few distinct values
+    /// takes a few bits for each
+    Values,
+    /// With a tree over the bytes of the fields, each followed by a byte
+    /// that appears in none of them, for columns of mostly distinct values
+    Bytes(u8),
+}
+
+/// Compresses the CSV file `text`, with the header row stored as it is, and
+/// a tree for each column, chosen to be over whole fields, or their bytes,
+/// whichever is smaller.
+///
+/// The output is an extension frame holding the header and how each column
+/// is compressed, then an RZ file of the number of fields, and the line
+/// ending, of each row, then an RZ file for each column. Fields are kept
+/// exactly as written, quotes included, so the file decompresses to the
+/// same bytes, even if rows have different numbers of fields
+pub fn compress(text: &str) -> io::Result<Vec<u8>> {
+    let mut rows = rows(text).into_iter();
+    let header = rows.next().map(|(fields, end)| {
+        (
+            fields.into_iter().map(str::to_string).collect::<Vec<_>>(),
+            end.to_string(),
+        )
+    });
+
+    let mut shapes: Vec<(u32, String)> = Vec::new();
+    let mut columns: Vec<Vec<&str>> = Vec::new();
+    for (fields, end) in rows {
+        let count = u32::try_from(fields.len()).map_err(io::Error::other)?;
+        shapes.push((count, end.to_string()));
+        for (i, field) in fields.into_iter().enumerate() {
+            if i == columns.len() {
+                columns.push(Vec::new());
+            }
+            columns[i].push(field);
+        }
+    }
+
+    let mut frames = Vec::new();
+    write_file(&shapes, &mut frames)?;
+    let mut layout = Layout {
+        header,
+        columns: Vec::with_capacity(columns.len()),
+    };
+    for fields in &columns {
+        let (column, frame) = compress_column(fields)?;
+        layout.columns.push(column);
+        frames.extend(frame);
+    }
+
+    let mut output = Vec::new();
+    Extension::new(TAG, bincode::serialize(&layout).map_err(io::Error::other)?)
+        .write_to(&mut output)?;
+    output.extend(frames);
+    Ok(output)
+}
+
+/// Decompresses a CSV file compressed by [`compress`] back to its bytes
+pub fn decompress(buf: &[u8]) -> Result<Vec<u8>, RzError> {
+    let mut reader = buf;
+    let layout = Extension::read_all_from(&mut reader)?
+        .into_iter()
+        .find(|extension| extension.tag == TAG)
+        .ok_or_else(|| RzError::Malformed("the file isn't a compressed CSV file".to_string()))?;
+    let layout: Layout = bincode::deserialize(&layout.data)
+        .map_err(|e| RzError::Malformed(format!("the CSV layout is malformed, as {}", e)))?;
+
+    let shapes: Vec<(u32, String)> = read_file(&mut reader)?;
+    let mut columns = Vec::with_capacity(layout.columns.len());
+    for column in &layout.columns {
+        columns.push(decompress_column(column, &mut reader)?.into_iter());
+    }
+
+    let mut output = Vec::new();
+    if let Some((fields, end)) = &layout.header {
+        output.extend(fields.join(",").bytes());
+        output.extend(end.bytes());
+    }
+    for (count, end) in shapes {
+        for i in 0..count as usize {
+            if i > 0 {
+                output.push(b',');
+            }
+            let field = columns
+                .get_mut(i)
+                .and_then(Iterator::next)
+                .ok_or_else(|| RzError::Malformed("a column has too few fields".to_string()))?;
+            output.extend(field);
+        }
+        output.extend(end.bytes());
+    }
+    Ok(output)
+}
+
+/// Splits `text` into rows of fields, each with the line ending after it,
+/// which is empty for a last row without one. Commas and line breaks in
+/// quotes are kept in their field, and fields keep their quotes, so joining
+/// the fields of each row with commas, then its line ending, gives back
+/// `text`
+fn rows(text: &str) -> Vec<(Vec<&str>, &str)> {
+    let mut rows = Vec::new();
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+
+    for (i, byte) in text.bytes().enumerate() {
+        match byte {
+            b'"' => quoted = !quoted,
+            b',' if !quoted => {
+                fields.push(&text[start..i]);
+                start = i + 1;
+            }
+            b'\n' if !quoted => {
+                let end = if text[start..i].ends_with('\r') {
+                    i - 1
+                } else {
+                    i
+                };
+                fields.push(&text[start..end]);
+                rows.push((std::mem::take(&mut fields), &text[end..=i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if start < text.len() || !fields.is_empty() {
+        fields.push(&text[start..]);
+        rows.push((fields, ""));
+    }
+    rows
+}
+
+/// Compresses a column's fields over whole fields, and over their bytes,
+/// returning whichever is smaller, and how it's compressed
+fn compress_column(fields: &[&str]) -> io::Result<(Column, Vec<u8>)> {
+    let values: Vec<String> = fields.iter().map(|field| field.to_string()).collect();
+    let mut best = (Column::Values, Vec::new());
+    write_file(&values, &mut best.1)?;
+
+    // A byte none of the fields hold marks the end of each, preferring a
+    // line break, as few fields hold one
+    let mut used = [false; 256];
+    for byte in fields.iter().flat_map(|field| field.bytes()) {
+        used[usize::from(byte)] = true;
+    }
+    let separator = std::iter::once(b'\n')
+        .chain(0..=u8::MAX)
+        .find(|byte| !used[usize::from(*byte)]);
+    if let Some(separator) = separator {
+        let mut bytes = Vec::new();
+        for field in fields {
+            bytes.extend(field.bytes());
+            bytes.push(separator);
+        }
+        let mut frame = Vec::new();
+        write_file(&bytes, &mut frame)?;
+        if frame.len() < best.1.len() {
+            best = (Column::Bytes(separator), frame);
+        }
+    }
+    Ok(best)
+}
+
+/// Reads a column compressed by [`compress_column`] from `reader`, giving
+/// the bytes of each field
+fn decompress_column<R: Read>(column: &Column, reader: R) -> Result<Vec<Vec<u8>>, RzError> {
+    Ok(match column {
+        Column::Values => read_file::<String, _>(reader)?
+            .into_iter()
+            .map(String::into_bytes)
+            .collect(),
+        Column::Bytes(separator) => {
+            let bytes: Vec<u8> = read_file(reader)?;
+            if bytes.last().is_some_and(|last| last != separator) {
+                return Err(RzError::Malformed(
+                    "a column's last field isn't ended".to_string(),
+                ));
+            }
+            let mut fields: Vec<Vec<u8>> = bytes
+                .split(|byte| byte == separator)
+                .map(<[u8]>::to_vec)
+                .collect();
+            // Every field is followed by the separator, so the last is empty
+            fields.pop();
+            fields
+        }
+    })
+}
+
+/// Writes `symbols` as an RZ file, with a tree trained on them, and a
+/// checksum
+fn write_file<T>(symbols: &[T], output: &mut Vec<u8>) -> io::Result<()>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    // The tree is built from the symbols, so holds every one
+    RZFile::compress(Huffman::from(&symbols.to_vec()), symbols)
+        .unwrap()
+        .with_checksum(symbols)
+        .write_to(output)
+}
+
+/// Reads an RZ file written by [`write_file`], decompressing it
+fn read_file<T, R>(reader: R) -> Result<Vec<T>, RzError>
+where
+    T: Serialize + for<'de> Deserialize<'de> + Ord + Clone + 'static,
+    R: Read,
+{
+    let file: RZFile<T> = RZFile::read_from(reader)?;
+    file.decompress_with(&DecodeOptions::new())
+        .map_err(RzError::from)
+}
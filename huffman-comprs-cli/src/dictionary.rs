@@ -0,0 +1,40 @@
+use std::{fs, io, path::Path};
+
+use huffman_comprs::Huffman;
+
+use crate::{atomic::AtomicFile, error::Error};
+
+/// Trains a tree on the bytes of every sample. Every byte is counted once
+/// more, so inputs unlike the samples can still be compressed, if less well
+pub fn train(samples: &[Vec<u8>]) -> Huffman<u8> {
+    let every_byte: Vec<u8> = (0..=255).collect();
+    Huffman::train(
+        samples
+            .iter()
+            .map(Vec::as_slice)
+            .chain(std::iter::once(every_byte.as_slice())),
+    )
+}
+
+/// Writes `tree` to `path` as a dictionary, being the tree serialized with
+/// bincode, so it's read back with the same dictionary id
+pub fn save(tree: &Huffman<u8>, path: &Path) -> io::Result<()> {
+    let mut file = AtomicFile::create(path)?;
+    bincode::serialize_into(&mut file, tree).map_err(io::Error::other)?;
+    file.persist()
+}
+
+/// Reads the dictionary written to `path` by [`save`]. The tree must be well
+/// formed, and give every byte a code, so compressing with it can't fail
+pub fn load(path: &Path) -> Result<Huffman<u8>, Error> {
+    let tree: Huffman<u8> = bincode::deserialize(&fs::read(path).map_err(Error::io(path))?)
+        .map_err(Error::format(path))?;
+    tree.validate().map_err(Error::format(path))?;
+    if (0..=u8::MAX).any(|byte| tree.get_code(byte).is_none()) {
+        return Err(Error::Format(
+            path.to_path_buf(),
+            "the dictionary doesn't hold every byte".to_string(),
+        ));
+    }
+    Ok(tree)
+}
@@ -0,0 +1,77 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+use huffman_comprs::RzError;
+
+/// Why a command failed, which decides the message printed, and the code the
+/// process exits with
+#[derive(Debug)]
+pub enum Error {
+    /// The output didn't decompress back to the input, so wasn't written
+    Verification(PathBuf),
+    /// The arguments given can't be used, such as a malformed range, or an
+    /// output that already exists
+    Usage(String),
+    /// A file couldn't be read, written or removed
+    Io(PathBuf, io::Error),
+    /// A file isn't valid for what it's used as, such as a corrupt `.rz`
+    /// file, or text that isn't UTF-8
+    Format(PathBuf, String),
+}
+
+impl Error {
+    /// Makes an [`Error::Io`] about `path`, to be passed to `map_err`
+    pub fn io<P: AsRef<Path>>(path: P) -> impl FnOnce(io::Error) -> Self {
+        move |e| Self::Io(path.as_ref().to_path_buf(), e)
+    }
+    /// Makes an [`Error::Format`] about `path`, to be passed to `map_err`
+    pub fn format<P: AsRef<Path>, E: fmt::Display>(path: P) -> impl FnOnce(E) -> Self {
+        move |e| Self::Format(path.as_ref().to_path_buf(), e.to_string())
+    }
+    /// Makes an [`Error::Io`] or [`Error::Format`] about `path`, to be passed
+    /// to `map_err`, depending on whether reading the `.rz` file failed, or it's
+    /// malformed
+    pub fn rz<P: AsRef<Path>>(path: P) -> impl FnOnce(RzError) -> Self {
+        move |e| match e {
+            RzError::Io(e) => Self::Io(path.as_ref().to_path_buf(), e),
+            e => Self::Format(path.as_ref().to_path_buf(), e.to_string()),
+        }
+    }
+    /// The code the process exits with: 1 if verification failed, 2 for
+    /// usage errors, including those clap finds, 3 for I/O errors, and 4 for
+    /// format errors
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::Verification(_) => 1,
+            Self::Usage(_) => 2,
+            Self::Io(..) => 3,
+            Self::Format(..) => 4,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Verification(path) => write!(
+                f,
+                "{} didn't decompress back to its input, so wasn't written",
+                path.display()
+            ),
+            Self::Usage(message) => f.write_str(message),
+            Self::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::Format(path, message) => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
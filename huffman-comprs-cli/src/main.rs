@@ -1,8 +1,46 @@
-use std::convert::TryFrom;
+use std::{
+    any::TypeId,
+    collections::BTreeMap,
+    convert::TryFrom,
+    ffi::OsString,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
-use huffman_comprs::{Huffman, RZFile};
+use huffman_comprs::{
+    ArchiveBuilder, DecodeError, DecodeOptions, Extension, Huffman, HuffmanBuilder, Metadata,
+    RZArchive, RZFile, RZ_EXTENSION_MAGIC,
+};
+use serde::{Deserialize, Serialize};
 
-use clap::{App, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+
+mod archive;
+mod atomic;
+mod bench;
+mod csv;
+mod dictionary;
+mod error;
+mod model;
+mod parallel;
+mod progress;
+mod watch;
+
+use atomic::AtomicFile;
+use error::Error;
+use model::{Frame, Model};
+
+/// The number of bytes in each block, when a file has to be split into blocks
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// The number of bytes compressed into each file at once, so large inputs
+/// don't have to fit in memory
+const CHUNK_LEN: usize = 1024 * 1024;
 
 fn main() {
     let matches = App::new("huffman-comprs-CLI")
@@ -16,9 +54,109 @@ fn main() {
                 .author("Elliot W")
                 .arg(
                     Arg::with_name("INPUT")
-                        .required(true)
+                        .required_unless("watch")
                         .index(1)
-                        .help("Input text file"),
+                        .help("Input text file, or directory with --recursive"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .conflicts_with_all(&["INPUT", "recursive", "append", "output", "dry-run"])
+                        .help("Compresses files as they're created or modified in a directory"),
+                )
+                .arg(
+                    Arg::with_name("exclude")
+                        .long("exclude")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .requires("watch")
+                        .help("Files not to compress when watching, such as '*.tmp'"),
+                )
+                .arg(
+                    Arg::with_name("debounce")
+                        .long("debounce")
+                        .takes_value(true)
+                        .default_value("1000")
+                        .help("How many milliseconds a watched file must be left unchanged for"),
+                )
+                .arg(
+                    Arg::with_name("verify")
+                        .long("verify")
+                        .help("Checks the output decompresses to the input before saving it"),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .long("dry-run")
+                        .conflicts_with_all(&["recursive", "rm"])
+                        .help("Prints how large the output would be, without writing it"),
+                )
+                .arg(
+                    Arg::with_name("append")
+                        .long("append")
+                        .help("Appends to an existing .rz file, rather than replacing it"),
+                )
+                .arg(
+                    Arg::with_name("recursive")
+                        .long("recursive")
+                        .short("r")
+                        .conflicts_with("append")
+                        .help("Compresses every file under a directory into one .rz archive"),
+                )
+                .arg(
+                    Arg::with_name("model")
+                        .long("model")
+                        .takes_value(true)
+                        .possible_values(&Model::NAMES)
+                        .default_value("byte")
+                        .help("What each symbol is, with char and word needing UTF-8 input"),
+                )
+                .arg(
+                    Arg::with_name("dict")
+                        .long("dict")
+                        .takes_value(true)
+                        .conflicts_with_all(&["recursive", "model"])
+                        .help("A dictionary made by train, to leave the tree out of the output"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .help("The file to write, rather than the input with .rz added"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .short("f")
+                        .help("Replaces the output if it already exists"),
+                )
+                .arg(
+                    Arg::with_name("quiet")
+                        .long("quiet")
+                        .short("q")
+                        .help("Doesn't show any progress"),
+                )
+                .arg(
+                    Arg::with_name("rm")
+                        .long("rm")
+                        .help("Deletes the input once it's been compressed, and the output verified"),
+                )
+                .arg(
+                    Arg::with_name("keep")
+                        .long("keep")
+                        .short("k")
+                        .conflicts_with("rm")
+                        .help("Keeps the input, which is the default"),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .short("T")
+                        .takes_value(true)
+                        .help("How many threads to use, defaulting to one per core"),
                 ),
         )
         .subcommand(
@@ -31,45 +169,1238 @@ fn main() {
                         .required(true)
                         .index(1)
                         .help("Input rz file"),
+                )
+                .arg(
+                    Arg::with_name("dict")
+                        .long("dict")
+                        .takes_value(true)
+                        .help("The dictionary the input was compressed with"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .help("The file, or directory for archives, to write, rather than the input without .rz"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .short("f")
+                        .help("Replaces the output if it already exists"),
+                )
+                .arg(
+                    Arg::with_name("quiet")
+                        .long("quiet")
+                        .short("q")
+                        .help("Doesn't show any progress"),
+                )
+                .arg(
+                    Arg::with_name("rm")
+                        .long("rm")
+                        .help("Deletes the input once it's been decompressed"),
+                )
+                .arg(
+                    Arg::with_name("keep")
+                        .long("keep")
+                        .short("k")
+                        .conflicts_with("rm")
+                        .help("Keeps the input, which is the default"),
+                )
+                .arg(
+                    Arg::with_name("threads")
+                        .long("threads")
+                        .short("T")
+                        .takes_value(true)
+                        .help("How many threads to use, defaulting to one per core"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("info")
+                .about("Describes a given rz file, without writing anything")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .required(true)
+                        .index(1)
+                        .help("Input rz file"),
+                )
+                .arg(
+                    Arg::with_name("codes")
+                        .long("codes")
+                        .help("Prints the code of every symbol in the first frame's tree"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about("Measures how well, and how quickly, a given file compresses")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .required(true)
+                        .index(1)
+                        .help("Input file"),
+                )
+                .arg(
+                    Arg::with_name("model")
+                        .long("model")
+                        .takes_value(true)
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .possible_values(&Model::NAMES)
+                        .default_value("byte")
+                        .help("The symbol models to compare, separated by commas"),
+                )
+                .arg(
+                    Arg::with_name("iterations")
+                        .long("iterations")
+                        .takes_value(true)
+                        .default_value("3")
+                        .help("How many times to run each model, keeping the fastest"),
                 ),
         )
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("archive")
+                .about("Stores many files and directories together in one rz archive")
+                .version("0.1.0")
+                .author("Elliot W")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("create")
+                        .about("Archives the given files, directories and globs")
+                        .arg(
+                            Arg::with_name("PATHS")
+                                .required(true)
+                                .multiple(true)
+                                .index(1)
+                                .help("Files, directories and globs such as 'logs/*.log'"),
+                        )
+                        .arg(
+                            Arg::with_name("output")
+                                .long("output")
+                                .short("o")
+                                .takes_value(true)
+                                .required(true)
+                                .help("The archive to write"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .short("f")
+                                .help("Replaces the archive if it already exists"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("Lists the files in an archive, with their sizes")
+                        .arg(
+                            Arg::with_name("ARCHIVE")
+                                .required(true)
+                                .index(1)
+                                .help("Input rz archive"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("extract")
+                        .about("Extracts the files in an archive")
+                        .arg(
+                            Arg::with_name("ARCHIVE")
+                                .required(true)
+                                .index(1)
+                                .help("Input rz archive"),
+                        )
+                        .arg(
+                            Arg::with_name("NAMES")
+                                .multiple(true)
+                                .index(2)
+                                .help("Only the files with these names or globs, rather than all"),
+                        )
+                        .arg(
+                            Arg::with_name("directory")
+                                .long("directory")
+                                .short("C")
+                                .takes_value(true)
+                                .default_value(".")
+                                .help("The directory to extract into"),
+                        )
+                        .arg(
+                            Arg::with_name("force")
+                                .long("force")
+                                .short("f")
+                                .help("Replaces files that already exist"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("cat")
+                .about("Writes the contents of a given rz file to stdout")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .required(true)
+                        .index(1)
+                        .help("Input rz file"),
+                )
+                .arg(
+                    Arg::with_name("RANGE")
+                        .index(2)
+                        .help("Only the bytes in a range, such as 100..200, 100.. or ..200"),
+                )
+                .arg(
+                    Arg::with_name("dict")
+                        .long("dict")
+                        .takes_value(true)
+                        .help("The dictionary the input was compressed with"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("train")
+                .about("Builds a dictionary from sample files, to compress small files like them")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("SAMPLES")
+                        .required(true)
+                        .multiple(true)
+                        .index(1)
+                        .help("Files like those to be compressed"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The dictionary file to write, such as dict.hfd"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .short("f")
+                        .help("Replaces the dictionary if it already exists"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("split")
+                .about("Splits a given rz file into parts of at most a given size")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .required(true)
+                        .index(1)
+                        .help("Input rz file"),
+                )
+                .arg(
+                    Arg::with_name("size")
+                        .long("size")
+                        .takes_value(true)
+                        .required(true)
+                        .help("The largest size of each part, in bytes"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("join")
+                .about("Joins parts made by split back into one rz file")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("OUTPUT")
+                        .required(true)
+                        .index(1)
+                        .help("Output rz file"),
+                )
+                .arg(
+                    Arg::with_name("PARTS")
+                        .required(true)
+                        .multiple(true)
+                        .index(2)
+                        .help("The parts, in order"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("csv-compress")
+                .about("Compresses a given CSV file a column at a time, with a tree for each")
+                .version("0.1.0")
+                .author("Elliot W")
+                .arg(
+                    Arg::with_name("INPUT")
+                        .required(true)
+                        .index(1)
+                        .help("Input CSV file, whose first row is the header"),
+                )
+                .arg(
+                    Arg::with_name("output")
+                        .long("output")
+                        .short("o")
+                        .takes_value(true)
+                        .help("The file to write, rather than the input with .rz added"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .long("force")
+                        .short("f")
+                        .help("Replaces the output if it already exists"),
+                ),
+        )
+        .get_matches_safe()
+        .unwrap_or_else(|e| {
+            // Help and the version are returned as errors too, but aren't
+            if !e.use_stderr() {
+                e.exit();
+            }
+            eprintln!("{}", e.message);
+            std::process::exit(Error::Usage(String::new()).exit_code());
+        });
+
+    if let Err(e) = run(&matches) {
+        eprintln!("error: {}", e);
+        std::process::exit(e.exit_code());
+    }
+}
 
+/// Runs the subcommand given
+fn run(matches: &ArgMatches) -> Result<(), Error> {
     if let Some(matches) = matches.subcommand_matches("compress") {
+        if let Some(dir) = matches.value_of("watch") {
+            let excludes: Vec<&str> = matches.values_of("exclude").into_iter().flatten().collect();
+            let debounce = Duration::from_millis(parse_arg(matches, "debounce")?);
+            // A file changed again is compressed again, replacing its output
+            let options = CompressOptions {
+                force: true,
+                ..CompressOptions::from_matches(matches)?
+            };
+            // A file that can't be compressed is skipped, so the rest still are
+            return watch::run(Path::new(dir), &excludes, debounce, |path| {
+                let result = match path.to_str() {
+                    Some(input) => compress_file(input, &compressed_path(path), &options),
+                    None => Err(Error::Format(
+                        path.to_path_buf(),
+                        "the path isn't valid UTF-8".to_string(),
+                    )),
+                };
+                match result {
+                    Ok(()) if !options.quiet => println!("Compressed {}", path.display()),
+                    Ok(()) => {}
+                    Err(e) => eprintln!("error: {}", e),
+                }
+            });
+        }
+
         let path = matches.value_of("INPUT").unwrap();
+        let force = matches.is_present("force");
+        let rm = matches.is_present("rm");
+        let output = matches
+            .value_of("output")
+            .map_or_else(|| compressed_path(Path::new(path)), PathBuf::from);
 
-        let input = match std::fs::read(path) {
-            Ok(t) => t,
-            Err(_) => path.as_bytes().to_vec(),
-        };
+        if matches.is_present("recursive") {
+            check_clobber(&output, force)?;
 
-        let tree = Huffman::from(&input);
+            let archive =
+                archive::add_dir(ArchiveBuilder::new(), Path::new(path), Path::new(path))?.build();
 
-        let data = tree.compress(&input).unwrap();
+            if matches.is_present("verify") || rm {
+                for entry in archive.entries() {
+                    let entry_path = Path::new(path).join(entry.name());
+                    let expected = std::fs::read(&entry_path).map_err(Error::io(&entry_path))?;
+                    if archive.extract_entry(entry).ok() != Some(expected) {
+                        return Err(Error::Verification(output));
+                    }
+                }
+            }
 
-        let file = RZFile::new(tree, data);
+            let mut file = AtomicFile::create(&output).map_err(Error::io(&output))?;
+            archive.write_to(&mut file).map_err(Error::io(&output))?;
+            file.persist().map_err(Error::io(&output))?;
+            if rm {
+                archive::remove_archived(Path::new(path), &archive)?;
+            }
+            return Ok(());
+        }
 
-        file.save_to_file(format!("{}.rz", path)).unwrap();
+        compress_file(path, &output, &CompressOptions::from_matches(matches)?)?;
     } else if let Some(matches) = matches.subcommand_matches("decompress") {
+        let input = Path::new(matches.value_of("INPUT").unwrap());
+        let force = matches.is_present("force");
+        let quiet = matches.is_present("quiet");
+        let rm = matches.is_present("rm");
+        let threads = threads(matches)?;
+        let output = matches.value_of("output").map(PathBuf::from);
+        let dictionary = load_dictionary(matches)?;
+
+        let file = File::open(input).map_err(Error::io(input))?;
+        let len = file.metadata().map_err(Error::io(input))?.len();
+        let bar = progress::bar(len, "Decompressing", quiet);
+        let mut reader = BufReader::new(bar.wrap_read(file));
+
+        // CSV files compressed by csv-compress start with their layout
+        let mut csv_start = RZ_EXTENSION_MAGIC.to_vec();
+        csv_start.extend(csv::TAG.to_be_bytes());
+        if reader
+            .fill_buf()
+            .map_err(Error::io(input))?
+            .starts_with(&csv_start)
+        {
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).map_err(Error::io(input))?;
+            let contents = csv::decompress(&buf).map_err(Error::rz(input))?;
+            bar.finish_and_clear();
+
+            let output = output.unwrap_or_else(|| decompressed_path(input));
+            check_clobber(&output, force)?;
+            let mut file = AtomicFile::create(&output).map_err(Error::io(&output))?;
+            file.write_all(&contents).map_err(Error::io(&output))?;
+            file.persist().map_err(Error::io(&output))?;
+            if rm {
+                std::fs::remove_file(input).map_err(Error::io(input))?;
+            }
+            return Ok(());
+        }
+
+        // A file may hold many appended files, which are decompressed in
+        // batches, one per thread
+        let mut writer: Option<(PathBuf, AtomicFile)> = None;
+        let mut metadata = None;
+        let mut model = Model::Byte;
+        let mut batch: Vec<(Frame, Option<Metadata>)> = Vec::with_capacity(threads);
+        loop {
+            while batch.len() < threads && more_frames(&mut reader, input)? {
+                let (frame, header) =
+                    read_frame(&mut reader, input, &mut model, dictionary.as_ref())?;
+
+                // Archives made with --recursive are extracted into a directory
+                if let (None, [], Frame::Byte(file)) = (&writer, batch.as_slice(), &frame) {
+                    if let Ok(archive) = RZArchive::try_from(file.clone()) {
+                        let dir = output.unwrap_or_else(|| decompressed_path(input));
+                        check_clobber(&dir, force)?;
+                        atomic::create_dir_with(&dir, |temp| archive.extract_to(temp))
+                            .map_err(Error::io(&dir))?;
+                        bar.finish_and_clear();
+                        if rm {
+                            std::fs::remove_file(input).map_err(Error::io(input))?;
+                        }
+                        return Ok(());
+                    }
+                }
+                batch.push((frame, header));
+            }
+            if batch.is_empty() {
+                break;
+            }
+
+            let contents = parallel::map(&batch, threads, |(frame, _)| frame.decompress());
+            for ((_, header), contents) in batch.drain(..).zip(contents) {
+                let contents = contents.map_err(Error::format(input))?;
+                let (file_name, writer) = match &mut writer {
+                    Some(writer) => writer,
+                    None => {
+                        let name = header.as_ref().and_then(Metadata::file_name);
+                        let file_name = match (&output, name) {
+                            (Some(output), _) => output.clone(),
+                            (None, Some(name)) => input.with_file_name(name),
+                            (None, None) => decompressed_path(input),
+                        };
+                        check_clobber(&file_name, force)?;
+                        let file = AtomicFile::create(&file_name).map_err(Error::io(&file_name))?;
+                        writer.insert((file_name, file))
+                    }
+                };
+
+                writer
+                    .write_all(&contents)
+                    .map_err(Error::io(&*file_name))?;
+                // The newest metadata describes the file as it was last
+                // appended to
+                if header.is_some() {
+                    metadata = header;
+                }
+            }
+        }
+        bar.finish_and_clear();
+
+        if let Some((file_name, writer)) = writer {
+            writer.persist().map_err(Error::io(&file_name))?;
+            if let Some(metadata) = metadata {
+                metadata
+                    .apply_to(&file_name)
+                    .map_err(Error::io(&file_name))?;
+            }
+            if rm {
+                std::fs::remove_file(input).map_err(Error::io(input))?;
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("info") {
+        let input = Path::new(matches.value_of("INPUT").unwrap());
+        let codes = matches.is_present("codes");
+
+        let buf = std::fs::read(input).map_err(Error::io(input))?;
+        let model = match Metadata::read_from_header(buf.as_slice())
+            .map_err(Error::format(input))?
+            .and_then(|metadata| metadata.symbols)
+        {
+            Some(symbols) => symbols.parse().map_err(Error::format(input))?,
+            None => Model::Byte,
+        };
+
+        println!("Model:            {}", model.name());
+        match model {
+            Model::Byte => {
+                let files = frames(input, &buf)?;
+                print_info(&buf, &files, |bytes| bytes, codes).map_err(Error::format(input))?;
+                if let Ok(archive) = RZArchive::try_from(files[0].clone()) {
+                    println!("Archived files:   {}", archive.entries().len());
+                }
+            }
+            Model::Char => print_info(&buf, &frames(input, &buf)?, model::join_chars, codes)
+                .map_err(Error::format(input))?,
+            Model::Word => print_info(&buf, &frames(input, &buf)?, model::join_words, codes)
+                .map_err(Error::format(input))?,
+        }
+    } else if let Some(matches) = matches.subcommand_matches("bench") {
+        let input = matches.value_of("INPUT").unwrap();
+        let input = std::fs::read(input).map_err(Error::io(input))?;
+        let iterations: u32 = parse_arg(matches, "iterations")?;
+
+        println!(
+            "{:<6} {:>8} {:>15} {:>15}",
+            "model", "ratio", "compress", "decompress"
+        );
+        for model in matches.values_of("model").unwrap() {
+            let model: Model = model.parse().map_err(Error::Usage)?;
+            bench::print(model, input.len(), bench::run(&input, model, iterations));
+        }
+    } else if let Some(matches) = matches.subcommand_matches("archive") {
+        if let Some(matches) = matches.subcommand_matches("create") {
+            let output = Path::new(matches.value_of("output").unwrap());
+            check_clobber(output, matches.is_present("force"))?;
+
+            let paths: Vec<&str> = matches.values_of("PATHS").unwrap().collect();
+            let built =
+                archive::add_paths(ArchiveBuilder::new(), &archive::expand(&paths)?)?.build();
+
+            let mut file = AtomicFile::create(output).map_err(Error::io(output))?;
+            built.write_to(&mut file).map_err(Error::io(output))?;
+            file.persist().map_err(Error::io(output))?;
+        } else if let Some(matches) = matches.subcommand_matches("list") {
+            let input = Path::new(matches.value_of("ARCHIVE").unwrap());
+            let buf = std::fs::read(input).map_err(Error::io(input))?;
+            archive::list(&RZArchive::try_from(buf.as_slice()).map_err(Error::format(input))?);
+        } else if let Some(matches) = matches.subcommand_matches("extract") {
+            let input = Path::new(matches.value_of("ARCHIVE").unwrap());
+            let buf = std::fs::read(input).map_err(Error::io(input))?;
+            let opened = RZArchive::try_from(buf.as_slice()).map_err(Error::format(input))?;
+            let names: Vec<&str> = matches.values_of("NAMES").into_iter().flatten().collect();
+            let dir = Path::new(matches.value_of("directory").unwrap());
+
+            let entries = archive::select(&opened, &names)?;
+            archive::extract(&opened, input, &entries, dir, matches.is_present("force"))?;
+        }
+    } else if let Some(matches) = matches.subcommand_matches("cat") {
+        let input = Path::new(matches.value_of("INPUT").unwrap());
+        let range = match matches.value_of("RANGE") {
+            Some(range) => parse_range(range)?,
+            None => 0..usize::MAX,
+        };
+        let dictionary = load_dictionary(matches)?;
+
+        let mut reader = BufReader::new(File::open(input).map_err(Error::io(input))?);
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+        // Where the next file's contents start in the output
+        let mut position = 0;
+        let mut model = Model::Byte;
+        while position < range.end && more_frames(&mut reader, input)? {
+            let (frame, _) = read_frame(&mut reader, input, &mut model, dictionary.as_ref())?;
+            let (contents, len) = frame
+                .decompress_range(
+                    range.start.saturating_sub(position)..range.end.saturating_sub(position),
+                )
+                .map_err(Error::format(input))?;
+            position += len;
+
+            // Whatever's reading may stop early, as head does
+            match stdout.write_all(&contents) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+                result => result.map_err(Error::io("stdout"))?,
+            }
+        }
+    } else if let Some(matches) = matches.subcommand_matches("train") {
+        let output = Path::new(matches.value_of("output").unwrap());
+        check_clobber(output, matches.is_present("force"))?;
+
+        let samples = matches
+            .values_of("SAMPLES")
+            .unwrap()
+            .map(|sample| std::fs::read(sample).map_err(Error::io(sample)))
+            .collect::<Result<Vec<_>, _>>()?;
+        dictionary::save(&dictionary::train(&samples), output).map_err(Error::io(output))?;
+    } else if let Some(matches) = matches.subcommand_matches("split") {
         let input = matches.value_of("INPUT").unwrap();
+        let size: usize = parse_arg(matches, "size")?;
 
-        let buf = std::fs::read(input).unwrap();
+        let buf = std::fs::read(input).map_err(Error::io(input))?;
+        let mut file: RZFile<u8> =
+            RZFile::try_from(buf.as_slice()).map_err(Error::format(input))?;
 
-        let file = RZFile::try_from(buf.as_slice()).unwrap();
+        // Only files split into blocks can be split into parts
+        if file.block_size().is_none() {
+            let contents = file
+                .decompress_with(&DecodeOptions::new())
+                .map_err(Error::format(input))?;
+            // The contents came from the tree, so every symbol is in it
+            let mut blocked =
+                RZFile::new_blocked(file.tree.clone(), &contents, BLOCK_SIZE).unwrap();
+            if let Some(metadata) = file.metadata() {
+                blocked = blocked.with_metadata(metadata.clone());
+            }
+            file = blocked;
+        }
 
-        let mut data = Vec::with_capacity(buf.len() * 8);
+        let parts = file
+            .split(size)
+            .map_err(|e| Error::Usage(format!("can't split {}, as {}", input, e)))?;
+        for (i, part) in parts.iter().enumerate() {
+            let part_name = format!("{}.{:03}", input, i);
+            part.save_to_file(&part_name)
+                .map_err(Error::io(&part_name))?;
+        }
+    } else if let Some(matches) = matches.subcommand_matches("csv-compress") {
+        let input = Path::new(matches.value_of("INPUT").unwrap());
+        let output = matches
+            .value_of("output")
+            .map_or_else(|| compressed_path(input), PathBuf::from);
+        check_clobber(&output, matches.is_present("force"))?;
 
-        for a in file.data() {
-            data.append(&mut huffman_comprs::u8_to_bits(*a));
+        let buf = std::fs::read(input).map_err(Error::io(input))?;
+        let text = std::str::from_utf8(&buf).map_err(|_| {
+            Error::Format(input.to_path_buf(), "the CSV file isn't UTF-8".to_string())
+        })?;
+        let compressed = csv::compress(text).map_err(Error::io(&output))?;
+        // Fields are kept as written, so this only fails on a bug
+        if csv::decompress(&compressed).ok().as_ref() != Some(&buf) {
+            return Err(Error::Verification(output));
         }
 
-        let contents = file.tree.reconstruct(data, file.zeros()).unwrap().to_vec();
+        let mut file = AtomicFile::create(&output).map_err(Error::io(&output))?;
+        file.write_all(&compressed).map_err(Error::io(&output))?;
+        file.persist().map_err(Error::io(&output))?;
+    } else if let Some(matches) = matches.subcommand_matches("join") {
+        let output = matches.value_of("OUTPUT").unwrap();
+
+        let names: Vec<&str> = matches.values_of("PARTS").unwrap().collect();
+        let parts = names
+            .iter()
+            .map(|part| std::fs::read(part).map_err(Error::io(part)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parts = names
+            .iter()
+            .zip(&parts)
+            .map(|(name, part)| {
+                RZFile::<u8>::try_from(part.as_slice()).map_err(Error::format(name))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let file = RZFile::join(parts)
+            .map_err(|e| Error::Usage(format!("can't join the parts, as {}", e)))?;
+        let contents = file
+            .decompress_with(&DecodeOptions::new())
+            .map_err(Error::format(output))?;
+
+        file.with_checksum(&contents)
+            .save_to_file(output)
+            .map_err(Error::io(output))?;
+    }
+    Ok(())
+}
+
+/// How `compress` compresses each file, from the options it's given
+struct CompressOptions {
+    model: Model,
+    dictionary: Option<Huffman<u8>>,
+    /// The input is only deleted once it's known it can be restored, so this
+    /// is also set by `rm`
+    verify: bool,
+    rm: bool,
+    force: bool,
+    append: bool,
+    dry_run: bool,
+    quiet: bool,
+    threads: usize,
+}
+
+impl CompressOptions {
+    fn from_matches(matches: &ArgMatches) -> Result<Self, Error> {
+        Ok(Self {
+            model: parse_arg(matches, "model")?,
+            dictionary: load_dictionary(matches)?,
+            verify: matches.is_present("verify") || matches.is_present("rm"),
+            rm: matches.is_present("rm"),
+            force: matches.is_present("force"),
+            append: matches.is_present("append"),
+            dry_run: matches.is_present("dry-run"),
+            quiet: matches.is_present("quiet"),
+            threads: threads(matches)?,
+        })
+    }
+}
+
+/// Compresses the file at `path` to `output`
+fn compress_file(path: &str, output: &Path, options: &CompressOptions) -> Result<(), Error> {
+    // The input is read twice, first to build the tree, then to compress
+    // it a chunk at a time, so it never has to fit in memory
+    let open = || File::open(path).map_err(Error::io(path));
+    let len = std::fs::metadata(path).map_err(Error::io(path))?.len();
 
-        let file_name = match input.rfind('.') {
-            Some(t) => input.split_at(t).0,
-            None => input,
+    let CompressOptions {
+        model,
+        ref dictionary,
+        verify,
+        rm,
+        force,
+        append,
+        dry_run,
+        quiet,
+        threads,
+    } = *options;
+    // The model is recorded, so decompressing knows which type of tree to
+    // read, even if nothing else is known about the input
+    let metadata = Metadata {
+        symbols: Some(model.name().to_string()),
+        ..Metadata::from_file(path).unwrap_or_default()
+    };
+
+    // Text is split into symbols all at once, as a chunk read could end
+    // part way through a character or word
+    let mut text = String::new();
+    if model != Model::Byte {
+        match open()?.read_to_string(&mut text) {
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return Err(Error::Format(
+                    path.into(),
+                    "the file isn't valid UTF-8, so needs --model byte".to_string(),
+                ))
+            }
+            result => result.map_err(Error::io(path))?,
+        };
+    }
+
+    if dry_run {
+        let bar = progress::bar(len, "Counting", quiet);
+        let compressed_len = match model {
+            Model::Byte => {
+                let counts = count_chunks(bar.wrap_read(open()?)).map_err(Error::io(path))?;
+                let tree = match &dictionary {
+                    Some(dictionary) => dictionary.clone(),
+                    None => byte_tree(&counts),
+                };
+                let histograms = counts.iter().map(|counts| {
+                    (0..=255)
+                        .zip(counts.iter().copied())
+                        .filter(|(_, count)| *count > 0)
+                });
+                compressed_len(&tree, histograms, metadata, dictionary.is_some())
+            }
+            Model::Char => {
+                let chars: Vec<char> = text.chars().collect();
+                let histograms = chars.chunks(CHUNK_LEN).map(histogram);
+                compressed_len(&Huffman::from(&chars), histograms, metadata, false)
+            }
+            Model::Word => {
+                let words = model::words(&text);
+                let histograms = words.chunks(CHUNK_LEN).map(histogram);
+                compressed_len(&Huffman::from(&words), histograms, metadata, false)
+            }
         };
+        bar.finish_and_clear();
+
+        println!("Original size:    {} bytes", len);
+        println!(
+            "Compressed size:  {} bytes ({:.1}% of the original)",
+            compressed_len,
+            100.0 * compressed_len as f64 / len.max(1) as f64
+        );
+        return Ok(());
+    }
+
+    // Even appending writes a new file, then moves it into place, so a
+    // failure leaves the existing file as it was
+    let mut writer = if append {
+        AtomicFile::append(output)
+    } else {
+        check_clobber(output, force)?;
+        AtomicFile::create(output)
+    }
+    .map_err(Error::io(output))?;
+
+    let verified = match model {
+        Model::Byte => {
+            // With a dictionary, the input doesn't need counting
+            let tree = match &dictionary {
+                Some(dictionary) => dictionary.clone(),
+                None => {
+                    let bar = progress::bar(len, "Counting", quiet);
+                    let counts = count_chunks(bar.wrap_read(open()?)).map_err(Error::io(path))?;
+                    bar.finish_and_clear();
+                    byte_tree(&counts)
+                }
+            };
+
+            let bar = progress::bar(len, "Compressing", quiet);
+            let mut reader = bar.wrap_read(open()?).take(0);
+            // A read that fails ends the chunks, with the error kept for after
+            let mut read_error = None;
+            let chunks = std::iter::from_fn(|| {
+                let mut chunk = Vec::with_capacity(CHUNK_LEN);
+                reader.set_limit(CHUNK_LEN as u64);
+                match reader.read_to_end(&mut chunk) {
+                    Ok(_) => Some(chunk),
+                    Err(e) => {
+                        read_error = Some(e);
+                        None
+                    }
+                }
+            });
+            let verified = write_chunks(
+                &tree,
+                chunks,
+                metadata,
+                verify,
+                dictionary.is_some(),
+                threads,
+                &mut writer,
+            );
+            bar.finish_and_clear();
+            if let Some(e) = read_error {
+                return Err(Error::Io(path.into(), e));
+            }
+            verified
+        }
+        Model::Char | Model::Word => {
+            let bar = progress::bar(len, "Compressing", quiet);
+            let verified = if model == Model::Char {
+                let chars: Vec<char> = text.chars().collect();
+                let chunks = chars.chunks(CHUNK_LEN).map(|chunk| {
+                    bar.inc(chunk.iter().map(|c| c.len_utf8() as u64).sum());
+                    chunk.to_vec()
+                });
+                write_chunks(
+                    &Huffman::from(&chars),
+                    chunks,
+                    metadata,
+                    verify,
+                    false,
+                    threads,
+                    &mut writer,
+                )
+            } else {
+                let words = model::words(&text);
+                let chunks = words.chunks(CHUNK_LEN).map(|chunk| {
+                    bar.inc(chunk.iter().map(|word| word.len() as u64).sum());
+                    chunk.to_vec()
+                });
+                write_chunks(
+                    &Huffman::from(&words),
+                    chunks,
+                    metadata,
+                    verify,
+                    false,
+                    threads,
+                    &mut writer,
+                )
+            };
+            bar.finish_and_clear();
+            verified
+        }
+    };
+
+    if !verified.map_err(Error::io(output))? {
+        drop(writer);
+        return Err(Error::Verification(output.to_path_buf()));
+    }
+    writer.persist().map_err(Error::io(output))?;
+
+    if rm {
+        std::fs::remove_file(path).map_err(Error::io(path))?;
+    }
+    Ok(())
+}
+
+/// Compresses each of `chunks` with `tree` on up to `threads` threads,
+/// writing each as a file of its own, in order, with `metadata` in the first.
+/// If `dictionary` is set, the tree is left out of every file. Even if there
+/// are no symbols, one file is written. Returns `false` if `verify` is set,
+/// and a chunk doesn't decompress back to itself, leaving the rest unwritten
+///
+/// # Errors
+/// Fails if there's any issue writing to `writer`
+fn write_chunks<T, I, W>(
+    tree: &Huffman<T>,
+    chunks: I,
+    metadata: Metadata,
+    verify: bool,
+    dictionary: bool,
+    threads: usize,
+    mut writer: W,
+) -> io::Result<bool>
+where
+    T: Serialize + Ord + Clone + Send + Sync + 'static,
+    I: IntoIterator<Item = Vec<T>>,
+    W: Write,
+{
+    let mut chunks = chunks.into_iter();
+    let mut metadata = Some(metadata);
+    loop {
+        // Only the very first chunk may be empty
+        let mut batch: Vec<Vec<T>> = Vec::with_capacity(threads);
+        for chunk in chunks.by_ref() {
+            if chunk.is_empty() && (metadata.is_none() || !batch.is_empty()) {
+                break;
+            }
+            batch.push(chunk);
+            if batch.len() == threads {
+                break;
+            }
+        }
+        // Text with no symbols has no chunks at all
+        if batch.is_empty() && metadata.is_some() {
+            batch.push(Vec::new());
+        }
+        if batch.is_empty() {
+            return Ok(true);
+        }
+
+        let files = parallel::map(&batch, threads, |chunk| {
+            // The tree is built from the input, or is a dictionary holding
+            // every byte, so every symbol is in it
+            let mut file = RZFile::compress(tree.clone(), chunk)
+                .unwrap()
+                .with_checksum(chunk);
+            if dictionary {
+                file = file.with_dictionary();
+            }
+            let verified = !verify || file.decompress().as_ref() == Some(chunk);
+            verified.then_some(file)
+        });
 
-        std::fs::write(format!("{}", file_name), contents).unwrap();
+        let full = batch.len() == threads;
+        for file in files {
+            let Some(mut file) = file else {
+                return Ok(false);
+            };
+            // Only the first chunk has the metadata, so decompressing knows
+            // what to call the file before writing it
+            if let Some(metadata) = metadata.take() {
+                file = file.with_metadata(metadata);
+            }
+            file.write_to(&mut writer)?;
+        }
+        if !full {
+            return Ok(true);
+        }
+    }
+}
+
+/// Counts how many times each byte appears in each chunk read from `reader`
+fn count_chunks<R: Read>(reader: R) -> io::Result<Vec<[usize; 256]>> {
+    let mut chunks = Vec::new();
+    for (i, byte) in BufReader::new(reader).bytes().enumerate() {
+        if i % CHUNK_LEN == 0 {
+            chunks.push([0; 256]);
+        }
+        chunks.last_mut().unwrap()[usize::from(byte?)] += 1;
+    }
+    Ok(chunks)
+}
+
+/// Builds a tree from the counts of every chunk together
+fn byte_tree(chunks: &[[usize; 256]]) -> Huffman<u8> {
+    let mut counts = [0; 256];
+    for chunk in chunks {
+        for (count, chunk_count) in counts.iter_mut().zip(chunk.iter()) {
+            *count += chunk_count;
+        }
+    }
+    HuffmanBuilder::new().build_from_frequencies(
+        (0..=255)
+            .zip(counts.iter().copied())
+            .filter(|(_, count)| *count > 0),
+    )
+}
+
+/// Counts how many times each symbol appears in `symbols`
+fn histogram<T: Ord + Clone>(symbols: &[T]) -> BTreeMap<T, usize> {
+    let mut counts = BTreeMap::new();
+    for symbol in symbols {
+        *counts.entry(symbol.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// How many bytes [`write_chunks`] would write, given how many times each
+/// symbol appears in each chunk. Each file's header and tree are the same
+/// size whatever the data, so they're measured by writing a file with none,
+/// and the data is the length of each symbol's code, times its count
+fn compressed_len<T, I, H>(
+    tree: &Huffman<T>,
+    histograms: I,
+    metadata: Metadata,
+    dictionary: bool,
+) -> u64
+where
+    T: Serialize + Ord + Clone + 'static,
+    I: IntoIterator<Item = H>,
+    H: IntoIterator<Item = (T, usize)>,
+{
+    let lengths: BTreeMap<T, u8> = tree.code_lengths().into_iter().collect();
+    let mut metadata = Some(metadata);
+    // There are no symbols to be missing from the tree, and writing to a
+    // `Vec` can't fail
+    // Bytes that wouldn't get any smaller are stored as they are instead
+    let bytes = TypeId::of::<T>() == TypeId::of::<u8>();
+    let mut file_len = |bits: usize, symbols: usize| {
+        let mut file = RZFile::compress(tree.clone(), &[])
+            .unwrap()
+            .with_checksum(&[]);
+        if dictionary {
+            file = file.with_dictionary();
+        }
+        if let Some(metadata) = metadata.take() {
+            file = file.with_metadata(metadata);
+        }
+        let mut header = Vec::new();
+        file.write_to(&mut header).unwrap();
+        let data_len = match bits.div_ceil(8) {
+            data_len if bytes && symbols > 0 => data_len.min(symbols),
+            data_len => data_len,
+        };
+        (header.len() + data_len) as u64
+    };
+
+    let mut total = 0;
+    let mut files = 0;
+    for histogram in histograms {
+        let (bits, symbols) =
+            histogram
+                .into_iter()
+                .fold((0, 0), |(bits, symbols), (symbol, count)| {
+                    (
+                        bits + count * usize::from(lengths[&symbol]),
+                        symbols + count,
+                    )
+                });
+        total += file_len(bits, symbols);
+        files += 1;
+    }
+    // Even if there are no symbols, one file is written
+    if files == 0 {
+        total += file_len(0, 0);
+    }
+    total
+}
+
+/// Skips any extension frames `reader` is at, returning whether another RZ
+/// file follows them
+fn more_frames<R: BufRead>(reader: &mut R, path: &Path) -> Result<bool, Error> {
+    Extension::read_all_from(&mut *reader).map_err(Error::rz(path))?;
+    Ok(!reader.fill_buf().map_err(Error::io(path))?.is_empty())
+}
+
+/// Reads the next file from `reader`, being the file at `path`, along with
+/// its metadata. Only the first of each chunked run of files records the
+/// model, so it's kept in `model` for the files following it
+fn read_frame<R: BufRead + Seek>(
+    reader: &mut R,
+    path: &Path,
+    model: &mut Model,
+    dictionary: Option<&Huffman<u8>>,
+) -> Result<(Frame, Option<Metadata>), Error> {
+    let start = reader.stream_position().map_err(Error::io(path))?;
+    let header = Metadata::read_from_header(&mut *reader).map_err(Error::rz(path))?;
+    if let Some(symbols) = header.as_ref().and_then(|m| m.symbols.as_ref()) {
+        *model = symbols.parse().map_err(Error::format(path))?;
+    }
+    reader
+        .seek(SeekFrom::Start(start))
+        .map_err(Error::io(path))?;
+
+    let frame = Frame::read_from(reader, *model, dictionary).map_err(Error::rz(path))?;
+    Ok((frame, header))
+}
+
+/// Parses a range of bytes written as `start..end`, where either may be left
+/// out
+fn parse_range(range: &str) -> Result<Range<usize>, Error> {
+    let invalid = || Error::Usage(format!("{} isn't a range such as 100..200", range));
+    let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+    let start = if start.is_empty() {
+        0
+    } else {
+        start.parse().map_err(|_| invalid())?
+    };
+    let end = if end.is_empty() {
+        usize::MAX
+    } else {
+        end.parse().map_err(|_| invalid())?
+    };
+    Ok(start..end)
+}
+
+/// Parses the value given for the argument `name`, which must have one
+fn parse_arg<T: FromStr>(matches: &ArgMatches, name: &str) -> Result<T, Error> {
+    let value = matches.value_of(name).unwrap();
+    value
+        .parse()
+        .map_err(|_| Error::Usage(format!("{} isn't a valid value for --{}", value, name)))
+}
+
+/// The number of threads asked for with `--threads`, or one per core
+fn threads(matches: &ArgMatches) -> Result<usize, Error> {
+    if matches.is_present("threads") {
+        Ok(parse_arg::<usize>(matches, "threads")?.max(1))
+    } else {
+        Ok(parallel::default_threads())
+    }
+}
+
+/// The dictionary given with `--dict`, if any
+fn load_dictionary(matches: &ArgMatches) -> Result<Option<Huffman<u8>>, Error> {
+    matches
+        .value_of("dict")
+        .map(|dict| dictionary::load(Path::new(dict)))
+        .transpose()
+}
+
+/// The path compressing `path` writes to by default, being `path` with `.rz`
+/// added
+fn compressed_path(path: &Path) -> PathBuf {
+    // Going through the components drops any trailing `/` from directories
+    let mut output = OsString::from(path.components().as_path());
+    output.push(".rz");
+    output.into()
+}
+
+/// The path decompressing `path` writes to by default, being `path` without
+/// its `.rz`, or with `.out` added if it doesn't end in `.rz`
+fn decompressed_path(path: &Path) -> PathBuf {
+    if path.extension() == Some("rz".as_ref()) {
+        path.with_extension("")
+    } else {
+        let mut output = path.as_os_str().to_owned();
+        output.push(".out");
+        output.into()
+    }
+}
+
+/// Fails if `path` already exists, unless `force` is set, so nothing is
+/// replaced by accident
+fn check_clobber(path: &Path, force: bool) -> Result<(), Error> {
+    if !force && path.exists() {
+        return Err(Error::Usage(format!(
+            "{} already exists, use --force to replace it",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Reads every file in `buf`, read from `path`, one after another, with
+/// symbols of type `T`. Fails unless there's at least one
+fn frames<'a, T>(path: &Path, buf: &'a [u8]) -> Result<Vec<RZFile<'a, T>>, Error>
+where
+    T: Serialize + Deserialize<'a> + Ord + Clone + 'static,
+{
+    let files = RZFile::frames(buf)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(Error::format(path))?;
+    if files.is_empty() {
+        return Err(Error::Format(path.into(), "the file is empty".to_string()));
+    }
+    Ok(files)
+}
+
+/// Prints what's known about `files`, read one after another from `buf`, and
+/// the codebook of the first file's tree if `codes` is set. The version is
+/// read straight from the first file's header
+fn print_info<T, J>(
+    buf: &[u8],
+    files: &[RZFile<T>],
+    join: J,
+    codes: bool,
+) -> Result<(), DecodeError>
+where
+    T: Serialize + Ord + Clone + fmt::Display + 'static,
+    J: Fn(Vec<T>) -> Vec<u8>,
+{
+    let first = &files[0];
+    let tree_len = first.tree_len();
+    let data_len: usize = files.iter().map(|file| file.data().len()).sum();
+
+    // Decompressing gives the exact size, and the frequency of each byte
+    let mut counts = [0_u64; 256];
+    let mut original = 0_u64;
+    for file in files {
+        for byte in join(file.decompress_with(&DecodeOptions::new())?) {
+            counts[usize::from(byte)] += 1;
+            original += 1;
+        }
+    }
+    let entropy: f64 = counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / original as f64;
+            -p * p.log2()
+        })
+        .sum();
+    let data_bits = data_len as u64 * 8 - files.iter().map(|f| u64::from(f.zeros())).sum::<u64>();
+
+    println!("Version:          {}", buf[4]);
+    println!("Frames:           {}", files.len());
+    println!(
+        "Tree:             {:?}, {} bytes, {} symbols",
+        first.tree_format(),
+        tree_len,
+        first.tree.contents().len()
+    );
+    if let Some(id) = first.dictionary_id() {
+        println!("Dictionary:       {:08x}", id);
+    }
+    println!("Original size:    {} bytes", original);
+    println!(
+        "Compressed size:  {} bytes, {} of them data ({:.1}% of the original)",
+        buf.len(),
+        data_len,
+        100.0 * buf.len() as f64 / original.max(1) as f64
+    );
+    println!("Padding:          {} bits", first.zeros());
+    println!("Entropy:          {:.3} bits per byte", entropy);
+    println!(
+        "Average code:     {:.3} bits per byte",
+        data_bits as f64 / original.max(1) as f64
+    );
+    let stored = files.iter().filter(|file| file.is_stored()).count();
+    if stored > 0 {
+        println!("Stored:           {} of {} frames", stored, files.len());
+    }
+    if let Some(checksum) = first.checksum() {
+        println!("Checksum:         {:08x}", checksum);
+    }
+    if let Some(block_size) = first.block_size() {
+        println!(
+            "Blocks:           {} of {} bytes",
+            first.block_count(),
+            block_size
+        );
+    }
+    if let Some(name) = first.metadata().and_then(Metadata::file_name) {
+        println!("Original name:    {}", name);
+    }
+    if codes {
+        print!("\n{}", first.tree);
     }
+    Ok(())
 }
@@ -0,0 +1,124 @@
+use std::{io::Read, ops::Range, str::FromStr};
+
+use huffman_comprs::{DecodeError, DecodeOptions, Huffman, RZFile, RzError};
+
+/// What the input is split into, to be compressed as symbols
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Model {
+    /// Each byte is a symbol, so any file can be compressed
+    Byte,
+    /// Each UTF-8 character is a symbol
+    Char,
+    /// Each run of alphanumeric characters is a symbol, as is every other
+    /// character
+    Word,
+}
+
+impl Model {
+    pub const ALL: [Self; 3] = [Self::Byte, Self::Char, Self::Word];
+    pub const NAMES: [&'static str; 3] = ["byte", "char", "word"];
+
+    pub const fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+}
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|model| model.name() == name)
+            .ok_or_else(|| format!("unknown model {}", name))
+    }
+}
+
+/// An RZ file read with the type of symbols its model says
+#[derive(Debug)]
+pub enum Frame {
+    Byte(RZFile<'static, u8>),
+    Char(RZFile<'static, char>),
+    Word(RZFile<'static, String>),
+}
+
+impl Frame {
+    /// Reads the next file from `reader`, with symbols of `model`'s type.
+    /// Byte files may leave their tree out, if it's `dictionary`
+    pub fn read_from<R: Read>(
+        reader: R,
+        model: Model,
+        dictionary: Option<&Huffman<u8>>,
+    ) -> Result<Self, RzError> {
+        Ok(match (model, dictionary) {
+            (Model::Byte, Some(dictionary)) => {
+                Self::Byte(RZFile::read_from_with_dictionary(reader, dictionary)?)
+            }
+            (Model::Byte, None) => Self::Byte(RZFile::read_from(reader)?),
+            (Model::Char, _) => Self::Char(RZFile::read_from(reader)?),
+            (Model::Word, _) => Self::Word(RZFile::read_from(reader)?),
+        })
+    }
+    /// Decompresses the file, joining its symbols back into bytes
+    pub fn decompress(&self) -> Result<Vec<u8>, DecodeError> {
+        let options = DecodeOptions::new();
+        Ok(match self {
+            Self::Byte(file) => file.decompress_with(&options)?,
+            Self::Char(file) => join_chars(file.decompress_with(&options)?),
+            Self::Word(file) => join_words(file.decompress_with(&options)?),
+        })
+    }
+    /// Decompresses only the bytes at the positions in `range`, returning
+    /// them with how many bytes the whole file decompresses to. Byte files
+    /// store their length, so are skipped entirely if `range` starts past
+    /// them, and only the blocks holding `range` are decoded if they're split
+    /// into blocks
+    pub fn decompress_range(&self, range: Range<usize>) -> Result<(Vec<u8>, usize), DecodeError> {
+        if let Self::Byte(file) = self {
+            if let Some(len) = file.len() {
+                if range.start >= len || range.start >= range.end {
+                    return Ok((Vec::new(), len));
+                }
+                return Ok((file.decompress_range(range)?, len));
+            }
+        }
+
+        let contents = self.decompress()?;
+        let len = contents.len();
+        let end = range.end.min(len);
+        let start = range.start.min(end);
+        Ok((contents[start..end].to_vec(), len))
+    }
+}
+
+/// Joins characters back into UTF-8 bytes
+pub fn join_chars(chars: Vec<char>) -> Vec<u8> {
+    chars.into_iter().collect::<String>().into_bytes()
+}
+
+/// Joins words split by [`words`] back into UTF-8 bytes
+pub fn join_words(words: Vec<String>) -> Vec<u8> {
+    words.concat().into_bytes()
+}
+
+/// Splits `text` into runs of alphanumeric characters, and every other
+/// character on its own, so joining them gives back `text`
+pub fn words(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+            continue;
+        }
+        if let Some(start) = start.take() {
+            words.push(text[start..i].to_string());
+        }
+        words.push(c.to_string());
+    }
+    if let Some(start) = start {
+        words.push(text[start..].to_string());
+    }
+    words
+}
@@ -0,0 +1,20 @@
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// Makes a bar showing how many of `len` bytes have been handled so far,
+/// labelled with `message`. The bar is hidden if `quiet` is set, or stderr
+/// isn't a terminal
+pub fn bar(len: u64, message: &'static str, quiet: bool) -> ProgressBar {
+    if quiet {
+        return ProgressBar::hidden();
+    }
+
+    let bar = ProgressBar::new(len).with_message(message);
+    bar.set_style(
+        ProgressStyle::with_template(
+            "{msg:>13} [{bar:40}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+        )
+        .unwrap()
+        .progress_chars("=> "),
+    );
+    bar
+}
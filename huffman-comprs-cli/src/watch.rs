@@ -0,0 +1,94 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use glob::Pattern;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+
+use crate::{atomic, error::Error};
+
+/// Watches `dir` and everything under it, calling `compress` with each file
+/// once it's been created or modified, then left unchanged for `debounce`,
+/// so a file still being written is only compressed once it's complete.
+///
+/// `.rz` files, and those matching any of `excludes` by name or by path
+/// relative to `dir`, are left alone. Only returns if watching fails
+pub fn run<F>(
+    dir: &Path,
+    excludes: &[&str],
+    debounce: Duration,
+    mut compress: F,
+) -> Result<(), Error>
+where
+    F: FnMut(&Path),
+{
+    let excludes = excludes
+        .iter()
+        .map(|exclude| {
+            Pattern::new(exclude)
+                .map_err(|e| Error::Usage(format!("{} isn't a valid glob, as {}", exclude, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let watch_error = |e: notify::Error| Error::Io(dir.to_path_buf(), io::Error::other(e));
+
+    // Events name files by their absolute paths, which excludes are matched
+    // relative to
+    let dir = dir.canonicalize().map_err(Error::io(dir))?;
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+    watcher
+        .watch(&dir, RecursiveMode::Recursive)
+        .map_err(watch_error)?;
+
+    // When each file changed was last changed
+    let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+    // Polled at least every 10ms, so a debounce of 0 doesn't spin
+    let poll = (debounce / 4).max(Duration::from_millis(10));
+    loop {
+        match receiver.recv_timeout(poll) {
+            Ok(event) => {
+                let event: Event = event.map_err(watch_error)?;
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if !is_excluded(&dir, &path, &excludes) {
+                            pending.insert(path, Instant::now());
+                        }
+                    }
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+
+        let settled: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, changed)| changed.elapsed() >= debounce)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in settled {
+            pending.remove(&path);
+            // The file may have been moved or deleted since
+            if path.is_file() {
+                compress(&path);
+            }
+        }
+    }
+}
+
+/// Whether `path` shouldn't be compressed, being compressed already, a file
+/// still being written by this program, or matching any of `excludes`
+fn is_excluded(dir: &Path, path: &Path, excludes: &[Pattern]) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy());
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+
+    path.extension().is_some_and(|extension| extension == "rz")
+        || atomic::is_temp(path)
+        || excludes.iter().any(|exclude| {
+            name.as_ref().is_some_and(|name| exclude.matches(name))
+                || exclude.matches_path(relative)
+        })
+}
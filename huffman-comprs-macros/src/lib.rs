@@ -0,0 +1,91 @@
+#![warn(clippy::pedantic, clippy::nursery)]
+
+//! Compile-time construction of [`huffman_comprs`](../huffman_comprs/index.html) trees
+//! # Example
+//! ```
+//! use huffman_comprs::StaticHuffman;
+//! use huffman_comprs_macros::include_huffman;
+//!
+//! // The path is relative to the crate's `Cargo.toml`
+//! static TREE: StaticHuffman<u8> = include_huffman!("tests/corpus.txt");
+//!
+//! assert!(TREE.get_code(b'e').is_some());
+//! ```
+
+use std::path::PathBuf;
+
+use proc_macro::{Literal, TokenStream, TokenTree};
+use syn::LitStr;
+
+use huffman_comprs::{codegen, Huffman};
+
+/// Builds a [`StaticHuffman`](../huffman_comprs/struct.StaticHuffman.html)
+/// tree from the file at the given path at compile time, expanding to a
+/// constant expression that can initialise a `static`.
+///
+/// The path is relative to the invoking crate's `Cargo.toml`. By default the
+/// tree is over the file's bytes, giving a `StaticHuffman<u8>`, while
+/// `include_huffman!("corpus.txt", char)` reads the file as UTF-8 and gives a
+/// `StaticHuffman<char>`.
+///
+/// # Panics
+/// Invalid input is reported with `compile_error!`, so this only panics if the
+/// compiler can't parse its own error message
+#[proc_macro]
+pub fn include_huffman(input: TokenStream) -> TokenStream {
+    match expand(input) {
+        Ok(t) => t,
+        Err(e) => format!("compile_error!({e:?})").parse().unwrap(),
+    }
+}
+
+fn expand(input: TokenStream) -> Result<TokenStream, String> {
+    let mut tokens = input.into_iter();
+
+    let path = match tokens.next() {
+        Some(TokenTree::Literal(literal)) => parse_str(literal)?,
+        _ => return Err("expected a path to a corpus, as a string literal".to_string()),
+    };
+
+    let model = match (tokens.next(), tokens.next()) {
+        (None, _) => "u8".to_string(),
+        (Some(TokenTree::Punct(p)), Some(TokenTree::Ident(model))) if p.as_char() == ',' => {
+            model.to_string()
+        }
+        _ => return Err("expected a symbol type, `u8` or `char`".to_string()),
+    };
+
+    let root = std::env::var("CARGO_MANIFEST_DIR").map_err(|e| e.to_string())?;
+    let path = PathBuf::from(root).join(path);
+
+    let corpus =
+        std::fs::read(&path).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+
+    let tree = match model.as_str() {
+        "u8" => codegen::static_tree(&Huffman::from(&corpus), "u8"),
+        "char" => {
+            let corpus = String::from_utf8(corpus)
+                .map_err(|_| format!("{} isn't valid UTF-8", path.display()))?;
+            codegen::static_tree(&Huffman::from(corpus.as_str()), "char")
+        }
+        other => return Err(format!("unsupported symbol type `{other}`")),
+    };
+
+    // Including the corpus makes cargo rebuild whenever it changes
+    format!(
+        "{{ const _: &[u8] = include_bytes!({:?}); {} }}",
+        path.display().to_string(),
+        tree
+    )
+    .parse()
+    .map_err(|e| format!("{e:?}"))
+}
+
+/// Reads the value of a string literal, raw or not, with any escapes
+/// replaced by the characters they stand for
+fn parse_str(literal: Literal) -> Result<String, String> {
+    let found = literal.to_string();
+    syn::parse::<LitStr>(TokenTree::Literal(literal).into())
+        .map(|literal| literal.value())
+        .map_err(|_| format!("expected a string literal, found {found}"))
+}
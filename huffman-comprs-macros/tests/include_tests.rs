@@ -0,0 +1,51 @@
+use huffman_comprs::{DecodeError, DecodeOptions, Huffman, StaticHuffman};
+use huffman_comprs_macros::include_huffman;
+
+static BYTES: StaticHuffman<u8> = include_huffman!("tests/corpus.txt");
+static CHARS: StaticHuffman<char> = include_huffman!("tests/corpus.txt", char);
+
+#[test]
+fn matches_runtime_tree() {
+    let corpus = include_str!("corpus.txt");
+
+    assert_eq!(
+        Huffman::from(&corpus.as_bytes().to_vec()).to_btree(),
+        BYTES.to_btree()
+    );
+    assert_eq!(Huffman::from(corpus).to_btree(), CHARS.to_btree());
+    assert_eq!(corpus.len(), BYTES.freq());
+}
+
+#[test]
+fn reads_raw_and_escaped_paths() {
+    static RAW: StaticHuffman<u8> = include_huffman!(r"tests/corpus.txt");
+    static ESCAPED: StaticHuffman<u8> = include_huffman!("tests\x2fcorpus.txt");
+
+    assert_eq!(BYTES.to_btree(), RAW.to_btree());
+    assert_eq!(BYTES.to_btree(), ESCAPED.to_btree());
+}
+
+#[test]
+fn decodes_as_runtime_tree() {
+    let corpus = include_bytes!("corpus.txt");
+    let tree = Huffman::from(&corpus.to_vec());
+
+    let mut data = BYTES.compress(corpus).unwrap();
+    assert_eq!(tree.compress(corpus).unwrap(), data);
+    data.reverse();
+    assert_eq!(Ok(corpus.to_vec()), BYTES.reconstruct(data.clone(), 0));
+    assert_eq!(
+        tree.try_reconstruct(data.clone(), 0),
+        BYTES.reconstruct(data.clone(), 0)
+    );
+
+    let limited = DecodeOptions::new().max_output_len(10);
+    assert_eq!(
+        Err(DecodeError::OutputLimitExceeded(10)),
+        BYTES.reconstruct_with(data, 0, &limited)
+    );
+    assert_eq!(
+        Err(DecodeError::DanglingBits),
+        BYTES.reconstruct(vec![true], 1)
+    );
+}
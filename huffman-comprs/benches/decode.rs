@@ -1,4 +1,4 @@
-use huffman_comprs::{Huffman, RZFile};
+use huffman_comprs::{Decoder, Encoder, Huffman, RZFile};
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
@@ -29,5 +29,23 @@ fn generation(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, from_file, generation);
+fn reused_tables(c: &mut Criterion) {
+    let input = b"{\"id\":12345,\"name\":\"a typical payload\",\"active\":true}".to_vec();
+
+    let tree = Huffman::from(&input);
+    let encoder = Encoder::new(&tree);
+    let decoder = Decoder::new(&tree);
+
+    let mut bytes = Vec::new();
+    let zeros = encoder.encode_into_bytes(&input, &mut bytes).unwrap();
+
+    c.bench_function("Encoder", |b| {
+        b.iter(|| encoder.encode_into_bytes(black_box(&input), &mut Vec::new()))
+    });
+    c.bench_function("Decoder", |b| {
+        b.iter(|| decoder.decode(black_box(&bytes), black_box(zeros)).unwrap())
+    });
+}
+
+criterion_group!(benches, from_file, generation, reused_tables);
 criterion_main!(benches);
@@ -0,0 +1,30 @@
+/* C interface to huffman-comprs, built with the `ffi` feature */
+#ifndef HUFFMAN_COMPRS_H
+#define HUFFMAN_COMPRS_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct huffman_tree huffman_tree;
+
+huffman_tree *huffman_tree_new(const uint8_t *data, size_t len);
+void huffman_tree_free(huffman_tree *tree);
+
+/* Both return 0 on success, or -1 on failure, see huffman_last_error */
+int huffman_compress(const huffman_tree *tree, const uint8_t *data, size_t len,
+                     uint8_t **out, size_t *out_len);
+int huffman_decompress(const uint8_t *data, size_t len, uint8_t **out,
+                       size_t *out_len);
+void huffman_buffer_free(uint8_t *data, size_t len);
+
+const char *huffman_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,263 @@
+//! Comparing how well, and how quickly, different coders and symbol models
+//! compress the same input, so an application can pick the best for its
+//! data.
+//!
+//! [`compare`](fn.compare.html) compresses the input with each
+//! [`CodecChoice`](enum.CodecChoice.html) as a whole `.rz` file, then reads
+//! it back and decompresses it, timing both:
+//! ```
+//! use huffman_comprs::analysis::{self, CodecChoice};
+//!
+//! let input = b"The same input, compressed every way it can be. ".repeat(20);
+//!
+//! let reports = analysis::compare(
+//!     &input,
+//!     &[CodecChoice::Huffman, CodecChoice::ShannonFano, CodecChoice::Chars],
+//! );
+//!
+//! let best = reports.iter().min_by_key(|report| report.compressed_len).unwrap();
+//! assert!(best.ratio < 1.0);
+//! ```
+
+use std::{
+    convert::TryFrom,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    Chars, Delimited, EntropyCoder, Huffman, Lines, MultiHuffman, RZFile, ShannonFano, Tokenizer,
+};
+
+/// A coder, and the symbols it codes, for [`compare`](fn.compare.html) to
+/// measure
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CodecChoice {
+    /// A [`Huffman`](../struct.Huffman.html) tree over bytes
+    Huffman,
+    /// [`ShannonFano`](../struct.ShannonFano.html) codes over bytes
+    ShannonFano,
+    /// A [`MultiHuffman`](../struct.MultiHuffman.html) over bytes, trained
+    /// with up to `trees` trees, on groups of `group_size` bytes
+    MultiHuffman {
+        /// The most trees to train
+        trees: usize,
+        /// How many bytes each group holds
+        group_size: usize,
+    },
+    /// A tree over bytes, with the input split into blocks of `block_size`
+    /// bytes, each coded by whichever
+    /// [`BlockCoder`](../enum.BlockCoder.html) makes it smallest, as
+    /// [`RZFile::new_blocked_adaptive`](../struct.RZFile.html#method.new_blocked_adaptive)
+    /// does
+    Adaptive {
+        /// How many bytes each block holds
+        block_size: usize,
+    },
+    /// A tree over the UTF-8 characters of text
+    Chars,
+    /// A tree over the lines of text
+    Lines,
+    /// A tree over the pieces of text ending with the given delimiter, such
+    /// as words ending with a space
+    Delimited(char),
+}
+
+impl CodecChoice {
+    /// Whether the choice splits the input as text, so only applies to
+    /// input that's UTF-8
+    #[must_use]
+    pub const fn is_text(self) -> bool {
+        matches!(self, Self::Chars | Self::Lines | Self::Delimited(_))
+    }
+}
+
+impl fmt::Display for CodecChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Huffman => write!(f, "huffman"),
+            Self::ShannonFano => write!(f, "shannon-fano"),
+            Self::MultiHuffman { trees, group_size } => {
+                write!(f, "multi-huffman ({trees} trees, groups of {group_size})")
+            }
+            Self::Adaptive { block_size } => write!(f, "adaptive (blocks of {block_size})"),
+            Self::Chars => write!(f, "chars"),
+            Self::Lines => write!(f, "lines"),
+            Self::Delimited(delimiter) => write!(f, "delimited by {delimiter:?}"),
+        }
+    }
+}
+
+/// How well, and how quickly, one [`CodecChoice`](enum.CodecChoice.html)
+/// compressed the input given to [`compare`](fn.compare.html)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CodecReport {
+    /// The choice measured
+    pub choice: CodecChoice,
+    /// The size of the whole `.rz` file, including the model
+    pub compressed_len: usize,
+    /// The compressed length as a fraction of the input's, so lower is
+    /// better. `0` if the input was empty
+    pub ratio: f64,
+    /// How long compressing took, including splitting the input into
+    /// symbols, building the model, and writing the file
+    pub compress_time: Duration,
+    /// How long decompressing took, including reading the file, and joining
+    /// the symbols back into bytes
+    pub decompress_time: Duration,
+}
+
+/// Compresses `input` with each of `choices`, then decompresses it again,
+/// reporting the size and timings of each, in the order they're given.
+///
+/// Choices splitting the input as text are left out if `input` isn't UTF-8,
+/// as are any given invalid parameters, such as blocks of 0 bytes.
+///
+/// Each is only run once, so for short inputs, the timings are only rough
+///
+/// # Panics
+/// Panics if a choice doesn't decompress back to `input`, which would be a
+/// bug in the crate
+#[must_use]
+pub fn compare(input: &[u8], choices: &[CodecChoice]) -> Vec<CodecReport> {
+    let text = std::str::from_utf8(input).ok();
+    choices
+        .iter()
+        .filter(|choice| text.is_some() || !choice.is_text())
+        .filter_map(|choice| {
+            let (compressed_len, compress_time, decompress_time) = match *choice {
+                CodecChoice::Huffman => measure(
+                    input,
+                    <[u8]>::to_vec,
+                    |symbols| RZFile::compress(Huffman::from(symbols), symbols),
+                    |bytes| bytes,
+                ),
+                CodecChoice::ShannonFano => measure(
+                    input,
+                    <[u8]>::to_vec,
+                    |symbols| RZFile::compress(ShannonFano::from(symbols), symbols),
+                    |bytes| bytes,
+                ),
+                CodecChoice::MultiHuffman { trees, group_size } => {
+                    if trees == 0 || group_size == 0 || u32::try_from(group_size).is_err() {
+                        return None;
+                    }
+                    measure(
+                        input,
+                        <[u8]>::to_vec,
+                        |symbols| {
+                            RZFile::compress(
+                                MultiHuffman::train(symbols, trees, group_size),
+                                symbols,
+                            )
+                        },
+                        |bytes| bytes,
+                    )
+                }
+                CodecChoice::Adaptive { block_size } => {
+                    if block_size == 0 || u32::try_from(block_size).is_err() {
+                        return None;
+                    }
+                    measure(
+                        input,
+                        <[u8]>::to_vec,
+                        |symbols| {
+                            RZFile::new_blocked_adaptive(
+                                Huffman::from(symbols),
+                                symbols,
+                                block_size,
+                            )
+                        },
+                        |bytes| bytes,
+                    )
+                }
+                CodecChoice::Chars => measure_tokens(input, &Chars),
+                CodecChoice::Lines => measure_tokens(input, &Lines),
+                CodecChoice::Delimited(delimiter) => measure_tokens(input, &Delimited(delimiter)),
+            };
+
+            // Precision is only lost beyond 2^52 bytes, which is far more
+            // than fits in memory
+            #[allow(clippy::cast_precision_loss)]
+            let ratio = if input.is_empty() {
+                0.0
+            } else {
+                compressed_len as f64 / input.len() as f64
+            };
+            Some(CodecReport {
+                choice: *choice,
+                compressed_len,
+                ratio,
+                compress_time,
+                decompress_time,
+            })
+        })
+        .collect()
+}
+
+/// Measures a tree over the tokens `tokenizer` splits `input` into, which
+/// must be UTF-8, joining them back together by writing each out in turn
+fn measure_tokens<K>(input: &[u8], tokenizer: &K) -> (usize, Duration, Duration)
+where
+    K: Tokenizer,
+    K::Token: Serialize + DeserializeOwned + Ord + Clone + fmt::Display + 'static,
+{
+    let split = |input: &[u8]| -> Vec<K::Token> {
+        let text = std::str::from_utf8(input).expect("only text is split into tokens");
+        tokenizer.tokens(text).collect()
+    };
+    let join = |tokens: Vec<K::Token>| -> Vec<u8> {
+        tokens
+            .iter()
+            .map(ToString::to_string)
+            .collect::<String>()
+            .into_bytes()
+    };
+    measure(
+        input,
+        split,
+        |symbols| RZFile::compress(Huffman::from(symbols), symbols),
+        join,
+    )
+}
+
+/// Splits `input` into symbols with `split`, and compresses them with
+/// `compress`, then reads the file back, decompresses it, and joins the
+/// symbols back into bytes with `join`. Returns the length of the file, and
+/// how long compressing and decompressing took
+fn measure<T, C, S, F, J>(
+    input: &[u8],
+    split: S,
+    compress: F,
+    join: J,
+) -> (usize, Duration, Duration)
+where
+    T: Serialize + DeserializeOwned + Ord + Clone + 'static,
+    C: EntropyCoder<T> + Clone,
+    S: Fn(&[u8]) -> Vec<T>,
+    F: Fn(&Vec<T>) -> Option<RZFile<'static, T, C>>,
+    J: Fn(Vec<T>) -> Vec<u8>,
+{
+    let start = Instant::now();
+    let symbols = split(input);
+    let mut buf = Vec::new();
+    compress(&symbols)
+        .expect("every symbol is in the model built from them")
+        .write_to(&mut buf)
+        .expect("writing to a `Vec` can't fail");
+    let compress_time = start.elapsed();
+
+    let start = Instant::now();
+    let output = join(
+        RZFile::<T, C>::parse_with_model(&buf, None)
+            .ok()
+            .and_then(|file| file.decompress())
+            .expect("the file was just written"),
+    );
+    let decompress_time = start.elapsed();
+
+    assert_eq!(input, output, "the input didn't decompress back to itself");
+    (buf.len(), compress_time, decompress_time)
+}
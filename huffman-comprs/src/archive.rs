@@ -0,0 +1,354 @@
+use std::{
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::{Component, Path},
+};
+
+use crate::{
+    rz::{malformed, RzError},
+    DecodeError, Huffman, RZFile,
+};
+
+/// The default number of bytes in each block of an archive
+const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+
+/// A single file stored in an [`RZArchive`](struct.RZArchive.html)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    name: String,
+    offset: usize,
+    len: usize,
+}
+
+impl Entry {
+    /// Returns the name the file was added with
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    /// Returns where the file starts in the archive's decompressed data
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+    /// Returns the length of the file, uncompressed
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    /// Returns whether the file is empty
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Collects files to store together in an [`RZArchive`](struct.RZArchive.html)
+/// # Examples
+/// ```
+/// use huffman_comprs::ArchiveBuilder;
+///
+/// let archive = ArchiveBuilder::new()
+///     .add("hello.txt", b"Hello".to_vec())
+///     .add("world.txt", b"World".to_vec())
+///     .build();
+///
+/// let names: Vec<&str> = archive.entries().iter().map(|e| e.name()).collect();
+/// assert_eq!(vec!["hello.txt", "world.txt"], names);
+/// assert_eq!(Ok(b"World".to_vec()), archive.extract("world.txt").unwrap());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ArchiveBuilder {
+    files: Vec<(String, Vec<u8>)>,
+    block_size: usize,
+}
+
+impl ArchiveBuilder {
+    /// Creates a builder without any files
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+    /// Sets how many bytes are compressed in each block. Extracting a file
+    /// only decodes the blocks holding it, so smaller blocks make extracting
+    /// small files faster, at some cost to the archive's size
+    #[must_use]
+    pub const fn block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+    /// Adds a file called `name`, replacing any file already added with the
+    /// same name
+    #[must_use]
+    pub fn add<S: Into<String>>(mut self, name: S, contents: Vec<u8>) -> Self {
+        let name = name.into();
+        match self.files.iter_mut().find(|(n, _)| *n == name) {
+            Some(file) => file.1 = contents,
+            None => self.files.push((name, contents)),
+        }
+        self
+    }
+    /// Adds the file at `path`, named by `path` as given
+    ///
+    /// # Errors
+    /// Fails if the file can't be read, or `path` isn't valid UTF-8
+    pub fn add_file<P: AsRef<Path>>(self, path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let name = path.to_str().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} isn't valid UTF-8", path.display()),
+            )
+        })?;
+
+        let contents = fs::read(path)?;
+        Ok(self.add(name, contents))
+    }
+    /// Compresses every file added, with a single tree built from all of them
+    ///
+    /// # Panics
+    /// Panics if the block size is 0 or more than `u32::MAX`, or if there are
+    /// more than `u32::MAX` files
+    #[must_use]
+    pub fn build(self) -> RZArchive<'static> {
+        let mut entries = Vec::with_capacity(self.files.len());
+        let mut data = Vec::with_capacity(self.files.iter().map(|(_, c)| c.len()).sum());
+        for (name, contents) in self.files {
+            entries.push(Entry {
+                name,
+                offset: data.len(),
+                len: contents.len(),
+            });
+            data.extend_from_slice(&contents);
+        }
+        assert!(
+            u32::try_from(entries.len()).is_ok(),
+            "an archive can't hold more than u32::MAX files"
+        );
+
+        // Every byte is in the tree, so compressing can't fail
+        let mut file = RZFile::new_blocked(Huffman::from(&data), &data, self.block_size)
+            .unwrap()
+            .with_checksum(&data);
+        file.entries = Some(entries);
+
+        RZArchive { file }
+    }
+}
+
+impl Default for ArchiveBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Many files compressed together into one RZ file, any of which can be
+/// listed and extracted without decompressing the rest.
+///
+/// An archive is an [`RZFile`](struct.RZFile.html) split into blocks, with
+/// flag `0x04` set, and an entry table following the block index. The table is
+/// the number of files as 4 bytes, then for each file the length of its name
+/// as 2 bytes, the name in UTF-8, and the length of the file as 8 bytes. Files
+/// are stored one after another, in the order listed
+#[derive(Clone, Debug)]
+pub struct RZArchive<'a> {
+    file: RZFile<'a, u8>,
+}
+
+impl<'a> RZArchive<'a> {
+    /// Lists every file in the archive
+    #[must_use]
+    pub fn entries(&self) -> &[Entry] {
+        self.file.entries.as_deref().unwrap_or_default()
+    }
+    /// Finds the file called `name`
+    #[must_use]
+    pub fn entry(&self, name: &str) -> Option<&Entry> {
+        self.entries().iter().find(|entry| entry.name == name)
+    }
+    /// Decompresses the file called `name`, or returns `None` if there's no
+    /// such file
+    ///
+    /// # Errors
+    /// Returns an error if the blocks holding the file aren't a valid encoding
+    /// under the archive's tree, or end before the file does
+    #[must_use]
+    pub fn extract(&self, name: &str) -> Option<Result<Vec<u8>, DecodeError>> {
+        self.entry(name).map(|entry| self.extract_entry(entry))
+    }
+    /// Decompresses the file `entry` describes, only decoding the blocks
+    /// holding it
+    ///
+    /// # Errors
+    /// Returns an error if the blocks holding the file aren't a valid encoding
+    /// under the archive's tree, or end before the file does
+    pub fn extract_entry(&self, entry: &Entry) -> Result<Vec<u8>, DecodeError> {
+        let contents = self
+            .file
+            .decompress_range(entry.offset..entry.offset + entry.len)?;
+
+        if contents.len() == entry.len {
+            Ok(contents)
+        } else {
+            Err(DecodeError::UnexpectedEof)
+        }
+    }
+    /// Extracts every file into the directory `dir`, creating any directories
+    /// needed
+    ///
+    /// # Errors
+    /// Fails if a file can't be decompressed or written, or if its name is an
+    /// absolute path, or would leave `dir`
+    pub fn extract_to<P: AsRef<Path>>(&self, dir: P) -> io::Result<()> {
+        let contents = self
+            .file
+            .decompress()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "the archive is corrupt"))?;
+
+        for entry in self.entries() {
+            let name = Path::new(&entry.name);
+            if !name
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} isn't a safe path to extract to", entry.name),
+                ));
+            }
+
+            let path = dir.as_ref().join(name);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let end = entry.offset + entry.len;
+            let file = contents.get(entry.offset..end).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "the archive is truncated")
+            })?;
+            fs::write(path, file)?;
+        }
+
+        Ok(())
+    }
+    /// Decompresses every file, so more can be added to them
+    ///
+    /// # Errors
+    /// Returns an error if any file can't be decompressed
+    pub fn into_builder(self) -> Result<ArchiveBuilder, DecodeError> {
+        let block_size = self.file.block_size().unwrap_or(DEFAULT_BLOCK_SIZE);
+
+        let mut builder = ArchiveBuilder::new().block_size(block_size);
+        for entry in self.entries() {
+            builder = builder.add(entry.name.clone(), self.extract_entry(entry)?);
+        }
+        Ok(builder)
+    }
+    /// Returns the RZ file holding the archive
+    #[must_use]
+    pub const fn file(&self) -> &RZFile<'a, u8> {
+        &self.file
+    }
+    /// Saves the archive to the file at `path`
+    ///
+    /// # Errors
+    /// Fails if there's any issue with writing to the file at path
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.file.write_to(&mut file)?;
+        file.flush()
+    }
+    /// Writes the archive to `writer`
+    ///
+    /// # Errors
+    /// Fails if there's any issue with writing to `writer`
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.file.write_to(writer)
+    }
+}
+
+impl RZArchive<'static> {
+    /// Reads an archive from `reader`
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or the file is
+    /// malformed or not an archive
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, RzError> {
+        Self::try_from(RZFile::read_from(reader)?)
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for RZArchive<'a> {
+    type Error = RzError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::try_from(RZFile::try_from(buf)?)
+    }
+}
+
+impl<'a> TryFrom<RZFile<'a, u8>> for RZArchive<'a> {
+    type Error = RzError;
+
+    fn try_from(file: RZFile<'a, u8>) -> Result<Self, Self::Error> {
+        if file.entries.is_none() {
+            Err(malformed("the file isn't an archive"))
+        } else {
+            Ok(Self { file })
+        }
+    }
+}
+
+/// Writes the entry table of an archive
+pub fn write_entries<W: Write>(entries: &[Entry], mut writer: W) -> io::Result<()> {
+    let count = u32::try_from(entries.len()).map_err(io::Error::other)?;
+    writer.write_all(&count.to_be_bytes())?;
+
+    for entry in entries {
+        let name_len = u16::try_from(entry.name.len()).map_err(io::Error::other)?;
+        writer.write_all(&name_len.to_be_bytes())?;
+        writer.write_all(entry.name.as_bytes())?;
+        writer.write_all(&(entry.len as u64).to_be_bytes())?;
+    }
+    Ok(())
+}
+
+/// Reads the entry table of an archive
+pub fn read_entries<R: Read>(mut reader: R) -> Result<Vec<Entry>, RzError> {
+    let mut count = [0; 4];
+    reader
+        .read_exact(&mut count)
+        .map_err(RzError::truncated("entry table"))?;
+
+    // Not trusting the count enough to allocate it all upfront
+    let mut entries = Vec::new();
+    let mut offset = 0_usize;
+    for _ in 0..u32::from_be_bytes(count) {
+        let mut name_len = [0; 2];
+        reader
+            .read_exact(&mut name_len)
+            .map_err(RzError::truncated("entry table"))?;
+        let mut name = vec![0; usize::from(u16::from_be_bytes(name_len))];
+        reader
+            .read_exact(&mut name)
+            .map_err(RzError::truncated("entry table"))?;
+        let name =
+            String::from_utf8(name).map_err(|_| malformed("a file's name isn't valid UTF-8"))?;
+
+        let mut len = [0; 8];
+        reader
+            .read_exact(&mut len)
+            .map_err(RzError::truncated("entry table"))?;
+        let len = usize::try_from(u64::from_be_bytes(len))
+            .ok()
+            .filter(|len| offset.checked_add(*len).is_some())
+            .ok_or_else(|| malformed(format!("{name} is too large to extract")))?;
+
+        entries.push(Entry { name, offset, len });
+        offset += len;
+    }
+
+    Ok(entries)
+}
@@ -0,0 +1,208 @@
+//! Compressing the values of Arrow `StringArray`s and `BinaryArray`s, as a
+//! column codec for analytics files.
+//!
+//! The column is coded dictionary style, with each distinct value as a
+//! single symbol of the tree, so a column repeating a few values, as most
+//! categorical columns do, takes only a few bits for each. Null values
+//! aren't coded at all, with the validity bitmap stored as it is:
+//! ```
+//! use arrow_array::StringArray;
+//! use huffman_comprs::arrow::ArrowColumn;
+//!
+//! let countries = StringArray::from(vec![
+//!     Some("GB"),
+//!     Some("FR"),
+//!     Some("GB"),
+//!     Some("DE"),
+//!     Some("GB"),
+//!     Some("FR"),
+//!     None,
+//! ]);
+//!
+//! let column = ArrowColumn::encode(&countries);
+//! let mut bytes = Vec::new();
+//! column.write_to(&mut bytes).unwrap();
+//!
+//! let read = ArrowColumn::read_from(bytes.as_slice()).unwrap();
+//! let decoded: StringArray = read.decode().unwrap();
+//! assert_eq!(countries, decoded);
+//! ```
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+
+use arrow_array::{types::ByteArrayType, Array, GenericByteArray};
+use arrow_buffer::{
+    ArrowNativeType, BooleanBuffer, Buffer, NullBuffer, OffsetBuffer, ScalarBuffer,
+};
+
+use crate::{rz::malformed, DecodeError, DecodeOptions, Huffman, RZFile, RzError};
+
+/// Whether bit `index` of `validity` is set, or every value is valid if
+/// there's no bitmap
+fn is_valid(validity: Option<&[u8]>, index: usize) -> bool {
+    validity.is_none_or(|validity| validity[index / 8] >> (index % 8) & 1 == 1)
+}
+
+/// The values of an Arrow array of variable length values, such as a
+/// `StringArray` or `BinaryArray`, compressed with a tree holding each
+/// distinct value as a symbol, alongside the validity bitmap
+#[derive(Clone, Debug)]
+pub struct ArrowColumn {
+    len: usize,
+    validity: Option<Vec<u8>>,
+    /// The values that aren't null
+    file: RZFile<'static, Vec<u8>>,
+}
+
+impl ArrowColumn {
+    /// Compresses the values of `array` that aren't null. Any array of
+    /// variable length values can be given, including the `Large` arrays
+    /// with `i64` offsets, and slices of larger arrays
+    ///
+    /// # Panics
+    /// Never panics, as every value is in the tree built from them
+    #[must_use]
+    pub fn encode<A: ByteArrayType>(array: &GenericByteArray<A>) -> Self {
+        let values: Vec<Vec<u8>> = array
+            .iter()
+            .flatten()
+            .map(|value| AsRef::<[u8]>::as_ref(value).to_vec())
+            .collect();
+        let file = RZFile::compress(Huffman::from(&values), &values)
+            .expect("every value is in the tree built from them")
+            .with_checksum(&values);
+
+        Self {
+            len: array.len(),
+            // Sliced, so the bitmap starts at the first value
+            validity: array
+                .nulls()
+                .map(|nulls| nulls.inner().sliced()[..array.len().div_ceil(8)].to_vec()),
+            file,
+        }
+    }
+    /// Decompresses the values back into an array, of whichever kind is
+    /// asked for, so a column of strings can be decoded as binary too. The
+    /// offsets start at 0, and null values are given no bytes
+    ///
+    /// # Errors
+    /// Returns an error if the values can't be decoded, or there are more or
+    /// fewer of them than the validity bitmap says aren't null, they're too
+    /// long for the array's offsets, or aren't valid for the array, such as
+    /// invalid UTF-8 in a `StringArray`
+    pub fn decode<A: ByteArrayType>(&self) -> Result<GenericByteArray<A>, RzError> {
+        let decoded = self.file.decompress_with(&DecodeOptions::new())?;
+        let mut decoded = decoded.into_iter();
+
+        // Without a bitmap, the length read isn't bounded by the bytes of the
+        // column, so can't be trusted to allocate with
+        let mut offsets = Vec::with_capacity(self.len.min(decoded.len()) + 1);
+        let mut values = Vec::new();
+        offsets.push(A::Offset::usize_as(0));
+        for index in 0..self.len {
+            if is_valid(self.validity.as_deref(), index) {
+                let value = decoded.next().ok_or(DecodeError::UnexpectedEof)?;
+                values.extend_from_slice(&value);
+            }
+            let offset = A::Offset::from_usize(values.len()).ok_or(DecodeError::ValueOverflow)?;
+            offsets.push(offset);
+        }
+        if decoded.next().is_some() {
+            return Err(DecodeError::DanglingBits.into());
+        }
+
+        let nulls = self.validity.as_ref().map(|validity| {
+            NullBuffer::new(BooleanBuffer::new(
+                Buffer::from_vec(validity.clone()),
+                0,
+                self.len,
+            ))
+        });
+        GenericByteArray::try_new(
+            OffsetBuffer::new(ScalarBuffer::from(offsets)),
+            Buffer::from_vec(values),
+            nulls,
+        )
+        .map_err(|e| malformed(format!("the values don't make a valid array: {e}")))
+    }
+    /// How many values the column holds, nulls included
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    /// Whether the column holds no values
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The tree coding the values, holding each distinct value once
+    #[must_use]
+    pub const fn tree(&self) -> &Huffman<Vec<u8>> {
+        &self.file.tree
+    }
+    /// Writes the column as the number of values, as 8 bytes, big endian,
+    /// then a byte of `1` followed by the validity bitmap if there is one, or
+    /// `0` if not, then an RZ file of the values that aren't null
+    ///
+    /// # Errors
+    /// Fails if there's any issue writing to `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.len as u64).to_be_bytes())?;
+        match &self.validity {
+            Some(validity) => {
+                writer.write_all(&[1])?;
+                writer.write_all(validity)?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        self.file.write_to(writer)
+    }
+    /// Reads a column written by [`write_to`](#method.write_to)
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or the column is
+    /// malformed
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, RzError> {
+        let mut len = [0; 8];
+        reader
+            .read_exact(&mut len)
+            .map_err(RzError::truncated("column length"))?;
+        let len = usize::try_from(u64::from_be_bytes(len))
+            .map_err(|_| malformed("the column is too long"))?;
+
+        let mut flag = [0];
+        reader
+            .read_exact(&mut flag)
+            .map_err(RzError::truncated("validity flag"))?;
+        let validity = match flag[0] {
+            0 => None,
+            1 => {
+                // Read rather than allocated upfront, so a malformed length
+                // can't make the reader hold more than there is
+                let mut validity = Vec::new();
+                reader
+                    .by_ref()
+                    .take(len.div_ceil(8) as u64)
+                    .read_to_end(&mut validity)?;
+                if validity.len() < len.div_ceil(8) {
+                    return Err(RzError::TruncatedHeader("validity bitmap"));
+                }
+                Some(validity)
+            }
+            flag => {
+                return Err(malformed(format!(
+                    "the validity flag is {flag}, rather than 0 or 1"
+                )))
+            }
+        };
+
+        Ok(Self {
+            len,
+            validity,
+            file: RZFile::read_from(reader)?,
+        })
+    }
+}
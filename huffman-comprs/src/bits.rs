@@ -0,0 +1,214 @@
+//! Packing bits into bytes, and reading them back out.
+//!
+//! Compressed data is a sequence of bits, which is stored as bytes with the
+//! last byte padded with zeros. [`BitWriter`](struct.BitWriter.html) packs
+//! bits, returning how many zeros pad the end, and
+//! [`BitReader`](struct.BitReader.html) reads them back, leaving the padding
+//! out:
+//! ```
+//! use huffman_comprs::{bits::{BitReader, BitWriter}, Huffman};
+//!
+//! let input = b"Packed into bytes".to_vec();
+//! let huffman = Huffman::from(&input);
+//!
+//! let mut writer = BitWriter::new();
+//! writer.extend(&huffman.compress(&input).unwrap());
+//! let (bytes, zeros) = writer.finish();
+//!
+//! let bits: Vec<bool> = BitReader::new(&bytes).with_zeros(zeros).collect();
+//! assert_eq!(huffman.compress(&input).unwrap(), bits);
+//! ```
+//!
+//! Bits are packed most significant first, as
+//! [`Huffman::compress_into_bytes`](../struct.Huffman.html#method.compress_into_bytes)
+//! does, unless [`BitOrder::LsbFirst`](enum.BitOrder.html#variant.LsbFirst)
+//! is given. The data section of an [`RZFile`](../struct.RZFile.html) holds
+//! its bits in reverse, least significant first, so reading it with
+//! `LsbFirst` gives the bits ready for
+//! [`Huffman::reconstruct`](../struct.Huffman.html#method.reconstruct)
+
+/// Which end of each byte is filled first
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitOrder {
+    /// The first bit is the most significant, `0x80`
+    #[default]
+    MsbFirst,
+    /// The first bit is the least significant, `0x01`
+    LsbFirst,
+}
+
+impl BitOrder {
+    /// How far the bit at `index` within a byte is shifted
+    const fn shift(self, index: usize) -> usize {
+        match self {
+            Self::MsbFirst => 7 - index,
+            Self::LsbFirst => index,
+        }
+    }
+}
+
+/// Packs bits into bytes, padding the last byte with zeros
+#[derive(Clone, Debug, Default)]
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    /// The byte being filled, and how many of its bits have been
+    byte: u8,
+    filled: usize,
+    order: BitOrder,
+}
+
+impl BitWriter {
+    /// Creates a writer packing bits most significant first
+    #[must_use]
+    pub const fn new() -> Self {
+        Self::with_order(BitOrder::MsbFirst)
+    }
+    /// Creates a writer packing bits in the given `order`
+    #[must_use]
+    pub const fn with_order(order: BitOrder) -> Self {
+        Self {
+            bytes: Vec::new(),
+            byte: 0,
+            filled: 0,
+            order,
+        }
+    }
+    /// Writes a single bit
+    pub fn write_bit(&mut self, bit: bool) {
+        self.byte |= u8::from(bit) << self.order.shift(self.filled);
+        self.filled += 1;
+        if self.filled == 8 {
+            self.bytes.push(self.byte);
+            self.byte = 0;
+            self.filled = 0;
+        }
+    }
+    /// Writes every bit of `bits`, in order
+    pub fn write_bits(&mut self, bits: &[bool]) {
+        self.extend(bits.iter().copied());
+    }
+    /// Writes the lowest `len` bits of `value`, most significant first,
+    /// whichever order bits are packed into bytes in
+    ///
+    /// # Panics
+    /// Panics if `len` is more than 64
+    pub fn write_int(&mut self, value: u64, len: u32) {
+        assert!(len <= 64, "a u64 has 64 bits, not {}", len);
+        for shift in (0..len).rev() {
+            self.write_bit(value >> shift & 1 == 1);
+        }
+    }
+    /// How many bits have been written
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.bytes.len() * 8 + self.filled
+    }
+    /// Whether no bits have been written
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Pads the last byte with zeros, returning the bytes and how many zeros
+    /// were added
+    #[must_use]
+    pub fn finish(mut self) -> (Vec<u8>, u8) {
+        if self.filled == 0 {
+            return (self.bytes, 0);
+        }
+        self.bytes.push(self.byte);
+        // `filled` is between 1 and 7, so this fits
+        #[allow(clippy::cast_possible_truncation)]
+        let zeros = (8 - self.filled) as u8;
+        (self.bytes, zeros)
+    }
+}
+
+impl Extend<bool> for BitWriter {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, bits: I) {
+        for bit in bits {
+            self.write_bit(bit);
+        }
+    }
+}
+
+/// Reads the bits packed into bytes, in order, as an iterator of `bool`s
+#[derive(Clone, Debug)]
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    /// The index of the next bit to read, and of the bit after the last
+    position: usize,
+    end: usize,
+    order: BitOrder,
+}
+
+impl<'a> BitReader<'a> {
+    /// Creates a reader of every bit of `bytes`, most significant first
+    #[must_use]
+    pub const fn new(bytes: &'a [u8]) -> Self {
+        Self::with_order(bytes, BitOrder::MsbFirst)
+    }
+    /// Creates a reader of every bit of `bytes`, in the given `order`
+    #[must_use]
+    pub const fn with_order(bytes: &'a [u8], order: BitOrder) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            end: bytes.len() * 8,
+            order,
+        }
+    }
+    /// Leaves out the last `zeros` bits, being the padding added by
+    /// [`BitWriter::finish`](struct.BitWriter.html#method.finish)
+    #[must_use]
+    pub fn with_zeros(mut self, zeros: u8) -> Self {
+        self.end = self
+            .end
+            .saturating_sub(usize::from(zeros))
+            .max(self.position);
+        self
+    }
+    /// Reads the next bit, or `None` once every bit has been read
+    pub fn read_bit(&mut self) -> Option<bool> {
+        if self.position == self.end {
+            return None;
+        }
+        let byte = self.bytes[self.position / 8];
+        let bit = byte >> self.order.shift(self.position % 8) & 1 == 1;
+        self.position += 1;
+        Some(bit)
+    }
+    /// Reads `len` bits as an integer, most significant first, as
+    /// [`BitWriter::write_int`](struct.BitWriter.html#method.write_int)
+    /// writes them, or `None` if fewer than `len` are left, in which case
+    /// none are read
+    ///
+    /// # Panics
+    /// Panics if `len` is more than 64
+    pub fn read_int(&mut self, len: u32) -> Option<u64> {
+        assert!(len <= 64, "a u64 has 64 bits, not {}", len);
+        if self.remaining() < len as usize {
+            return None;
+        }
+        Some((0..len).fold(0, |value, _| {
+            value << 1 | u64::from(self.read_bit().unwrap_or_default())
+        }))
+    }
+    /// How many bits are left to read
+    #[must_use]
+    pub const fn remaining(&self) -> usize {
+        self.end - self.position
+    }
+}
+
+impl Iterator for BitReader<'_> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        self.read_bit()
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining(), Some(self.remaining()))
+    }
+}
+
+impl ExactSizeIterator for BitReader<'_> {}
@@ -0,0 +1,262 @@
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{count, Huffman, TreeError};
+
+/// How to choose between nodes of equal frequency while building a tree
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TieBreak {
+    /// Merge the most recently created node first. This is what
+    /// [`Huffman::from`](struct.Huffman.html) does
+    #[default]
+    Newest,
+    /// Merge the oldest node first, which favours merging leaves before
+    /// combined nodes, giving a tree with the least variance in code length
+    Oldest,
+}
+
+/// Builds a [`Huffman`](struct.Huffman.html) tree with more control than the
+/// `From` implementations give.
+/// # Examples
+/// ```
+/// use huffman_comprs::{HuffmanBuilder, TieBreak};
+///
+/// let input: Vec<char> = "aaaabbbccd!".chars().collect();
+///
+/// let huffman = HuffmanBuilder::new()
+///     .tie_break(TieBreak::Oldest)
+///     .max_depth(3)
+///     .min_freq(2)
+///     .escape('\0')
+///     .canonical(true)
+///     .build(&input);
+///
+/// assert!(huffman.get_code('d').is_none());
+/// assert!(huffman.get_code('\0').unwrap().len() <= 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HuffmanBuilder<T> {
+    tie_break: TieBreak,
+    max_depth: Option<usize>,
+    min_freq: usize,
+    escape: Option<T>,
+    end_of_stream: Option<T>,
+    canonical: bool,
+}
+
+impl<T> HuffmanBuilder<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Creates a builder that gives the same tree as `Huffman::from`
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            tie_break: TieBreak::Newest,
+            max_depth: None,
+            min_freq: 0,
+            escape: None,
+            end_of_stream: None,
+            canonical: false,
+        }
+    }
+    /// Sets how nodes of equal frequency are chosen between
+    #[must_use]
+    pub const fn tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+    /// Limits the length of every code to `max_depth` bits, at the cost of
+    /// slightly worse compression. Frequencies are repeatedly halved until the
+    /// tree fits, much like bzip2 does
+    #[must_use]
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+    /// Leaves out any symbol appearing fewer than `min_freq` times
+    #[must_use]
+    pub const fn min_freq(mut self, min_freq: usize) -> Self {
+        self.min_freq = min_freq;
+        self
+    }
+    /// Adds `escape` to the tree, as a reserved symbol standing in for any
+    /// symbol left out of it. It's given the combined frequency of every
+    /// symbol left out by [`min_freq`](#method.min_freq), or 1 if there are
+    /// none. `escape` should not appear in the input
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // `T` may need dropping
+    pub fn escape(mut self, escape: T) -> Self {
+        self.escape = Some(escape);
+        self
+    }
+    /// Adds `eos` to the tree with a frequency of 1, as a reserved symbol
+    /// marking the end of a stream written by
+    /// [`Huffman::compress_terminated`](struct.Huffman.html#method.compress_terminated).
+    /// `eos` should not appear in the input
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // `T` may need dropping
+    pub fn end_of_stream(mut self, eos: T) -> Self {
+        self.end_of_stream = Some(eos);
+        self
+    }
+    /// Whether to rearrange the tree so its codes are canonical, meaning they
+    /// can be rebuilt from the length of each symbol's code alone
+    #[must_use]
+    pub const fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+    /// Builds a tree from the frequency of each symbol in `input`
+    ///
+    /// # Panics
+    /// Panics if a maximum depth is set that's too small to give each symbol
+    /// its own code
+    #[must_use]
+    pub fn build(&self, input: &[T]) -> Huffman<T> {
+        self.build_from_frequencies(count(input.iter().cloned()))
+    }
+    /// Builds a tree from each symbol paired with how often it appears. Each
+    /// symbol should only appear once
+    ///
+    /// # Panics
+    /// Panics if a maximum depth is set that's too small to give each symbol
+    /// its own code
+    #[must_use]
+    pub fn build_from_frequencies<I>(&self, frequencies: I) -> Huffman<T>
+    where
+        I: IntoIterator<Item = (T, usize)>,
+    {
+        let (mut leaves, left_out): (Vec<_>, Vec<_>) = frequencies
+            .into_iter()
+            .partition(|(_, freq)| *freq >= self.min_freq);
+
+        if let Some(escape) = &self.escape {
+            let freq = left_out.iter().map(|(_, freq)| freq).sum::<usize>();
+            leaves.push((escape.clone(), freq.max(1)));
+        }
+        if let Some(eos) = &self.end_of_stream {
+            leaves.push((eos.clone(), 1));
+        }
+
+        if let Some(max_depth) = self.max_depth {
+            assert!(
+                leaves.len() <= 1 << max_depth.min(usize::BITS as usize - 1),
+                "{} symbols can't fit in a tree of depth {}",
+                leaves.len(),
+                max_depth
+            );
+        }
+
+        let (symbols, freqs): (Vec<T>, Vec<usize>) = leaves.into_iter().unzip();
+
+        // The shape is found over each leaf's index, so symbols are only
+        // moved into the tree once, however many times it's rebuilt
+        let mut weights = freqs.clone();
+        let shape = loop {
+            let shape = Huffman::build(
+                weights.iter().copied().enumerate().collect(),
+                self.tie_break,
+            );
+
+            match self.max_depth {
+                Some(max_depth) if shape.depths().iter().any(|depth| *depth > max_depth) => {
+                    for weight in &mut weights {
+                        *weight = weight.div_ceil(2).max(1);
+                    }
+                }
+                _ => break shape,
+            }
+        };
+
+        if self.canonical {
+            let depths = shape.depths();
+            Huffman::build_canonical(
+                symbols
+                    .into_iter()
+                    .zip(freqs)
+                    .zip(depths)
+                    .map(|((symbol, freq), depth)| (symbol, freq, depth))
+                    .collect(),
+            )
+        } else {
+            // Leaves keep the order they were given in, so the `n`th symbol
+            // belongs to the `n`th node
+            let mut tree = Huffman {
+                contents: symbols,
+                nodes: shape.nodes,
+            };
+            tree.set_frequencies(freqs);
+            tree
+        }
+    }
+    /// Builds a tree from each symbol paired with an arbitrary weight, rather
+    /// than a count, so symbols can be given shorter codes than their
+    /// frequency alone would give them. Only the weights' sizes relative to
+    /// each other matter, so integer weights can be given to
+    /// [`build_from_frequencies`](#method.build_from_frequencies) instead.
+    ///
+    /// Weights are scaled to integers summing to about `u32::MAX`, which the
+    /// tree stores as its frequencies. A weight of `0` is treated as the
+    /// smallest weight possible, so the symbol still gets a code
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::HuffmanBuilder;
+    ///
+    /// let huffman = HuffmanBuilder::new()
+    ///     .build_from_weights(vec![('a', 0.5), ('b', 0.3), ('c', 0.2)])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(1, huffman.get_code('a').unwrap().len());
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if any weight is negative, infinite or NaN
+    ///
+    /// # Panics
+    /// Panics if a maximum depth is set that's too small to give each symbol
+    /// its own code
+    pub fn build_from_weights<I>(&self, weights: I) -> Result<Huffman<T>, TreeError>
+    where
+        I: IntoIterator<Item = (T, f64)>,
+    {
+        let weights: Vec<(T, f64)> = weights.into_iter().collect();
+        if let Some(invalid) = weights
+            .iter()
+            .position(|(_, weight)| !weight.is_finite() || *weight < 0.0)
+        {
+            return Err(TreeError::InvalidWeight(invalid));
+        }
+
+        // Weights are divided by the largest first, so summing them can't
+        // overflow
+        let largest = weights
+            .iter()
+            .map(|(_, weight)| *weight)
+            .fold(0.0, f64::max);
+        let total: f64 = weights.iter().map(|(_, weight)| weight / largest).sum();
+        let scale = if largest > 0.0 {
+            f64::from(u32::MAX) / total
+        } else {
+            0.0
+        };
+
+        // Each scaled weight is between 0 and `u32::MAX`, so fits
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let frequencies = weights.into_iter().map(|(symbol, weight)| {
+            let weight = if largest > 0.0 { weight / largest } else { 0.0 };
+            (symbol, ((weight * scale).round() as usize).max(1))
+        });
+
+        Ok(self.build_from_frequencies(frequencies))
+    }
+}
+
+impl<T> Default for HuffmanBuilder<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
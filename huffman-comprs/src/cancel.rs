@@ -0,0 +1,128 @@
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::DecodeError;
+
+/// How many symbols are coded between each check of a `Cancel` flag, so a
+/// single large block, or input, can still be stopped part way through
+pub const CHECK_INTERVAL: usize = 64 * 1024;
+
+/// A flag for stopping long-running operations from another thread, such as
+/// when the user of a desktop app presses cancel.
+///
+/// Clones share the same flag, so one clone can be handed to the operation,
+/// and another kept to [`cancel`](#method.cancel) it. Operations check the
+/// flag between chunks, blocks, or runs of symbols, and fail with
+/// [`DecodeError::Cancelled`](enum.DecodeError.html#variant.Cancelled) once
+/// it's set. A `Cancel` is given to decoding with
+/// [`DecodeOptions::cancel`](struct.DecodeOptions.html#method.cancel), to
+/// compressing with
+/// [`RZFile::compress_with`](struct.RZFile.html#method.compress_with), and to
+/// anything reading or writing a stream by wrapping the stream with
+/// [`wrap`](#method.wrap)
+/// # Examples
+/// ```
+/// use huffman_comprs::{copy_encode, Cancel, DecodeError, Huffman};
+///
+/// let input = b"Never finished".repeat(1000);
+/// let tree = Huffman::from(&input);
+///
+/// let cancel = Cancel::new();
+/// cancel.cancel();
+///
+/// let error = copy_encode(cancel.wrap(input.as_slice()), Vec::new(), &tree).unwrap_err();
+/// assert_eq!(Some(DecodeError::Cancelled), DecodeError::from_io(&error));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Cancel(Arc<AtomicBool>);
+
+impl Cancel {
+    /// A flag that isn't set yet
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Sets the flag, so every operation given it, or a clone of it, stops
+    /// at the next chunk or block it checks
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+    /// Whether the flag has been set
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+    /// Fails with [`DecodeError::Cancelled`](enum.DecodeError.html#variant.Cancelled)
+    /// if the flag has been set
+    ///
+    /// # Errors
+    /// Returns an error if the flag has been set
+    pub fn check(&self) -> Result<(), DecodeError> {
+        if self.is_cancelled() {
+            Err(DecodeError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+    /// Wraps a reader or writer, so every read from or write to it fails
+    /// once the flag is set, stopping whatever is streaming through it, such
+    /// as [`copy_decode`](fn.copy_decode.html) or a
+    /// [`Pipeline`](struct.Pipeline.html)
+    #[must_use]
+    pub fn wrap<T>(&self, inner: T) -> Cancellable<T> {
+        Cancellable {
+            inner,
+            cancel: self.clone(),
+        }
+    }
+    fn check_io(&self) -> io::Result<()> {
+        self.check().map_err(io::Error::other)
+    }
+}
+
+/// Clones of the same flag are equal, even if neither has been set
+impl PartialEq for Cancel {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Cancel {}
+
+/// A reader or writer that fails once a [`Cancel`](struct.Cancel.html) is
+/// set, created by [`Cancel::wrap`](struct.Cancel.html#method.wrap)
+#[derive(Debug)]
+pub struct Cancellable<T> {
+    inner: T,
+    cancel: Cancel,
+}
+
+impl<T> Cancellable<T> {
+    /// Returns the reader or writer wrapped
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<R: Read> Read for Cancellable<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.cancel.check_io()?;
+        self.inner.read(buf)
+    }
+}
+
+impl<W: Write> Write for Cancellable<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.cancel.check_io()?;
+        self.inner.write(buf)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.cancel.check_io()?;
+        self.inner.flush()
+    }
+}
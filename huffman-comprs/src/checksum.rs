@@ -0,0 +1,81 @@
+use std::io::{self, Write};
+
+/// The lookup table for the reflected IEEE polynomial, as used by zlib, gzip
+/// and PNG
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i: u32 = 0;
+    while i < 256 {
+        let mut crc = i;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                crc >> 1 ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i as usize] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// A running CRC-32 checksum. Bytes are added by writing to it, so values can
+/// be serialized straight into it
+#[derive(Clone, Copy, Debug)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(!0)
+    }
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = TABLE[((self.0 ^ u32::from(*byte)) & 0xff) as usize] ^ self.0 >> 8;
+        }
+    }
+    pub const fn finish(self) -> u32 {
+        !self.0
+    }
+}
+
+impl Write for Crc32 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A running 64-bit FNV-1a hash. As with [`Crc32`], bytes are added by writing
+/// to it
+#[derive(Clone, Copy, Debug)]
+pub struct Fnv64(u64);
+
+impl Fnv64 {
+    pub const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    pub const fn finish(self) -> u64 {
+        self.0
+    }
+}
+
+impl Write for Fnv64 {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
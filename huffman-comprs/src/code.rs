@@ -0,0 +1,184 @@
+use std::{fmt, iter::FromIterator};
+
+use crate::bits::BitReader;
+
+/// A sequence of bits, such as a symbol's code, or compressed data, packed 8
+/// to a byte, most significant first.
+///
+/// Converts to and from `Vec<bool>`, for code written before it existed
+///
+/// # Examples
+/// ```
+/// use huffman_comprs::{Code, Huffman};
+///
+/// let huffman = Huffman::from("aabbbc");
+/// let code = huffman.compress_str("cab").unwrap();
+///
+/// assert_eq!(5, code.len());
+/// assert_eq!("00011", code.to_string());
+/// assert_eq!(vec![false, false, false, true, true], Vec::from(code));
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct Code {
+    /// The bits, with any bits of the last byte past `len` unset
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Code {
+    /// Creates an empty code
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            len: 0,
+        }
+    }
+    /// Creates an empty code, with room for `bits` bits before reallocating
+    #[must_use]
+    pub fn with_capacity(bits: usize) -> Self {
+        Self {
+            bytes: Vec::with_capacity(bits.div_ceil(8)),
+            len: 0,
+        }
+    }
+    /// How many bits there are
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    /// Whether there are no bits
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The bit at `index`, or `None` if there are fewer bits than that
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<bool> {
+        (index < self.len).then(|| self.bytes[index / 8] >> (7 - index % 8) & 1 == 1)
+    }
+    /// Adds `bit` to the end
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            self.bytes[self.len / 8] |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+    }
+    /// Removes the last bit, returning it, or `None` if there are no bits
+    pub fn pop(&mut self) -> Option<bool> {
+        let bit = self.get(self.len.checked_sub(1)?)?;
+        self.len -= 1;
+        if self.len.is_multiple_of(8) {
+            self.bytes.pop();
+        } else {
+            self.bytes[self.len / 8] &= !(0x80 >> (self.len % 8));
+        }
+        Some(bit)
+    }
+    /// Reverses the order of the bits, as
+    /// [`Huffman::reconstruct`](struct.Huffman.html#method.reconstruct)
+    /// reads its input back to front
+    pub fn reverse(&mut self) {
+        let mut bits = self.to_vec();
+        bits.reverse();
+        *self = Self::from(bits);
+    }
+    /// Iterates over the bits, in order
+    #[must_use]
+    pub fn iter(&self) -> BitReader<'_> {
+        BitReader::new(&self.bytes).with_zeros(self.zeros())
+    }
+    /// The bits packed into bytes, most significant first, with the last
+    /// byte padded with [`zeros`](#method.zeros) zeros
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    /// How many zeros pad the last byte of [`as_bytes`](#method.as_bytes)
+    #[must_use]
+    pub const fn zeros(&self) -> u8 {
+        // Always less than 8, so the cast can't truncate
+        #[allow(clippy::cast_possible_truncation)]
+        let zeros = ((8 - self.len % 8) % 8) as u8;
+        zeros
+    }
+    /// Copies the bits into a `Vec<bool>`
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<bool> {
+        self.iter().collect()
+    }
+}
+
+impl fmt::Debug for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Code({self})")
+    }
+}
+
+/// Writes the bits as `0`s and `1`s
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for bit in self {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&[bool]> for Code {
+    fn from(bits: &[bool]) -> Self {
+        bits.iter().copied().collect()
+    }
+}
+
+impl From<Vec<bool>> for Code {
+    fn from(bits: Vec<bool>) -> Self {
+        Self::from(bits.as_slice())
+    }
+}
+
+impl From<Code> for Vec<bool> {
+    fn from(code: Code) -> Self {
+        code.to_vec()
+    }
+}
+
+impl FromIterator<bool> for Code {
+    fn from_iter<I: IntoIterator<Item = bool>>(bits: I) -> Self {
+        let mut code = Self::new();
+        code.extend(bits);
+        code
+    }
+}
+
+impl Extend<bool> for Code {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, bits: I) {
+        for bit in bits {
+            self.push(bit);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a Code {
+    type Item = bool;
+    type IntoIter = BitReader<'a>;
+
+    fn into_iter(self) -> BitReader<'a> {
+        self.iter()
+    }
+}
+
+impl PartialEq<Vec<bool>> for Code {
+    fn eq(&self, other: &Vec<bool>) -> bool {
+        self.len == other.len() && self.iter().eq(other.iter().copied())
+    }
+}
+
+impl PartialEq<Code> for Vec<bool> {
+    fn eq(&self, other: &Code) -> bool {
+        other == self
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{trace, Code, DecodeError, DecodeOptions, Huffman, Tokenizer};
+
+/// How many bits the decode table looks up at once
+const TABLE_BITS: usize = 8;
+
+/// Compresses with a table of codes built once, so it's cheap to reuse
+/// across many inputs.
+///
+/// [`Huffman::compress`](struct.Huffman.html#method.compress) builds the
+/// table again for every call, which adds up when many small inputs are
+/// compressed with the same tree
+/// # Examples
+/// ```
+/// use huffman_comprs::{Decoder, Encoder, Huffman};
+///
+/// let tree = Huffman::from(&b"{\"id\":120,\"ok\":true}".to_vec());
+/// let encoder = Encoder::new(&tree);
+/// let decoder = Decoder::new(&tree);
+///
+/// for message in &[b"{\"id\":1,\"ok\":true}", b"{\"id\":2,\"ok\":true}"] {
+///     let mut bytes = Vec::new();
+///     let zeros = encoder.encode_into_bytes(*message, &mut bytes).unwrap();
+///
+///     assert_eq!(Ok(message.to_vec()), decoder.decode(&bytes, zeros));
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Encoder<T> {
+    codes: BTreeMap<T, Vec<bool>>,
+}
+
+impl<T> Encoder<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Builds the table of codes for `tree`
+    #[must_use]
+    pub fn new(tree: &Huffman<T>) -> Self {
+        Self {
+            codes: tree.to_btree(),
+        }
+    }
+    /// The code of `symbol`, borrowed from the table, so looking up one
+    /// symbol at a time doesn't allocate as
+    /// [`Huffman::get_code`](struct.Huffman.html#method.get_code) does. As
+    /// with [`Huffman::to_btree`](struct.Huffman.html#method.to_btree), a
+    /// tree of a single symbol gives it an empty code
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Encoder, Huffman};
+    ///
+    /// let tree = Huffman::from("aabc");
+    /// let encoder = Encoder::new(&tree);
+    ///
+    /// assert_eq!(Some(&[true][..]), encoder.code_of(&'a'));
+    /// assert_eq!(None, encoder.code_of(&'z'));
+    /// ```
+    #[must_use]
+    pub fn code_of(&self, symbol: &T) -> Option<&[bool]> {
+        self.codes.get(symbol).map(Vec::as_slice)
+    }
+    /// Compresses `input` to bits, as
+    /// [`Huffman::compress`](struct.Huffman.html#method.compress) does
+    ///
+    /// # Errors
+    /// Returns `None` if any of the symbols in `input` are not contained in
+    /// the tree
+    #[must_use]
+    pub fn encode(&self, input: &[T]) -> Option<Code> {
+        trace::span!("encode", symbols = input.len());
+        let mut output = Code::with_capacity(input.len());
+
+        for symbol in input {
+            output.extend(self.codes.get(symbol)?.iter().copied());
+        }
+
+        trace::event!(bits = output.len(), "encoded");
+        Some(output)
+    }
+    /// Compresses each token `tokenizer` splits `input` into, as
+    /// [`Huffman::compress_tokens`](struct.Huffman.html#method.compress_tokens)
+    /// does
+    ///
+    /// # Errors
+    /// Returns `None` if any of the tokens are not contained in the tree
+    #[must_use]
+    pub fn encode_tokens<K>(&self, input: &str, tokenizer: &K) -> Option<Code>
+    where
+        K: Tokenizer<Token = T>,
+    {
+        let mut output = Code::with_capacity(input.len());
+
+        for token in tokenizer.tokens(input) {
+            output.extend(self.codes.get(&token)?.iter().copied());
+        }
+
+        Some(output)
+    }
+    /// Compresses `input`, appending the bits to `output`
+    ///
+    /// # Errors
+    /// Returns `None`, leaving `output` as it was, if any of the symbols in
+    /// `input` are not contained in the tree
+    pub fn encode_into(&self, input: &[T], output: &mut Vec<bool>) -> Option<()> {
+        let start = output.len();
+
+        for symbol in input {
+            if let Some(code) = self.codes.get(symbol) {
+                output.extend_from_slice(code);
+            } else {
+                output.truncate(start);
+                return None;
+            }
+        }
+
+        Some(())
+    }
+    /// Compresses `input`, appending the bits to `output` packed into bytes,
+    /// most significant bit first, as
+    /// [`Huffman::compress_into_bytes`](struct.Huffman.html#method.compress_into_bytes)
+    /// does. Returns how many zeros pad the last byte
+    ///
+    /// # Errors
+    /// Returns `None`, leaving `output` as it was, if any of the symbols in
+    /// `input` are not contained in the tree
+    pub fn encode_into_bytes(&self, input: &[T], output: &mut Vec<u8>) -> Option<u8> {
+        let start = output.len();
+
+        let mut byte = 0_u8;
+        let mut filled = 0_u8;
+        for symbol in input {
+            let Some(code) = self.codes.get(symbol) else {
+                output.truncate(start);
+                return None;
+            };
+            for bit in code {
+                byte = byte << 1 | u8::from(*bit);
+                filled += 1;
+                if filled == 8 {
+                    output.push(byte);
+                    byte = 0;
+                    filled = 0;
+                }
+            }
+        }
+
+        if filled == 0 {
+            Some(0)
+        } else {
+            output.push(byte << (8 - filled));
+            Some(8 - filled)
+        }
+    }
+}
+
+/// Where decoding the next [`TABLE_BITS`] bits from the root leads
+#[derive(Clone, Copy, Debug)]
+enum Step {
+    /// A leaf is reached after the first `len` bits
+    Leaf { leaf: u32, len: u8 },
+    /// Every bit is used, ending at this node
+    Node(u32),
+}
+
+/// Decompresses bytes packed by an [`Encoder`](struct.Encoder.html), with a
+/// table built once, so it's cheap to reuse across many inputs.
+///
+/// Codes are looked up 8 bits at a time, rather than one bit at a time as
+/// [`Huffman::reconstruct`](struct.Huffman.html#method.reconstruct) does,
+/// only walking the tree for codes longer than that.
+///
+/// Input arriving a piece at a time, such as from the network, can be
+/// decoded as it arrives with [`feed`](#method.feed), then
+/// [`finish`](#method.finish) once it's all arrived
+/// # Examples
+/// ```
+/// use huffman_comprs::{Decoder, Encoder, Huffman};
+///
+/// let input = b"Decoded a packet at a time".to_vec();
+/// let tree = Huffman::from(&input);
+///
+/// let mut bytes = Vec::new();
+/// let zeros = Encoder::new(&tree).encode_into_bytes(&input, &mut bytes).unwrap();
+///
+/// let mut decoder = Decoder::new(&tree);
+/// let mut output = Vec::new();
+/// for packet in bytes.chunks(3) {
+///     output.extend(decoder.feed(packet));
+/// }
+/// output.extend(decoder.finish(zeros).unwrap());
+///
+/// assert_eq!(input, output);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Decoder<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    tree: Huffman<T>,
+    /// Indexed by the next bits, most significant first. Empty if the tree
+    /// has fewer than 2 symbols, so no codes
+    table: Vec<Step>,
+    /// The node reached part way through a code, when the bits fed so far
+    /// end inside one
+    partial: Option<usize>,
+    /// The last byte fed, kept back as it may be padded
+    held: Option<u8>,
+    /// Set when bits were fed that can't be decoded, returned by `finish`
+    error: Option<DecodeError>,
+}
+
+impl<T> Decoder<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Builds the decode table for `tree`
+    #[must_use]
+    pub fn new(tree: &Huffman<T>) -> Self {
+        let table = match tree.root() {
+            Some(root) if tree.children(root).is_some() => (0..1 << TABLE_BITS)
+                .map(|bits: usize| {
+                    let mut node = root;
+                    for len in 0..=TABLE_BITS {
+                        match tree.children(node) {
+                            // `len` is at most `TABLE_BITS`, so fits
+                            #[allow(clippy::cast_possible_truncation)]
+                            None => {
+                                return Step::Leaf {
+                                    leaf: crate::index(node),
+                                    len: len as u8,
+                                }
+                            }
+                            Some(_) if len == TABLE_BITS => break,
+                            Some([left, right]) => {
+                                node = if bits >> (TABLE_BITS - 1 - len) & 1 == 1 {
+                                    right
+                                } else {
+                                    left
+                                };
+                            }
+                        }
+                    }
+                    Step::Node(crate::index(node))
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Self {
+            tree: tree.clone(),
+            table,
+            partial: None,
+            held: None,
+            error: None,
+        }
+    }
+    /// Decompresses `bytes`, the last of which is padded with `zeros` zeros,
+    /// as written by [`Encoder::encode_into_bytes`](struct.Encoder.html#method.encode_into_bytes)
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` isn't a valid encoding, such as if it ends
+    /// part way through a code, or the padding is wrong
+    pub fn decode(&self, bytes: &[u8], zeros: u8) -> Result<Vec<T>, DecodeError> {
+        self.decode_with(bytes, zeros, &DecodeOptions::new())
+    }
+    /// Decompresses `bytes` as [`decode`](#method.decode) does, applying the
+    /// limits in `options`
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` isn't a valid encoding, or if decoding it
+    /// would break one of the limits in `options`
+    pub fn decode_with(
+        &self,
+        bytes: &[u8],
+        zeros: u8,
+        options: &DecodeOptions,
+    ) -> Result<Vec<T>, DecodeError> {
+        let mut output = Vec::with_capacity(bytes.len());
+
+        self.decode_into(bytes, zeros, &mut output, options)?;
+
+        Ok(output)
+    }
+    /// Decompresses `bytes` as [`decode_with`](#method.decode_with) does,
+    /// appending the symbols to `output`, so the same buffer can be reused
+    /// across many calls
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` isn't a valid encoding, or if decoding it
+    /// would break one of the limits in `options`, in which case `output`
+    /// will have been extended with every symbol decoded before the error
+    pub fn decode_into(
+        &self,
+        bytes: &[u8],
+        zeros: u8,
+        output: &mut Vec<T>,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError> {
+        check_padding(bytes.last().copied(), zeros)?;
+        let len = bytes.len() * 8 - usize::from(zeros);
+        if len == 0 {
+            return Ok(());
+        }
+        self.check_codes()?;
+
+        let limit = options.max_output_len.unwrap_or(usize::MAX);
+        match self.decode_bits(bytes, len, None, output, limit)? {
+            Some(_) => Err(DecodeError::UnexpectedEof),
+            None => Ok(()),
+        }
+    }
+    /// Decodes `bytes`, which needn't be all of the input, keeping any code
+    /// they end part way through to be finished by the next call. The last
+    /// byte may be padded, so it's kept back until more bytes are fed, or
+    /// [`finish`](#method.finish) says how many zeros pad it
+    pub fn feed(&mut self, bytes: &[u8]) -> Vec<T> {
+        let mut output = Vec::new();
+        let Some((&last, complete)) = bytes.split_last() else {
+            return output;
+        };
+        let held = self.held.replace(last);
+        if self.error.is_some() {
+            return output;
+        }
+
+        for bytes in held
+            .as_slice()
+            .iter()
+            .map(std::slice::from_ref)
+            .chain([complete])
+        {
+            if bytes.is_empty() {
+                continue;
+            }
+            if let Err(e) = self.check_codes() {
+                self.error = Some(e);
+                break;
+            }
+            // Without a limit, decoding can't fail
+            self.partial = self
+                .decode_bits(
+                    bytes,
+                    bytes.len() * 8,
+                    self.partial,
+                    &mut output,
+                    usize::MAX,
+                )
+                .unwrap_or(None);
+        }
+        output
+    }
+    /// Decodes the last byte fed, the end of which is padded with `zeros`
+    /// zeros, leaving the decoder ready for another input
+    ///
+    /// # Errors
+    /// Returns an error if the bytes fed since the last call aren't a valid
+    /// encoding, such as if they end part way through a code, or the padding
+    /// is wrong
+    pub fn finish(&mut self, zeros: u8) -> Result<Vec<T>, DecodeError> {
+        let held = self.held.take();
+        let partial = self.partial.take();
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        check_padding(held, zeros)?;
+
+        let mut output = Vec::new();
+        let len = 8 - usize::from(zeros);
+        if let Some(held) = held {
+            self.check_codes()?;
+            if self
+                .decode_bits(&[held], len, partial, &mut output, usize::MAX)?
+                .is_some()
+            {
+                return Err(DecodeError::UnexpectedEof);
+            }
+        } else if partial.is_some() {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        Ok(output)
+    }
+    /// Fails unless the tree has codes, so there are bits to decode
+    const fn check_codes(&self) -> Result<(), DecodeError> {
+        match self.tree.root() {
+            None => Err(DecodeError::EmptyTree),
+            // A tree of a single symbol gives it an empty code, so no bits
+            // can be decoded
+            Some(_) if self.table.is_empty() => Err(DecodeError::DanglingBits),
+            Some(_) => Ok(()),
+        }
+    }
+    /// Decodes the first `len` bits of `bytes`, continuing the code ended part
+    /// way through at `partial`, if any. Returns the node reached if the bits
+    /// end part way through a code. The tree must have codes
+    fn decode_bits(
+        &self,
+        bytes: &[u8],
+        len: usize,
+        mut partial: Option<usize>,
+        output: &mut Vec<T>,
+        limit: usize,
+    ) -> Result<Option<usize>, DecodeError> {
+        let bit = |position: usize| bytes[position / 8] >> (7 - position % 8) & 1 == 1;
+        let start = output.len();
+        let mut position = 0;
+        loop {
+            let mut node = if let Some(node) = partial.take() {
+                node
+            } else if position == len {
+                return Ok(None);
+            } else if output.len() - start == limit {
+                return Err(DecodeError::OutputLimitExceeded(limit));
+            } else if len - position >= TABLE_BITS {
+                let offset = position % 8;
+                let next = bytes.get(position / 8 + 1).copied().unwrap_or(0);
+                let window = (u16::from(bytes[position / 8]) << 8 | u16::from(next)) << offset;
+                match self.table[usize::from(window >> 8)] {
+                    Step::Leaf { leaf, len } => {
+                        output.push(self.tree.contents[leaf as usize].clone());
+                        position += usize::from(len);
+                        continue;
+                    }
+                    Step::Node(node) => {
+                        position += TABLE_BITS;
+                        node as usize
+                    }
+                }
+            } else {
+                // The tree has codes, so has a root
+                self.tree.root().unwrap()
+            };
+
+            while let Some([left, right]) = self.tree.children(node) {
+                if position == len {
+                    return Ok(Some(node));
+                }
+                node = if bit(position) { right } else { left };
+                position += 1;
+            }
+            output.push(self.tree.contents[node].clone());
+        }
+    }
+}
+
+/// Checks the `zeros` bits padding the end of `last`, the last byte of the
+/// input if there is one, are all unset
+fn check_padding(last: Option<u8>, zeros: u8) -> Result<(), DecodeError> {
+    if zeros > 7
+        || (zeros > 0 && last.is_none())
+        || last.is_some_and(|last| last & ((1 << zeros) - 1) != 0)
+    {
+        Err(DecodeError::DanglingBits)
+    } else {
+        Ok(())
+    }
+}
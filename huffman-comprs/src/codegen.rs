@@ -0,0 +1,71 @@
+//! Helpers for embedding a [`Huffman`](../struct.Huffman.html) tree into Rust source.
+//!
+//! A fixed tree can be built ahead of time and stored in a `static`, without
+//! any construction or file I/O at runtime.
+//!
+//! The `include_huffman!` macro from `huffman-comprs-macros` uses this to build
+//! a tree from a corpus at compile time. The same output can be written from a
+//! build script, and pulled in with `include!`:
+//! ```ignore
+//! // build.rs
+//! let corpus = std::fs::read("corpus.txt").unwrap();
+//! let tree = huffman_comprs::Huffman::from(&corpus);
+//!
+//! let source = format!(
+//!     "static TREE: huffman_comprs::StaticHuffman<u8> = {};",
+//!     huffman_comprs::codegen::static_tree(&tree, "u8")
+//! );
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! std::fs::write(std::path::Path::new(&out_dir).join("tree.rs"), source).unwrap();
+//!
+//! // main.rs
+//! include!(concat!(env!("OUT_DIR"), "/tree.rs"));
+//! ```
+
+use std::fmt::{Debug, Write};
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::Huffman;
+
+/// Generates a constant expression evaluating to a
+/// [`StaticHuffman`](../struct.StaticHuffman.html) with the same codes as
+/// `tree`.
+///
+/// `symbol_type` must be the name of `T` in the generated code. Symbols are
+/// written with their `Debug` representation, so this works for primitives
+/// like `u8` and `char`
+#[must_use]
+pub fn static_tree<T>(tree: &Huffman<T>, symbol_type: &str) -> String
+where
+    T: Serialize + Ord + Clone + Debug,
+{
+    let mut contents = String::new();
+    for symbol in &tree.contents {
+        write!(contents, "{symbol:?}, ").unwrap();
+    }
+
+    let mut nodes = String::new();
+    for node in &tree.nodes {
+        write!(
+            nodes,
+            "::huffman_comprs::Node::new({}, {:?}), ",
+            node.freq, node.children
+        )
+        .unwrap();
+    }
+
+    format!(
+        "{{ \
+            static CONTENTS: [{ty}; {}] = [{}]; \
+            static NODES: [::huffman_comprs::Node; {}] = [{}]; \
+            ::huffman_comprs::StaticHuffman::<{ty}>::from_static(&CONTENTS, &NODES) \
+        }}",
+        tree.contents.len(),
+        contents,
+        tree.nodes.len(),
+        nodes,
+        ty = symbol_type,
+    )
+}
@@ -0,0 +1,138 @@
+use std::io;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancel::CHECK_INTERVAL, rz::parse_tree, Cancel, Code, DecodeError, DecodeOptions, Encoder,
+    Huffman, RzError, TieBreak, TreeFormat,
+};
+
+/// A way of compressing symbols to bits and back, with a model that can be
+/// stored alongside the data.
+///
+/// [`RZFile`](struct.RZFile.html) is generic over its coder, defaulting to
+/// [`Huffman`](struct.Huffman.html), so code written against `RZFile` doesn't
+/// change when another coder is used
+pub trait EntropyCoder<T>: Sized {
+    /// Compresses `input` to bits
+    ///
+    /// # Errors
+    /// Returns `None` if `input` holds a symbol the model can't encode
+    fn encode(&self, input: &[T]) -> Option<Code>;
+    /// Compresses `input` as [`encode`](#tymethod.encode) does, stopping
+    /// once `cancel` is set. Models that can't be stopped part way through
+    /// only check the flag before and after encoding
+    ///
+    /// # Errors
+    /// Returns [`DecodeError::Cancelled`](enum.DecodeError.html#variant.Cancelled)
+    /// if `cancel` is set, or `Ok(None)` if `input` holds a symbol the model
+    /// can't encode
+    fn encode_cancellable(
+        &self,
+        input: &[T],
+        cancel: &Cancel,
+    ) -> Result<Option<Code>, DecodeError> {
+        cancel.check()?;
+        let code = self.encode(input);
+        cancel.check()?;
+        Ok(code)
+    }
+    /// Decodes `data`, appending the symbols to `output`. `data` holds the
+    /// bits back to front, as
+    /// [`Huffman::reconstruct`](struct.Huffman.html#method.reconstruct) takes
+    /// them, padded with `zeros` zeros. If `len` is given, exactly that many
+    /// symbols must be decoded
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding, or if decoding it
+    /// would break one of the limits in `options`
+    fn decode(
+        &self,
+        data: Vec<bool>,
+        zeros: u8,
+        len: Option<usize>,
+        output: &mut Vec<T>,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError>;
+    /// Serializes the model with `format`, to be stored with the data
+    ///
+    /// # Errors
+    /// Fails if the model can't be serialized with `format`
+    fn serialize_model(&self, format: TreeFormat) -> io::Result<Vec<u8>>;
+    /// Deserializes a model written by
+    /// [`serialize_model`](#tymethod.serialize_model), checking it's well
+    /// formed
+    ///
+    /// # Errors
+    /// Fails if `model` can't be deserialized with `format`, or isn't well
+    /// formed
+    fn deserialize_model<'de>(model: &'de [u8], format: TreeFormat) -> Result<Self, RzError>
+    where
+        T: Deserialize<'de>;
+    /// The length of the longest code the model holds, checked against
+    /// [`DecodeOptions::max_tree_depth`](struct.DecodeOptions.html#method.max_tree_depth).
+    /// Models without codes of their own leave this as 0, so aren't limited
+    fn depth(&self) -> usize {
+        0
+    }
+    /// A model holding no symbols, given to files whose bytes are stored
+    /// uncompressed when they're read back, as those leave the model out.
+    /// Models that can't be empty leave this as `None`, so such files can
+    /// only be read with the model given
+    #[must_use]
+    fn empty() -> Option<Self> {
+        None
+    }
+}
+
+impl<T> EntropyCoder<T> for Huffman<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn encode(&self, input: &[T]) -> Option<Code> {
+        self.compress(input)
+    }
+    fn encode_cancellable(
+        &self,
+        input: &[T],
+        cancel: &Cancel,
+    ) -> Result<Option<Code>, DecodeError> {
+        let encoder = Encoder::new(self);
+        let mut output = Vec::with_capacity(input.len());
+        for chunk in input.chunks(CHECK_INTERVAL) {
+            cancel.check()?;
+            if encoder.encode_into(chunk, &mut output).is_none() {
+                return Ok(None);
+            }
+        }
+        Ok(Some(output.into()))
+    }
+    fn decode(
+        &self,
+        data: Vec<bool>,
+        zeros: u8,
+        len: Option<usize>,
+        output: &mut Vec<T>,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError> {
+        match len {
+            Some(len) => self.decode_exact(data, zeros, len, output, options),
+            None => self.decode_extend(data, zeros, output, options),
+        }
+    }
+    fn serialize_model(&self, format: TreeFormat) -> io::Result<Vec<u8>> {
+        format.serialize(self)
+    }
+    fn deserialize_model<'de>(model: &'de [u8], format: TreeFormat) -> Result<Self, RzError>
+    where
+        T: Deserialize<'de>,
+    {
+        parse_tree(model, format)
+    }
+    fn depth(&self) -> usize {
+        self.max_depth()
+    }
+    fn empty() -> Option<Self> {
+        Some(Self::build(Vec::new(), TieBreak::default()))
+    }
+}
@@ -0,0 +1,403 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::{
+    DecodeOptions, EntropyCoder, Extension, Huffman, Progress, RZFile, TieBreak, TreeFormat,
+};
+
+/// How many bytes of input are compressed into each RZ file written by
+/// [`copy_encode`](fn.copy_encode.html)
+const CHUNK_LEN: usize = 1024 * 1024;
+
+/// Compresses everything read from `reader` with `tree`, writing it to
+/// `writer`, and returning how many bytes were read.
+///
+/// The input is read a chunk at a time, with each chunk written as an RZ file
+/// of its own, holding the tree and a checksum, one after another. Only one
+/// chunk is held in memory at once, and the output can be read back with
+/// [`copy_decode`](fn.copy_decode.html), or
+/// [`RZFile::frames`](struct.RZFile.html#method.frames)
+/// # Examples
+/// ```
+/// use huffman_comprs::{copy_decode, copy_encode, Huffman};
+///
+/// let input = b"Straight from one stream to another".to_vec();
+/// let tree = Huffman::from(&input);
+///
+/// let mut compressed = Vec::new();
+/// copy_encode(input.as_slice(), &mut compressed, &tree).unwrap();
+///
+/// let mut output = Vec::new();
+/// copy_decode(compressed.as_slice(), &mut output).unwrap();
+/// assert_eq!(input, output);
+/// ```
+///
+/// # Errors
+/// Fails if there's any issue reading from `reader` or writing to `writer`,
+/// or if a byte read isn't in `tree`
+pub fn copy_encode<R, W>(reader: R, writer: W, tree: &Huffman<u8>) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+{
+    copy_encode_with_progress(reader, writer, tree, |_| {})
+}
+
+/// Compresses everything read from `reader`, as
+/// [`copy_encode`](fn.copy_encode.html) does, calling `progress` with how
+/// many bytes have been read and written so far after each chunk is written
+/// # Examples
+/// ```
+/// use huffman_comprs::{copy_encode_with_progress, Huffman};
+///
+/// let input = b"Reported a chunk at a time".to_vec();
+/// let tree = Huffman::from(&input);
+///
+/// let mut reports = Vec::new();
+/// let mut compressed = Vec::new();
+/// copy_encode_with_progress(input.as_slice(), &mut compressed, &tree, |progress| {
+///     reports.push(progress)
+/// })
+/// .unwrap();
+///
+/// assert_eq!(input.len() as u64, reports[0].consumed);
+/// assert_eq!(compressed.len() as u64, reports[0].produced);
+/// ```
+///
+/// # Errors
+/// Fails if there's any issue reading from `reader` or writing to `writer`,
+/// or if a byte read isn't in `tree`
+pub fn copy_encode_with_progress<R, W, F>(
+    reader: R,
+    writer: W,
+    tree: &Huffman<u8>,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(Progress),
+{
+    let mut writer = Counting::new(writer);
+    let mut reader = reader.take(0);
+    let mut chunk = Vec::with_capacity(CHUNK_LEN);
+    let mut read = 0;
+
+    loop {
+        chunk.clear();
+        reader.set_limit(CHUNK_LEN as u64);
+        reader.read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+        read += chunk.len() as u64;
+
+        let file = RZFile::compress(tree.clone(), &chunk)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "a byte read isn't in the tree")
+            })?
+            .with_checksum(&chunk);
+        file.write_to(&mut writer)?;
+        progress(Progress {
+            consumed: read,
+            produced: writer.written,
+        });
+    }
+
+    writer.flush()?;
+    Ok(read)
+}
+
+/// When [`copy_encode_retraining`](fn.copy_encode_retraining.html) trains a
+/// new tree on the input, rather than keeping the one it has.
+/// # Examples
+/// ```
+/// use huffman_comprs::Retrain;
+///
+/// // A new tree at least every 64 megabytes, or sooner if the current tree
+/// // gives data 5% larger than a tree trained on it would
+/// let retrain = Retrain::new().every(64 * 1024 * 1024).max_loss(0.05);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Retrain {
+    every: Option<u64>,
+    max_loss: Option<f64>,
+}
+
+impl Retrain {
+    /// Never retrains, so the tree trained on the first chunk is used for
+    /// the whole input
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            every: None,
+            max_loss: None,
+        }
+    }
+    /// Trains a new tree once `bytes` bytes have been compressed with the
+    /// current one. Trees are only trained at the start of a chunk, of a
+    /// megabyte, so this is rounded up to a whole number of chunks
+    #[must_use]
+    pub const fn every(mut self, bytes: u64) -> Self {
+        self.every = Some(bytes);
+        self
+    }
+    /// Trains a new tree whenever compressing a chunk with the current tree
+    /// takes more than `loss` times more bits than a tree trained on the
+    /// chunk itself would, such as `0.05` for 5%
+    #[must_use]
+    pub const fn max_loss(mut self, loss: f64) -> Self {
+        self.max_loss = Some(loss);
+        self
+    }
+}
+
+/// Compresses everything read from `reader`, as
+/// [`copy_encode`](fn.copy_encode.html) does, but with trees trained on the
+/// input itself, returning how many bytes were read.
+///
+/// The first chunk's tree is trained on it, and kept for the chunks after it
+/// until `retrain` says to train a new one, so a tree follows content that
+/// drifts over time, such as a day's logs. Only a chunk starting with a new
+/// tree holds it, with the chunks after it referencing it by its
+/// [`dictionary_id`](struct.Huffman.html#method.dictionary_id), so the
+/// output must be read back in order with
+/// [`copy_decode`](fn.copy_decode.html).
+///
+/// Every byte is given a code, so a tree can compress any chunk, if less well
+/// than one trained on it
+/// # Examples
+/// ```
+/// use huffman_comprs::{copy_decode, copy_encode_retraining, Retrain};
+///
+/// let input = b"Trained on what it compresses".repeat(100);
+///
+/// let mut compressed = Vec::new();
+/// copy_encode_retraining(input.as_slice(), &mut compressed, &Retrain::new().max_loss(0.1))
+///     .unwrap();
+///
+/// let mut output = Vec::new();
+/// copy_decode(compressed.as_slice(), &mut output).unwrap();
+/// assert_eq!(input, output);
+/// ```
+///
+/// # Errors
+/// Fails if there's any issue reading from `reader` or writing to `writer`
+pub fn copy_encode_retraining<R, W>(reader: R, writer: W, retrain: &Retrain) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+{
+    copy_encode_retraining_with_progress(reader, writer, retrain, |_| {})
+}
+
+/// Compresses everything read from `reader` with trees trained on it, as
+/// [`copy_encode_retraining`](fn.copy_encode_retraining.html) does.
+///
+/// `progress` is called with how many bytes have been read and written so
+/// far after each chunk is written
+///
+/// # Errors
+/// Fails if there's any issue reading from `reader` or writing to `writer`
+pub fn copy_encode_retraining_with_progress<R, W, F>(
+    reader: R,
+    writer: W,
+    retrain: &Retrain,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(Progress),
+{
+    let mut writer = Counting::new(writer);
+    let mut reader = reader.take(0);
+    let mut chunk = Vec::with_capacity(CHUNK_LEN);
+    let mut read = 0;
+    // The current tree, and how many bytes it's compressed
+    let mut current: Option<(Huffman<u8>, u64)> = None;
+
+    loop {
+        chunk.clear();
+        reader.set_limit(CHUNK_LEN as u64);
+        reader.read_to_end(&mut chunk)?;
+        if chunk.is_empty() {
+            break;
+        }
+        read += chunk.len() as u64;
+
+        let mut counts = [0_usize; 256];
+        for byte in &chunk {
+            counts[usize::from(*byte)] += 1;
+        }
+        let stale = match &current {
+            None => true,
+            Some((_, compressed)) if retrain.every.is_some_and(|every| *compressed >= every) => {
+                true
+            }
+            Some((tree, _)) => retrain
+                .max_loss
+                .is_some_and(|max_loss| loss(tree, &counts) > max_loss),
+        };
+
+        let (tree, compressed) = match &mut current {
+            Some(current) if !stale => current,
+            current => {
+                // Compressing with the tree as it's read back, so the chunks
+                // after it reference it by the id the reader will find
+                let format = TreeFormat::default();
+                let model = train(&counts).serialize_model(format)?;
+                current.insert((Huffman::deserialize_model(&model, format)?, 0))
+            }
+        };
+        *compressed += chunk.len() as u64;
+
+        // Every byte is in the tree, so this can't fail
+        let file = RZFile::compress(tree.clone(), &chunk)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidInput, "a byte read isn't in the tree")
+            })?
+            .with_checksum(&chunk);
+        let file = if stale { file } else { file.with_dictionary() };
+        file.write_to(&mut writer)?;
+        progress(Progress {
+            consumed: read,
+            produced: writer.written,
+        });
+    }
+
+    writer.flush()?;
+    Ok(read)
+}
+
+/// Trains a tree on a chunk's `counts` of each byte, giving every byte a code
+pub fn train(counts: &[usize; 256]) -> Huffman<u8> {
+    Huffman::build(
+        (0..=u8::MAX)
+            .zip(counts.iter())
+            .map(|(byte, count)| (byte, count + 1))
+            .collect(),
+        TieBreak::default(),
+    )
+}
+
+/// How many bits compressing bytes with `counts` of each takes with `tree`
+fn cost(tree: &Huffman<u8>, counts: &[usize; 256]) -> usize {
+    tree.code_lengths()
+        .into_iter()
+        .map(|(byte, len)| counts[usize::from(byte)] * usize::from(len))
+        .sum()
+}
+
+/// How many times more bits compressing bytes with `counts` of each takes
+/// with `tree` than with a tree trained on them
+fn loss(tree: &Huffman<u8>, counts: &[usize; 256]) -> f64 {
+    // A chunk's cost is at most a few million bits, well within what an
+    // `f64` holds exactly
+    #[allow(clippy::cast_precision_loss)]
+    let (cost, trained) = (
+        cost(tree, counts) as f64,
+        cost(&train(counts), counts) as f64,
+    );
+    cost / trained.max(1.0) - 1.0
+}
+
+/// Decompresses every RZ file read from `reader`, writing the bytes they hold
+/// to `writer`, and returning how many bytes were written.
+///
+/// Each file is decompressed as soon as it's read, so only one is held in
+/// memory at once. A file leaving its tree out, as
+/// [`copy_encode_retraining`](fn.copy_encode_retraining.html) writes, uses the
+/// tree of the file before it
+///
+/// # Errors
+/// Fails if there's any issue reading from `reader` or writing to `writer`,
+/// or if a file is malformed or doesn't match its checksum
+pub fn copy_decode<R, W>(reader: R, writer: W) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+{
+    copy_decode_with_progress(reader, writer, |_| {})
+}
+
+/// Decompresses every RZ file read from `reader`, as
+/// [`copy_decode`](fn.copy_decode.html) does, calling `progress` with how
+/// many bytes have been read and written so far after each file is
+/// decompressed
+///
+/// # Errors
+/// Fails if there's any issue reading from `reader` or writing to `writer`,
+/// or if a file is malformed or doesn't match its checksum
+pub fn copy_decode_with_progress<R, W, F>(
+    reader: R,
+    mut writer: W,
+    mut progress: F,
+) -> io::Result<u64>
+where
+    R: Read,
+    W: Write,
+    F: FnMut(Progress),
+{
+    let mut reader = BufReader::new(Counting::new(reader));
+    let mut written = 0;
+    let mut last: Option<Huffman<u8>> = None;
+
+    loop {
+        Extension::read_all_from(&mut reader)?;
+        if reader.fill_buf()?.is_empty() {
+            break;
+        }
+        let file: RZFile<u8> = RZFile::read_from_with_model(&mut reader, last.as_ref())?;
+        last = Some(file.tree.clone());
+        let output = file
+            .decompress_with(&DecodeOptions::new())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        writer.write_all(&output)?;
+        written += output.len() as u64;
+        // Whatever's buffered past the end of the file hasn't been used yet
+        progress(Progress {
+            consumed: reader.get_ref().read - reader.buffer().len() as u64,
+            produced: written,
+        });
+    }
+
+    writer.flush()?;
+    Ok(written)
+}
+
+/// Counts the bytes read from or written to the reader or writer it wraps
+struct Counting<T> {
+    inner: T,
+    read: u64,
+    written: u64,
+}
+
+impl<T> Counting<T> {
+    const fn new(inner: T) -> Self {
+        Self {
+            inner,
+            read: 0,
+            written: 0,
+        }
+    }
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read as u64;
+        Ok(read)
+    }
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,347 @@
+use std::{error::Error, fmt, io};
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{cancel::CHECK_INTERVAL, tree::TreeRef, Cancel, Code, Huffman};
+
+/// Limits applied while decoding, to guard against hostile input, and a
+/// flag for stopping it part way through.
+/// # Examples
+/// ```
+/// use huffman_comprs::{DecodeError, DecodeOptions, Huffman};
+///
+/// let input = "aaaaaaaaaaaaaaaab";
+/// let huffman = Huffman::from(input);
+///
+/// let mut data = huffman.compress_str(input).unwrap();
+/// data.reverse();
+///
+/// let options = DecodeOptions::new().max_output_len(8);
+///
+/// assert_eq!(
+///     Err(DecodeError::OutputLimitExceeded(8)),
+///     huffman.reconstruct_with(data, 0, &options)
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[allow(clippy::struct_field_names)] // Named after the builder methods setting them
+pub struct DecodeOptions {
+    pub(crate) max_output_len: Option<usize>,
+    pub(crate) max_tree_bytes: Option<usize>,
+    pub(crate) max_tree_depth: Option<usize>,
+    pub(crate) cancel: Option<Cancel>,
+}
+
+impl DecodeOptions {
+    /// Creates a set of options without any limits
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            max_output_len: None,
+            max_tree_bytes: None,
+            max_tree_depth: None,
+            cancel: None,
+        }
+    }
+    /// Stops decoding with an error once more than `max_output_len` symbols
+    /// have been decoded. A small compressed input can expand to a very large
+    /// output, so this should be set when decoding untrusted data
+    #[must_use]
+    pub const fn max_output_len(mut self, max_output_len: usize) -> Self {
+        self.max_output_len = Some(max_output_len);
+        self
+    }
+    /// Rejects RZ files whose tree takes up more than `max_tree_bytes`
+    /// bytes, before any of it is read, see
+    /// [`RZFile::parse_with_options`](struct.RZFile.html#method.parse_with_options)
+    #[must_use]
+    pub const fn max_tree_bytes(mut self, max_tree_bytes: usize) -> Self {
+        self.max_tree_bytes = Some(max_tree_bytes);
+        self
+    }
+    /// Rejects RZ files whose tree has codes longer than `max_tree_depth`
+    /// bits. Decoding walks the tree a bit at a time, so a deep tree makes
+    /// every symbol slow to decode
+    #[must_use]
+    pub const fn max_tree_depth(mut self, max_tree_depth: usize) -> Self {
+        self.max_tree_depth = Some(max_tree_depth);
+        self
+    }
+    /// Stops decoding an RZ file with
+    /// [`DecodeError::Cancelled`](enum.DecodeError.html#variant.Cancelled)
+    /// once `cancel` is set. The flag is checked before each block, and
+    /// every 65536 symbols within it, so a file that isn't split into blocks
+    /// can still be stopped part way through
+    #[must_use]
+    pub fn cancel(mut self, cancel: Cancel) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+    pub(crate) fn check_cancelled(&self) -> Result<(), DecodeError> {
+        self.cancel.as_ref().map_or(Ok(()), Cancel::check)
+    }
+}
+
+/// The ways decoding can fail
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The data ended part way through a code
+    UnexpectedEof,
+    /// The bits at the end of the data aren't valid padding. Either the
+    /// padding is a byte or more long, or runs past the start of the data, or
+    /// has bits set, or the tree has no codes for the bits to decode to
+    DanglingBits,
+    /// There's data to decode, but the tree is empty
+    EmptyTree,
+    /// The data decoded, but doesn't match the checksum stored with it
+    ChecksumMismatch,
+    /// More symbols would have been decoded than the given limit
+    OutputLimitExceeded(usize),
+    /// The raw bits following an escape code, or a packed value, aren't a
+    /// valid symbol
+    InvalidLiteral,
+    /// An HPACK string literal holds the end of string symbol, which may
+    /// only pad its end
+    EndOfString,
+    /// The tree given to decode a terminated stream doesn't hold its end of
+    /// stream symbol
+    NoEndOfStream,
+    /// An integer code is for a value too large to hold
+    ValueOverflow,
+    /// The [`Cancel`](struct.Cancel.html) flag given was set before the
+    /// operation finished
+    Cancelled,
+}
+
+impl DecodeError {
+    /// The `DecodeError` an IO error holds, if any, such as the error
+    /// [`copy_decode`](fn.copy_decode.html) fails with when a stream it's
+    /// given is [cancelled](struct.Cancel.html#method.wrap)
+    #[must_use]
+    pub fn from_io(e: &io::Error) -> Option<Self> {
+        e.get_ref()
+            .and_then(|inner| inner.downcast_ref::<Self>())
+            .copied()
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "the data ended part way through a code"),
+            Self::DanglingBits => write!(f, "the data ends with invalid padding"),
+            Self::EmptyTree => write!(f, "there's data to decode, but the tree is empty"),
+            Self::ChecksumMismatch => write!(f, "the decoded data doesn't match its checksum"),
+            Self::OutputLimitExceeded(limit) => {
+                write!(f, "the output is longer than the limit of {limit} symbols")
+            }
+            Self::InvalidLiteral => write!(
+                f,
+                "an escaped literal, or packed value, isn't a valid symbol"
+            ),
+            Self::EndOfString => write!(f, "the string holds the end of string symbol"),
+            Self::NoEndOfStream => write!(f, "the tree has no end of stream symbol"),
+            Self::ValueOverflow => write!(f, "a value is too large to hold"),
+            Self::Cancelled => write!(f, "the operation was cancelled"),
+        }
+    }
+}
+
+impl Error for DecodeError {}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Reconstructs `data` as [`reconstruct`](#method.reconstruct) does, but
+    /// saying why decoding failed, if it does
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding, such as if it ends
+    /// part way through a code, or the padding is wrong
+    pub fn try_reconstruct(&self, data: impl Into<Code>, zeros: u8) -> Result<Vec<T>, DecodeError> {
+        self.reconstruct_with(data, zeros, &DecodeOptions::new())
+    }
+    /// Reconstructs `data` as [`reconstruct`](#method.reconstruct) does,
+    /// applying the limits in `options`
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding, or if decoding it
+    /// would break one of the limits in `options`
+    pub fn reconstruct_with(
+        &self,
+        data: impl Into<Code>,
+        zeros: u8,
+        options: &DecodeOptions,
+    ) -> Result<Vec<T>, DecodeError> {
+        let data = data.into();
+        let mut output = Vec::with_capacity(data.len() / 8);
+
+        self.decode_extend(data.to_vec(), zeros, &mut output, options)?;
+
+        Ok(output)
+    }
+    /// Reconstructs exactly `len` symbols from `data`, as
+    /// [`reconstruct`](#method.reconstruct) does. Knowing the length lets the
+    /// output be allocated upfront, and lets a tree of a single symbol, whose
+    /// code is empty, be decoded
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding of `len` symbols,
+    /// such as if it ends early, or has bits left over
+    pub fn reconstruct_exact(
+        &self,
+        data: impl Into<Code>,
+        zeros: u8,
+        len: usize,
+    ) -> Result<Vec<T>, DecodeError> {
+        let mut output = Vec::with_capacity(len);
+
+        self.decode_exact(
+            data.into().to_vec(),
+            zeros,
+            len,
+            &mut output,
+            &DecodeOptions::new(),
+        )?;
+
+        Ok(output)
+    }
+    pub(crate) fn decode_extend<E>(
+        &self,
+        data: Vec<bool>,
+        zeros: u8,
+        output: &mut E,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError>
+    where
+        E: Extend<T>,
+    {
+        self.tree_ref().decode_extend(data, zeros, output, options)
+    }
+    pub(crate) fn decode_exact<E>(
+        &self,
+        data: Vec<bool>,
+        zeros: u8,
+        len: usize,
+        output: &mut E,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError>
+    where
+        E: Extend<T>,
+    {
+        self.tree_ref()
+            .decode_exact(data, zeros, len, output, options)
+    }
+}
+
+impl<T> TreeRef<'_, T>
+where
+    T: Ord + Clone,
+{
+    pub fn decode_extend<E>(
+        &self,
+        mut data: Vec<bool>,
+        zeros: u8,
+        output: &mut E,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError>
+    where
+        E: Extend<T>,
+    {
+        strip_padding(&mut data, zeros)?;
+
+        match self.root() {
+            _ if data.is_empty() => return Ok(()),
+            None => return Err(DecodeError::EmptyTree),
+            // A tree of a single symbol gives it an empty code, so no bits
+            // can be decoded
+            Some(root) if self.children(root).is_none() => return Err(DecodeError::DanglingBits),
+            Some(_) => {}
+        }
+
+        let limit = options.max_output_len.unwrap_or(usize::MAX);
+        let mut decoded = 0;
+        let mut error = None;
+
+        output.extend(std::iter::from_fn(|| {
+            if data.is_empty() {
+                return None;
+            }
+            if decoded == limit {
+                error = Some(DecodeError::OutputLimitExceeded(limit));
+                return None;
+            }
+            if decoded % CHECK_INTERVAL == 0 {
+                if let Err(e) = options.check_cancelled() {
+                    error = Some(e);
+                    return None;
+                }
+            }
+            let symbol = self.get_char_rec(&mut data);
+            if symbol.is_none() {
+                error = Some(DecodeError::UnexpectedEof);
+            }
+            decoded += 1;
+            symbol
+        }));
+
+        error.map_or(Ok(()), Err)
+    }
+    pub fn decode_exact<E>(
+        &self,
+        mut data: Vec<bool>,
+        zeros: u8,
+        len: usize,
+        output: &mut E,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError>
+    where
+        E: Extend<T>,
+    {
+        if let Some(limit) = options.max_output_len.filter(|limit| len > *limit) {
+            return Err(DecodeError::OutputLimitExceeded(limit));
+        }
+        strip_padding(&mut data, zeros)?;
+
+        if self.root().is_none() && len > 0 {
+            return Err(DecodeError::EmptyTree);
+        }
+
+        let mut error = None;
+        output.extend((0..len).map_while(|decoded| {
+            if decoded % CHECK_INTERVAL == 0 {
+                if let Err(e) = options.check_cancelled() {
+                    error = Some(e);
+                    return None;
+                }
+            }
+            let symbol = self.get_char_rec(&mut data);
+            if symbol.is_none() {
+                error = Some(DecodeError::UnexpectedEof);
+            }
+            symbol
+        }));
+
+        match error {
+            Some(error) => Err(error),
+            None if !data.is_empty() => Err(DecodeError::DanglingBits),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Removes `zeros` bits of padding from the end of `data`, checking they're
+/// all unset
+pub fn strip_padding(data: &mut Vec<bool>, zeros: u8) -> Result<(), DecodeError> {
+    if zeros > 7 || usize::from(zeros) > data.len() {
+        return Err(DecodeError::DanglingBits);
+    }
+    for _ in 0..zeros {
+        if data.pop() == Some(true) {
+            return Err(DecodeError::DanglingBits);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,75 @@
+//! The fixed Huffman codes of DEFLATE, from
+//! [RFC 1951](https://www.rfc-editor.org/rfc/rfc1951#section-3.2.6), which
+//! blocks of type 1 use instead of storing codes of their own.
+//!
+//! Symbols are `u16`s, as the literal/length alphabet has 288 symbols: every
+//! byte as a literal, 256 for the end of the block, and 257 to 287 for the
+//! lengths of matches. The distance alphabet has 32. The last two symbols of
+//! each can't appear in valid data, but are part of the codes, so the trees
+//! are full. Both are built from their code lengths, so their codes are the
+//! ones the RFC lists:
+//! ```
+//! use huffman_comprs::Huffman;
+//!
+//! let literals = Huffman::deflate_literals();
+//! assert_eq!("00110000", literals.get_code(0).unwrap().to_string());
+//! assert_eq!("0000000", literals.get_code(256).unwrap().to_string());
+//! assert_eq!("110010000", literals.get_code(144).unwrap().to_string());
+//!
+//! let distances = Huffman::deflate_distances();
+//! assert_eq!("00011", distances.get_code(3).unwrap().to_string());
+//! ```
+//!
+//! DEFLATE packs bits into bytes least significant first, but each code
+//! most significant bit first, as a [`BitWriter`](../bits/struct.BitWriter.html)
+//! does with [`BitOrder::LsbFirst`](../bits/enum.BitOrder.html#variant.LsbFirst)
+
+use crate::Huffman;
+
+/// The symbol ending a block, in the literal/length alphabet
+pub const END_OF_BLOCK: u16 = 256;
+
+/// The length of each literal/length symbol's fixed code, indexed by the
+/// symbol
+pub const FIXED_LITERAL_LENGTHS: [u8; 288] = {
+    let mut lengths = [8; 288];
+    let mut symbol = 144;
+    while symbol < 256 {
+        lengths[symbol] = 9;
+        symbol += 1;
+    }
+    while symbol < 280 {
+        lengths[symbol] = 7;
+        symbol += 1;
+    }
+    lengths
+};
+
+/// The length of each distance symbol's fixed code, indexed by the symbol
+pub const FIXED_DISTANCE_LENGTHS: [u8; 32] = [5; 32];
+
+impl Huffman<u16> {
+    /// The fixed literal/length code of DEFLATE, built from
+    /// [`FIXED_LITERAL_LENGTHS`](deflate/constant.FIXED_LITERAL_LENGTHS.html)
+    ///
+    /// # Panics
+    /// Never panics, as the code lengths describe a full tree
+    #[must_use]
+    pub fn deflate_literals() -> Self {
+        Self::from_lengths_table(&FIXED_LITERAL_LENGTHS)
+    }
+    /// The fixed distance code of DEFLATE, built from
+    /// [`FIXED_DISTANCE_LENGTHS`](deflate/constant.FIXED_DISTANCE_LENGTHS.html)
+    ///
+    /// # Panics
+    /// Never panics, as the code lengths describe a full tree
+    #[must_use]
+    pub fn deflate_distances() -> Self {
+        Self::from_lengths_table(&FIXED_DISTANCE_LENGTHS)
+    }
+    /// Builds the tree giving each symbol the code length at its index
+    fn from_lengths_table(table: &[u8]) -> Self {
+        let lengths: Vec<(u16, u8)> = (0..).zip(table.iter().copied()).collect();
+        Self::from_code_lengths(&lengths).expect("the code lengths describe a full tree")
+    }
+}
@@ -0,0 +1,159 @@
+//! Elias gamma and delta codes, universal codes for integers with no upper
+//! bound, such as the lengths of blocks, or the distances back to a match.
+//!
+//! Neither needs any parameters, or knows anything of the values it codes
+//! beyond smaller values being more common. Gamma codes each value as its
+//! number of bits in unary, followed by the bits, so takes about twice as
+//! many bits as the value has. Delta codes the number of bits in gamma
+//! instead, so is longer for the smallest values, but far shorter for large
+//! ones. Both code every `u64`, 0 included:
+//! ```
+//! use huffman_comprs::elias::Elias;
+//!
+//! let lengths = [0, 3, 17, 1 << 20, u64::MAX];
+//!
+//! for code in [Elias::Gamma, Elias::Delta] {
+//!     let bytes = code.encode(&lengths);
+//!     assert_eq!(Ok(lengths.to_vec()), code.decode(&bytes, lengths.len()));
+//! }
+//!
+//! assert_eq!(41, Elias::Gamma.code_len(1 << 20));
+//! assert_eq!(29, Elias::Delta.code_len(1 << 20));
+//! ```
+//!
+//! As with [`Golomb`](../golomb/struct.Golomb.html) codes,
+//! [`Elias::write`](enum.Elias.html#method.write) and
+//! [`Elias::read`](enum.Elias.html#method.read) code a single value with a
+//! [`BitWriter`](../bits/struct.BitWriter.html) or
+//! [`BitReader`](../bits/struct.BitReader.html)
+
+use std::convert::TryFrom;
+
+use crate::{
+    bits::{BitReader, BitWriter},
+    DecodeError,
+};
+
+/// An Elias code. As they code positive integers, each value is coded as one
+/// more than it is, so 0 has the shortest code
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Elias {
+    /// The number of bits after the first `1` in unary, as that many `0`s,
+    /// then the bits, leading `1` included
+    Gamma,
+    /// The number of bits in gamma, then the bits, without the leading `1`
+    Delta,
+}
+
+impl Elias {
+    /// The number of bits `value` is coded in
+    #[must_use]
+    pub const fn code_len(self, value: u64) -> u64 {
+        let len = bit_len(value as u128 + 1) as u64;
+        match self {
+            Self::Gamma => 2 * len - 1,
+            Self::Delta => 2 * bit_len(len as u128) as u64 - 1 + len - 1,
+        }
+    }
+    /// The number of bits `values` are coded in, without coding them
+    #[must_use]
+    pub fn encoded_len(self, values: &[u64]) -> u64 {
+        values.iter().map(|value| self.code_len(*value)).sum()
+    }
+    /// Writes the code of `value`
+    pub fn write(self, writer: &mut BitWriter, value: u64) {
+        let n = u128::from(value) + 1;
+        let len = bit_len(n);
+        match self {
+            Self::Gamma => write_gamma(writer, n),
+            Self::Delta => {
+                write_gamma(writer, u128::from(len));
+                write_low(writer, n, len - 1);
+            }
+        }
+    }
+    /// Reads the code of a value
+    ///
+    /// # Errors
+    /// Returns an error if the bits end part way through the code, or it
+    /// codes a value too large for a `u64`
+    pub fn read(self, reader: &mut BitReader) -> Result<u64, DecodeError> {
+        let n = match self {
+            Self::Gamma => read_gamma(reader)?,
+            Self::Delta => {
+                let len = read_gamma(reader)?;
+                if len > u128::from(u64::BITS) + 1 {
+                    return Err(DecodeError::ValueOverflow);
+                }
+                // At most 65, so this fits
+                #[allow(clippy::cast_possible_truncation)]
+                let low = read_low(reader, len as u32 - 1)?;
+                1 << (len - 1) | low
+            }
+        };
+        u64::try_from(n - 1).map_err(|_| DecodeError::ValueOverflow)
+    }
+    /// Codes every value in `values`, padding the last byte with zeros
+    #[must_use]
+    pub fn encode(self, values: &[u64]) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+        for value in values {
+            self.write(&mut writer, *value);
+        }
+        writer.finish().0
+    }
+    /// Reads `count` values coded by [`encode`](#method.encode)
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` end before `count` values have been read,
+    /// or any value is too large for a `u64`
+    pub fn decode(self, bytes: &[u8], count: usize) -> Result<Vec<u64>, DecodeError> {
+        let mut reader = BitReader::new(bytes);
+        (0..count).map(|_| self.read(&mut reader)).collect()
+    }
+}
+
+/// The number of bits in `n`, up to and including its highest `1`
+const fn bit_len(n: u128) -> u32 {
+    u128::BITS - n.leading_zeros()
+}
+
+/// Writes the Elias gamma code of `n`, which is at least 1
+fn write_gamma(writer: &mut BitWriter, n: u128) {
+    let len = bit_len(n);
+    for _ in 1..len {
+        writer.write_bit(false);
+    }
+    write_low(writer, n, len);
+}
+
+/// Reads an Elias gamma code, giving a value which is at least 1, and no
+/// more than 65 bits long
+fn read_gamma(reader: &mut BitReader) -> Result<u128, DecodeError> {
+    let mut zeros = 0;
+    while !reader.read_bit().ok_or(DecodeError::UnexpectedEof)? {
+        zeros += 1;
+        if zeros > u64::BITS {
+            return Err(DecodeError::ValueOverflow);
+        }
+    }
+    Ok(1 << zeros | read_low(reader, zeros)?)
+}
+
+/// Writes the lowest `len` bits of `n`, most significant first
+fn write_low(writer: &mut BitWriter, n: u128, len: u32) {
+    if len > u64::BITS {
+        writer.write_bit(n >> u64::BITS & 1 == 1);
+    }
+    // Only the lowest 64 bits are left to write
+    #[allow(clippy::cast_possible_truncation)]
+    writer.write_int(n as u64, len.min(u64::BITS));
+}
+
+/// Reads `len` bits, which is no more than 64
+fn read_low(reader: &mut BitReader, len: u32) -> Result<u128, DecodeError> {
+    reader
+        .read_int(len)
+        .map(u128::from)
+        .ok_or(DecodeError::UnexpectedEof)
+}
@@ -0,0 +1,142 @@
+use std::convert::TryFrom;
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{decode::strip_padding, Code, DecodeError, Huffman};
+
+/// A symbol that can be written as a fixed number of raw bits, so it can
+/// follow an escape code when it has no code of its own
+pub trait Literal: Sized {
+    /// How many bits every literal takes, at most 64
+    const BITS: u32;
+
+    /// The symbol's bits, in the lowest [`BITS`](#associatedconstant.BITS)
+    /// bits
+    fn to_literal(&self) -> u64;
+    /// The symbol with the given bits, or `None` if there isn't one
+    fn from_literal(literal: u64) -> Option<Self>;
+}
+
+macro_rules! impl_literal {
+    ($($ty:ty),*) => {
+        $(
+            impl Literal for $ty {
+                const BITS: u32 = <$ty>::BITS;
+
+                fn to_literal(&self) -> u64 {
+                    u64::from(*self)
+                }
+                fn from_literal(literal: u64) -> Option<Self> {
+                    Self::try_from(literal).ok()
+                }
+            }
+        )*
+    };
+}
+
+impl_literal!(u8, u16, u32, u64);
+
+impl Literal for char {
+    /// Enough for every Unicode scalar value
+    const BITS: u32 = 21;
+
+    fn to_literal(&self) -> u64 {
+        u64::from(u32::from(*self))
+    }
+    fn from_literal(literal: u64) -> Option<Self> {
+        u32::try_from(literal).ok().and_then(Self::from_u32)
+    }
+}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone + Literal,
+{
+    /// Compresses `input` as [`compress`](#method.compress) does, but writes
+    /// any symbol without a code as the code of `escape` followed by the
+    /// symbol's raw bits, rather than failing. `escape` itself is always
+    /// written this way, so it should be a symbol that's rarely seen, such as
+    /// one added with
+    /// [`HuffmanBuilder::escape`](struct.HuffmanBuilder.html#method.escape)
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, HuffmanBuilder};
+    ///
+    /// let training: Vec<char> = "trained on lowercase".chars().collect();
+    /// let tree = HuffmanBuilder::new().escape('\0').build(&training);
+    ///
+    /// let input: Vec<char> = "Then came CAPITALS".chars().collect();
+    /// assert!(tree.compress(&input).is_none());
+    ///
+    /// let mut data = tree.compress_escaped(&input, &'\0').unwrap();
+    /// data.reverse();
+    /// assert_eq!(Ok(input), tree.reconstruct_escaped(data, 0, &'\0'));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `None` if `escape` isn't in the tree
+    #[must_use]
+    pub fn compress_escaped(&self, input: &[T], escape: &T) -> Option<Code> {
+        let codes = self.to_btree();
+        let escape_code = codes.get(escape)?;
+        let mut output = Code::with_capacity(input.len());
+
+        for symbol in input {
+            match codes.get(symbol) {
+                Some(code) if symbol != escape => output.extend(code.iter().copied()),
+                _ => {
+                    output.extend(escape_code.iter().copied());
+                    let literal = symbol.to_literal();
+                    output.extend((0..T::BITS).rev().map(|i| literal >> i & 1 == 1));
+                }
+            }
+        }
+
+        Some(output)
+    }
+    /// Reconstructs data compressed by
+    /// [`compress_escaped`](#method.compress_escaped), as
+    /// [`reconstruct`](#method.reconstruct) does, reading the raw bits after
+    /// each code of `escape`
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding, or an escaped
+    /// literal isn't a valid symbol
+    pub fn reconstruct_escaped(
+        &self,
+        data: impl Into<Code>,
+        zeros: u8,
+        escape: &T,
+    ) -> Result<Vec<T>, DecodeError> {
+        let mut data = data.into().to_vec();
+        strip_padding(&mut data, zeros)?;
+
+        match self.root() {
+            _ if data.is_empty() => return Ok(Vec::new()),
+            None => return Err(DecodeError::EmptyTree),
+            Some(root) if self.children(root).is_none() => return Err(DecodeError::DanglingBits),
+            Some(_) => {}
+        }
+
+        let mut output = Vec::with_capacity(data.len() / 8);
+        while !data.is_empty() {
+            let symbol = self
+                .get_char_rec(&mut data)
+                .ok_or(DecodeError::UnexpectedEof)?;
+            if symbol != *escape {
+                output.push(symbol);
+                continue;
+            }
+
+            let mut literal = 0;
+            for _ in 0..T::BITS {
+                let bit = data.pop().ok_or(DecodeError::UnexpectedEof)?;
+                literal = literal << 1 | u64::from(bit);
+            }
+            output.push(T::from_literal(literal).ok_or(DecodeError::InvalidLiteral)?);
+        }
+
+        Ok(output)
+    }
+}
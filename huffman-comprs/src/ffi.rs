@@ -0,0 +1,170 @@
+//! A C interface to the crate, for linking against the `cdylib` built with
+//! the `ffi` feature. The functions are declared for C and C++ in
+//! `include/huffman_comprs.h`.
+//!
+//! Trees are handed out as opaque pointers, freed with [`huffman_tree_free`].
+//! Compressed and decompressed data is returned through an out pointer and
+//! length, as a whole `.rz` file when compressing, and freed with
+//! [`huffman_buffer_free`]. Functions returning an `int` return 0 on success,
+//! or -1 on failure, with the reason available from [`huffman_last_error`]
+//! ```c
+//! #include "huffman_comprs.h"
+//!
+//! huffman_tree *tree = huffman_tree_new(sample, sample_len);
+//! uint8_t *compressed;
+//! size_t compressed_len;
+//! if (huffman_compress(tree, input, input_len, &compressed, &compressed_len) != 0) {
+//!     fprintf(stderr, "%s\n", huffman_last_error());
+//! }
+//! huffman_buffer_free(compressed, compressed_len);
+//! huffman_tree_free(tree);
+//! ```
+
+use std::{
+    cell::RefCell,
+    ffi::CString,
+    fmt::Display,
+    os::raw::{c_char, c_int},
+    ptr, slice,
+};
+
+use crate::{DecodeOptions, Huffman, RZFile};
+
+thread_local! {
+    /// The reason the last call on this thread failed
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records `e` as the last error, returning -1 to be returned to C
+fn fail(e: impl Display) -> c_int {
+    // Interior nul bytes would cut the message short, so they're replaced
+    let message = e.to_string().replace('\0', " ");
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).ok());
+    -1
+}
+
+/// Borrows `len` bytes from `data`, which may be null if `len` is 0
+const unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        unsafe { slice::from_raw_parts(data, len) }
+    }
+}
+
+/// Hands `buf` over to C, writing it to `out` and `out_len`
+unsafe fn give(buf: Vec<u8>, out: *mut *mut u8, out_len: *mut usize) {
+    let len = buf.len();
+    let buf = Box::into_raw(buf.into_boxed_slice());
+    unsafe {
+        *out_len = len;
+        *out = buf.cast();
+    }
+}
+
+/// Builds a tree from the frequency of each byte in `data`, to be freed with
+/// [`huffman_tree_free`]
+///
+/// # Safety
+/// `data` must point to `len` readable bytes, or may be null if `len` is 0
+#[no_mangle]
+pub unsafe extern "C" fn huffman_tree_new(data: *const u8, len: usize) -> *mut Huffman<u8> {
+    let data = unsafe { bytes(data, len) };
+    Box::into_raw(Box::new(Huffman::from(data.to_vec())))
+}
+
+/// Frees a tree made by [`huffman_tree_new`]. Null is ignored
+///
+/// # Safety
+/// `tree` must be null, or a tree from [`huffman_tree_new`] not yet freed
+#[no_mangle]
+pub unsafe extern "C" fn huffman_tree_free(tree: *mut Huffman<u8>) {
+    if !tree.is_null() {
+        drop(unsafe { Box::from_raw(tree) });
+    }
+}
+
+/// Compresses `len` bytes from `data` with `tree`, into an `.rz` file with a
+/// checksum, writing it to `out` and its length to `out_len`. Returns 0 on
+/// success, or -1 if a byte isn't in the tree
+///
+/// # Safety
+/// `tree` must be a tree from [`huffman_tree_new`], `data` must point to
+/// `len` readable bytes, or may be null if `len` is 0, and `out` and
+/// `out_len` must be writable
+#[no_mangle]
+pub unsafe extern "C" fn huffman_compress(
+    tree: *const Huffman<u8>,
+    data: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    if tree.is_null() {
+        return fail("the tree is null");
+    }
+    let (tree, data) = unsafe { (&*tree, bytes(data, len)) };
+
+    let Some(file) = RZFile::compress(tree.clone(), data) else {
+        return fail("a byte of the input isn't in the tree");
+    };
+    let mut buf = Vec::new();
+    if let Err(e) = file.with_checksum(data).write_to(&mut buf) {
+        return fail(e);
+    }
+
+    unsafe { give(buf, out, out_len) };
+    0
+}
+
+/// Decompresses `len` bytes of `.rz` files from `data`, one after another.
+///
+/// The bytes they hold are written to `out`, and their length to `out_len`.
+/// Returns 0 on success, or -1 if a file is malformed, or doesn't match its
+/// checksum
+///
+/// # Safety
+/// `data` must point to `len` readable bytes, or may be null if `len` is 0,
+/// and `out` and `out_len` must be writable
+#[no_mangle]
+pub unsafe extern "C" fn huffman_decompress(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> c_int {
+    let data = unsafe { bytes(data, len) };
+
+    let mut buf = Vec::new();
+    for file in RZFile::<u8>::frames(data) {
+        match file.map(|file| file.decompress_with(&DecodeOptions::new())) {
+            Ok(Ok(output)) => buf.extend(output),
+            Ok(Err(e)) => return fail(e),
+            Err(e) => return fail(e),
+        }
+    }
+
+    unsafe { give(buf, out, out_len) };
+    0
+}
+
+/// Frees a buffer written by [`huffman_compress`] or [`huffman_decompress`].
+/// Null is ignored
+///
+/// # Safety
+/// `data` must be null, or a buffer not yet freed, with `len` the length it
+/// was returned with
+#[no_mangle]
+pub unsafe extern "C" fn huffman_buffer_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)) });
+    }
+}
+
+/// The reason the last call on this thread failed, or null if none has. The
+/// string is owned by the crate, and valid until the next failing call on
+/// this thread
+#[no_mangle]
+pub extern "C" fn huffman_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
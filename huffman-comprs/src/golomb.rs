@@ -0,0 +1,195 @@
+//! Golomb and Rice codes, for integers that are mostly small, with larger
+//! ones growing rarer geometrically, such as the differences between
+//! readings of a sensor, or the lengths of runs.
+//!
+//! A value is divided by the code's divisor, writing the quotient in unary,
+//! as that many `1`s and a `0`, then the remainder in binary. Where a
+//! [`Huffman`](../struct.Huffman.html) tree needs a code for every value,
+//! stored alongside the data, a Golomb code is described by its divisor
+//! alone, and codes values it's never seen. A Rice code is a Golomb code
+//! whose divisor is a power of two, so every remainder takes the same number
+//! of bits:
+//! ```
+//! use huffman_comprs::golomb::{self, Golomb};
+//!
+//! let readings = [1000_i64, 1003, 1001, 998, 1004, 1004, 1002];
+//! let deltas: Vec<u64> = readings
+//!     .windows(2)
+//!     .map(|pair| golomb::zigzag(pair[1] - pair[0]))
+//!     .collect();
+//!
+//! let code = Golomb::fit_rice(&deltas);
+//! let bytes = code.encode(&deltas);
+//!
+//! assert_eq!(Ok(deltas), code.decode(&bytes, 6));
+//! ```
+//!
+//! [`Golomb::write`](struct.Golomb.html#method.write) and
+//! [`Golomb::read`](struct.Golomb.html#method.read) code a single value with
+//! a [`BitWriter`](../bits/struct.BitWriter.html) or
+//! [`BitReader`](../bits/struct.BitReader.html), so values can be mixed with
+//! other codes in the same stream
+
+use crate::{
+    bits::{BitReader, BitWriter},
+    DecodeError,
+};
+
+/// A Golomb code, with a divisor of at least 1
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Golomb {
+    divisor: u64,
+    /// The number of bits a remainder takes, less one if it's below `cutoff`
+    bits: u32,
+    cutoff: u64,
+}
+
+impl Golomb {
+    /// The Golomb code dividing values by `divisor`, or `None` if `divisor`
+    /// is 0
+    #[must_use]
+    pub const fn new(divisor: u64) -> Option<Self> {
+        if divisor == 0 {
+            return None;
+        }
+        // Remainders are coded in truncated binary, so the first `cutoff`
+        // take a bit less than the rest
+        let bits = u64::BITS - (divisor - 1).leading_zeros();
+        let cutoff = if bits == 64 {
+            0_u64.wrapping_sub(divisor)
+        } else {
+            (1 << bits) - divisor
+        };
+        Some(Self {
+            divisor,
+            bits,
+            cutoff,
+        })
+    }
+    /// The Rice code dividing values by `2^k`, so writing the lowest `k` bits
+    /// of each value as they are, or `None` if `k` is 64 or more
+    #[must_use]
+    pub const fn rice(k: u32) -> Option<Self> {
+        if k >= u64::BITS {
+            return None;
+        }
+        Self::new(1 << k)
+    }
+    /// The Rice code which codes `values` in the fewest bits
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::golomb::Golomb;
+    ///
+    /// assert_eq!(Golomb::rice(0), Some(Golomb::fit_rice(&[0, 1, 0, 0, 2])));
+    /// assert_eq!(Golomb::rice(4), Some(Golomb::fit_rice(&[20, 9, 31, 17])));
+    /// ```
+    ///
+    /// # Panics
+    /// Never panics, as there's a Rice code for every `k` below 64
+    #[must_use]
+    pub fn fit_rice(values: &[u64]) -> Self {
+        (0..u64::BITS)
+            .filter_map(Self::rice)
+            .min_by_key(|code| code.encoded_len(values))
+            .expect("there's a Rice code for every k below 64")
+    }
+    /// The divisor of the code
+    #[must_use]
+    pub const fn divisor(self) -> u64 {
+        self.divisor
+    }
+    /// The number of bits `value` is coded in. As the quotient is written in
+    /// unary, this grows with `value / divisor`
+    #[must_use]
+    pub const fn code_len(self, value: u64) -> u64 {
+        let bits = if value % self.divisor < self.cutoff {
+            self.bits - 1
+        } else {
+            self.bits
+        };
+        (value / self.divisor).saturating_add(1 + bits as u64)
+    }
+    /// The number of bits `values` are coded in, without coding them
+    #[must_use]
+    pub fn encoded_len(self, values: &[u64]) -> u64 {
+        values
+            .iter()
+            .map(|value| self.code_len(*value))
+            .fold(0, u64::saturating_add)
+    }
+    /// Writes the code of `value`
+    pub fn write(self, writer: &mut BitWriter, value: u64) {
+        for _ in 0..value / self.divisor {
+            writer.write_bit(true);
+        }
+        writer.write_bit(false);
+
+        let remainder = value % self.divisor;
+        if remainder < self.cutoff {
+            writer.write_int(remainder, self.bits - 1);
+        } else {
+            writer.write_int(remainder.wrapping_add(self.cutoff), self.bits);
+        }
+    }
+    /// Reads the code of a value
+    ///
+    /// # Errors
+    /// Returns an error if the bits end part way through the code, or it
+    /// codes a value too large for a `u64`
+    pub fn read(self, reader: &mut BitReader) -> Result<u64, DecodeError> {
+        let mut quotient: u64 = 0;
+        while reader.read_bit().ok_or(DecodeError::UnexpectedEof)? {
+            quotient = quotient.checked_add(1).ok_or(DecodeError::ValueOverflow)?;
+        }
+
+        let mut remainder = if self.bits == 0 {
+            0
+        } else {
+            reader
+                .read_int(self.bits - 1)
+                .ok_or(DecodeError::UnexpectedEof)?
+        };
+        if remainder >= self.cutoff && self.bits > 0 {
+            let bit = reader.read_bit().ok_or(DecodeError::UnexpectedEof)?;
+            remainder = (remainder << 1 | u64::from(bit)).wrapping_sub(self.cutoff);
+        }
+
+        quotient
+            .checked_mul(self.divisor)
+            .and_then(|value| value.checked_add(remainder))
+            .ok_or(DecodeError::ValueOverflow)
+    }
+    /// Codes every value in `values`, padding the last byte with zeros
+    #[must_use]
+    pub fn encode(self, values: &[u64]) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+        for value in values {
+            self.write(&mut writer, *value);
+        }
+        writer.finish().0
+    }
+    /// Reads `count` values coded by [`encode`](#method.encode)
+    ///
+    /// # Errors
+    /// Returns an error if `bytes` end before `count` values have been read,
+    /// or any value is too large for a `u64`
+    pub fn decode(self, bytes: &[u8], count: usize) -> Result<Vec<u64>, DecodeError> {
+        let mut reader = BitReader::new(bytes);
+        (0..count).map(|_| self.read(&mut reader)).collect()
+    }
+}
+
+/// Maps a signed value to an unsigned one, interleaving positive and
+/// negative values, so that those near zero stay small: 0, -1, 1, -2 and 2
+/// become 0, 1, 2, 3 and 4
+#[must_use]
+pub const fn zigzag(value: i64) -> u64 {
+    (value << 1 ^ value >> 63).cast_unsigned()
+}
+
+/// Maps a value given by [`zigzag`](fn.zigzag.html) back to the signed value
+/// it was given
+#[must_use]
+pub const fn unzigzag(value: u64) -> i64 {
+    (value >> 1).cast_signed() ^ -(value & 1).cast_signed()
+}
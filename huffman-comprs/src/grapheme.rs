@@ -0,0 +1,52 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Code, Huffman, Tokenizer};
+
+/// Splits text into extended grapheme clusters, being what a reader sees as a
+/// single character.
+///
+/// An emoji with a skin tone modifier, or a letter with
+/// combining accents, is then one token, rather than several `char`s
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Graphemes;
+
+impl Tokenizer for Graphemes {
+    type Token = String;
+
+    fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = String> + 'a {
+        input.graphemes(true).map(str::to_string)
+    }
+}
+
+impl Huffman<String> {
+    /// Builds a tree whose symbols are the extended grapheme clusters of
+    /// `text`, as split by [`Graphemes`](struct.Graphemes.html)
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let text = "👍🏽 e\u{301}";
+    /// let tree = Huffman::from_graphemes(text);
+    ///
+    /// assert_eq!(3, tree.contents().len());
+    ///
+    /// let mut data = tree.compress_graphemes(text).unwrap();
+    /// data.reverse();
+    /// assert_eq!(text, tree.reconstruct(data, 0).unwrap().concat());
+    /// ```
+    #[must_use]
+    pub fn from_graphemes(text: &str) -> Self {
+        Self::from_tokens(text, &Graphemes)
+    }
+    /// Compresses each extended grapheme cluster of `text`, as
+    /// [`compress_str`](#method.compress_str) does for each `char`. The
+    /// decompressed clusters can be joined back together with `concat`
+    ///
+    /// # Errors
+    /// Returns `None` if any of the grapheme clusters in `text` are not
+    /// contained in self's tree
+    #[must_use]
+    pub fn compress_graphemes(&self, text: &str) -> Option<Code> {
+        self.compress_tokens(text, &Graphemes)
+    }
+}
@@ -0,0 +1,151 @@
+//! The static Huffman code of HPACK, from
+//! [RFC 7541](https://www.rfc-editor.org/rfc/rfc7541#appendix-B), which
+//! HTTP/2 and QPACK, HTTP/3's header compression, use for string literals.
+//!
+//! The code has 257 symbols, being every byte, and an end of string symbol,
+//! [`EOS`](constant.EOS.html), so [`Huffman::hpack`](../struct.Huffman.html#method.hpack)
+//! is a tree of `u16`s. A string literal is padded to a whole byte with the
+//! start of the code for `EOS`, which is all ones, rather than with zeros,
+//! so [`encode`](fn.encode.html) and [`decode`](fn.decode.html) handle the
+//! padding as HPACK does:
+//! ```
+//! use huffman_comprs::hpack;
+//!
+//! // From RFC 7541, appendix C.4.1
+//! let encoded = hpack::encode(b"www.example.com");
+//! assert_eq!(
+//!     vec![0xf1, 0xe3, 0xc2, 0xe5, 0xf2, 0x3a, 0x6b, 0xa0, 0xab, 0x90, 0xf4, 0xff],
+//!     encoded
+//! );
+//!
+//! assert_eq!(Ok(b"www.example.com".to_vec()), hpack::decode(&encoded));
+//! ```
+
+use std::{convert::TryFrom, sync::OnceLock};
+
+use crate::{
+    bits::{BitReader, BitWriter},
+    Code, DecodeError, Huffman,
+};
+
+/// The end of string symbol, which only ever pads the end of a string
+pub const EOS: u16 = 256;
+
+impl Huffman<u16> {
+    /// The static Huffman code of HPACK, with a symbol for every byte, and
+    /// [`hpack::EOS`](hpack/constant.EOS.html) for the end of the string.
+    /// The codes are canonical, so built from their lengths
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::hpack();
+    ///
+    /// assert_eq!("00011", tree.get_code(u16::from(b'a')).unwrap().to_string());
+    /// assert_eq!("1".repeat(30), tree.get_code(256).unwrap().to_string());
+    /// ```
+    ///
+    /// # Panics
+    /// Never panics, as the table of code lengths describes a full tree
+    #[must_use]
+    pub fn hpack() -> Self {
+        let lengths: Vec<(u16, u8)> = (0..).zip(LENGTHS).collect();
+        Self::from_code_lengths(&lengths).expect("the HPACK code lengths describe a full tree")
+    }
+}
+
+/// Encodes `input` as an HPACK string literal, padding the last byte with
+/// ones
+#[must_use]
+pub fn encode(input: &[u8]) -> Vec<u8> {
+    let (codes, _) = cached();
+    let mut writer = BitWriter::new();
+    for byte in input {
+        writer.extend(&codes[usize::from(*byte)]);
+    }
+
+    let (mut bytes, zeros) = writer.finish();
+    if let Some(last) = bytes.last_mut() {
+        *last |= (1 << zeros) - 1;
+    }
+    bytes
+}
+
+/// The number of bytes [`encode`](fn.encode.html) would encode `input` to,
+/// so an encoder can choose whether Huffman coding a string is worth it,
+/// without encoding it
+#[must_use]
+pub fn encoded_len(input: &[u8]) -> usize {
+    let bits: usize = input
+        .iter()
+        .map(|byte| usize::from(LENGTHS[usize::from(*byte)]))
+        .sum();
+    bits.div_ceil(8)
+}
+
+/// Decodes an HPACK string literal
+///
+/// # Errors
+/// Fails if `input` ends part way through a code, with padding that's a
+/// byte or longer, or isn't all ones, as
+/// [`DecodeError::DanglingBits`](../enum.DecodeError.html#variant.DanglingBits),
+/// or holds [`EOS`](constant.EOS.html), as
+/// [`DecodeError::EndOfString`](../enum.DecodeError.html#variant.EndOfString)
+pub fn decode(input: &[u8]) -> Result<Vec<u8>, DecodeError> {
+    let (_, tree) = cached();
+    // A tree of 257 symbols always has a root with children
+    let root = tree.root().ok_or(DecodeError::EmptyTree)?;
+    let mut output = Vec::with_capacity(input.len() * 8 / 5);
+
+    let mut node = root;
+    // How many bits into the current code, and whether they're all ones, so
+    // could be padding
+    let mut depth = 0;
+    let mut ones = true;
+    for bit in BitReader::new(input) {
+        let [left, right] = tree.children(node).ok_or(DecodeError::EmptyTree)?;
+        node = if bit { right } else { left };
+        depth += 1;
+        ones &= bit;
+
+        if tree.children(node).is_none() {
+            let symbol = tree.contents[node];
+            output.push(u8::try_from(symbol).map_err(|_| DecodeError::EndOfString)?);
+            node = root;
+            depth = 0;
+            ones = true;
+        }
+    }
+
+    if depth > 7 || !ones {
+        return Err(DecodeError::DanglingBits);
+    }
+    Ok(output)
+}
+
+/// The code of every symbol, indexed by the symbol, and the tree, which
+/// every call shares, built on first use
+fn cached() -> &'static (Vec<Code>, Huffman<u16>) {
+    static CACHED: OnceLock<(Vec<Code>, Huffman<u16>)> = OnceLock::new();
+    CACHED.get_or_init(|| {
+        let tree = Huffman::hpack();
+        let codes = (0..=EOS)
+            .filter_map(|symbol| tree.get_code(symbol))
+            .collect();
+        (codes, tree)
+    })
+}
+
+/// The length of each symbol's code, in bits, indexed by the symbol
+const LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 30, 28,
+    28, 28, 28, 28, 28, 28, 28, 28, 6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6, 5, 5,
+    5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10, 13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7,
+    7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6, 15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6,
+    6, 5, 6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28, 20, 22, 20, 20, 22, 22, 22, 23, 22,
+    23, 23, 23, 23, 23, 24, 23, 24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24, 22,
+    21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23, 21, 21, 22, 21, 23, 22, 23, 23, 20,
+    22, 22, 22, 23, 22, 22, 23, 26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25, 19,
+    21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27, 20, 24, 20, 21, 22, 21, 21, 23, 22,
+    22, 25, 25, 24, 24, 26, 23, 26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26, 30,
+];
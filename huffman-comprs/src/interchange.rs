@@ -0,0 +1,106 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::Huffman;
+
+#[cfg(feature = "json")]
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Serializes the tree to JSON, so it can be read from other languages
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::from("abbccc");
+    /// let json = tree.to_json().unwrap();
+    ///
+    /// let read: Huffman<char> = Huffman::from_json(&json).unwrap();
+    /// assert_eq!(tree.get_code('b'), read.get_code('b'));
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if a symbol can't be serialized to JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + DeserializeOwned + Clone,
+{
+    /// Deserializes a tree from JSON written by
+    /// [`to_json`](#method.to_json). The tree should be checked with
+    /// [`validate`](#method.validate) if it's from an untrusted source
+    ///
+    /// # Errors
+    /// Fails if `json` isn't a serialized tree
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Serializes the tree to CBOR, so it can be read from other languages
+    ///
+    /// # Errors
+    /// Fails if a symbol can't be serialized to CBOR
+    pub fn to_cbor(&self) -> Result<Vec<u8>, ciborium::ser::Error<std::io::Error>> {
+        let mut buf = Vec::new();
+        ciborium::into_writer(self, &mut buf)?;
+        Ok(buf)
+    }
+}
+
+#[cfg(feature = "cbor")]
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + DeserializeOwned + Clone,
+{
+    /// Deserializes a tree from CBOR written by
+    /// [`to_cbor`](#method.to_cbor). The tree should be checked with
+    /// [`validate`](#method.validate) if it's from an untrusted source
+    ///
+    /// # Errors
+    /// Fails if `cbor` isn't a serialized tree
+    pub fn from_cbor(cbor: &[u8]) -> Result<Self, ciborium::de::Error<std::io::Error>> {
+        ciborium::from_reader(cbor)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Serializes the tree to `MessagePack`, with named fields, so it can be
+    /// read from other languages
+    ///
+    /// # Errors
+    /// Fails if a symbol can't be serialized to `MessagePack`
+    pub fn to_msgpack(&self) -> Result<Vec<u8>, rmp_serde::encode::Error> {
+        rmp_serde::to_vec_named(self)
+    }
+}
+
+#[cfg(feature = "msgpack")]
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + DeserializeOwned + Clone,
+{
+    /// Deserializes a tree from `MessagePack` written by
+    /// [`to_msgpack`](#method.to_msgpack). The tree should be checked with
+    /// [`validate`](#method.validate) if it's from an untrusted source
+    ///
+    /// # Errors
+    /// Fails if `msgpack` isn't a serialized tree
+    pub fn from_msgpack(msgpack: &[u8]) -> Result<Self, rmp_serde::decode::Error> {
+        rmp_serde::from_slice(msgpack)
+    }
+}
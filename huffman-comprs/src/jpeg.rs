@@ -0,0 +1,330 @@
+//! Reading and writing the Huffman tables of JPEG files, which are held in
+//! DHT, define Huffman table, segments.
+//!
+//! A table is stored as BITS, the number of codes of each length from 1 to
+//! 16 bits, then HUFFVAL, the symbols in order of their codes. Codes are
+//! assigned counting upwards, as canonical codes are, but in the order the
+//! symbols are listed rather than by their value, and the code of all ones is
+//! never used, so a JPEG table never describes a full tree.
+//!
+//! [`Huffman::from_jpeg_table`](../struct.Huffman.html#method.from_jpeg_table)
+//! gives the codes left unused to bytes missing from the table, with a
+//! frequency of 0, so that the tree is full, and
+//! [`Huffman::to_jpeg_table`](../struct.Huffman.html#method.to_jpeg_table)
+//! leaves out any symbol with a frequency of 0:
+//! ```
+//! use huffman_comprs::jpeg::{DhtTable, TableClass};
+//!
+//! // The luminance DC table suggested by the JPEG standard
+//! let table = DhtTable {
+//!     class: TableClass::Dc,
+//!     id: 0,
+//!     counts: [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+//!     values: (0..12).collect(),
+//! };
+//!
+//! let tree = table.to_huffman().unwrap();
+//! assert_eq!("00", tree.get_code(0).unwrap().to_string());
+//! assert_eq!("111111110", tree.get_code(11).unwrap().to_string());
+//!
+//! let mut segment = Vec::new();
+//! DhtTable::write_segment(&[table.clone()], &mut segment).unwrap();
+//! assert_eq!(Ok(vec![table]), DhtTable::parse_segment(&segment));
+//! ```
+
+use std::{
+    convert::TryFrom,
+    error::Error,
+    fmt,
+    io::{self, Write},
+};
+
+use crate::{Huffman, TreeError};
+
+/// The marker every DHT segment starts with
+pub const DHT_MARKER: [u8; 2] = [0xff, 0xc4];
+
+/// The longest code a JPEG table can hold
+const MAX_LEN: usize = 16;
+
+/// Which coefficients a table codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TableClass {
+    /// The DC coefficient of each block
+    Dc,
+    /// The AC coefficients of each block
+    Ac,
+}
+
+/// A single Huffman table of a DHT segment
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DhtTable {
+    pub class: TableClass,
+    /// Which of the 4 table slots the table is installed in, from 0 to 3
+    pub id: u8,
+    /// BITS, the number of codes of each length, from 1 to 16 bits
+    pub counts: [u8; MAX_LEN],
+    /// HUFFVAL, the symbols, in order of their codes
+    pub values: Vec<u8>,
+}
+
+/// The ways reading a DHT segment can fail
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DhtError {
+    /// The segment doesn't start with [`DHT_MARKER`](constant.DHT_MARKER.html)
+    BadMarker,
+    /// The segment ends part way through its length, or a table
+    Truncated,
+    /// A table has a class other than 0 or 1, or a slot over 3. Holds the
+    /// byte giving both
+    BadTable(u8),
+    /// The segment holds no tables
+    Empty,
+}
+
+impl fmt::Display for DhtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BadMarker => write!(f, "the segment doesn't start with the DHT marker"),
+            Self::Truncated => write!(f, "the segment ends part way through a table"),
+            Self::BadTable(byte) => write!(f, "{byte:#04x} isn't a valid table class and slot"),
+            Self::Empty => write!(f, "the segment holds no tables"),
+        }
+    }
+}
+
+impl Error for DhtError {}
+
+impl DhtTable {
+    /// Builds a table from `tree`, as
+    /// [`Huffman::to_jpeg_table`](../struct.Huffman.html#method.to_jpeg_table)
+    /// does
+    ///
+    /// # Errors
+    /// Fails as [`Huffman::to_jpeg_table`](../struct.Huffman.html#method.to_jpeg_table)
+    /// does
+    pub fn from_huffman(class: TableClass, id: u8, tree: &Huffman<u8>) -> Result<Self, TreeError> {
+        let (counts, values) = tree.to_jpeg_table()?;
+        Ok(Self {
+            class,
+            id,
+            counts,
+            values,
+        })
+    }
+    /// Builds the tree the table describes, as
+    /// [`Huffman::from_jpeg_table`](../struct.Huffman.html#method.from_jpeg_table)
+    /// does
+    ///
+    /// # Errors
+    /// Fails as [`Huffman::from_jpeg_table`](../struct.Huffman.html#method.from_jpeg_table)
+    /// does
+    pub fn to_huffman(&self) -> Result<Huffman<u8>, TreeError> {
+        Huffman::from_jpeg_table(&self.counts, &self.values)
+    }
+    /// Parses the DHT segment at the start of `buf`, from its marker, which
+    /// may hold many tables. Anything after the segment is ignored, so `buf`
+    /// can be the rest of a JPEG file
+    ///
+    /// # Errors
+    /// Fails if `buf` doesn't start with a DHT segment, or the segment is
+    /// malformed
+    pub fn parse_segment(buf: &[u8]) -> Result<Vec<Self>, DhtError> {
+        if buf.len() < 4 {
+            return Err(DhtError::Truncated);
+        }
+        if buf[0..2] != DHT_MARKER {
+            return Err(DhtError::BadMarker);
+        }
+        // The length counts itself, but not the marker
+        let len = usize::from(u16::from_be_bytes([buf[2], buf[3]]));
+        let mut buf = buf.get(4..2 + len).ok_or(DhtError::Truncated)?;
+
+        let mut tables = Vec::new();
+        while let Some((&slot, rest)) = buf.split_first() {
+            let class = match slot >> 4 {
+                0 => TableClass::Dc,
+                1 => TableClass::Ac,
+                _ => return Err(DhtError::BadTable(slot)),
+            };
+            if slot & 0x0f > 3 {
+                return Err(DhtError::BadTable(slot));
+            }
+            let (counts, rest) = rest
+                .split_first_chunk::<MAX_LEN>()
+                .ok_or(DhtError::Truncated)?;
+            let count = counts.iter().map(|c| usize::from(*c)).sum();
+            if rest.len() < count {
+                return Err(DhtError::Truncated);
+            }
+            let (values, rest) = rest.split_at(count);
+
+            tables.push(Self {
+                class,
+                id: slot & 0x0f,
+                counts: *counts,
+                values: values.to_vec(),
+            });
+            buf = rest;
+        }
+
+        if tables.is_empty() {
+            return Err(DhtError::Empty);
+        }
+        Ok(tables)
+    }
+    /// Writes a DHT segment holding `tables` to `writer`, from its marker
+    ///
+    /// # Errors
+    /// Fails if there's any issue writing to `writer`, or the tables are too
+    /// long to fit in one segment
+    pub fn write_segment<W: Write>(tables: &[Self], mut writer: W) -> io::Result<()> {
+        let len: usize = tables
+            .iter()
+            .map(|table| 1 + MAX_LEN + table.values.len())
+            .sum();
+        let len = u16::try_from(len + 2).map_err(io::Error::other)?;
+
+        writer.write_all(&DHT_MARKER)?;
+        writer.write_all(&len.to_be_bytes())?;
+        for table in tables {
+            let class = match table.class {
+                TableClass::Dc => 0,
+                TableClass::Ac => 1,
+            };
+            writer.write_all(&[(class << 4) | (table.id & 0x0f)])?;
+            writer.write_all(&table.counts)?;
+            writer.write_all(&table.values)?;
+        }
+        Ok(())
+    }
+}
+
+impl Huffman<u8> {
+    /// Builds the tree a JPEG Huffman table describes, from BITS, the number
+    /// of codes of each length, and HUFFVAL, the symbols in order of their
+    /// codes, so each symbol has the same code it would in a JPEG file.
+    ///
+    /// The codes the table leaves unused, such as the code of all ones, are
+    /// given to bytes missing from the table, with a frequency of 0, so that
+    /// the tree is full. Every other symbol has a frequency of 1
+    ///
+    /// # Errors
+    /// Fails if the number of symbols isn't the sum of `counts`, a symbol
+    /// appears more than once, there are more codes of a length than fit, or
+    /// there aren't enough bytes missing from the table to hold the codes
+    /// left unused
+    pub fn from_jpeg_table(counts: &[u8; MAX_LEN], values: &[u8]) -> Result<Self, TreeError> {
+        let count: usize = counts.iter().map(|c| usize::from(*c)).sum();
+        if count != values.len() {
+            return Err(TreeError::WrongSymbolCount(count));
+        }
+        let mut seen = [false; 256];
+        for (i, value) in values.iter().enumerate() {
+            if std::mem::replace(&mut seen[usize::from(*value)], true) {
+                return Err(TreeError::DuplicateSymbol(i));
+            }
+        }
+        if values.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let mut leaves = Vec::with_capacity(values.len());
+        let mut codes = Vec::with_capacity(values.len());
+        let mut values = values.iter();
+        let mut code: u128 = 0;
+        for (len, count) in (1..=MAX_LEN).zip(counts) {
+            for value in values.by_ref().take(usize::from(*count)) {
+                if code >> len > 0 {
+                    return Err(TreeError::KraftViolation);
+                }
+                leaves.push((*value, 1, len));
+                codes.push(code);
+                code += 1;
+            }
+            code <<= 1;
+        }
+
+        // Covers the codes left unused with as few leaves as possible, each
+        // as short as it can be, in units of 16 bit codes
+        let mut missing = (0..=u8::MAX).filter(|byte| !seen[usize::from(*byte)]);
+        let mut next = code >> 1;
+        while next < 1 << MAX_LEN {
+            let mut shift = next.trailing_zeros() as usize;
+            while next + (1 << shift) > 1 << MAX_LEN {
+                shift -= 1;
+            }
+            let filler = missing.next().ok_or(TreeError::IncompleteCode)?;
+            leaves.push((filler, 0, MAX_LEN - shift));
+            codes.push(next >> shift);
+            next += 1 << shift;
+        }
+
+        Ok(Self::build_from_codes(leaves, &codes))
+    }
+    /// Gives the JPEG Huffman table for the tree, as BITS, the number of
+    /// codes of each length, and HUFFVAL, the symbols in order of their
+    /// codes. Symbols with a frequency of 0 are left out, so a tree from
+    /// [`from_jpeg_table`](#method.from_jpeg_table) gives back the same table.
+    ///
+    /// JPEG assigns codes by length alone, so symbols keep the lengths of
+    /// their codes, but may not keep the codes themselves. If the tree uses
+    /// every code, one symbol's code is lengthened to free the code of all
+    /// ones, and codes over 16 bits are shortened, as libjpeg does
+    ///
+    /// # Errors
+    /// Fails if the tree has codes over 16 bits, but doesn't use every code,
+    /// as can happen once symbols with a frequency of 0 are left out
+    pub fn to_jpeg_table(&self) -> Result<([u8; MAX_LEN], Vec<u8>), TreeError> {
+        let depths = self.depths();
+        // Leaves are in order of their codes when the tree is canonical, as
+        // trees from tables are, so sorting by length alone keeps that order
+        let mut leaves: Vec<(usize, u8)> = self
+            .frequencies()
+            .zip(depths)
+            .filter(|((_, freq), _)| *freq > 0)
+            .map(|((symbol, _), depth)| (depth.max(1), *symbol))
+            .collect();
+        leaves.sort_by_key(|(len, _)| *len);
+
+        let max = leaves.last().map_or(0, |(len, _)| *len);
+        let mut per_len = vec![0_usize; max + 2];
+        for (len, _) in &leaves {
+            per_len[*len] += 1;
+        }
+
+        if leaves.len() == self.contents.len() && leaves.len() > 1 {
+            // A pseudo-symbol sharing the longest code of all ones, which is
+            // removed once lengths are limited, as in libjpeg
+            per_len[max] -= 1;
+            per_len[max + 1] += 2;
+            for len in (MAX_LEN + 1..per_len.len()).rev() {
+                while per_len[len] > 0 {
+                    let mut shorter = len - 2;
+                    while per_len[shorter] == 0 {
+                        shorter -= 1;
+                    }
+                    per_len[len] -= 2;
+                    per_len[len - 1] += 1;
+                    per_len[shorter + 1] += 2;
+                    per_len[shorter] -= 1;
+                }
+            }
+            if let Some(longest) = per_len.iter().rposition(|count| *count > 0) {
+                per_len[longest] -= 1;
+            }
+        } else if let Some(long) = leaves.iter().position(|(len, _)| *len > MAX_LEN) {
+            return Err(TreeError::CodeTooLong(long));
+        }
+
+        let mut counts = [0; MAX_LEN];
+        for (count, per_len) in counts.iter_mut().zip(&per_len[1..]) {
+            // At most 256 symbols, all but one of which can share a length
+            *count = u8::try_from(*per_len).unwrap_or(u8::MAX);
+        }
+        Ok((
+            counts,
+            leaves.into_iter().map(|(_, symbol)| symbol).collect(),
+        ))
+    }
+}
@@ -14,14 +14,134 @@
 //! assert_eq!(vec![true, false, true], huffman.get_code('i').unwrap());
 //! ```
 
-use std::collections::BTreeMap;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BinaryHeap},
+    convert::TryFrom,
+    fmt,
+    hash::{Hash, Hasher},
+    io::{self, Read},
+    iter::FromIterator,
+};
 
 #[cfg(feature = "serde_support")]
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "rz")]
+pub mod analysis;
+#[cfg(feature = "rz")]
+mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod bits;
+mod builder;
+mod cancel;
+#[cfg(feature = "rz")]
+mod checksum;
+mod code;
+mod codec;
+pub mod codegen;
+#[cfg(feature = "rz")]
+mod coder;
+#[cfg(feature = "rz")]
+mod copy;
+mod decode;
+#[cfg(feature = "prebuilt")]
+pub mod deflate;
+pub mod elias;
+mod escape;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod golomb;
+#[cfg(feature = "unicode")]
+mod grapheme;
+#[cfg(feature = "prebuilt")]
+pub mod hpack;
+#[cfg(any(feature = "json", feature = "cbor", feature = "msgpack"))]
+mod interchange;
+pub mod jpeg;
+#[cfg(feature = "rz")]
+pub mod logs;
+#[cfg(feature = "rz")]
+mod metadata;
+#[cfg(feature = "rz")]
+mod multi;
+#[cfg(feature = "rz")]
+pub mod packing;
+#[cfg(feature = "rz")]
+mod pipeline;
+#[cfg(feature = "prebuilt")]
+mod prebuilt;
+#[cfg(feature = "rz")]
+mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "rz")]
+mod reader;
 #[cfg(feature = "rz")]
 mod rz;
-pub use rz::RZFile;
+mod sample;
+mod shannon_fano;
+mod static_tree;
+mod stats;
+#[cfg(feature = "async")]
+mod stream;
+mod terminated;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod tokenizer;
+mod trace;
+mod tree;
+mod validate;
+mod walk;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "rz")]
+pub use archive::{ArchiveBuilder, Entry, RZArchive};
+#[cfg(feature = "rz")]
+pub use coder::EntropyCoder;
+#[cfg(feature = "rz")]
+pub use copy::{
+    copy_decode, copy_decode_with_progress, copy_encode, copy_encode_retraining,
+    copy_encode_retraining_with_progress, copy_encode_with_progress, Retrain,
+};
+#[cfg(feature = "rz")]
+pub use metadata::Metadata;
+#[cfg(feature = "rz")]
+pub use multi::MultiHuffman;
+#[cfg(feature = "rz")]
+pub use pipeline::Pipeline;
+#[cfg(feature = "rz")]
+pub use progress::Progress;
+#[cfg(feature = "rz")]
+pub use reader::RzReader;
+pub use rz::{
+    from_compressed_bytes, to_compressed_bytes, BlockCoder, Extension, Frames, RZFile, RzError,
+    SplitError, TreeFormat, RZ_EXTENSION_MAGIC, RZ_MAGIC, RZ_VERSION,
+};
+#[cfg(feature = "async")]
+pub use stream::{AsyncHuffmanReader, AsyncHuffmanWriter};
+
+pub use builder::{HuffmanBuilder, TieBreak};
+pub use cancel::{Cancel, Cancellable};
+pub use code::Code;
+pub use codec::{Decoder, Encoder};
+pub use decode::{DecodeError, DecodeOptions};
+pub use escape::Literal;
+#[cfg(feature = "unicode")]
+pub use grapheme::Graphemes;
+pub use shannon_fano::ShannonFano;
+pub use static_tree::StaticHuffman;
+pub use stats::CompressionStats;
+pub use tokenizer::{Chars, Delimited, Lines, LogTokens, Tokenizer};
+pub use validate::TreeError;
+pub use walk::{NodeRef, NodeView};
+
+use tree::TreeRef;
+
+/// How many bytes [`Huffman::from_reader`](struct.Huffman.html#method.from_reader)
+/// counts at a time
+const READ_CHUNK_LEN: usize = 64 * 1024;
 
 /// A huffman encoding metadata tree.
 /// # Examples
@@ -40,201 +160,598 @@ pub struct Huffman<T>
 where
     T: Serialize + Ord + Clone,
 {
-    freq: usize,
-    left: Option<Box<Huffman<T>>>,
-    right: Option<Box<Huffman<T>>>,
+    /// The symbols of the tree, the `n`th symbol belonging to the `n`th node
     contents: Vec<T>,
+    /// Every node in the tree, leaves first, with the root last
+    nodes: Vec<Node>,
+}
+
+/// A single node of a [`Huffman`](struct.Huffman.html) tree. Nodes without
+/// children are leaves, holding the symbol of the same index.
+///
+/// Only public so [`codegen`](codegen/index.html) output can name it
+#[doc(hidden)]
+#[cfg_attr(feature = "serde_support", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Node {
+    freq: usize,
+    children: Option<[u32; 2]>,
+}
+
+impl Node {
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn new(freq: usize, children: Option<[u32; 2]>) -> Self {
+        Self { freq, children }
+    }
 }
 
 impl<T> Huffman<T>
 where
     T: Serialize + Ord + Clone,
 {
-    fn new(contents: Vec<T>, freq: usize) -> Self {
-        Self {
-            freq,
-            left: None,
-            right: None,
-            contents,
+    /// Builds a tree from each symbol and its frequency, by repeatedly merging
+    /// the two least frequent nodes. Ties are broken by `tie_break`, so the
+    /// resulting tree doesn't depend on the heap's internals
+    fn build(leaves: Vec<(T, usize)>, tie_break: TieBreak) -> Self {
+        trace::span!("build_tree", symbols = leaves.len());
+        let mut contents = Vec::with_capacity(leaves.len());
+        let mut nodes = Vec::with_capacity(leaves.len() * 2);
+
+        for (symbol, freq) in leaves {
+            contents.push(symbol);
+            nodes.push(Node {
+                freq,
+                children: None,
+            });
+        }
+
+        let order = |i: usize| match tie_break {
+            TieBreak::Newest => i,
+            TieBreak::Oldest => usize::MAX - i,
+        };
+
+        let mut heap: BinaryHeap<(Reverse<usize>, usize, usize)> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (Reverse(n.freq), order(i), i))
+            .collect();
+
+        while heap.len() > 1 {
+            let (Reverse(left_freq), _, left) = heap.pop().unwrap();
+            let (Reverse(right_freq), _, right) = heap.pop().unwrap();
+
+            let parent = nodes.len();
+            heap.push((Reverse(left_freq + right_freq), order(parent), parent));
+            nodes.push(Node {
+                freq: left_freq + right_freq,
+                children: Some([index(left), index(right)]),
+            });
+        }
+        trace::event!(nodes = nodes.len(), "built tree");
+
+        Self { contents, nodes }
+    }
+    /// Builds the canonical tree giving each symbol a code of the paired
+    /// length, keeping the paired frequency. Codes are assigned in order of
+    /// length then symbol, counting upwards, so the lengths alone are enough
+    /// to recreate the tree
+    fn build_canonical(mut leaves: Vec<(T, usize, usize)>) -> Self {
+        leaves.sort_by(|(a, _, a_len), (b, _, b_len)| a_len.cmp(b_len).then_with(|| a.cmp(b)));
+
+        let mut codes = Vec::with_capacity(leaves.len());
+        let mut code: u128 = 0;
+        let mut prev_len = leaves.first().map_or(0, |(_, _, len)| *len);
+        for (_, _, len) in &leaves {
+            code <<= len - prev_len;
+            codes.push(code);
+            code += 1;
+            prev_len = *len;
+        }
+
+        Self::build_from_codes(leaves, &codes)
+    }
+    /// Builds the tree giving each symbol the paired code, of the paired
+    /// length, keeping the paired frequency. The codes must be given in
+    /// order, and form a full prefix code
+    fn build_from_codes(leaves: Vec<(T, usize, usize)>, codes: &[u128]) -> Self {
+        trace::span!("build_tree_from_codes", symbols = leaves.len());
+        let mut contents = Vec::with_capacity(leaves.len());
+        let mut nodes = Vec::with_capacity(leaves.len() * 2);
+        let mut lengths = Vec::with_capacity(leaves.len());
+        for (symbol, freq, len) in leaves {
+            contents.push(symbol);
+            nodes.push(Node {
+                freq,
+                children: None,
+            });
+            lengths.push(len);
+        }
+
+        if !nodes.is_empty() {
+            Self::build_canonical_rec(&mut nodes, codes, &lengths, 0, 0..codes.len());
+        }
+
+        Self { contents, nodes }
+    }
+    /// Creates the node covering the leaves in `range`, whose codes all share
+    /// their first `depth` bits, returning its index
+    fn build_canonical_rec(
+        nodes: &mut Vec<Node>,
+        codes: &[u128],
+        lengths: &[usize],
+        depth: usize,
+        range: std::ops::Range<usize>,
+    ) -> usize {
+        if range.len() == 1 && lengths[range.start] == depth {
+            return range.start;
+        }
+
+        let bit = |i: usize| (codes[i] >> (lengths[i] - depth - 1)) & 1 == 1;
+        let split = range.clone().find(|i| bit(*i)).unwrap_or(range.end);
+
+        let left = Self::build_canonical_rec(nodes, codes, lengths, depth + 1, range.start..split);
+        let right = Self::build_canonical_rec(nodes, codes, lengths, depth + 1, split..range.end);
+
+        nodes.push(Node {
+            freq: nodes[left].freq + nodes[right].freq,
+            children: Some([index(left), index(right)]),
+        });
+        nodes.len() - 1
+    }
+    /// Replaces the frequency of each leaf, in order, updating every parent
+    /// to match
+    fn set_frequencies<I: IntoIterator<Item = usize>>(&mut self, freqs: I) {
+        let nodes = &mut self.nodes;
+
+        for (node, freq) in nodes.iter_mut().zip(freqs) {
+            node.freq = freq;
+        }
+        for i in 0..nodes.len() {
+            if let Some([left, right]) = nodes[i].children {
+                nodes[i].freq = nodes[left as usize].freq + nodes[right as usize].freq;
+            }
+        }
+    }
+    /// The depth of every node, indexed the same as `nodes`
+    fn depths(&self) -> Vec<usize> {
+        let mut depths = vec![0; self.nodes.len()];
+
+        // Parents always come after their children
+        for node in (0..self.nodes.len()).rev() {
+            if let Some([left, right]) = self.children(node) {
+                depths[left] = depths[node] + 1;
+                depths[right] = depths[node] + 1;
+            }
         }
+
+        depths
     }
-    fn build_from_children(left: Self, right: Self) -> Self {
-        let mut contents = left.contents.clone();
-        contents.append(&mut right.contents.clone());
+    /// Rebuilds the tree with each symbol's frequency increased by the count
+    /// paired with it, adding any symbols not already in the tree
+    fn absorb<I>(&mut self, counts: I)
+    where
+        I: IntoIterator<Item = (T, usize)>,
+    {
+        let mut leaves: Vec<(T, usize)> = self
+            .frequencies()
+            .map(|(symbol, freq)| (symbol.clone(), freq))
+            .collect();
+        let mut positions: BTreeMap<T, usize> = leaves
+            .iter()
+            .enumerate()
+            .map(|(i, (symbol, _))| (symbol.clone(), i))
+            .collect();
 
-        Self {
-            freq: left.freq + right.freq,
-            left: Some(Box::new(left)),
-            right: Some(Box::new(right)),
-            contents,
+        for (symbol, freq) in counts {
+            if let Some(&i) = positions.get(&symbol) {
+                leaves[i].1 += freq;
+            } else {
+                positions.insert(symbol.clone(), leaves.len());
+                leaves.push((symbol, freq));
+            }
         }
+
+        *self = Self::build(leaves, TieBreak::default());
     }
-    /// Gets the code of a specified character
+    /// The length of the longest code, or 0 if the tree is empty
+    fn max_depth(&self) -> usize {
+        self.depths().into_iter().max().unwrap_or(0)
+    }
+    /// Builds one tree from the combined frequencies of every sample, to be
+    /// shared between many small inputs like them. Each input then only needs
+    /// its data stored, rather than a tree of its own, see
+    /// [`RZFile::with_dictionary`](struct.RZFile.html#method.with_dictionary).
     ///
-    /// # Errors
-    /// Returns `None` if no matching code
-    /// is found in the tree
+    /// Only symbols appearing in the samples can be compressed with the tree
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let samples = [r#"{"id":1}"#, r#"{"id":22}"#, r#"{"id":333}"#];
+    /// let samples: Vec<Vec<char>> = samples.iter().map(|s| s.chars().collect()).collect();
+    ///
+    /// let tree = Huffman::train(samples.iter().map(Vec::as_slice));
+    ///
+    /// let message: Vec<char> = r#"{"id":123}"#.chars().collect();
+    /// assert!(tree.compress(&message).is_some());
+    /// ```
     #[must_use]
-    pub fn get_code(&self, to_get: T) -> Option<Vec<bool>> {
-        let mut code = Vec::new();
-        self._get_code(to_get, &mut code);
+    pub fn train<'s, I>(samples: I) -> Self
+    where
+        T: 's,
+        I: IntoIterator<Item = &'s [T]>,
+    {
+        Self::build(
+            count(samples.into_iter().flatten().cloned()),
+            TieBreak::default(),
+        )
+    }
+    /// Builds a tree from each symbol paired with an arbitrary weight, such as
+    /// a probability, as
+    /// [`HuffmanBuilder::build_from_weights`](struct.HuffmanBuilder.html#method.build_from_weights)
+    /// does. This lets symbols be favoured beyond how often they appear
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// // 'b' is rarely sent, but given the shortest code
+    /// let tree = Huffman::from_weights(vec![('a', 2.0), ('b', 4.0), ('c', 1.0)]).unwrap();
+    ///
+    /// assert_eq!(1, tree.get_code('b').unwrap().len());
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if any weight is negative, infinite or NaN
+    pub fn from_weights<I>(weights: I) -> Result<Self, TreeError>
+    where
+        I: IntoIterator<Item = (T, f64)>,
+    {
+        HuffmanBuilder::new().build_from_weights(weights)
+    }
+    /// Builds the canonical tree giving each symbol a code of the paired
+    /// length, as used by DEFLATE and JPEG. Codes are assigned in order of
+    /// length then symbol, counting upwards. Symbols with a length of 0 are
+    /// left out, as they are in DEFLATE.
+    ///
+    /// The lengths must describe a full tree, with no code left unused, so a
+    /// single symbol on its own can't be given a code. Frequencies aren't
+    /// known, so every symbol is given a frequency of 1
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::from_code_lengths(&[('a', 1), ('b', 2), ('c', 3), ('d', 3)]).unwrap();
+    ///
+    /// assert_eq!("0", tree.get_code('a').unwrap().to_string());
+    /// assert_eq!("110", tree.get_code('c').unwrap().to_string());
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if a symbol appears more than once, a length is over 127, or the
+    /// lengths don't describe a full tree
+    pub fn from_code_lengths(lengths: &[(T, u8)]) -> Result<Self, TreeError> {
+        let mut sorted: Vec<(&T, usize)> =
+            lengths.iter().map(|(symbol, _)| symbol).zip(0..).collect();
+        sorted.sort();
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(TreeError::DuplicateSymbol(pair[0].1.max(pair[1].1)));
+        }
+        if let Some(long) = lengths.iter().position(|(_, len)| *len > 127) {
+            return Err(TreeError::CodeTooLong(long));
+        }
 
-        if code.is_empty() {
-            None
-        } else {
-            Some(code)
+        // The sum of `2^-len`, in units of `2^-127`. Going over `u128::MAX`
+        // means the sum is at least 2
+        let kraft = lengths
+            .iter()
+            .filter(|(_, len)| *len > 0)
+            .try_fold(0_u128, |sum, (_, len)| sum.checked_add(1 << (127 - len)));
+        match kraft {
+            Some(0) => {}
+            Some(sum) if sum == 1 << 127 => {}
+            Some(sum) if sum < 1 << 127 => return Err(TreeError::IncompleteCode),
+            _ => return Err(TreeError::KraftViolation),
         }
+
+        Ok(Self::build_canonical(
+            lengths
+                .iter()
+                .filter(|(_, len)| *len > 0)
+                .map(|(symbol, len)| (symbol.clone(), 1, usize::from(*len)))
+                .collect(),
+        ))
     }
-    fn _get_code(&self, to_get: T, code: &mut Vec<bool>) {
-        if let Some(left) = &self.left {
-            if left.contents.contains(&to_get) {
-                code.push(false);
-                left._get_code(to_get, code);
-            } else if let Some(right) = &self.right {
-                if right.contents.contains(&to_get) {
-                    code.push(true);
-                    right._get_code(to_get, code);
-                }
-            }
+    pub(crate) fn tree_ref(&self) -> TreeRef<'_, T> {
+        TreeRef {
+            contents: &self.contents,
+            nodes: &self.nodes,
         }
     }
+    const fn root(&self) -> Option<usize> {
+        self.nodes.len().checked_sub(1)
+    }
+    fn children(&self, node: usize) -> Option<[usize; 2]> {
+        self.tree_ref().children(node)
+    }
+    /// Gets the code of a specified character, walking the tree to find it.
+    /// [`Encoder::code_of`](struct.Encoder.html#method.code_of) borrows codes
+    /// from a table built once instead, for looking up many symbols
+    ///
+    /// # Errors
+    /// Returns `None` if no matching code
+    /// is found in the tree
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)] // Kept by value for compatibility
+    pub fn get_code(&self, to_get: T) -> Option<Code> {
+        self.tree_ref().get_code(&to_get)
+    }
     /// Attempts to get the `char` associated with a given code.
     /// # Errors
     /// Returns `None` if no matching code is found in the tree
     #[must_use]
     pub fn get_char(&self, mut input: Vec<bool>) -> Option<T> {
         input.reverse();
-        self._get_char(&mut input)
+        self.get_char_rec(&mut input)
     }
-    fn _get_char(&self, input: &mut Vec<bool>) -> Option<T> {
-        if self.contents.len() == 1 {
-            Some(self.contents[0].clone())
-        } else if input.pop()? {
-            self.right.as_ref().and_then(|right| right._get_char(input))
-        } else {
-            self.left.as_ref().and_then(|left| left._get_char(input))
-        }
+    fn get_char_rec(&self, input: &mut Vec<bool>) -> Option<T> {
+        self.tree_ref().get_char_rec(input)
     }
     /// The frequency of all the characters in the huffman tree.
     /// This value should be equal to the total length of the string
     /// used to generate this Huffman tree
     #[must_use]
     pub fn freq(&self) -> usize {
-        self.freq
+        self.root().map_or(0, |root| self.nodes[root].freq)
+    }
+    /// The frequency of each symbol in the tree, in the order they're stored.
+    /// These sum to [`freq`](#method.freq)
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let huffman = Huffman::from("abbccc");
+    /// let mut frequencies: Vec<_> = huffman.frequencies().collect();
+    /// frequencies.sort();
+    ///
+    /// assert_eq!(vec![(&'a', 1), (&'b', 2), (&'c', 3)], frequencies);
+    /// ```
+    pub fn frequencies(&self) -> impl Iterator<Item = (&T, usize)> {
+        self.contents
+            .iter()
+            .zip(self.nodes.iter())
+            .map(|(symbol, node)| (symbol, node.freq))
     }
     /// Gets a reference to the current tree's contents
     #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // Kept as it has always been
     pub fn contents(&self) -> &Vec<T> {
         &self.contents
     }
-    /// Converts self to a `BTreeMap<char, Vec<bool>>`, to allow for faster compression
+    /// Converts self to a `BTreeMap<char, Vec<bool>>`, to allow for faster compression.
+    /// A tree of a single symbol gives it an empty code, so it can only be
+    /// decoded when the number of symbols is known
     #[must_use]
     pub fn to_btree(&self) -> BTreeMap<T, Vec<bool>> {
-        let mut b_tree = BTreeMap::new();
-
-        if let Some(left) = &self.left {
-            left._to_btree(&mut b_tree, vec![false]);
-        }
-        if let Some(right) = &self.right {
-            right._to_btree(&mut b_tree, vec![true]);
-        }
-
-        b_tree
+        self.tree_ref().to_btree()
     }
-    fn _to_btree(&self, b_tree: &mut BTreeMap<T, Vec<bool>>, path: Vec<bool>) {
-        if self.contents.len() == 1 {
-            b_tree.insert(self.contents[0].clone(), path);
-        } else {
-            if let Some(left) = &self.left {
-                let mut left_path = path.clone();
-                left_path.push(false);
-                left._to_btree(b_tree, left_path);
-            }
-            if let Some(right) = &self.right {
-                let mut right_path = path;
-                right_path.push(true);
-                right._to_btree(b_tree, right_path);
-            }
-        }
+    /// Lists the length of each symbol's code, in order of symbol, so a
+    /// compatible decoder can be built elsewhere. Passing them to
+    /// [`from_code_lengths`](#method.from_code_lengths) gives the canonical
+    /// tree with the same lengths, which has the same codes if this tree was
+    /// built canonical with [`HuffmanBuilder`](struct.HuffmanBuilder.html).
+    /// A tree of a single symbol gives it a length of 0
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let huffman = Huffman::from("aaaabbc");
+    ///
+    /// assert_eq!(vec![('a', 1), ('b', 2), ('c', 2)], huffman.code_lengths());
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if a code is longer than 255 bits
+    #[must_use]
+    pub fn code_lengths(&self) -> Vec<(T, u8)> {
+        let depths = self.depths();
+
+        let mut lengths: Vec<(T, u8)> = self
+            .contents
+            .iter()
+            .zip(depths)
+            .map(|(symbol, depth)| {
+                let len = u8::try_from(depth).expect("a code is longer than 255 bits");
+                (symbol.clone(), len)
+            })
+            .collect();
+        lengths.sort_by(|(a, _), (b, _)| a.cmp(b));
+        lengths
     }
-    /// Attempts to reconstruct a String from a given Vec<bool>, also taking
-    /// a u8 'zeros', indicating how many '0's are appended upon the end of
-    /// input. This should be the fifth byte of the .rz file
+    /// Attempts to reconstruct a String from a given [`Code`](struct.Code.html),
+    /// or `Vec<bool>`, also taking a u8 'zeros', indicating how many '0's are
+    /// appended upon the end of input. This is the `zeros` byte of the .rz
+    /// header
     #[must_use]
-    pub fn reconstruct(&self, mut data: Vec<bool>, zeros: u8) -> Option<Vec<T>> {
+    pub fn reconstruct(&self, data: impl Into<Code>, zeros: u8) -> Option<Vec<T>> {
         let mut to_return = Vec::new();
 
-        for _ in 0..zeros {
-            data.pop();
-        }
-
-        while !data.is_empty() {
-            to_return.push(self._get_char(&mut data)?);
-        }
+        self.reconstruct_into(data, zeros, &mut to_return)?;
 
         Some(to_return)
     }
+    /// Reconstructs `data` as [`reconstruct`](#method.reconstruct) does,
+    /// appending to `output` rather than allocating a new `Vec`
+    ///
+    /// # Errors
+    /// Returns `None` if `data` isn't a valid encoding, in which case `output`
+    /// will hold every symbol decoded before the error
+    pub fn reconstruct_into(
+        &self,
+        data: impl Into<Code>,
+        zeros: u8,
+        output: &mut Vec<T>,
+    ) -> Option<()> {
+        let data = data.into();
+        output.reserve(data.len() / 8);
+        self.reconstruct_extend(data, zeros, output)
+    }
+    /// Reconstructs `data` as [`reconstruct`](#method.reconstruct) does,
+    /// streaming each symbol into any collection implementing `Extend`
+    ///
+    /// # Errors
+    /// Returns `None` if `data` isn't a valid encoding, in which case `output`
+    /// will have been extended with every symbol decoded before the error
+    pub fn reconstruct_extend<E>(
+        &self,
+        data: impl Into<Code>,
+        zeros: u8,
+        output: &mut E,
+    ) -> Option<()>
+    where
+        E: Extend<T>,
+    {
+        self.decode_extend(data.into().to_vec(), zeros, output, &DecodeOptions::new())
+            .ok()
+    }
 }
 
 impl<T> Huffman<T>
 where
     T: Serialize + Ord + Clone,
 {
-    /// Attempts to compress a given `&[T]` to a `Vec<bool>`, representing it's
-    /// compressed version
+    /// Attempts to compress a given `&[T]` to a [`Code`](struct.Code.html),
+    /// representing it's compressed version
     ///
     /// # Errors
     /// This method will fail and return `None` if any of the characters in `input`
     /// are not contained in self's tree
     #[must_use]
-    pub fn compress(&self, input: &[T]) -> Option<Vec<bool>> {
-        let mut output = Vec::with_capacity(input.len());
+    pub fn compress(&self, input: &[T]) -> Option<Code> {
+        Encoder::new(self).encode(input)
+    }
+    /// Compresses `input` as [`compress`](#method.compress) does, appending
+    /// the bits to `output` rather than allocating a new `Vec`, so the same
+    /// buffer can be reused across many calls. The table of codes is still
+    /// built on every call, which an [`Encoder`](struct.Encoder.html) avoids
+    ///
+    /// # Errors
+    /// Returns `None`, leaving `output` as it was, if any of the characters in
+    /// `input` are not contained in self's tree
+    pub fn compress_into(&self, input: &[T], output: &mut Vec<bool>) -> Option<()> {
+        Encoder::new(self).encode_into(input, output)
+    }
+    /// Compresses `input`, appending the bits to `output` packed into bytes,
+    /// most significant bit first. The last byte is padded with zeros, and
+    /// how many is returned, to be given back when decompressing
+    ///
+    /// # Errors
+    /// Returns `None`, leaving `output` as it was, if any of the characters in
+    /// `input` are not contained in self's tree
+    pub fn compress_into_bytes(&self, input: &[T], output: &mut Vec<u8>) -> Option<u8> {
+        Encoder::new(self).encode_into_bytes(input, output)
+    }
+    /// Compresses `input` as [`compress`](#method.compress) does, then decodes
+    /// the result, and checks it matches `input` before returning it. This
+    /// guarantees the output can be decompressed, at the cost of doing so once
+    ///
+    /// # Errors
+    /// Returns `None` if any of the characters in `input` are not contained in
+    /// self's tree, or if the compressed data doesn't decode back to `input`
+    #[must_use]
+    pub fn compress_verified(&self, input: &[T]) -> Option<Code> {
+        let output = self.compress(input)?;
 
-        let symbols = self.to_btree();
+        // `reconstruct` reads the data back to front
+        let mut reversed = output.clone();
+        reversed.reverse();
 
-        for character in input.iter() {
-            let c = symbols.get(character)?;
-            output.reserve(c.len());
-            for t in c {
-                output.push(*t);
-            }
+        if self.reconstruct(reversed, 0)? == input {
+            Some(output)
+        } else {
+            None
         }
-
-        Some(output)
     }
 }
 
 impl Huffman<char> {
-    /// Attempts to compress a given `&str` to a `Vec<bool>`, representing it's
-    /// compressed version
+    /// Attempts to compress a given `&str` to a [`Code`](struct.Code.html),
+    /// representing it's compressed version
     ///
     /// # Errors
     /// This method will fail and return `None` if any of the characters in `input`
     /// are not contained in self's tree
     #[must_use]
-    pub fn compress_str(&self, input: &str) -> Option<Vec<bool>> {
-        let mut output = Vec::with_capacity(input.len());
+    pub fn compress_str(&self, input: &str) -> Option<Code> {
+        let mut output = Code::with_capacity(input.len());
 
         let symbols = self.to_btree();
 
         for character in input.chars() {
-            let c = symbols.get(&character)?;
-            output.reserve(c.len());
-            for t in c {
-                output.push(*t);
-            }
+            output.extend(symbols.get(&character)?.iter().copied());
         }
 
         Some(output)
     }
 }
 
+impl Huffman<u8> {
+    /// Builds a tree from every byte read from `reader`, counting them a
+    /// chunk at a time rather than reading it all into memory. This gives the
+    /// same tree as [`Huffman::from`](#impl-From%3C%26Vec%3CT%3E%3E) on the
+    /// whole input, so suits the first pass over a file too large to hold
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let input = b"Counted without being held in memory".to_vec();
+    ///
+    /// let tree = Huffman::from_reader(input.as_slice()).unwrap();
+    ///
+    /// assert_eq!(Huffman::from(&input), tree);
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`
+    pub fn from_reader<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut counts = [0_usize; 256];
+        // Kept so ties are broken as `count` would break them
+        let mut order = Vec::with_capacity(256);
+
+        let mut chunk = vec![0; READ_CHUNK_LEN];
+        loop {
+            let read = match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            for &byte in &chunk[..read] {
+                if counts[usize::from(byte)] == 0 {
+                    order.push(byte);
+                }
+                counts[usize::from(byte)] += 1;
+            }
+        }
+
+        Ok(Self::build(
+            order
+                .into_iter()
+                .map(|byte| (byte, counts[usize::from(byte)]))
+                .collect(),
+            TieBreak::default(),
+        ))
+    }
+}
+
 impl<T> PartialEq for Huffman<T>
 where
     T: Serialize + Ord + Clone,
 {
+    /// Trees are equal if they have the same symbols, in the same places,
+    /// with the same frequencies
     fn eq(&self, other: &Self) -> bool {
-        self.freq == other.freq
+        self.contents == other.contents && self.nodes == other.nodes
     }
 }
 
@@ -245,7 +762,7 @@ where
     T: Serialize + Ord + Clone,
 {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(other.freq.cmp(&self.freq))
+        Some(self.cmp(other))
     }
 }
 
@@ -253,8 +770,87 @@ impl<T> Ord for Huffman<T>
 where
     T: Serialize + Ord + Clone,
 {
+    /// Orders trees by frequency, most frequent first, then by their
+    /// structure, so only equal trees are ordered equal
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        other.freq.cmp(&self.freq)
+        other
+            .freq()
+            .cmp(&self.freq())
+            .then_with(|| self.contents.cmp(&other.contents))
+            .then_with(|| self.nodes.cmp(&other.nodes))
+    }
+}
+
+impl<T> Hash for Huffman<T>
+where
+    T: Serialize + Ord + Clone + Hash,
+{
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.contents.hash(state);
+        self.nodes.hash(state);
+    }
+}
+
+/// Prints the codebook, a table of each symbol in order, with its code, the
+/// code's length, and the symbol's frequency
+/// # Examples
+/// ```
+/// use huffman_comprs::Huffman;
+///
+/// let huffman = Huffman::from("aabc");
+///
+/// assert_eq!(
+///     "symbol  code  length  frequency\n\
+///      a       1          1          2\n\
+///      b       01         2          1\n\
+///      c       00         2          1\n",
+///     huffman.to_string()
+/// );
+/// ```
+impl<T> fmt::Display for Huffman<T>
+where
+    T: Serialize + Ord + Clone + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let frequencies: BTreeMap<&T, usize> = self.frequencies().collect();
+        let rows: Vec<(String, String, usize)> = self
+            .to_btree()
+            .into_iter()
+            .map(|(symbol, code)| {
+                let freq = frequencies[&symbol];
+                let code = code
+                    .iter()
+                    .map(|&bit| if bit { '1' } else { '0' })
+                    .collect();
+                (symbol.to_string(), code, freq)
+            })
+            .collect();
+
+        let symbol_width = rows
+            .iter()
+            .map(|(symbol, _, _)| symbol.chars().count())
+            .fold("symbol".len(), usize::max);
+        let code_width = rows
+            .iter()
+            .map(|(_, code, _)| code.len())
+            .fold("code".len(), usize::max);
+
+        writeln!(
+            f,
+            "{:symbol_width$}  {:code_width$}  length  frequency",
+            "symbol", "code"
+        )?;
+        for (symbol, code, freq) in rows {
+            writeln!(
+                f,
+                "{:symbol_width$}  {:code_width$}  {:>6}  {:>9}",
+                symbol,
+                code,
+                code.len(),
+                freq
+            )?;
+        }
+        Ok(())
     }
 }
 
@@ -272,60 +868,114 @@ where
     T: Serialize + Ord + Clone,
 {
     fn from(buf: &Vec<T>) -> Self {
-        let mut contents = Vec::new();
-        for character in buf.iter() {
-            if let Some(i) = contents
-                .iter()
-                .position(|a: &Self| a.contents[0] == *character)
-            {
-                contents[i].freq += 1;
-            } else {
-                contents.push(Self::new(vec![character.clone()], 1));
-            }
-        }
+        Self::build(count(buf.iter().cloned()), TieBreak::default())
+    }
+}
 
-        while contents.len() > 1 {
-            contents.sort();
+/// Builds a tree from every symbol in an iterator, as
+/// [`Huffman::from`](#impl-From%3C%26Vec%3CT%3E%3E) does from a `Vec`
+/// # Examples
+/// ```
+/// use huffman_comprs::Huffman;
+///
+/// let tree: Huffman<char> = "aabc".chars().filter(|c| *c != 'c').collect();
+///
+/// assert_eq!(Huffman::from("aab"), tree);
+/// ```
+impl<T> FromIterator<T> for Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::build(count(iter), TieBreak::default())
+    }
+}
 
-            let parent =
-                Self::build_from_children(contents.pop().unwrap(), contents.pop().unwrap());
-            contents.push(parent);
-        }
+/// Builds a tree from each symbol paired with how often it appears. Each
+/// symbol should only appear once
+/// # Examples
+/// ```
+/// use huffman_comprs::Huffman;
+///
+/// let tree: Huffman<char> = vec![('a', 2), ('b', 1), ('c', 1)].into_iter().collect();
+///
+/// assert_eq!(Huffman::from("aabc"), tree);
+/// ```
+impl<T> FromIterator<(T, usize)> for Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    fn from_iter<I: IntoIterator<Item = (T, usize)>>(iter: I) -> Self {
+        Self::build(iter.into_iter().collect(), TieBreak::default())
+    }
+}
+
+/// Adds every symbol in an iterator to the frequencies the tree was built
+/// from, then rebuilds it, so training data can be gathered in batches
+/// without keeping counts on the side. Trees read back from a format that
+/// drops frequencies, such as [`TreeFormat::Compact`](enum.TreeFormat.html),
+/// count each of their symbols as appearing once
+/// # Examples
+/// ```
+/// use huffman_comprs::Huffman;
+///
+/// let mut tree = Huffman::from("first batch, ");
+/// tree.extend("second batch".chars());
+///
+/// assert_eq!(Huffman::from("first batch, second batch").to_btree(), tree.to_btree());
+/// ```
+impl<T> Extend<T> for Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.absorb(count(iter));
+    }
+}
 
-        contents.pop().unwrap()
+/// Adds each symbol paired with how often it appears to the frequencies the
+/// tree was built from, then rebuilds it, as extending it with symbols does
+impl<T> Extend<(T, usize)> for Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    fn extend<I: IntoIterator<Item = (T, usize)>>(&mut self, iter: I) {
+        self.absorb(iter);
     }
 }
 
 impl From<&str> for Huffman<char> {
     fn from(buf: &str) -> Self {
-        let mut contents = Vec::new();
-        for character in buf.chars() {
-            if let Some(i) = contents
-                .iter()
-                .position(|a: &Self| a.contents[0] == character)
-            {
-                contents[i].freq += 1;
-            } else {
-                contents.push(Self::new(vec![character], 1));
-            }
-        }
-
-        while contents.len() > 1 {
-            contents.sort();
+        Self::build(count(buf.chars()), TieBreak::default())
+    }
+}
 
-            let parent =
-                Self::build_from_children(contents.pop().unwrap(), contents.pop().unwrap());
-            contents.push(parent);
+/// Counts how many times each symbol appears, in order of first appearance,
+/// which decides how ties between equal frequencies are broken. Each symbol's
+/// place in the counts is looked up in a map, rather than by scanning them,
+/// so large alphabets, such as words, are counted in `O(n log k)`
+fn count<T, I>(symbols: I) -> Vec<(T, usize)>
+where
+    T: Ord + Clone,
+    I: IntoIterator<Item = T>,
+{
+    let mut counts: Vec<(T, usize)> = Vec::new();
+    let mut positions: BTreeMap<T, usize> = BTreeMap::new();
+    for symbol in symbols {
+        if let Some(&i) = positions.get(&symbol) {
+            counts[i].1 += 1;
+        } else {
+            positions.insert(symbol.clone(), counts.len());
+            counts.push((symbol, 1));
         }
-
-        contents.pop().unwrap()
     }
+    counts
 }
 
 /// A utility function, splitting up `byte` into a `Vec<bool>`, representing
 /// it's bits
 #[must_use]
-#[inline(always)]
+#[inline]
 pub fn u8_to_bits(byte: u8) -> Vec<bool> {
     vec![
         byte & 1 == 1,
@@ -338,3 +988,40 @@ pub fn u8_to_bits(byte: u8) -> Vec<bool> {
         byte & 128 == 128,
     ]
 }
+
+/// Splits up every byte of `bytes` into bits, as [`u8_to_bits`] does, one
+/// byte after another.
+///
+/// This turns the [`data`](struct.RZFile.html#method.data) of an `RZFile`
+/// into the bits [`reconstruct`](struct.Huffman.html#method.reconstruct)
+/// takes
+#[must_use]
+pub fn bytes_to_bits(bytes: &[u8]) -> Vec<bool> {
+    bits::BitReader::with_order(bytes, bits::BitOrder::LsbFirst).collect()
+}
+
+/// Packs `bits` into bytes, undoing [`bytes_to_bits`], with the first of each
+/// 8 bits in the lowest bit of its byte. The last byte is padded with zeros,
+/// and how many is returned alongside the bytes
+#[must_use]
+pub fn bits_to_bytes(bits: &[bool]) -> (Vec<u8>, u8) {
+    let bytes = bits
+        .chunks(8)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .fold(0, |byte, (i, bit)| byte | u8::from(*bit) << i)
+        })
+        .collect();
+
+    // Always less than 8, so the cast can't truncate
+    #[allow(clippy::cast_possible_truncation)]
+    let zeros = ((8 - bits.len() % 8) % 8) as u8;
+    (bytes, zeros)
+}
+
+/// Converts a node's position to the `u32` stored in its parent
+fn index(node: usize) -> u32 {
+    u32::try_from(node).expect("a Huffman tree can't have more than u32::MAX nodes")
+}
@@ -0,0 +1,204 @@
+//! A model for structured logs, coding the words and punctuation that recur
+//! from line to line as single symbols.
+//!
+//! A tree over the characters of a log can't see that `level=INFO` or
+//! `db::pool` come up on every other line, so spends bits on each character
+//! of them every time. [`LogModel`](struct.LogModel.html) splits the log
+//! with [`LogTokens`](../struct.LogTokens.html), and gives each of the most
+//! frequent tokens a code of its own. The rest, such as timestamps, ids and
+//! counts, which rarely repeat, are written as an escape symbol, with their
+//! bytes coded by a second tree, over bytes:
+//! ```
+//! use huffman_comprs::logs::{LogFile, LogModel};
+//!
+//! let log: String = (0..200)
+//!     .map(|i| format!("ts={i} level=INFO module=db::pool msg=\"checked out\" conn={}\n", i % 7))
+//!     .collect();
+//!
+//! let model = LogModel::train(&log, 1000);
+//! let file = model.compress(&log);
+//! let mut bytes = Vec::new();
+//! file.write_to(&mut bytes).unwrap();
+//! assert!(bytes.len() < log.len() / 3);
+//!
+//! let read = LogFile::read_from(bytes.as_slice()).unwrap();
+//! assert_eq!(Ok(log), read.decompress());
+//! ```
+
+use std::{
+    collections::BTreeSet,
+    io::{self, Read, Write},
+};
+
+use crate::{
+    copy::train, count, DecodeError, DecodeOptions, Huffman, LogTokens, RZFile, RzError, TieBreak,
+    Tokenizer,
+};
+
+/// The byte ending each escaped token's bytes, which never appears in UTF-8
+const END: u8 = 0xff;
+
+/// The most tokens [`LogFile::compress`](struct.LogFile.html#method.compress)
+/// gives codes of their own
+pub const DEFAULT_MAX_TOKENS: usize = 4096;
+
+/// A tree over the frequent tokens of a log, with `None` as the escape for
+/// every other token, and a tree over the bytes of escaped tokens
+#[derive(Clone, Debug)]
+pub struct LogModel {
+    tokens: Huffman<Option<String>>,
+    bytes: Huffman<u8>,
+}
+
+impl LogModel {
+    /// Trains a model on `text`, giving codes to up to `max_tokens` of its
+    /// most frequent tokens, leaving out any only seen once. Every byte has
+    /// a code in the tree over escaped bytes, so the model compresses any
+    /// text, not just the text it's trained on
+    #[must_use]
+    pub fn train(text: &str, max_tokens: usize) -> Self {
+        let mut counts = count(LogTokens.tokens(text));
+        counts.retain(|(_, count)| *count > 1);
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        counts.truncate(max_tokens);
+        let known: BTreeSet<&str> = counts.iter().map(|(token, _)| token.as_str()).collect();
+
+        let mut escapes = 0;
+        let mut bytes = [0; 256];
+        for token in LogTokens.tokens(text) {
+            if known.contains(token.as_str()) {
+                continue;
+            }
+            escapes += 1;
+            for byte in token.bytes().chain(std::iter::once(END)) {
+                bytes[usize::from(byte)] += 1;
+            }
+        }
+        let mut tokens: Vec<(Option<String>, usize)> = counts
+            .into_iter()
+            .map(|(token, count)| (Some(token), count))
+            .collect();
+        // The escape always has a code, so text the model wasn't trained on
+        // can be compressed
+        tokens.push((None, escapes.max(1)));
+
+        Self {
+            tokens: Huffman::build(tokens, TieBreak::default()),
+            bytes: train(&bytes),
+        }
+    }
+    /// Compresses `text`, writing each token with a code of its own as that
+    /// code, and every other token as the escape, with its bytes coded by
+    /// the tree over bytes, each followed by a byte of `0xff`
+    ///
+    /// # Panics
+    /// Never panics, as every token not in the tree is escaped, and every
+    /// byte is in the tree over bytes
+    #[must_use]
+    pub fn compress(&self, text: &str) -> LogFile {
+        let known: BTreeSet<&str> = self
+            .tokens
+            .contents()
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        let mut symbols = Vec::new();
+        let mut escaped = Vec::new();
+        for token in LogTokens.tokens(text) {
+            if known.contains(token.as_str()) {
+                symbols.push(Some(token));
+            } else {
+                escaped.extend(token.bytes().chain(std::iter::once(END)));
+                symbols.push(None);
+            }
+        }
+
+        LogFile {
+            tokens: RZFile::compress(self.tokens.clone(), &symbols)
+                .expect("every token is either in the tree or escaped")
+                .with_checksum(&symbols),
+            bytes: RZFile::compress(self.bytes.clone(), &escaped)
+                .expect("every byte is in the tree")
+                .with_checksum(&escaped),
+        }
+    }
+    /// The tree over tokens, with `None` as the escape
+    #[must_use]
+    pub const fn tokens(&self) -> &Huffman<Option<String>> {
+        &self.tokens
+    }
+    /// The tree over the bytes of escaped tokens
+    #[must_use]
+    pub const fn bytes(&self) -> &Huffman<u8> {
+        &self.bytes
+    }
+}
+
+/// A log compressed by a [`LogModel`](struct.LogModel.html), as an RZ file
+/// of the tokens, with the escape in place of each token without a code,
+/// and an RZ file of the bytes of those tokens
+#[derive(Clone, Debug)]
+pub struct LogFile {
+    tokens: RZFile<'static, Option<String>>,
+    bytes: RZFile<'static, u8>,
+}
+
+impl LogFile {
+    /// Compresses `text` with a model trained on it, giving codes to up to
+    /// [`DEFAULT_MAX_TOKENS`](constant.DEFAULT_MAX_TOKENS.html) tokens
+    #[must_use]
+    pub fn compress(text: &str) -> Self {
+        LogModel::train(text, DEFAULT_MAX_TOKENS).compress(text)
+    }
+    /// Decompresses the log, putting the bytes of each escaped token back in
+    /// place of its escape
+    ///
+    /// # Errors
+    /// Returns an error if either file can't be decoded, there are more or
+    /// fewer escaped tokens than escapes, or they aren't UTF-8
+    pub fn decompress(&self) -> Result<String, DecodeError> {
+        let options = DecodeOptions::new();
+        let tokens = self.tokens.decompress_with(&options)?;
+        let bytes = self.bytes.decompress_with(&options)?;
+
+        let mut escaped = bytes.split(|byte| *byte == END);
+        let mut output = Vec::with_capacity(bytes.len());
+        for token in tokens {
+            match token {
+                Some(token) => output.extend_from_slice(token.as_bytes()),
+                None => output.extend_from_slice(escaped.next().ok_or(DecodeError::UnexpectedEof)?),
+            }
+        }
+        // Every escaped token is followed by an end byte, so only an empty
+        // piece is left after them
+        if escaped.next().is_none_or(|rest| !rest.is_empty()) || escaped.next().is_some() {
+            return Err(DecodeError::DanglingBits);
+        }
+        String::from_utf8(output).map_err(|_| DecodeError::InvalidLiteral)
+    }
+    /// The tree over tokens the log was compressed with
+    #[must_use]
+    pub const fn tokens(&self) -> &Huffman<Option<String>> {
+        &self.tokens.tree
+    }
+    /// Writes the RZ file of tokens, then the RZ file of escaped bytes
+    ///
+    /// # Errors
+    /// Fails if there's any issue writing to `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        self.tokens.write_to(&mut writer)?;
+        self.bytes.write_to(writer)
+    }
+    /// Reads a log written by [`write_to`](#method.write_to)
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or either file is
+    /// malformed
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, RzError> {
+        Ok(Self {
+            tokens: RZFile::read_from(&mut reader)?,
+            bytes: RZFile::read_from(reader)?,
+        })
+    }
+}
@@ -0,0 +1,265 @@
+use std::{
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, Read, Write},
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::rz::{malformed, RzError};
+
+/// Set when the original file's name is stored
+const FIELD_NAME: u8 = 0x01;
+/// Set when the original file's modification time is stored
+const FIELD_MODIFIED: u8 = 0x02;
+/// Set when the original file's permissions are stored
+const FIELD_PERMISSIONS: u8 = 0x04;
+/// Set when what each symbol is is stored
+const FIELD_SYMBOLS: u8 = 0x08;
+
+/// Details of the file an [`RZFile`](struct.RZFile.html) was compressed
+/// from, so it can be restored as it was.
+///
+/// Metadata is stored when flag `0x08` is set, after any entry table. It
+/// starts with a byte marking which fields follow, with `0x01` for the name,
+/// stored as its length in 2 bytes then the name in UTF-8, `0x02` for the
+/// modification time, stored as seconds from the Unix epoch in 8 signed bytes
+/// then nanoseconds in 4, `0x04` for the permissions, stored in 4 bytes, and
+/// `0x08` for what each symbol is, stored like the name
+/// # Examples
+/// ```
+/// use huffman_comprs::{Huffman, Metadata, RZFile};
+///
+/// let input = b"Restored with its name".to_vec();
+/// let tree = Huffman::from(&input);
+/// let data = tree.compress(&input).unwrap();
+///
+/// let metadata = Metadata {
+///     name: Some("notes.txt".to_string()),
+///     ..Metadata::default()
+/// };
+/// let file = RZFile::new(tree, data).with_metadata(metadata.clone());
+///
+/// assert_eq!(Some(&metadata), file.metadata());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The original file's name, without any directories
+    pub name: Option<String>,
+    /// When the original file was last modified
+    pub modified: Option<SystemTime>,
+    /// The original file's permissions. On Unix these are the mode bits,
+    /// elsewhere only whether the file is read only is kept, as `0o444` or
+    /// `0o644`
+    pub permissions: Option<u32>,
+    /// What each symbol is, such as `byte` or `char`, so a reader knows which
+    /// type of tree to read, when files may be compressed with different types
+    pub symbols: Option<String>,
+}
+
+impl Metadata {
+    /// Reads the name, modification time and permissions of the file at
+    /// `path`
+    ///
+    /// # Errors
+    /// Fails if the file's metadata can't be read
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let path = path.as_ref();
+        let metadata = fs::metadata(path)?;
+
+        Ok(Self {
+            name: path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(str::to_string),
+            modified: metadata.modified().ok(),
+            permissions: Some(mode(&metadata.permissions())),
+            symbols: None,
+        })
+    }
+    /// Sets the modification time and permissions of the file at `path` to
+    /// those stored, if they are. The name isn't used, as `path` is already
+    /// chosen
+    ///
+    /// # Errors
+    /// Fails if the file can't be opened, or its metadata can't be changed
+    pub fn apply_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+
+        if let Some(modified) = self.modified {
+            File::options()
+                .write(true)
+                .open(path)?
+                .set_modified(modified)?;
+        }
+        if let Some(permissions) = self.permissions {
+            let mut current = fs::metadata(path)?.permissions();
+            set_mode(&mut current, permissions);
+            fs::set_permissions(path, current)?;
+        }
+
+        Ok(())
+    }
+    /// Returns just the last component of the stored name, so a malicious
+    /// name can't be used to write outside the intended directory
+    #[must_use]
+    pub fn file_name(&self) -> Option<&str> {
+        self.name
+            .as_deref()
+            .and_then(|name| Path::new(name).file_name())
+            .and_then(|name| name.to_str())
+    }
+    pub(crate) fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut fields = 0;
+        if self.name.is_some() {
+            fields |= FIELD_NAME;
+        }
+        if self.modified.is_some() {
+            fields |= FIELD_MODIFIED;
+        }
+        if self.permissions.is_some() {
+            fields |= FIELD_PERMISSIONS;
+        }
+        if self.symbols.is_some() {
+            fields |= FIELD_SYMBOLS;
+        }
+        writer.write_all(&[fields])?;
+
+        if let Some(name) = &self.name {
+            write_string(name, &mut writer)?;
+        }
+        if let Some(modified) = self.modified {
+            let (secs, nanos) = match modified.duration_since(UNIX_EPOCH) {
+                Ok(after) => (i64::try_from(after.as_secs()), after.subsec_nanos()),
+                // Before the epoch, the seconds are rounded down, and the
+                // nanoseconds counted forwards from there
+                Err(e) => {
+                    let before = e.duration();
+                    let secs = before.as_secs() + u64::from(before.subsec_nanos() > 0);
+                    let nanos = (1_000_000_000 - before.subsec_nanos()) % 1_000_000_000;
+                    (i64::try_from(secs).map(|secs| -secs), nanos)
+                }
+            };
+            writer.write_all(&secs.map_err(io::Error::other)?.to_be_bytes())?;
+            writer.write_all(&nanos.to_be_bytes())?;
+        }
+        if let Some(permissions) = self.permissions {
+            writer.write_all(&permissions.to_be_bytes())?;
+        }
+        if let Some(symbols) = &self.symbols {
+            write_string(symbols, &mut writer)?;
+        }
+
+        Ok(())
+    }
+    pub(crate) fn read_from<R: Read>(mut reader: R) -> Result<Self, RzError> {
+        let mut fields = [0];
+        reader
+            .read_exact(&mut fields)
+            .map_err(RzError::truncated("metadata"))?;
+        let fields = fields[0];
+        if fields & !(FIELD_NAME | FIELD_MODIFIED | FIELD_PERMISSIONS | FIELD_SYMBOLS) != 0 {
+            return Err(malformed(format!(
+                "the metadata has unsupported fields {fields:#010b}"
+            )));
+        }
+
+        let name = if fields & FIELD_NAME == 0 {
+            None
+        } else {
+            Some(read_string(&mut reader, "name")?)
+        };
+
+        let modified = if fields & FIELD_MODIFIED == 0 {
+            None
+        } else {
+            let mut secs = [0; 8];
+            reader
+                .read_exact(&mut secs)
+                .map_err(RzError::truncated("metadata"))?;
+            let mut nanos = [0; 4];
+            reader
+                .read_exact(&mut nanos)
+                .map_err(RzError::truncated("metadata"))?;
+
+            let secs = i64::from_be_bytes(secs);
+            let nanos = Duration::from_nanos(u64::from(u32::from_be_bytes(nanos)));
+            let time = if secs < 0 {
+                UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+            } else {
+                UNIX_EPOCH.checked_add(Duration::from_secs(secs.unsigned_abs()))
+            };
+            Some(
+                time.and_then(|time| time.checked_add(nanos))
+                    .ok_or_else(|| malformed("the modification time is out of range"))?,
+            )
+        };
+
+        let permissions = if fields & FIELD_PERMISSIONS == 0 {
+            None
+        } else {
+            let mut permissions = [0; 4];
+            reader
+                .read_exact(&mut permissions)
+                .map_err(RzError::truncated("metadata"))?;
+            Some(u32::from_be_bytes(permissions))
+        };
+
+        let symbols = if fields & FIELD_SYMBOLS == 0 {
+            None
+        } else {
+            Some(read_string(&mut reader, "symbols' description")?)
+        };
+
+        Ok(Self {
+            name,
+            modified,
+            permissions,
+            symbols,
+        })
+    }
+}
+
+/// Writes `string`'s length as 2 bytes, then `string` in UTF-8
+fn write_string<W: Write>(string: &str, mut writer: W) -> io::Result<()> {
+    let len = u16::try_from(string.len()).map_err(io::Error::other)?;
+    writer.write_all(&len.to_be_bytes())?;
+    writer.write_all(string.as_bytes())
+}
+
+/// Reads a string written by `write_string`, called `field` in errors
+fn read_string<R: Read>(mut reader: R, field: &str) -> Result<String, RzError> {
+    let mut len = [0; 2];
+    reader
+        .read_exact(&mut len)
+        .map_err(RzError::truncated("metadata"))?;
+    let mut string = vec![0; usize::from(u16::from_be_bytes(len))];
+    reader
+        .read_exact(&mut string)
+        .map_err(RzError::truncated("metadata"))?;
+    String::from_utf8(string).map_err(|_| malformed(format!("the {field} isn't valid UTF-8")))
+}
+
+#[cfg(unix)]
+fn mode(permissions: &fs::Permissions) -> u32 {
+    std::os::unix::fs::PermissionsExt::mode(permissions)
+}
+
+#[cfg(not(unix))]
+fn mode(permissions: &fs::Permissions) -> u32 {
+    if permissions.readonly() {
+        0o444
+    } else {
+        0o644
+    }
+}
+
+#[cfg(unix)]
+fn set_mode(permissions: &mut fs::Permissions, mode: u32) {
+    std::os::unix::fs::PermissionsExt::set_mode(permissions, mode);
+}
+
+#[cfg(not(unix))]
+fn set_mode(permissions: &mut fs::Permissions, mode: u32) {
+    permissions.set_readonly(mode & 0o222 == 0);
+}
@@ -0,0 +1,286 @@
+use std::{collections::BTreeMap, convert::TryFrom, io};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cancel::CHECK_INTERVAL, decode::strip_padding, rz::parse_tree, Code, DecodeError,
+    DecodeOptions, Encoder, EntropyCoder, Huffman, RzError, TieBreak, TreeFormat,
+};
+
+/// How many times the trees are retrained on the groups that chose them
+const ITERATIONS: usize = 4;
+
+/// Many Huffman trees, with each group of symbols compressed by whichever
+/// tree codes it smallest, as bzip2 does.
+///
+/// Each group is preceded by a selector, the index of the tree it chose,
+/// in as few bits as can index every tree. Input whose content changes part
+/// way through, such as text with base64 embedded in it, compresses better
+/// than with one tree for everything, as each kind of content gets a tree
+/// of its own.
+///
+/// `MultiHuffman` is an [`EntropyCoder`](trait.EntropyCoder.html), so is
+/// stored in an [`RZFile`](struct.RZFile.html) as a tree is
+/// # Examples
+/// ```
+/// use huffman_comprs::{MultiHuffman, RZFile};
+///
+/// let mut input = b"Plain text, followed by base64: ".repeat(20);
+/// input.extend(b"SGVsbG8gV29ybGQhIEJhc2U2NCBlbmNvZGVkIGRhdGE=".repeat(20));
+///
+/// let coder = MultiHuffman::train(&input, 2, 50);
+/// let file = RZFile::compress(coder, &input).unwrap();
+///
+/// assert_eq!(Some(input), file.decompress());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MultiHuffman<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    trees: Vec<Huffman<T>>,
+    group_size: usize,
+}
+
+impl<T> MultiHuffman<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    /// Uses `trees`, selecting one for each group of `group_size` symbols
+    ///
+    /// # Panics
+    /// Panics if there are no trees, more than `u32::MAX` trees, or
+    /// `group_size` is 0 or more than `u32::MAX`
+    #[must_use]
+    pub fn new(trees: Vec<Huffman<T>>, group_size: usize) -> Self {
+        assert!(!trees.is_empty(), "there must be at least one tree");
+        assert!(
+            u32::try_from(trees.len()).is_ok(),
+            "there can't be over u32::MAX trees"
+        );
+        assert!(group_size > 0, "groups must hold at least one symbol");
+        assert!(
+            u32::try_from(group_size).is_ok(),
+            "groups can't hold over u32::MAX symbols"
+        );
+        Self { trees, group_size }
+    }
+    /// Trains up to `trees` trees on `input`, split into groups of
+    /// `group_size` symbols. The input is first split evenly between the
+    /// trees, then each group picks the tree that codes it smallest, and
+    /// each tree is trained again on the groups that picked it, a few times
+    /// over. Every tree can code every symbol in `input`
+    ///
+    /// # Panics
+    /// Panics if `trees` or `group_size` is 0, or `group_size` is more than
+    /// `u32::MAX`
+    #[must_use]
+    pub fn train(input: &[T], trees: usize, group_size: usize) -> Self {
+        assert!(trees > 0, "there must be at least one tree");
+        assert!(group_size > 0, "groups must hold at least one symbol");
+        let groups: Vec<&[T]> = input.chunks(group_size).collect();
+        let count = trees.min(groups.len()).max(1);
+
+        let mut choices: Vec<usize> = (0..groups.len())
+            .map(|group| group * count / groups.len())
+            .collect();
+        let mut trees = Vec::new();
+        for _ in 0..ITERATIONS {
+            let mut counts: Vec<BTreeMap<T, usize>> = vec![BTreeMap::new(); count];
+            for symbol in input {
+                for counts in &mut counts {
+                    counts.entry(symbol.clone()).or_insert(1);
+                }
+            }
+            for (group, choice) in groups.iter().zip(&choices) {
+                for symbol in *group {
+                    *counts[*choice].get_mut(symbol).unwrap() += 1;
+                }
+            }
+            trees = counts
+                .into_iter()
+                .map(|counts| Huffman::build(counts.into_iter().collect(), TieBreak::default()))
+                .collect();
+
+            let lengths: Vec<_> = trees.iter().map(lengths).collect();
+            choices = groups
+                .iter()
+                .map(|group| cheapest(&lengths, group).unwrap_or(0))
+                .collect();
+        }
+
+        Self::new(trees, group_size)
+    }
+    /// The trees a group can select from
+    #[must_use]
+    pub fn trees(&self) -> &[Huffman<T>] {
+        &self.trees
+    }
+    /// The number of symbols in each group
+    #[must_use]
+    pub const fn group_size(&self) -> usize {
+        self.group_size
+    }
+    /// How many bits a selector takes up
+    const fn selector_len(&self) -> u32 {
+        usize::BITS - (self.trees.len() - 1).leading_zeros()
+    }
+}
+
+impl<T> EntropyCoder<T> for MultiHuffman<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn encode(&self, input: &[T]) -> Option<Code> {
+        let lengths: Vec<_> = self.trees.iter().map(lengths).collect();
+        let encoders: Vec<_> = self.trees.iter().map(Encoder::new).collect();
+        let selector_len = self.selector_len();
+
+        let mut output = Code::with_capacity(input.len());
+        for group in input.chunks(self.group_size) {
+            let choice = cheapest(&lengths, group)?;
+            output.extend((0..selector_len).rev().map(|bit| choice >> bit & 1 == 1));
+            output.extend(encoders[choice].encode(group)?.iter());
+        }
+        Some(output)
+    }
+    fn decode(
+        &self,
+        mut data: Vec<bool>,
+        zeros: u8,
+        len: Option<usize>,
+        output: &mut Vec<T>,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError> {
+        let limit = options.max_output_len.unwrap_or(usize::MAX);
+        if len.is_some_and(|len| len > limit) {
+            return Err(DecodeError::OutputLimitExceeded(limit));
+        }
+        strip_padding(&mut data, zeros)?;
+
+        let mut left = len.unwrap_or(usize::MAX);
+        let mut decoded = 0;
+        while left > 0 && (len.is_some() || !data.is_empty()) {
+            let mut choice = 0;
+            for _ in 0..self.selector_len() {
+                choice = choice << 1 | usize::from(data.pop().ok_or(DecodeError::UnexpectedEof)?);
+            }
+            let tree = self.trees.get(choice).ok_or(DecodeError::DanglingBits)?;
+
+            for _ in 0..self.group_size.min(left) {
+                if len.is_none() && data.is_empty() {
+                    break;
+                }
+                if decoded == limit {
+                    return Err(DecodeError::OutputLimitExceeded(limit));
+                }
+                if decoded % CHECK_INTERVAL == 0 {
+                    options.check_cancelled()?;
+                }
+                output.push(
+                    tree.get_char_rec(&mut data)
+                        .ok_or(DecodeError::UnexpectedEof)?,
+                );
+                decoded += 1;
+                left -= 1;
+            }
+        }
+
+        if data.is_empty() {
+            Ok(())
+        } else {
+            Err(DecodeError::DanglingBits)
+        }
+    }
+    /// The group size and number of trees, each as 4 bytes, then each tree,
+    /// preceded by its length as 4 bytes
+    fn serialize_model(&self, format: TreeFormat) -> io::Result<Vec<u8>> {
+        let mut model = Vec::new();
+        for len in [self.group_size, self.trees.len()] {
+            let len = u32::try_from(len).map_err(io::Error::other)?;
+            model.extend_from_slice(&len.to_be_bytes());
+        }
+        for tree in &self.trees {
+            let tree = format.serialize(tree)?;
+            let len = u32::try_from(tree.len()).map_err(io::Error::other)?;
+            model.extend_from_slice(&len.to_be_bytes());
+            model.extend_from_slice(&tree);
+        }
+        Ok(model)
+    }
+    fn deserialize_model<'de>(mut model: &'de [u8], format: TreeFormat) -> Result<Self, RzError>
+    where
+        T: Deserialize<'de>,
+    {
+        let group_size = read_len(&mut model)?;
+        let count = read_len(&mut model)?;
+        if group_size == 0 || count == 0 {
+            return Err(RzError::TreeDecode(
+                "there must be at least one tree, and one symbol in each group".to_string(),
+            ));
+        }
+
+        // Not trusting `count` enough to allocate it all upfront
+        let mut trees = Vec::new();
+        for _ in 0..count {
+            let len = read_len(&mut model)?;
+            if len > model.len() {
+                return Err(RzError::TruncatedHeader("tree"));
+            }
+            let (tree, rest) = model.split_at(len);
+            trees.push(parse_tree(tree, format)?);
+            model = rest;
+        }
+
+        if !model.is_empty() {
+            return Err(RzError::TreeDecode(format!(
+                "{} bytes follow the last tree",
+                model.len()
+            )));
+        }
+        Ok(Self { trees, group_size })
+    }
+    fn depth(&self) -> usize {
+        self.trees.iter().map(Huffman::max_depth).max().unwrap_or(0)
+    }
+    fn empty() -> Option<Self> {
+        Some(Self {
+            trees: vec![Huffman::empty()?],
+            group_size: 1,
+        })
+    }
+}
+
+/// Reads a length written by `serialize_model` from the start of `model`
+fn read_len(model: &mut &[u8]) -> Result<usize, RzError> {
+    let (len, rest) = model
+        .split_first_chunk::<4>()
+        .ok_or(RzError::TruncatedHeader("tree"))?;
+    *model = rest;
+    Ok(u32::from_be_bytes(*len) as usize)
+}
+
+/// The length of each symbol's code in `tree`
+fn lengths<T>(tree: &Huffman<T>) -> BTreeMap<T, usize>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    tree.code_lengths()
+        .into_iter()
+        .map(|(symbol, len)| (symbol, usize::from(len)))
+        .collect()
+}
+
+/// The index of the tree, given by the lengths of its codes, that codes
+/// `group` in the fewest bits, or `None` if none can code every symbol
+fn cheapest<T: Ord>(lengths: &[BTreeMap<T, usize>], group: &[T]) -> Option<usize> {
+    lengths
+        .iter()
+        .enumerate()
+        .filter_map(|(i, lengths)| {
+            let cost: Option<usize> = group.iter().map(|symbol| lengths.get(symbol)).sum();
+            cost.map(|cost| (cost, i))
+        })
+        .min()
+        .map(|(_, i)| i)
+}
@@ -0,0 +1,321 @@
+//! Packing input written with only a few distinct bytes, such as DNA
+//! sequences of `A`, `C`, `G` and `T`, into fewer bits for each byte before
+//! it's entropy coded.
+//!
+//! A tree over bytes already codes four equally likely bases in 2 bits
+//! each, but can't see past a single base. Packed 2 bits to a base, each
+//! byte coded holds 4 bases, so the tree learns which runs of bases are
+//! common, as repeats in a genome are. Alphabets of up to 2, 4 or 16 bytes
+//! are packed into 1, 2 or 4 bits a byte, with the alphabet recorded in the
+//! header, and any bytes outside it, such as the `N`s marking unknown bases,
+//! kept as runs beside the packed data:
+//! ```
+//! use huffman_comprs::packing::Packed;
+//!
+//! let sequence = b"ACGTTGCAACGTNNNNACGGTACCGTTA".repeat(100);
+//!
+//! let packed = Packed::with_alphabet(&sequence, b"ACGT").unwrap();
+//! assert_eq!(2, packed.bits());
+//! assert_eq!(100, packed.exceptions());
+//!
+//! let mut bytes = Vec::new();
+//! packed.write_to(&mut bytes).unwrap();
+//!
+//! let read = Packed::read_from(bytes.as_slice()).unwrap();
+//! assert_eq!(sequence, read.unpack().unwrap());
+//! ```
+
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Write},
+};
+
+use crate::{rz::malformed, DecodeError, DecodeOptions, Huffman, RZFile, RzError};
+
+/// The most bytes an alphabet can hold, being packed into 4 bits each
+pub const MAX_ALPHABET: usize = 16;
+
+/// A run of the same byte outside the alphabet, packed as the alphabet's
+/// first byte, and put back when unpacking
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Run {
+    start: u64,
+    len: u64,
+    byte: u8,
+}
+
+/// Input packed into a few bits for each byte, compressed with a tree over
+/// the packed bytes, with the alphabet, and any runs of bytes outside it
+#[derive(Clone, Debug)]
+pub struct Packed {
+    len: usize,
+    alphabet: Vec<u8>,
+    runs: Vec<Run>,
+    file: RZFile<'static, u8>,
+}
+
+impl Packed {
+    /// Packs `input` with the alphabet, and number of bits, that makes it
+    /// smallest, counting each run of bytes outside the alphabet as well as
+    /// the packed bytes. Returns `None` if no alphabet packs `input` smaller
+    /// than it is, such as for text using many distinct bytes
+    #[must_use]
+    pub fn pack(input: &[u8]) -> Option<Self> {
+        let mut counts = [0_usize; 256];
+        for byte in input {
+            counts[usize::from(*byte)] += 1;
+        }
+        let mut common: Vec<u8> = (0..=u8::MAX)
+            .filter(|byte| counts[usize::from(*byte)] > 0)
+            .collect();
+        common.sort_by_key(|byte| std::cmp::Reverse(counts[usize::from(*byte)]));
+
+        let (alphabet, size) = [1_u32, 2, 4]
+            .iter()
+            .map(|bits| {
+                let mut alphabet = common[..common.len().min(1 << bits)].to_vec();
+                alphabet.sort_unstable();
+                let runs = runs(input, &alphabet).len();
+                let size = packed_len(input.len(), *bits).saturating_add(runs * RUN_LEN);
+                (alphabet, size)
+            })
+            .min_by_key(|(_, size)| *size)?;
+
+        if size >= input.len() {
+            return None;
+        }
+        Self::with_alphabet(input, &alphabet)
+    }
+    /// Packs `input` with the bytes of `alphabet`, in 1 bit a byte for 2 or
+    /// fewer, 2 bits for 4 or fewer, and 4 bits for 16 or fewer. Bytes
+    /// outside the alphabet are kept as runs, so are best kept rare. Returns
+    /// `None` if `alphabet` is empty, holds more than
+    /// [`MAX_ALPHABET`](constant.MAX_ALPHABET.html) bytes, or holds a byte
+    /// twice
+    ///
+    /// # Panics
+    /// Never panics, as every packed byte is in the tree built from them
+    #[must_use]
+    pub fn with_alphabet(input: &[u8], alphabet: &[u8]) -> Option<Self> {
+        let mut sorted = alphabet.to_vec();
+        sorted.sort_unstable();
+        sorted.dedup();
+        if alphabet.is_empty() || alphabet.len() > MAX_ALPHABET || sorted.len() != alphabet.len() {
+            return None;
+        }
+
+        let mut indices = [0_u8; 256];
+        for (index, byte) in (0..).zip(alphabet) {
+            indices[usize::from(*byte)] = index;
+        }
+        let bits = bits(alphabet.len());
+        let per_byte = 8 / bits;
+        let packed: Vec<u8> = input
+            .chunks(per_byte as usize)
+            .map(|chunk| {
+                let mut byte = 0;
+                for (i, symbol) in (0..).zip(chunk) {
+                    byte |= indices[usize::from(*symbol)] << (8 - bits * (i + 1));
+                }
+                byte
+            })
+            .collect();
+
+        let file = RZFile::compress(Huffman::from(&packed), &packed)
+            .expect("every packed byte is in the tree built from them")
+            .with_checksum(&packed);
+        Some(Self {
+            len: input.len(),
+            alphabet: alphabet.to_vec(),
+            runs: runs(input, alphabet),
+            file,
+        })
+    }
+    /// Unpacks the input back into bytes
+    ///
+    /// # Errors
+    /// Returns an error if the packed bytes can't be decoded, there are
+    /// more or fewer of them than the input needs, or they hold a value past
+    /// the end of the alphabet
+    pub fn unpack(&self) -> Result<Vec<u8>, DecodeError> {
+        let packed = self.file.decompress_with(&DecodeOptions::new())?;
+        let bits = bits(self.alphabet.len());
+        let expected = packed_len(self.len, bits);
+        if packed.len() < expected {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        if packed.len() > expected {
+            return Err(DecodeError::DanglingBits);
+        }
+
+        let mask = (1 << bits) - 1;
+        let mut output: Vec<u8> = packed
+            .iter()
+            .flat_map(|byte| (1..=8 / bits).map(move |i| byte >> (8 - bits * i) & mask))
+            .take(self.len)
+            .map(|index| {
+                self.alphabet
+                    .get(usize::from(index))
+                    .copied()
+                    .ok_or(DecodeError::InvalidLiteral)
+            })
+            .collect::<Result<_, _>>()?;
+        // The runs are checked to be in bounds when they're read
+        #[allow(clippy::cast_possible_truncation)]
+        for run in &self.runs {
+            output[run.start as usize..(run.start + run.len) as usize].fill(run.byte);
+        }
+        Ok(output)
+    }
+    /// How many bytes the input holds
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+    /// Whether the input is empty
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The bytes packed, in the order of the values they're packed as
+    #[must_use]
+    pub fn alphabet(&self) -> &[u8] {
+        &self.alphabet
+    }
+    /// How many bits each byte is packed into, being 1, 2 or 4
+    #[must_use]
+    pub const fn bits(&self) -> u32 {
+        bits(self.alphabet.len())
+    }
+    /// How many runs of bytes outside the alphabet the input holds
+    #[must_use]
+    pub const fn exceptions(&self) -> usize {
+        self.runs.len()
+    }
+    /// The tree coding the packed bytes
+    #[must_use]
+    pub const fn tree(&self) -> &Huffman<u8> {
+        &self.file.tree
+    }
+    /// Writes the input's length, as 8 bytes, big endian, then the
+    /// alphabet's length as a byte, then the alphabet, then the number of
+    /// runs outside it, as 8 bytes, and each run as its start and length, as
+    /// 8 bytes each, and its byte, then an RZ file of the packed bytes
+    ///
+    /// # Errors
+    /// Fails if there's any issue writing to `writer`
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&(self.len as u64).to_be_bytes())?;
+        // The alphabet holds no more than 16 bytes
+        #[allow(clippy::cast_possible_truncation)]
+        writer.write_all(&[self.alphabet.len() as u8])?;
+        writer.write_all(&self.alphabet)?;
+        writer.write_all(&(self.runs.len() as u64).to_be_bytes())?;
+        for run in &self.runs {
+            writer.write_all(&run.start.to_be_bytes())?;
+            writer.write_all(&run.len.to_be_bytes())?;
+            writer.write_all(&[run.byte])?;
+        }
+        self.file.write_to(writer)
+    }
+    /// Reads input packed and written by [`write_to`](#method.write_to)
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or the header is
+    /// malformed, such as if the alphabet is empty or too large, or the runs
+    /// overlap, or go past the end of the input
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, RzError> {
+        let len = read_u64(&mut reader, "packed length")?;
+        let len = usize::try_from(len).map_err(|_| malformed("the packed input is too long"))?;
+
+        let mut size = [0];
+        reader
+            .read_exact(&mut size)
+            .map_err(RzError::truncated("alphabet length"))?;
+        let size = usize::from(size[0]);
+        if size == 0 || size > MAX_ALPHABET {
+            return Err(malformed(format!(
+                "the alphabet holds {size} bytes, rather than 1 to {MAX_ALPHABET}"
+            )));
+        }
+        let mut alphabet = vec![0; size];
+        reader
+            .read_exact(&mut alphabet)
+            .map_err(RzError::truncated("alphabet"))?;
+
+        let count = read_u64(&mut reader, "run count")?;
+        let mut runs = Vec::new();
+        let mut end = 0;
+        for _ in 0..count {
+            let start = read_u64(&mut reader, "run")?;
+            let run_len = read_u64(&mut reader, "run")?;
+            let mut byte = [0];
+            reader
+                .read_exact(&mut byte)
+                .map_err(RzError::truncated("run"))?;
+            if start < end || start.checked_add(run_len).is_none_or(|e| e > len as u64) {
+                return Err(malformed(
+                    "the runs outside the alphabet overlap, or go past the end of the input",
+                ));
+            }
+            end = start + run_len;
+            runs.push(Run {
+                start,
+                len: run_len,
+                byte: byte[0],
+            });
+        }
+
+        Ok(Self {
+            len,
+            alphabet,
+            runs,
+            file: RZFile::read_from(reader)?,
+        })
+    }
+}
+
+/// How many bytes each run takes when written
+const RUN_LEN: usize = 17;
+
+/// How many bits each byte of an alphabet of `size` bytes is packed into
+const fn bits(size: usize) -> u32 {
+    match size {
+        0..=2 => 1,
+        3..=4 => 2,
+        _ => 4,
+    }
+}
+
+/// How many bytes `len` bytes take, packed into `bits` bits each
+const fn packed_len(len: usize, bits: u32) -> usize {
+    len.div_ceil(8 / bits as usize)
+}
+
+/// Finds each run of the same byte in `input` outside `alphabet`
+fn runs(input: &[u8], alphabet: &[u8]) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for (i, byte) in (0_u64..).zip(input) {
+        if alphabet.contains(byte) {
+            continue;
+        }
+        match runs.last_mut() {
+            Some(run) if run.byte == *byte && run.start + run.len == i => run.len += 1,
+            _ => runs.push(Run {
+                start: i,
+                len: 1,
+                byte: *byte,
+            }),
+        }
+    }
+    runs
+}
+
+/// Reads a big endian `u64`, naming it `section` if it's cut short
+fn read_u64<R: Read>(mut reader: R, section: &'static str) -> Result<u64, RzError> {
+    let mut bytes = [0; 8];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(RzError::truncated(section))?;
+    Ok(u64::from_be_bytes(bytes))
+}
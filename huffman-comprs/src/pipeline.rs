@@ -0,0 +1,207 @@
+use std::{
+    io::{self, Read, Write},
+    sync::mpsc::{sync_channel, Receiver, SyncSender},
+    thread,
+};
+
+use crate::{checksum::Crc32, copy::train, Progress, RZFile};
+
+/// Compresses a stream on several threads, one for each stage, connected by
+/// bounded channels, so reading, counting, and encoding one chunk overlap
+/// with writing the chunks before it.
+///
+/// The input is read a chunk at a time, with each chunk's bytes counted and
+/// checksummed on one thread, then coded with a tree trained on it on the
+/// next, and written as an RZ file of its own, holding the tree and the
+/// checksum, as [`copy_encode`](fn.copy_encode.html) writes, so the output
+/// can be read back with [`copy_decode`](fn.copy_decode.html). Each channel
+/// holds at most [`queue_len`](#method.queue_len) chunks, so no more than a
+/// few chunks are held in memory at once, however far one stage gets ahead
+/// of the next
+/// # Examples
+/// ```
+/// use huffman_comprs::{copy_decode, Pipeline};
+///
+/// let input = b"Read, counted, and coded all at once".repeat(1000);
+///
+/// let mut compressed = Vec::new();
+/// let pipeline = Pipeline::new().chunk_len(4096).queue_len(2);
+/// pipeline.run(input.as_slice(), &mut compressed).unwrap();
+///
+/// let mut output = Vec::new();
+/// copy_decode(compressed.as_slice(), &mut output).unwrap();
+/// assert_eq!(input, output);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Pipeline {
+    chunk_len: usize,
+    queue_len: usize,
+}
+
+impl Pipeline {
+    /// A pipeline compressing chunks of a megabyte, with up to 4 chunks
+    /// waiting between each stage
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            chunk_len: 1024 * 1024,
+            queue_len: 4,
+        }
+    }
+    /// Reads the input `len` bytes at a time, coding each chunk with a tree
+    /// of its own. Larger chunks spend fewer bytes on trees, but hold more in
+    /// memory. A length of 0 is taken as 1
+    #[must_use]
+    pub const fn chunk_len(mut self, len: usize) -> Self {
+        self.chunk_len = if len == 0 { 1 } else { len };
+        self
+    }
+    /// Lets up to `len` chunks wait between one stage and the next. With 0,
+    /// each stage waits for the next to take its chunk before starting
+    /// another
+    #[must_use]
+    pub const fn queue_len(mut self, len: usize) -> Self {
+        self.queue_len = len;
+        self
+    }
+    /// Compresses everything read from `reader`, writing it to `writer`, and
+    /// returning how many bytes were read. `reader` is read from on a thread
+    /// of its own, while `writer` is written to on the calling thread.
+    ///
+    /// If any stage fails, the stages before it stop once they next hand a
+    /// chunk on, and the first error is returned
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader` or writing to `writer`
+    ///
+    /// # Panics
+    /// Panics if a stage's thread panics, which would be a bug in the crate
+    pub fn run<R, W>(&self, reader: R, writer: W) -> io::Result<u64>
+    where
+        R: Read + Send,
+        W: Write,
+    {
+        self.run_with_progress(reader, writer, |_| {})
+    }
+    /// Compresses everything read from `reader`, as [`run`](#method.run)
+    /// does, calling `progress` on the calling thread with how many bytes
+    /// have been read and written so far after each chunk is written
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader` or writing to `writer`
+    ///
+    /// # Panics
+    /// Panics if a stage's thread panics, which would be a bug in the crate
+    pub fn run_with_progress<R, W, F>(
+        &self,
+        reader: R,
+        mut writer: W,
+        mut progress: F,
+    ) -> io::Result<u64>
+    where
+        R: Read + Send,
+        W: Write,
+        F: FnMut(Progress),
+    {
+        let (chunks_in, chunks) = sync_channel(self.queue_len);
+        let (counted_in, counted) = sync_channel(self.queue_len);
+        let (files_in, files) = sync_channel(self.queue_len);
+        let chunk_len = self.chunk_len;
+
+        thread::scope(|scope| {
+            scope.spawn(move || read_chunks(reader, chunk_len, &chunks_in));
+            scope.spawn(move || count_chunks(&chunks, &counted_in));
+            scope.spawn(move || encode_chunks(&counted, &files_in));
+
+            let mut read = 0;
+            let mut written = 0;
+            for file in files {
+                let (file, len) = file?;
+                writer.write_all(&file)?;
+                read += len;
+                written += file.len() as u64;
+                progress(Progress {
+                    consumed: read,
+                    produced: written,
+                });
+            }
+            writer.flush()?;
+            Ok(read)
+        })
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A chunk of input, with how many of each byte it holds, and its checksum
+struct Counted {
+    chunk: Vec<u8>,
+    counts: [usize; 256],
+    checksum: u32,
+}
+
+/// The first stage, reading `reader` into chunks of `chunk_len` bytes, until
+/// it's read to the end, fails, or the next stage stops
+fn read_chunks<R: Read>(reader: R, chunk_len: usize, output: &SyncSender<io::Result<Vec<u8>>>) {
+    let mut reader = reader.take(0);
+    loop {
+        let mut chunk = Vec::with_capacity(chunk_len);
+        reader.set_limit(chunk_len as u64);
+        let chunk = match reader.read_to_end(&mut chunk) {
+            Ok(0) => return,
+            Ok(_) => Ok(chunk),
+            Err(e) => Err(e),
+        };
+        let failed = chunk.is_err();
+        if output.send(chunk).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// The second stage, counting and checksumming each chunk
+fn count_chunks(input: &Receiver<io::Result<Vec<u8>>>, output: &SyncSender<io::Result<Counted>>) {
+    for chunk in input {
+        let counted = chunk.map(|chunk| {
+            let mut counts = [0_usize; 256];
+            for byte in &chunk {
+                counts[usize::from(*byte)] += 1;
+            }
+            let mut crc = Crc32::new();
+            crc.update(&chunk);
+            Counted {
+                chunk,
+                counts,
+                checksum: crc.finish(),
+            }
+        });
+        if output.send(counted).is_err() {
+            return;
+        }
+    }
+}
+
+/// The third stage, coding each chunk with a tree trained on its counts, as
+/// an RZ file, paired with the chunk's length
+fn encode_chunks(
+    input: &Receiver<io::Result<Counted>>,
+    output: &SyncSender<io::Result<(Vec<u8>, u64)>>,
+) {
+    for counted in input {
+        let file = counted.and_then(|counted| {
+            let mut file = RZFile::compress(train(&counted.counts), &counted.chunk)
+                .expect("every byte is in a trained tree");
+            file.checksum = Some(counted.checksum);
+            let mut bytes = Vec::new();
+            file.write_to(&mut bytes)?;
+            Ok((bytes, counted.chunk.len() as u64))
+        });
+        if output.send(file).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{Huffman, TieBreak};
+
+impl Huffman<u8> {
+    /// A tree for English prose, favouring lowercase letters and spaces
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::english();
+    /// let input = b"The quick brown fox jumps over the lazy dog.".to_vec();
+    ///
+    /// let compressed = tree.compress(&input).unwrap();
+    /// assert!(compressed.len() < input.len() * 8);
+    /// ```
+    #[must_use]
+    pub fn english() -> Self {
+        Self::from_table(&ENGLISH)
+    }
+    /// A tree for JSON, favouring quotes, digits and lowercase keys
+    #[must_use]
+    pub fn json() -> Self {
+        Self::from_table(&JSON)
+    }
+    /// A tree for base64, with every character of the standard alphabet
+    /// equally likely
+    #[must_use]
+    pub fn base64() -> Self {
+        Self::from_table(&BASE64)
+    }
+    /// A tree for URLs, favouring lowercase letters, slashes and
+    /// percent-encoding
+    #[must_use]
+    pub fn url() -> Self {
+        Self::from_table(&URL)
+    }
+    /// Builds the tree for one of the tables below
+    fn from_table(table: &[u32; 256]) -> Self {
+        let leaves = (0..=u8::MAX)
+            .zip(table.iter())
+            .map(|(byte, freq)| (byte, *freq as usize))
+            .collect();
+        Self::build(leaves, TieBreak::default())
+    }
+}
+
+// How often each byte appears in typical content of each kind, out of about
+// 100,000 bytes. Every byte appears at least once, so any input can be
+// compressed with any of the trees, if not well
+
+const ENGLISH: [u32; 256] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1500, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 16500, 36, 250, 1, 1, 1, 1, 240, 18, 18, 1, 1, 600, 150, 650, 1, 69, 51, 47, 44, 40, 37, 33,
+    30, 26, 23, 36, 36, 1, 1, 1, 55, 1, 222, 99, 123, 74, 74, 74, 49, 148, 247, 25, 25, 74, 123,
+    74, 74, 99, 5, 74, 173, 394, 25, 25, 148, 2, 49, 2, 1, 1, 1, 1, 1, 1, 5894, 1078, 2013, 3091,
+    9128, 1581, 1438, 4385, 5031, 108, 575, 2875, 1725, 4816, 5391, 1366, 72, 4313, 4528, 6541,
+    2013, 719, 1725, 108, 1438, 50, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+];
+const JSON: [u32; 256] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 200, 1200, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 6000, 1, 12000, 33, 1, 1, 1, 1, 1, 1, 1, 33, 3500, 600, 800, 300, 2069, 1517, 1414, 1310,
+    1207, 1103, 1000, 897, 793, 690, 3500, 1, 1, 1, 1, 1, 33, 246, 45, 84, 129, 380, 66, 60, 183,
+    210, 4, 24, 120, 72, 201, 225, 57, 3, 180, 189, 273, 84, 30, 72, 4, 60, 2, 200, 100, 200, 1,
+    1500, 1, 3602, 659, 1230, 1889, 5579, 966, 879, 2679, 3075, 66, 351, 1757, 1054, 2943, 3294,
+    835, 44, 2636, 2767, 3997, 1230, 439, 1054, 66, 879, 31, 600, 1, 600, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+];
+const BASE64: [u32; 256] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1300, 1, 1, 200, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1533, 1, 1, 1, 1533, 1533, 1533, 1533, 1533, 1533, 1533,
+    1533, 1533, 1533, 1533, 1, 1, 1, 400, 1, 1, 1, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533,
+    1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533,
+    1533, 1533, 1, 1, 1, 1, 1, 1, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533,
+    1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1533, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1,
+];
+const URL: [u32; 256] = [
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 100, 1, 1500, 1000, 1, 1, 1, 1, 300, 1, 2500, 3000, 6000, 1552, 1138, 1060, 983, 905,
+    828, 750, 672, 595, 517, 800, 1, 1, 1500, 1, 500, 1, 664, 530, 556, 586, 754, 544, 40, 122,
+    140, 3, 16, 80, 48, 134, 150, 38, 2, 120, 126, 182, 56, 20, 48, 3, 40, 1, 1, 1, 1, 1, 1000, 1,
+    4748, 869, 1621, 2490, 7353, 1274, 1158, 3532, 4053, 87, 463, 2316, 1390, 3879, 4343, 1100, 58,
+    3474, 3648, 5269, 1621, 579, 1390, 87, 1158, 41, 1, 1, 1, 50, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+];
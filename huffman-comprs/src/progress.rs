@@ -0,0 +1,17 @@
+/// How far a long-running operation has got, passed to the callback given to
+/// one, such as
+/// [`copy_encode_with_progress`](fn.copy_encode_with_progress.html), each
+/// time it finishes a chunk or block.
+///
+/// Both counts are totals since the operation started, so a frontend can
+/// show how much of a file of known size has been read, or how large the
+/// output has grown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Progress {
+    /// How many bytes have been taken from the input, or symbols if the input
+    /// isn't bytes
+    pub consumed: u64,
+    /// How many bytes have been written to the output, or symbols if the
+    /// output isn't bytes
+    pub produced: u64,
+}
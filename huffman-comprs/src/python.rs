@@ -0,0 +1,205 @@
+//! Python bindings, through [PyO3](https://pyo3.rs), built as an extension
+//! module with [maturin](https://www.maturin.rs), which reads the features to
+//! build with from `pyproject.toml`.
+//!
+//! Trees only hold bytes, and compressed data is always a whole `.rz` file,
+//! so files written by Rust services or the CLI can be read from Python:
+//! ```python
+//! from huffman_comprs import Huffman, RZFile
+//!
+//! tree = Huffman(b"a sample of the data")
+//! compressed = tree.compress(b"the data")
+//! assert tree.decompress(compressed) == b"the data"
+//!
+//! file = RZFile(open("data.rz", "rb").read())
+//! print(file.checksum, len(file.decompress()))
+//! ```
+//! Errors are raised as `ValueError`s.
+
+use std::{borrow::Cow, convert::TryFrom};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{DecodeOptions, Huffman, RZFile};
+
+#[allow(clippy::needless_pass_by_value)] // so it can be passed to `map_err`
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Adds a checksum of `input` to `file`, then writes it out
+fn write(file: RZFile<u8>, input: &[u8]) -> Cow<'static, [u8]> {
+    let mut buf = Vec::new();
+    // Writing to a `Vec` can't fail
+    file.with_checksum(input).write_to(&mut buf).unwrap();
+    Cow::Owned(buf)
+}
+
+/// A tree of bytes, exported to Python as `Huffman`
+#[pyclass(name = "Huffman", module = "huffman_comprs")]
+#[derive(Clone, Debug)]
+pub struct PyHuffman {
+    tree: Huffman<u8>,
+}
+
+#[pymethods]
+impl PyHuffman {
+    /// Builds a tree from the frequency of each byte in `sample`
+    #[new]
+    #[must_use]
+    pub fn new(sample: &[u8]) -> Self {
+        Self {
+            tree: Huffman::from(sample.to_vec()),
+        }
+    }
+    /// How many different bytes the tree holds
+    #[getter]
+    #[must_use]
+    pub fn symbols(&self) -> usize {
+        self.tree.contents().len()
+    }
+    /// Compresses `data` into an `.rz` file, with this tree and a checksum
+    ///
+    /// # Errors
+    /// Raises if a byte of `data` isn't in the tree
+    pub fn compress(&self, data: &[u8]) -> PyResult<Cow<'static, [u8]>> {
+        let file = RZFile::compress(self.tree.clone(), data)
+            .ok_or_else(|| value_error("a byte of the data isn't in the tree"))?;
+        Ok(write(file, data))
+    }
+    /// Decompresses an `.rz` file, using this tree if the file was written
+    /// without one
+    ///
+    /// # Errors
+    /// Raises if `data` isn't an `.rz` file, or the data is corrupt
+    pub fn decompress(&self, data: &[u8]) -> PyResult<Cow<'static, [u8]>> {
+        let file = RZFile::parse_with_dictionary(data, &self.tree).map_err(value_error)?;
+        let output = file
+            .decompress_with(&DecodeOptions::new())
+            .map_err(value_error)?;
+        Ok(Cow::Owned(output))
+    }
+    fn __repr__(&self) -> String {
+        format!("Huffman(symbols={})", self.symbols())
+    }
+}
+
+/// An `.rz` file of bytes, exported to Python as `RZFile`
+#[pyclass(name = "RZFile", module = "huffman_comprs")]
+#[derive(Debug)]
+pub struct PyRZFile {
+    file: RZFile<'static, u8>,
+}
+
+#[pymethods]
+impl PyRZFile {
+    /// Parses an `.rz` file from `data`
+    ///
+    /// # Errors
+    /// Raises if `data` isn't an `.rz` file
+    #[new]
+    pub fn new(data: &[u8]) -> PyResult<Self> {
+        let file: RZFile<u8> = RZFile::try_from(data).map_err(value_error)?;
+        Ok(Self {
+            file: file.into_owned(),
+        })
+    }
+    /// Compresses `data` with `tree`, or a tree built from `data` itself if
+    /// it's `None`, storing a checksum
+    ///
+    /// # Errors
+    /// Raises if a byte of `data` isn't in `tree`
+    #[staticmethod]
+    #[pyo3(signature = (data, tree = None))]
+    pub fn compress(data: &[u8], tree: Option<&PyHuffman>) -> PyResult<Self> {
+        let tree = tree.map_or_else(|| Huffman::from(data.to_vec()), |tree| tree.tree.clone());
+        let file = RZFile::compress(tree, data)
+            .ok_or_else(|| value_error("a byte of the data isn't in the tree"))?;
+        Ok(Self {
+            file: file.with_checksum(data),
+        })
+    }
+    /// Decompresses the file, checking its checksum, if it has one
+    ///
+    /// # Errors
+    /// Raises if the data is corrupt
+    pub fn decompress(&self) -> PyResult<Cow<'static, [u8]>> {
+        let output = self
+            .file
+            .decompress_with(&DecodeOptions::new())
+            .map_err(value_error)?;
+        Ok(Cow::Owned(output))
+    }
+    /// Writes the file out as bytes
+    ///
+    /// # Errors
+    /// Raises if the tree can't be serialized
+    pub fn to_bytes(&self) -> PyResult<Cow<'static, [u8]>> {
+        let mut buf = Vec::new();
+        self.file.write_to(&mut buf).map_err(value_error)?;
+        Ok(Cow::Owned(buf))
+    }
+    /// The file's tree
+    #[getter]
+    #[must_use]
+    pub fn tree(&self) -> PyHuffman {
+        PyHuffman {
+            tree: self.file.tree.clone(),
+        }
+    }
+    /// The checksum of the uncompressed data, if the file has one
+    #[getter]
+    #[must_use]
+    pub const fn checksum(&self) -> Option<u32> {
+        self.file.checksum()
+    }
+    /// How many bytes the file holds, if it's stored
+    #[getter(len)]
+    #[must_use]
+    pub const fn stored_len(&self) -> Option<usize> {
+        self.file.len()
+    }
+}
+
+/// Compresses `data` into an `.rz` file, with a tree built from `data`
+/// itself and a checksum
+#[pyfunction]
+#[must_use]
+#[allow(clippy::missing_panics_doc)] // every byte is in the tree, so it can't
+pub fn compress(data: &[u8]) -> Cow<'static, [u8]> {
+    write(
+        RZFile::compress(Huffman::from(data.to_vec()), data).unwrap(),
+        data,
+    )
+}
+
+/// Decompresses an `.rz` file, or many of them one after another, checking
+/// any checksums
+///
+/// # Errors
+/// Raises if `data` isn't an `.rz` file, or the data is corrupt
+#[pyfunction]
+pub fn decompress(data: &[u8]) -> PyResult<Cow<'static, [u8]>> {
+    let mut output = Vec::new();
+    for file in RZFile::<u8>::frames(data) {
+        let file = file.map_err(value_error)?;
+        output.extend(
+            file.decompress_with(&DecodeOptions::new())
+                .map_err(value_error)?,
+        );
+    }
+    Ok(Cow::Owned(output))
+}
+
+/// The `huffman_comprs` Python module
+///
+/// # Errors
+/// Fails if a class or function can't be added to the module
+#[pymodule]
+pub fn huffman_comprs(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyHuffman>()?;
+    module.add_class::<PyRZFile>()?;
+    module.add_function(wrap_pyfunction!(compress, module)?)?;
+    module.add_function(wrap_pyfunction!(decompress, module)?)?;
+    Ok(())
+}
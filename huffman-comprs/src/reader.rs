@@ -0,0 +1,143 @@
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use crate::RZFile;
+
+/// Reads the decompressed bytes of an [`RZFile`](struct.RZFile.html), a
+/// block at a time.
+///
+/// Seeking only decodes the block holding the new position. Files that aren't
+/// split into blocks are decoded all at once, on the first read. The checksum
+/// covers the whole file, so isn't checked while reading
+/// # Examples
+/// ```
+/// use std::io::{Read, Seek, SeekFrom};
+///
+/// use huffman_comprs::{Huffman, RZFile, RzReader};
+///
+/// let input = b"Jumping around a large compressed log".to_vec();
+/// let file = RZFile::new_blocked(Huffman::from(&input), &input, 8).unwrap();
+///
+/// let mut reader = RzReader::new(file);
+/// reader.seek(SeekFrom::Start(17)).unwrap();
+///
+/// let mut word = [0; 5];
+/// reader.read_exact(&mut word).unwrap();
+/// assert_eq!(b"large", &word);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RzReader<'a> {
+    file: RZFile<'a, u8>,
+    position: u64,
+    /// The index of the last block decoded, and its contents
+    block: Option<(usize, Vec<u8>)>,
+    /// The length of the decompressed data, once it's been worked out
+    len: Option<u64>,
+}
+
+impl<'a> RzReader<'a> {
+    /// Creates a reader starting at the beginning of `file`'s data
+    #[must_use]
+    pub const fn new(file: RZFile<'a, u8>) -> Self {
+        Self {
+            file,
+            position: 0,
+            block: None,
+            len: None,
+        }
+    }
+    /// Returns the file being read
+    #[allow(clippy::missing_const_for_fn)] // `RZFile` may need dropping
+    #[must_use]
+    pub fn into_inner(self) -> RZFile<'a, u8> {
+        self.file
+    }
+    /// Returns the length of the decompressed data, decoding the last block
+    /// to find it
+    ///
+    /// # Errors
+    /// Fails if the last block isn't a valid encoding under the file's tree
+    pub fn len(&mut self) -> io::Result<u64> {
+        if let Some(len) = self.len.or_else(|| self.file.len().map(|len| len as u64)) {
+            return Ok(len);
+        }
+
+        let len = match self.file.block_count().checked_sub(1) {
+            Some(last) => self.block_start(last) + self.load(last)?.len() as u64,
+            None => 0,
+        };
+        self.len = Some(len);
+        Ok(len)
+    }
+    /// Returns whether there's no decompressed data
+    ///
+    /// # Errors
+    /// Fails if the last block isn't a valid encoding under the file's tree
+    pub fn is_empty(&mut self) -> io::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+    /// The position in the decompressed data the block at `index` starts at
+    fn block_start(&self, index: usize) -> u64 {
+        self.file
+            .block_size()
+            .map_or(0, |size| index as u64 * size as u64)
+    }
+    /// Decodes the block at `index`, unless it's already decoded
+    fn load(&mut self, index: usize) -> io::Result<&[u8]> {
+        if self.block.as_ref().map(|(i, _)| *i) != Some(index) {
+            let block = self
+                .file
+                .decompress_block(index)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            self.block = Some((index, block));
+        }
+
+        Ok(self.block.as_ref().map_or(&[], |(_, block)| block))
+    }
+}
+
+impl Read for RzReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let index = match self.file.block_size() {
+            Some(size) => usize::try_from(self.position / size as u64).unwrap_or(usize::MAX),
+            None => 0,
+        };
+        if index >= self.file.block_count() {
+            return Ok(0);
+        }
+
+        let offset = self.position - self.block_start(index);
+        let block = self.load(index)?;
+        let Some(remaining) = usize::try_from(offset).ok().and_then(|o| block.get(o..)) else {
+            return Ok(0);
+        };
+
+        let read = remaining.len().min(buf.len());
+        buf[..read].copy_from_slice(&remaining[..read]);
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for RzReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(position) => {
+                self.position = position;
+                return Ok(position);
+            }
+            SeekFrom::End(offset) => (self.len()?, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+
+        self.position = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "can't seek before the start of the data",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
@@ -1,11 +1,87 @@
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
 use std::{
+    any::{Any, TypeId},
     convert::{TryFrom, TryInto},
+    fs::File,
+    io::{self, BufWriter, Read, Write},
+    marker::PhantomData,
+    ops::{Deref, Range},
     path::Path,
 };
 
-use serde::{Deserialize, Serialize};
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
 
-use crate::Huffman;
+mod block_coder;
+#[cfg(feature = "crypto")]
+mod crypto;
+mod error;
+mod extension;
+mod format;
+mod split;
+mod value;
+pub use block_coder::BlockCoder;
+pub use error::RzError;
+pub use extension::{Extension, RZ_EXTENSION_MAGIC};
+pub use format::TreeFormat;
+pub use split::SplitError;
+pub use value::{from_compressed_bytes, to_compressed_bytes};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "crypto")]
+use crypto::Encryption;
+
+use crate::{
+    archive::{self, Entry},
+    bits::{BitOrder, BitReader, BitWriter},
+    checksum::{Crc32, Fnv64},
+    trace, Cancel, Code, DecodeError, DecodeOptions, EntropyCoder, Huffman, Metadata, Progress,
+};
+
+/// The bytes every RZ file starts with
+pub const RZ_MAGIC: [u8; 4] = *b"\x89RZ\n";
+/// The newest version of the RZ format, which is the version written
+pub const RZ_VERSION: u8 = 2;
+
+/// The length of the fixed size part of the header, before the tree
+pub const HEADER_LEN: usize = 11;
+
+/// Set when the header holds a checksum of the uncompressed symbols
+const FLAG_CHECKSUM: u8 = 0x01;
+/// Set when the data is split into independently compressed blocks
+const FLAG_BLOCKS: u8 = 0x02;
+/// Set when the data holds many files, listed in an entry table
+const FLAG_ENTRIES: u8 = 0x04;
+/// Set when the header holds metadata about the original file
+const FLAG_METADATA: u8 = 0x08;
+/// Set when the header holds the number of uncompressed symbols
+const FLAG_LEN: u8 = 0x10;
+/// Set when the header holds the length of the data section, so another file
+/// can follow it
+const FLAG_DATA_LEN: u8 = 0x20;
+/// Set when the tree is left out, and referenced by its dictionary id instead
+const FLAG_DICTIONARY: u8 = 0x40;
+/// Set when the tree is serialized with a format other than bincode
+const FLAG_TREE_FORMAT: u8 = 0x80;
+// Every flag is now in use, so version 2 adds a byte of extended flags
+
+/// Set when the data is the input's bytes, stored as they are, as compressing
+/// them wouldn't make them any smaller
+const EXTENDED_STORED: u8 = 0x01;
+/// Set when the data is encrypted, with the salt and nonce in the header
+const EXTENDED_ENCRYPTED: u8 = 0x02;
+/// Set when the tree is encrypted too
+const EXTENDED_ENCRYPTED_TREE: u8 = 0x04;
+/// Set when each block in the block index is followed by the id of its
+/// [`BlockCoder`](enum.BlockCoder.html)
+const EXTENDED_BLOCK_CODERS: u8 = 0x08;
+
+/// The length of the salt a key is derived from a password with
+const SALT_LEN: usize = 16;
+/// The length of the nonce the payload is encrypted with
+const NONCE_LEN: usize = 12;
 
 /// A RZ file, with a tree, and data.
 ///
@@ -13,54 +89,651 @@ use crate::Huffman;
 ///
 /// | name      | size             | usage                                                                                |
 /// |-----------|------------------|--------------------------------------------------------------------------------------|
-/// | tree_len  | 4 bytes          | Indicates how many bytes the tree takes up                                           |
+/// | magic     | 4 bytes          | Always [`RZ_MAGIC`](constant.RZ_MAGIC.html), marking the file as an RZ file          |
+/// | version   | 1 byte           | The version of the format, currently [`RZ_VERSION`](constant.RZ_VERSION.html)        |
+/// | flags     | 1 byte           | Marks which of the optional fields below are present                                 |
+/// | `tree_len`| 4 bytes          | Indicates how many bytes the tree takes up                                           |
 /// | zeros     | 1 byte           | Indicates how many 0s are appended onto the end of the data, so to make full bytes   |
-/// | tree      | tree_len bytes   | The actual Huffman tree                                                              |
+/// | extended  | 1 byte           | From version 2, more flags, with `0x01` set if the data is stored uncompressed       |
+/// | checksum  | 4 bytes          | If flag `0x01` is set, a CRC-32 of the uncompressed symbols                          |
+/// | length    | 8 bytes          | If flag `0x10` is set, the number of uncompressed symbols                            |
+/// | data len  | 8 bytes          | If flag `0x20` is set, the number of bytes of data                                   |
+/// | index     | 8 + 5n or 6n bytes | If flag `0x02` is set, the block index described below                             |
+/// | entries   | varies           | If flag `0x04` is set, the files in an [`RZArchive`](struct.RZArchive.html)          |
+/// | metadata  | varies           | If flag `0x08` is set, the original file's [`Metadata`](struct.Metadata.html)        |
+/// | dictionary| 4 bytes          | If flag `0x40` is set, the id of the tree, which is left out                         |
+/// | format    | 1 byte           | If flag `0x80` is set, the [`TreeFormat`](enum.TreeFormat.html) of the tree          |
+/// | encryption| 28 bytes         | If extended flag `0x02` is set, the 16 byte salt, then the 12 byte nonce             |
+/// | tree      | `tree_len` bytes | The actual Huffman tree, left out if the data is stored uncompressed                 |
 /// | data      | rest of the file | The data, compressed with the above huffman tree                                     |
 ///
-/// [`RZFile`](struct.RZFile.html)s can be generated from `&[u8]`
+/// The length of the data is always written, so RZ files can be concatenated,
+/// like gzip members, and read back one after another with
+/// [`frames`](#method.frames) or [`read_from`](#method.read_from). Files
+/// without it take up the rest of the input
+///
+/// Files made with [`new_blocked`](#method.new_blocked) split their data into
+/// blocks, each compressed on its own, so part of the data can be decompressed
+/// without decoding the rest. The block index is the number of symbols in each
+/// block as 4 bytes, the number of blocks as 4 bytes, then for each block its
+/// compressed length as 4 bytes, and its own zeros byte. Every block but the
+/// last holds exactly that many symbols
+///
+/// Files made with [`new_blocked_adaptive`](#method.new_blocked_adaptive)
+/// code each block of bytes with the tree, as runs, or as the bytes
+/// themselves, whichever is smallest. When any block isn't coded with the
+/// tree, extended flag `0x08` is set, and each block's entry in the index is
+/// followed by the id of its [`BlockCoder`](enum.BlockCoder.html)
+///
+/// When compressing bytes, with the tree they need, doesn't make them any
+/// smaller, as for data that's already compressed,
+/// [`compress`](#method.compress) stores them as they are instead, setting
+/// extended flag `0x01`. The tree is left out, with a `tree_len` of 0, and
+/// decompressing copies the bytes back out
+///
+/// With the `crypto` feature, [`with_encryption`](#method.with_encryption)
+/// encrypts the data with ChaCha20-Poly1305, setting extended flag `0x02`,
+/// and the tree too, setting `0x04`. Each is followed by its 16 byte tag, and
+/// the tree is encrypted with the nonce's last byte flipped. Everything before
+/// the tree is authenticated with each, and a tree that isn't encrypted is
+/// authenticated with the data, so changing any of them fails decryption
+///
+/// [`Extension`](struct.Extension.html) frames, holding an application's own
+/// data, may come before, between or after files, and are skipped by every
+/// reader
+///
+/// [`RZFile`](struct.RZFile.html)s can be generated from `&[u8]`, in which
+/// case the data section is borrowed from the buffer rather than copied, or
+/// with the `mmap` feature, from a memory mapped file.
+///
+/// The data is compressed with a [`Huffman`](struct.Huffman.html) tree, unless
+/// another [`EntropyCoder`](trait.EntropyCoder.html) is given as `C`, in which
+/// case the tree section holds that coder's model
 #[derive(Clone, Debug)]
-pub struct RZFile<T>
+pub struct RZFile<'a, T, C = Huffman<T>>
 where
-    T: Serialize + Ord + Clone,
+    T: Serialize + Ord + Clone + 'static,
 {
-    tree_len: u32,
     zeros: u8,
-    pub tree: Huffman<T>,
-    data: Vec<u8>,
+    pub(crate) checksum: Option<u32>,
+    len: Option<usize>,
+    blocks: Option<Blocks>,
+    /// The files held, if this is an archive
+    pub(crate) entries: Option<Vec<Entry>>,
+    metadata: Option<Metadata>,
+    /// The id of the tree, if it's left out when writing
+    dictionary: Option<u32>,
+    /// How the tree is serialized when writing
+    format: TreeFormat,
+    /// Whether the data is stored uncompressed, which is only done for bytes
+    stored: bool,
+    /// The key the payload is encrypted with when writing, if any
+    #[cfg(feature = "crypto")]
+    encryption: Option<Encryption>,
+    pub tree: C,
+    data: Data<'a>,
+    marker: PhantomData<T>,
 }
 
-impl<T> RZFile<T>
-where
-    T: Serialize + Ord + Clone,
-{
-    /// Generates a RZ file from a given [`Huffman`](struct.Huffman.html) tree, and `Vec<bool>`, being the compressed data
-    #[must_use]
-    pub fn new(tree: Huffman<T>, mut data: Vec<bool>) -> Self {
-        let tree_len = bincode::serialize(&tree).unwrap().len() as u32;
-
-        let mut data_new = Vec::with_capacity(data.len() / 8);
+/// How a file's payload is encrypted, as stored in its header. Without the
+/// `crypto` feature, it's only read to be rejected
+#[derive(Clone, Debug)]
+#[cfg_attr(not(feature = "crypto"), allow(dead_code))]
+struct Sealed {
+    /// The salt the key is derived from a password with
+    salt: [u8; SALT_LEN],
+    nonce: [u8; NONCE_LEN],
+    /// Whether the tree is encrypted too
+    tree: bool,
+}
 
-        let zeros = if data.len() % 8 != 0 {
-            let zeros = 8 - (data.len() % 8);
-            zeros as u8
-        } else {
-            0
+impl Sealed {
+    #[cfg(feature = "crypto")]
+    fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(&self.salt)?;
+        writer.write_all(&self.nonce)
+    }
+    fn read_from<R: Read>(mut reader: R, tree: bool) -> Result<Self, RzError> {
+        let mut sealed = Self {
+            salt: [0; SALT_LEN],
+            nonce: [0; NONCE_LEN],
+            tree,
         };
+        reader
+            .read_exact(&mut sealed.salt)
+            .and_then(|()| reader.read_exact(&mut sealed.nonce))
+            .map_err(RzError::truncated("encryption parameters"))?;
+        Ok(sealed)
+    }
+}
 
-        while !data.is_empty() {
-            let mut to_add = 0;
-            for i in 0..8 {
-                to_add += (data.pop().unwrap_or(false) as u8) * 2_u16.pow(i) as u8;
-            }
-            data_new.push(to_add);
+/// The index of a file whose data is split into blocks
+#[derive(Clone, Debug)]
+struct Blocks {
+    /// How many symbols each block holds, apart from the last
+    size: u32,
+    blocks: Vec<Block>,
+}
+
+/// Where a single block's compressed data is found, and how it's coded
+#[derive(Clone, Copy, Debug)]
+struct Block {
+    start: usize,
+    len: u32,
+    zeros: u8,
+    coder: BlockCoder,
+}
+
+/// Where an [`RZFile`](struct.RZFile.html)'s data section is held
+#[derive(Clone, Debug)]
+enum Data<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Vec<u8>),
+    /// The given range of a memory mapped file
+    #[cfg(feature = "mmap")]
+    Mapped(Arc<Mmap>, Range<usize>),
+}
+
+impl Deref for Data<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Self::Borrowed(data) => data,
+            Self::Owned(data) => data,
+            #[cfg(feature = "mmap")]
+            Self::Mapped(map, range) => &map[range.clone()],
         }
+    }
+}
+
+impl<T, C> RZFile<'_, T, C>
+where
+    T: Serialize + Ord + Clone + 'static,
+    C: EntropyCoder<T>,
+{
+    /// Generates a RZ file from a given [`Huffman`](struct.Huffman.html) tree, and [`Code`](struct.Code.html), being the compressed data
+    #[must_use]
+    pub fn new(tree: C, data: impl Into<Code>) -> Self {
+        let (data, zeros) = pack(&data.into());
 
         Self {
-            tree_len,
             zeros,
+            checksum: None,
+            len: None,
+            blocks: None,
+            entries: None,
+            metadata: None,
+            dictionary: None,
+            format: TreeFormat::default(),
+            stored: false,
+            #[cfg(feature = "crypto")]
+            encryption: None,
             tree,
-            data: data_new,
+            data: Data::Owned(data),
+            marker: PhantomData,
+        }
+    }
+    /// Compresses `input` with `tree`, storing how many symbols there are, so
+    /// decompressing knows exactly when to stop, and can allocate the output
+    /// upfront.
+    ///
+    /// If `input` is bytes, and compressing them doesn't make them any
+    /// smaller, counting the tree written with them, they're stored
+    /// uncompressed instead, and the tree is left out when writing
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, RZFile};
+    ///
+    /// // Every byte appears equally often, so none can be given shorter codes
+    /// let input: Vec<u8> = (0..=255).chain(0..=255).collect();
+    /// let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    ///
+    /// assert!(file.is_stored());
+    /// assert_eq!(input, file.data());
+    /// assert_eq!(Some(input), file.decompress());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `None` if any of the symbols in `input` are not contained in
+    /// `tree`
+    #[must_use]
+    pub fn compress(tree: C, input: &[T]) -> Option<Self> {
+        let data = tree.encode(input)?;
+        Some(Self::compressed(tree, input, data))
+    }
+    /// Compresses `input` with `tree`, as [`compress`](#method.compress)
+    /// does, stopping once `cancel` is set. The flag is checked every 65536
+    /// symbols encoded, so a large input can be stopped part way through
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Cancel, DecodeError, Huffman, RZFile};
+    ///
+    /// let input = b"Never finished".repeat(1000);
+    /// let cancel = Cancel::new();
+    /// cancel.cancel();
+    ///
+    /// let result = RZFile::compress_with(Huffman::from(&input), &input, &cancel);
+    /// assert_eq!(Some(DecodeError::Cancelled), result.err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns [`DecodeError::Cancelled`](enum.DecodeError.html#variant.Cancelled)
+    /// if `cancel` is set, or `Ok(None)` if any of the symbols in `input` are
+    /// not contained in `tree`
+    pub fn compress_with(
+        tree: C,
+        input: &[T],
+        cancel: &Cancel,
+    ) -> Result<Option<Self>, DecodeError> {
+        Ok(tree
+            .encode_cancellable(input, cancel)?
+            .map(|data| Self::compressed(tree, input, data)))
+    }
+    /// Builds the file for `input` compressed to `data`, storing `input`
+    /// instead if that's no larger
+    fn compressed(tree: C, input: &[T], data: Code) -> Self {
+        let mut file = Self::new(tree, data);
+        file.len = Some(input.len());
+        // The header is the same either way, so only the tree and data are
+        // compared with the input
+        if is_bytes::<T>()
+            && !input.is_empty()
+            && file.tree_len().saturating_add(file.data.len()) >= input.len()
+        {
+            file.data = Data::Owned(to_bytes(input));
+            file.zeros = 0;
+            file.stored = true;
+        }
+        file
+    }
+    /// Compresses `input` with `tree`, splitting it into blocks of
+    /// `block_size` symbols that are each compressed independently, so any
+    /// range of the data can be decompressed with
+    /// [`decompress_range`](#method.decompress_range) by decoding only the
+    /// blocks it covers
+    ///
+    /// # Errors
+    /// Returns `None` if any of the symbols in `input` are not contained in
+    /// `tree`
+    ///
+    /// # Panics
+    /// Panics if `block_size` is 0 or more than `u32::MAX`, or if a block
+    /// compresses to more than `u32::MAX` bytes
+    #[must_use]
+    pub fn new_blocked(tree: C, input: &[T], block_size: usize) -> Option<Self> {
+        Self::blocked(tree, input, block_size, false)
+    }
+    /// Compresses `input` with `tree`, splitting it into blocks of
+    /// `block_size` symbols, as [`new_blocked`](#method.new_blocked) does.
+    /// If `input` is bytes, each block is coded with whichever
+    /// [`BlockCoder`](enum.BlockCoder.html) makes it smallest, so files
+    /// mixing text with runs of the same byte, or with data that's already
+    /// compressed, get the best of each
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{BlockCoder, Huffman, RZFile};
+    ///
+    /// let mut input = b"Some text, ".repeat(10);
+    /// input.extend([0; 110]);
+    /// input.extend(130..240);
+    ///
+    /// let file = RZFile::new_blocked_adaptive(Huffman::from(&input), &input, 110).unwrap();
+    ///
+    /// assert_eq!(BlockCoder::Tree, file.block_coder(0));
+    /// assert_eq!(BlockCoder::Rle, file.block_coder(1));
+    /// assert_eq!(BlockCoder::Stored, file.block_coder(2));
+    /// assert_eq!(Some(input), file.decompress());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `None` if any of the symbols in `input` are not contained in
+    /// `tree`
+    ///
+    /// # Panics
+    /// Panics if `block_size` is 0 or more than `u32::MAX`, or if a block
+    /// compresses to more than `u32::MAX` bytes
+    #[must_use]
+    pub fn new_blocked_adaptive(tree: C, input: &[T], block_size: usize) -> Option<Self> {
+        Self::blocked(tree, input, block_size, is_bytes::<T>())
+    }
+    /// Splits `input` into blocks of `block_size` symbols, coding each with
+    /// `tree`, or if `adaptive`, with whichever coder is smallest
+    fn blocked(tree: C, input: &[T], block_size: usize, adaptive: bool) -> Option<Self> {
+        assert!(block_size > 0, "blocks must hold at least one symbol");
+        let size = u32::try_from(block_size).expect("blocks can't hold over u32::MAX symbols");
+
+        let mut data = Vec::new();
+        let mut blocks = Vec::with_capacity(input.len().div_ceil(block_size));
+        for chunk in input.chunks(block_size) {
+            let (mut packed, mut zeros) = pack(&tree.encode(chunk)?);
+            let mut coder = BlockCoder::Tree;
+            if adaptive {
+                let bytes = to_bytes(chunk);
+                let rle = block_coder::encode_rle(&bytes);
+                // Ties go to whichever is quickest to decode
+                if bytes.len() <= rle.len().min(packed.len()) {
+                    (packed, zeros, coder) = (bytes, 0, BlockCoder::Stored);
+                } else if rle.len() <= packed.len() {
+                    (packed, zeros, coder) = (rle, 0, BlockCoder::Rle);
+                }
+            }
+            blocks.push(Block {
+                start: data.len(),
+                len: u32::try_from(packed.len()).unwrap(),
+                zeros,
+                coder,
+            });
+            data.extend_from_slice(&packed);
+        }
+
+        let mut file = Self::new(tree, Code::new());
+        file.len = Some(input.len());
+        file.blocks = Some(Blocks { size, blocks });
+        file.data = Data::Owned(data);
+        Some(file)
+    }
+    /// Stores a checksum of `input`, which should be the uncompressed data,
+    /// so corruption can be detected when decompressing
+    ///
+    /// # Panics
+    /// Panics if a symbol can't be serialized
+    #[must_use]
+    pub fn with_checksum(mut self, input: &[T]) -> Self {
+        self.checksum = Some(checksum(input));
+        self
+    }
+    /// Stores `metadata` about the original file, so it can be restored when
+    /// decompressing
+    #[must_use]
+    #[allow(clippy::missing_const_for_fn)] // The old metadata may need dropping
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+    /// Serializes the tree with `format` when writing, rather than the
+    /// compact format, so it can be read by other languages
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, RZFile, TreeFormat};
+    ///
+    /// let input = b"Readable from anywhere".to_vec();
+    /// let file = RZFile::compress(Huffman::from(&input), &input)
+    ///     .unwrap()
+    ///     .with_tree_format(TreeFormat::Bincode);
+    ///
+    /// assert_eq!(TreeFormat::Bincode, file.tree_format());
+    /// ```
+    #[must_use]
+    pub const fn with_tree_format(mut self, format: TreeFormat) -> Self {
+        self.format = format;
+        self
+    }
+    /// Returns how the tree is serialized when writing
+    #[must_use]
+    pub const fn tree_format(&self) -> TreeFormat {
+        self.format
+    }
+    /// Leaves the tree out when writing, storing only its
+    /// [`dictionary_id`](struct.Huffman.html#method.dictionary_id), for when
+    /// many small files share one tree, such as one made by
+    /// [`Huffman::train`](struct.Huffman.html#method.train). The same tree
+    /// must then be given to read the file, with
+    /// [`parse_with_dictionary`](#method.parse_with_dictionary) or
+    /// [`read_from_with_dictionary`](#method.read_from_with_dictionary).
+    /// Bytes [stored](#method.is_stored) only as the tree made compressing
+    /// them larger are compressed after all, now it's left out
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    #[must_use]
+    pub fn with_dictionary(mut self) -> Self {
+        self.dictionary = Some(model_id::<T, _>(&self.tree).unwrap());
+        if self.stored {
+            let mut input: Vec<T> = Vec::with_capacity(self.data.len());
+            // Only bytes are stored, and copying them out can't break a limit
+            extend_stored(&self.data, &mut input, &DecodeOptions::new()).unwrap();
+            let packed = self.tree.encode(&input).map(|data| pack(&data));
+            if let Some((data, zeros)) = packed.filter(|(data, _)| data.len() < input.len()) {
+                self.data = Data::Owned(data);
+                self.zeros = zeros;
+                self.stored = false;
+            }
+        }
+        self
+    }
+    /// Returns the id of the tree, if it's left out when writing
+    #[must_use]
+    pub const fn dictionary_id(&self) -> Option<u32> {
+        self.dictionary
+    }
+    /// Encrypts the data section when writing, and the tree too if
+    /// `encrypt_tree` is set, with ChaCha20-Poly1305. The key is derived from
+    /// `password` with Argon2id, and a new random salt, which is stored in the
+    /// header along with a new random nonce. Such a file is read with
+    /// [`read_from_with_password`](#method.read_from_with_password), which
+    /// also detects any change to the encrypted parts
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, RZFile, RzError};
+    ///
+    /// let input = b"Kept from prying eyes".to_vec();
+    /// let file = RZFile::compress(Huffman::from(&input), &input)
+    ///     .unwrap()
+    ///     .with_encryption(b"hunter2", true);
+    ///
+    /// let mut buf = Vec::new();
+    /// file.write_to(&mut buf).unwrap();
+    ///
+    /// let read = RZFile::<u8>::read_from_with_password(buf.as_slice(), b"hunter2").unwrap();
+    /// assert_eq!(Some(input), read.decompress());
+    /// assert!(matches!(
+    ///     RZFile::<u8>::read_from_with_password(buf.as_slice(), b"hunter3"),
+    ///     Err(RzError::DecryptionFailed)
+    /// ));
+    /// ```
+    #[cfg(feature = "crypto")]
+    #[must_use]
+    pub fn with_encryption(mut self, password: &[u8], encrypt_tree: bool) -> Self {
+        self.encryption = Some(Encryption::new(password, encrypt_tree));
+        self
+    }
+    /// Returns whether the payload is encrypted when writing. Files read are
+    /// decrypted, so are written unencrypted unless encrypted again
+    #[cfg(feature = "crypto")]
+    #[must_use]
+    pub const fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+    /// Returns the metadata about the original file, if any is stored
+    #[must_use]
+    pub const fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+    /// Returns how many symbols the data decompresses to, if it's stored
+    #[must_use]
+    pub const fn len(&self) -> Option<usize> {
+        self.len
+    }
+    /// Returns whether the data decompresses to nothing, if the length is
+    /// stored
+    #[must_use]
+    pub fn is_empty(&self) -> Option<bool> {
+        self.len.map(|len| len == 0)
+    }
+    /// Returns the checksum of the uncompressed data, if one is stored
+    #[must_use]
+    pub const fn checksum(&self) -> Option<u32> {
+        self.checksum
+    }
+    /// Returns whether the data is the original bytes, stored uncompressed
+    /// as compressing them didn't make them any smaller
+    #[must_use]
+    pub const fn is_stored(&self) -> bool {
+        self.stored
+    }
+    /// Returns the flags byte of the header written, marking which optional
+    /// sections it holds, as listed in the format table
+    #[must_use]
+    pub fn flags(&self) -> u8 {
+        let mut flags = FLAG_DATA_LEN;
+        if self.checksum.is_some() {
+            flags |= FLAG_CHECKSUM;
+        }
+        if self.len.is_some() {
+            flags |= FLAG_LEN;
+        }
+        if self.blocks.is_some() {
+            flags |= FLAG_BLOCKS;
+        }
+        if self.entries.is_some() {
+            flags |= FLAG_ENTRIES;
+        }
+        if self.metadata.is_some() {
+            flags |= FLAG_METADATA;
+        }
+        if self.dictionary.is_some() {
+            flags |= FLAG_DICTIONARY;
+        }
+        if self.format != TreeFormat::Bincode {
+            flags |= FLAG_TREE_FORMAT;
+        }
+        flags
+    }
+    /// Returns the extended flags byte of the header written, which follows
+    /// the fixed size part from version 2
+    #[must_use]
+    pub fn extended_flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.stored {
+            flags |= EXTENDED_STORED;
+        }
+        if self.blocks.as_ref().is_some_and(Blocks::has_coders) {
+            flags |= EXTENDED_BLOCK_CODERS;
+        }
+        #[cfg(feature = "crypto")]
+        if let Some(encryption) = &self.encryption {
+            flags |= EXTENDED_ENCRYPTED;
+            if encryption.sealed().tree {
+                flags |= EXTENDED_ENCRYPTED_TREE;
+            }
+        }
+        flags
+    }
+    /// Returns how many bytes the tree takes when written, which is 0 if
+    /// it's left out with [`with_dictionary`](#method.with_dictionary), or
+    /// the data is [stored](#method.is_stored)
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    #[must_use]
+    pub fn tree_len(&self) -> usize {
+        let len = self.serialized_tree().unwrap().len();
+        #[cfg(feature = "crypto")]
+        if self.encrypts_tree(len) {
+            return len + crypto::TAG_LEN;
+        }
+        len
+    }
+    /// Returns how many bytes the whole file takes when written, being the
+    /// header, tree and data
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, RZFile};
+    ///
+    /// let input = b"Sized without writing it anywhere".to_vec();
+    /// let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    ///
+    /// let mut buf = Vec::new();
+    /// file.write_to(&mut buf).unwrap();
+    /// assert_eq!(buf.len(), file.compressed_len());
+    /// assert_eq!(Some(input.len()), file.uncompressed_len());
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    #[must_use]
+    pub fn compressed_len(&self) -> usize {
+        let mut counter = Counter(0);
+        // Counting can't fail, so only serializing the tree can
+        self.write_to(&mut counter).unwrap();
+        counter.0
+    }
+    /// Returns how many symbols the data decompresses to, if it's stored, as
+    /// [`len`](#method.len) does
+    #[must_use]
+    pub const fn uncompressed_len(&self) -> Option<usize> {
+        self.len
+    }
+    /// Returns how many bytes the whole file takes for each symbol it
+    /// decompresses to, if the length is stored and isn't 0. For bytes, this
+    /// is the compressed size as a fraction of the original
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // Only approximate for huge files
+    pub fn ratio(&self) -> Option<f64> {
+        self.len
+            .filter(|len| *len > 0)
+            .map(|len| self.compressed_len() as f64 / len as f64)
+    }
+    /// The tree as it's written, before any encryption, which is empty if
+    /// it's left out
+    fn serialized_tree(&self) -> io::Result<Vec<u8>> {
+        // Stored data is never decoded with the tree, so it's left out
+        if self.stored {
+            return Ok(Vec::new());
+        }
+        match self.dictionary {
+            Some(_) => Ok(Vec::new()),
+            None => self.tree.serialize_model(self.format),
+        }
+    }
+    /// Whether a tree serialized to `len` bytes is encrypted when writing. A
+    /// tree that's left out has nothing to hide, so is never encrypted
+    #[cfg(feature = "crypto")]
+    fn encrypts_tree(&self, len: usize) -> bool {
+        len > 0 && self.encryption.as_ref().is_some_and(|e| e.sealed().tree)
+    }
+    /// Checks the data decompresses without error and, if a checksum is
+    /// stored, that it matches the decompressed data
+    ///
+    /// # Errors
+    /// Returns an error if the data isn't a valid encoding under the tree, or
+    /// doesn't match the checksum
+    pub fn verify(&self) -> Result<(), DecodeError> {
+        self.decompress_with(&DecodeOptions::new()).map(|_| ())
+    }
+    /// Copies the data section if it's borrowed, so the `RZFile` no longer
+    /// depends on the buffer it was read from
+    #[must_use]
+    pub fn into_owned(mut self) -> RZFile<'static, T, C> {
+        let data = match std::mem::replace(&mut self.data, Data::Owned(Vec::new())) {
+            Data::Borrowed(data) => Data::Owned(data.to_vec()),
+            Data::Owned(data) => Data::Owned(data),
+            #[cfg(feature = "mmap")]
+            Data::Mapped(map, range) => Data::Mapped(map, range),
+        };
+        self.with_data(data)
+    }
+    /// Swaps the data section for `data`, which must hold the same bytes
+    fn with_data(self, data: Data<'_>) -> RZFile<'_, T, C> {
+        RZFile {
+            zeros: self.zeros,
+            checksum: self.checksum,
+            len: self.len,
+            blocks: self.blocks,
+            entries: self.entries,
+            metadata: self.metadata,
+            dictionary: self.dictionary,
+            format: self.format,
+            stored: self.stored,
+            #[cfg(feature = "crypto")]
+            encryption: self.encryption,
+            tree: self.tree,
+            data,
+            marker: PhantomData,
         }
     }
     /// Returns a reference to the `RZFile`'s data, which is compressed
@@ -68,45 +741,1336 @@ where
     pub fn data(&self) -> &[u8] {
         &self.data
     }
+    /// Decompresses the data, using the file's tree
+    ///
+    /// # Errors
+    /// Returns `None` if the data isn't a valid encoding under the tree, or
+    /// doesn't match the stored checksum
+    #[must_use]
+    pub fn decompress(&self) -> Option<Vec<T>> {
+        self.decompress_with(&DecodeOptions::new()).ok()
+    }
+    /// Decompresses the data, as [`decompress`](#method.decompress) does,
+    /// applying the limits in `options`
+    ///
+    /// # Errors
+    /// Returns an error if the data isn't a valid encoding under the tree,
+    /// doesn't match the stored checksum, or if decoding it would break one of
+    /// the limits in `options`
+    pub fn decompress_with(&self, options: &DecodeOptions) -> Result<Vec<T>, DecodeError> {
+        self.decompress_with_progress(options, |_| {})
+    }
+    /// Decompresses the data, as [`decompress_with`](#method.decompress_with)
+    /// does, calling `progress` with how many bytes of data have been decoded,
+    /// and how many symbols they held, after each block. A file that isn't
+    /// split into blocks is reported once, when it's all decoded
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{DecodeOptions, Huffman, RZFile};
+    ///
+    /// let input = b"Decoded a block at a time".repeat(10);
+    /// let file = RZFile::new_blocked(Huffman::from(&input), &input, 100).unwrap();
+    ///
+    /// let mut decoded = Vec::new();
+    /// file.decompress_with_progress(&DecodeOptions::new(), |progress| {
+    ///     decoded.push(progress.produced)
+    /// })
+    /// .unwrap();
+    /// assert_eq!(vec![100, 200, 250], decoded);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the data isn't a valid encoding under the tree,
+    /// doesn't match the stored checksum, or if decoding it would break one of
+    /// the limits in `options`
+    pub fn decompress_with_progress<F>(
+        &self,
+        options: &DecodeOptions,
+        mut progress: F,
+    ) -> Result<Vec<T>, DecodeError>
+    where
+        F: FnMut(Progress),
+    {
+        trace::span!("decompress", blocks = self.block_count());
+        let capacity = match (self.len, options.max_output_len) {
+            (Some(len), Some(limit)) if len > limit => {
+                return Err(DecodeError::OutputLimitExceeded(limit))
+            }
+            // The length is read from the header, so isn't trusted beyond a
+            // symbol for each bit of data
+            (Some(len), _) => len.min(self.data.len().saturating_mul(8)),
+            (None, _) => self.data.len(),
+        };
+        let mut output = Vec::with_capacity(capacity);
+        let mut consumed = 0;
+
+        for block in 0..self.block_count() {
+            options.check_cancelled()?;
+            // The limit applies to the whole output, not each block
+            let mut remaining = options.clone();
+            remaining.max_output_len = options.max_output_len.map(|limit| limit - output.len());
+            self.decode_block(block, &mut output, &remaining)
+                .map_err(|e| match (e, options.max_output_len) {
+                    (DecodeError::OutputLimitExceeded(_), Some(limit)) => {
+                        DecodeError::OutputLimitExceeded(limit)
+                    }
+                    (e, _) => e,
+                })?;
+            consumed += self
+                .blocks
+                .as_ref()
+                .map_or(self.data.len(), |blocks| blocks.blocks[block].len as usize);
+            progress(Progress {
+                consumed: consumed as u64,
+                produced: output.len() as u64,
+            });
+        }
+
+        trace::event!(symbols = output.len(), "decompressed");
+        match self.checksum {
+            Some(expected) if checksum(&output) != expected => Err(DecodeError::ChecksumMismatch),
+            _ => Ok(output),
+        }
+    }
+    /// Decompresses only the symbols at the positions in `range`, decoding
+    /// just the blocks that hold them if the file is split into blocks, or
+    /// the whole file otherwise. Like a HTTP range request, the part of
+    /// `range` past the end of the data is left out.
+    ///
+    /// The checksum covers the whole file, so it's only checked if the file
+    /// isn't split into blocks
+    ///
+    /// # Errors
+    /// Returns an error if any block decoded isn't a valid encoding under the
+    /// tree
+    pub fn decompress_range(&self, range: Range<usize>) -> Result<Vec<T>, DecodeError> {
+        let (blocks, offset) = match self.block_size() {
+            Some(size) => (
+                range.start / size..range.end.div_ceil(size).min(self.block_count()),
+                range.start / size * size,
+            ),
+            None => {
+                return Ok(slice_range(
+                    self.decompress_with(&DecodeOptions::new())?,
+                    range,
+                    0,
+                ))
+            }
+        };
+
+        let mut output = Vec::new();
+        for block in blocks {
+            self.decode_block(block, &mut output, &DecodeOptions::new())?;
+        }
+
+        Ok(slice_range(output, range, offset))
+    }
+    /// Decompresses the block at `index` on its own. A file that isn't split
+    /// into blocks is treated as one block, holding all of the data
+    ///
+    /// # Errors
+    /// Returns an error if the block isn't a valid encoding under the tree
+    ///
+    /// # Panics
+    /// Panics if `index` isn't less than [`block_count`](#method.block_count)
+    pub fn decompress_block(&self, index: usize) -> Result<Vec<T>, DecodeError> {
+        let mut output = Vec::new();
+        self.decode_block(index, &mut output, &DecodeOptions::new())?;
+        Ok(output)
+    }
+    /// Returns how many symbols each block holds, or `None` if the file isn't
+    /// split into blocks
+    #[must_use]
+    pub fn block_size(&self) -> Option<usize> {
+        self.blocks.as_ref().map(|blocks| blocks.size as usize)
+    }
+    /// Returns how many blocks the data is split into, which is 1 for a file
+    /// that isn't split into blocks
+    #[must_use]
+    pub fn block_count(&self) -> usize {
+        self.blocks.as_ref().map_or(1, |blocks| blocks.blocks.len())
+    }
+    /// Returns how the block at `index` is coded. A file that isn't split
+    /// into blocks is treated as one block, coded with the tree, even if its
+    /// data is [stored](#method.is_stored)
+    ///
+    /// # Panics
+    /// Panics if `index` isn't less than [`block_count`](#method.block_count)
+    #[must_use]
+    pub fn block_coder(&self, index: usize) -> BlockCoder {
+        self.blocks.as_ref().map_or_else(
+            || {
+                assert_eq!(index, 0, "a file without blocks only has block 0");
+                BlockCoder::Tree
+            },
+            |blocks| blocks.blocks[index].coder,
+        )
+    }
+    fn decode_block(
+        &self,
+        index: usize,
+        output: &mut Vec<T>,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError> {
+        let (data, zeros, len) = self.blocks.as_ref().map_or_else(
+            || {
+                assert_eq!(index, 0, "a file without blocks only has block 0");
+                (&self.data[..], self.zeros, self.len)
+            },
+            |blocks| {
+                let block = blocks.blocks[index];
+                let end = block.start + block.len as usize;
+                let size = blocks.size as usize;
+                let len = self.len.map(|len| (len - index * size).min(size));
+                (&self.data[block.start..end], block.zeros, len)
+            },
+        );
+
+        match self.block_coder(index) {
+            _ if self.stored => extend_stored(data, output, options),
+            BlockCoder::Tree => self.tree.decode(unpack(data), zeros, len, output, options),
+            BlockCoder::Rle => block_coder::extend_rle(
+                data,
+                (output as &mut dyn Any)
+                    .downcast_mut()
+                    .expect("only bytes are run length encoded"),
+                len,
+                options,
+            ),
+            BlockCoder::Stored => extend_stored(data, output, options),
+        }
+    }
     /// Returns how many zeros should be appended in the file
     #[must_use]
-    pub fn zeros(&self) -> u8 {
+    pub const fn zeros(&self) -> u8 {
         self.zeros
     }
     /// Saves the compressed version of self to the file at `path`
     ///
     /// # Errors
     /// Fails if there's any issue with writing to the file at path
-    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
-        let mut contents = Vec::with_capacity(5 + self.tree_len as usize + self.data.len());
-        contents.append(&mut self.tree_len.to_be_bytes().to_vec());
-        contents.push(self.zeros);
-        contents.append(&mut bincode::serialize(&self.tree).unwrap());
-        contents.append(&mut self.data.to_vec());
-        std::fs::write(path, contents)
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+    /// Appends the compressed version of self to the file at `path`, creating
+    /// it if it doesn't exist, so many RZ files can be stored one after another
+    /// and read back with [`frames`](#method.frames)
+    ///
+    /// # Errors
+    /// Fails if there's any issue with writing to the file at path
+    pub fn append_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = File::options().create(true).append(true).open(path)?;
+        let mut file = BufWriter::new(file);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+    /// Writes the compressed version of self to `writer`, piece by piece,
+    /// without building the whole file in memory first
+    ///
+    /// # Errors
+    /// Fails if there's any issue with writing to `writer`, or if the tree
+    /// can't be serialized
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let tree = self.serialized_tree()?;
+        #[cfg(feature = "crypto")]
+        if let Some(encryption) = &self.encryption {
+            return self.write_encrypted(encryption, &tree, writer);
+        }
+
+        self.write_header(&mut writer, tree.len(), self.data.len())?;
+        writer.write_all(&tree)?;
+        writer.write_all(&self.data)
+    }
+    /// Writes the file with its data encrypted, and its tree too if it's
+    /// set to be. The header, and the tree if it isn't encrypted, are
+    /// authenticated along with them, so can't be changed unnoticed either
+    #[cfg(feature = "crypto")]
+    fn write_encrypted<W: Write>(
+        &self,
+        encryption: &Encryption,
+        tree: &[u8],
+        mut writer: W,
+    ) -> io::Result<()> {
+        let encrypts_tree = self.encrypts_tree(tree.len());
+        let tree_len = tree.len() + if encrypts_tree { crypto::TAG_LEN } else { 0 };
+        let mut header = Vec::new();
+        self.write_header(&mut header, tree_len, self.data.len() + crypto::TAG_LEN)?;
+
+        let (tree, aad) = if encrypts_tree {
+            (encryption.encrypt_tree(tree, &header), header.clone())
+        } else {
+            (tree.to_vec(), [header.as_slice(), tree].concat())
+        };
+        writer.write_all(&header)?;
+        writer.write_all(&tree)?;
+        writer.write_all(&encryption.encrypt_data(&self.data, &aad))
+    }
+    /// Writes the header, everything before the tree, for a tree and data of
+    /// `tree_len` and `data_len` bytes as they're written
+    fn write_header<W: Write>(
+        &self,
+        mut writer: W,
+        tree_len: usize,
+        data_len: usize,
+    ) -> io::Result<()> {
+        let tree_len = u32::try_from(tree_len).map_err(io::Error::other)?;
+
+        writer.write_all(&RZ_MAGIC)?;
+        writer.write_all(&[RZ_VERSION, self.flags()])?;
+        writer.write_all(&tree_len.to_be_bytes())?;
+        writer.write_all(&[self.zeros])?;
+        writer.write_all(&[self.extended_flags()])?;
+        if let Some(checksum) = self.checksum {
+            writer.write_all(&checksum.to_be_bytes())?;
+        }
+        if let Some(len) = self.len {
+            writer.write_all(&(len as u64).to_be_bytes())?;
+        }
+        writer.write_all(&(data_len as u64).to_be_bytes())?;
+        if let Some(blocks) = &self.blocks {
+            blocks.write_to(&mut writer)?;
+        }
+        if let Some(entries) = &self.entries {
+            archive::write_entries(entries, &mut writer)?;
+        }
+        if let Some(metadata) = &self.metadata {
+            metadata.write_to(&mut writer)?;
+        }
+        if let Some(id) = self.dictionary {
+            writer.write_all(&id.to_be_bytes())?;
+        }
+        if self.format != TreeFormat::Bincode {
+            writer.write_all(&[self.format.id()])?;
+        }
+        #[cfg(feature = "crypto")]
+        if let Some(encryption) = &self.encryption {
+            encryption.sealed().write_to(&mut writer)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T> RZFile<'static, T>
+where
+    T: Serialize + Ord + DeserializeOwned + Clone + 'static,
+{
+    /// Reads an RZ file from `reader`, reading the header and tree exactly,
+    /// then the data, so the file doesn't have to be read into memory before
+    /// parsing. If the header holds the length of the data, only that much is
+    /// read, leaving `reader` at the start of any file that follows
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or the file is
+    /// malformed
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, RzError> {
+        Self::read_from_with_model(reader, None)
+    }
+    /// Reads an RZ file from `reader`, as [`read_from`](#method.read_from)
+    /// does, rejecting it if it breaks any of the limits in `options`. The
+    /// tree's length is checked before it's read, so an untrusted file can't
+    /// make the reader hold more than the limits allow
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, the file is
+    /// malformed, or it breaks one of the limits in `options`
+    pub fn read_from_with_options<R: Read>(
+        reader: R,
+        options: &DecodeOptions,
+    ) -> Result<Self, RzError> {
+        Self::read_with(reader, None, None, options)
+    }
+    /// Reads an RZ file from `reader`, as [`read_from`](#method.read_from)
+    /// does, using `dictionary` as the tree if the file leaves it out
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, the file is
+    /// malformed, or it needs a different dictionary
+    pub fn read_from_with_dictionary<R: Read>(
+        reader: R,
+        dictionary: &Huffman<T>,
+    ) -> Result<Self, RzError> {
+        Self::read_from_with_model(reader, Some(dictionary))
+    }
+    /// Reads an RZ file from `reader`, as [`read_from`](#method.read_from)
+    /// does, decrypting it with `password` if it was written with
+    /// [`with_encryption`](#method.with_encryption)
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, the file is
+    /// malformed, or it can't be decrypted with `password`
+    #[cfg(feature = "crypto")]
+    pub fn read_from_with_password<R: Read>(reader: R, password: &[u8]) -> Result<Self, RzError> {
+        Self::read_with(reader, None, Some(password), &DecodeOptions::new())
+    }
+    /// Opens the RZ file at `path` by memory mapping it, so the data section
+    /// is decoded straight from the mapping rather than being read into memory.
+    ///
+    /// The file must not be changed by anything else while it's mapped, as
+    /// the data would change underneath the `RZFile`
+    ///
+    /// # Errors
+    /// Fails if the file can't be opened or mapped, or is malformed
+    #[cfg(feature = "mmap")]
+    pub fn open_mmap<P: AsRef<Path>>(path: P) -> Result<Self, RzError> {
+        let file = File::open(path)?;
+        // Safety: the caller is told not to modify the file while it's mapped
+        let map = Arc::new(unsafe { Mmap::map(&file)? });
+
+        let file = RZFile::try_from(&map[..])?;
+        // Extensions may follow the data, so it needn't run to the end of the
+        // map. Parsing borrows the data from the map, so it's found from
+        // where the slice starts
+        let start = file.data.as_ptr() as usize - map.as_ptr() as usize;
+        let range = start..start + file.data.len();
+
+        Ok(file.with_data(Data::Mapped(Arc::clone(&map), range)))
+    }
+}
+
+impl<T, C> RZFile<'static, T, C>
+where
+    T: Serialize + Ord + DeserializeOwned + Clone + 'static,
+    C: EntropyCoder<T> + Clone,
+{
+    /// Reads an RZ file compressed with any
+    /// [`EntropyCoder`](trait.EntropyCoder.html), as
+    /// [`read_from`](#method.read_from) does for Huffman trees, using `model`
+    /// as the model if the file leaves it out
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, the file is
+    /// malformed, or it needs a different model
+    pub fn read_from_with_model<R: Read>(reader: R, model: Option<&C>) -> Result<Self, RzError> {
+        Self::read_with(reader, model, None, &DecodeOptions::new())
+    }
+    /// Reads an RZ file from `reader`, decrypting it with `password` if it's
+    /// encrypted, and checking it against the limits in `options`
+    #[cfg_attr(not(feature = "crypto"), allow(unused_variables))]
+    fn read_with<R: Read>(
+        mut reader: R,
+        model: Option<&C>,
+        password: Option<&[u8]>,
+        options: &DecodeOptions,
+    ) -> Result<Self, RzError> {
+        trace::span!("read_rz");
+        let header = Header::read_from(&mut reader)?;
+
+        // The header is authenticated along with an encrypted payload, so is
+        // kept as it was read
+        let mut recorded = Recorded::new(&mut reader, header.to_bytes().to_vec());
+        let sections = Sections::read_from(&mut recorded, &header)?;
+        let header_bytes = recorded.bytes;
+        sections.check_limits(&header, options)?;
+        #[cfg(feature = "crypto")]
+        let encryption = sections.open(password)?;
+        #[cfg(not(feature = "crypto"))]
+        if sections.sealed.is_some() {
+            return Err(RzError::Encrypted);
+        }
+
+        // Not trusting `tree_len` enough to allocate it all upfront
+        let mut tree = Vec::new();
+        (&mut reader)
+            .take(u64::from(header.tree_len))
+            .read_to_end(&mut tree)?;
+        if tree.len() != header.tree_len as usize {
+            return Err(RzError::TruncatedHeader("tree"));
+        }
+        // Only the header is authenticated with the data if the tree is
+        // encrypted, as the tree has a tag of its own
+        #[cfg(feature = "crypto")]
+        let aad = match encryption.as_ref() {
+            Some(encryption) if encryption.sealed().tree && !tree.is_empty() => {
+                tree = encryption.decrypt_tree(&tree, &header_bytes)?;
+                header_bytes
+            }
+            _ => [header_bytes.as_slice(), &tree].concat(),
+        };
+
+        let mut data = Vec::new();
+        match sections.data_len {
+            Some(data_len) => {
+                reader.take(data_len as u64).read_to_end(&mut data)?;
+                if data.len() != data_len {
+                    return Err(malformed(format!(
+                        "the data is {} bytes long, but only {} bytes follow the tree",
+                        data_len,
+                        data.len()
+                    )));
+                }
+            }
+            None => {
+                reader.read_to_end(&mut data)?;
+            }
+        }
+        #[cfg(feature = "crypto")]
+        if let Some(encryption) = &encryption {
+            data = encryption.decrypt_data(&data, &aad)?;
+        }
+        // Only parsed once the payload is known not to have been changed
+        let tree = sections.tree(&tree, model)?;
+        check_depth(&tree, options)?;
+        header.check_padding(&data)?;
+        sections.check::<T>(data.len())?;
+        trace::event!(
+            tree_len = header.tree_len,
+            data_len = data.len(),
+            symbols = ?sections.len,
+            blocks = ?sections.blocks.as_ref().map(|blocks| blocks.blocks.len()),
+            "read file"
+        );
+
+        Ok(sections.into_file(&header, tree, Data::Owned(data)))
+    }
+}
+
+impl<'a, T> TryFrom<&'a [u8]> for RZFile<'a, T>
+where
+    T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
+{
+    type Error = RzError;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        Self::parse_with_model(buf, None)
     }
 }
 
-impl<'a: 'de, 'de, T> TryFrom<&'a [u8]> for RZFile<T>
+impl<'a, T> RZFile<'a, T>
 where
-    T: Serialize + Ord + Deserialize<'de> + Clone,
+    T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
 {
-    type Error = bincode::Error;
+    /// Iterates over every RZ file in `buf`, where many have been stored one
+    /// after another, such as by [`append_to_file`](#method.append_to_file).
+    /// Iteration stops after the first error
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, RZFile};
+    ///
+    /// let mut buf = Vec::new();
+    /// for line in &["first line\n", "second line\n"] {
+    ///     let file = RZFile::compress(Huffman::from(*line), &line.chars().collect::<Vec<_>>());
+    ///     file.unwrap().write_to(&mut buf).unwrap();
+    /// }
+    ///
+    /// let mut decompressed = String::new();
+    /// for file in RZFile::<char>::frames(&buf) {
+    ///     decompressed.extend(file.unwrap().decompress().unwrap());
+    /// }
+    /// assert_eq!("first line\nsecond line\n", decompressed);
+    /// ```
+    #[must_use]
+    pub const fn frames(buf: &'a [u8]) -> Frames<'a, T> {
+        Frames {
+            buf,
+            marker: PhantomData,
+        }
+    }
+    /// Parses an RZ file from `buf`, as `try_from` does, using `dictionary`
+    /// as the tree if the file leaves it out
+    ///
+    /// # Errors
+    /// Fails if the file is malformed, or needs a different dictionary
+    pub fn parse_with_dictionary(buf: &'a [u8], dictionary: &Huffman<T>) -> Result<Self, RzError> {
+        Self::parse_all(buf, Some(dictionary), &DecodeOptions::new())
+    }
+    /// Parses an RZ file from `buf`, as `try_from` does, rejecting it if it
+    /// breaks any of the limits in `options`. The limits are checked before
+    /// the tree is deserialized, so an untrusted file can't make the parser
+    /// allocate more than they allow
+    /// # Examples
+    /// ```
+    /// use std::convert::TryFrom;
+    /// use huffman_comprs::{DecodeOptions, Huffman, RZFile, RzError};
+    ///
+    /// let input = b"An untrusted upload, big enough to be compressed. ".repeat(4);
+    /// let mut buf = Vec::new();
+    /// RZFile::compress(Huffman::from(&input), &input)
+    ///     .unwrap()
+    ///     .write_to(&mut buf)
+    ///     .unwrap();
+    ///
+    /// let options = DecodeOptions::new().max_tree_bytes(16);
+    ///
+    /// assert!(matches!(
+    ///     RZFile::<u8>::parse_with_options(&buf, &options),
+    ///     Err(RzError::TreeTooLarge(16))
+    /// ));
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if the file is malformed, or breaks one of the limits in
+    /// `options`
+    pub fn parse_with_options(buf: &'a [u8], options: &DecodeOptions) -> Result<Self, RzError> {
+        Self::parse_all(buf, None, options)
+    }
+}
 
-    fn try_from(mut buf: &'de [u8]) -> Result<Self, Self::Error> {
-        let tree_len = u32::from_be_bytes(buf[0..4].try_into().unwrap());
-        let zeros = buf[4];
-        buf = &buf[5..];
+impl<'a, T, C> RZFile<'a, T, C>
+where
+    T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
+    C: EntropyCoder<T> + Clone,
+{
+    /// Parses an RZ file compressed with any
+    /// [`EntropyCoder`](trait.EntropyCoder.html) from `buf`, as `try_from`
+    /// does for Huffman trees, using `model` as the model if the file leaves
+    /// it out
+    ///
+    /// # Errors
+    /// Fails if the file is malformed, or needs a different model
+    pub fn parse_with_model(buf: &'a [u8], model: Option<&C>) -> Result<Self, RzError> {
+        Self::parse_all(buf, model, &DecodeOptions::new())
+    }
+    /// Parses the RZ file making up the whole of `buf`, other than any
+    /// extension frames
+    fn parse_all(
+        buf: &'a [u8],
+        model: Option<&C>,
+        options: &DecodeOptions,
+    ) -> Result<Self, RzError> {
+        match Self::parse_frame(buf, model, options)? {
+            (file, rest) if extension::skip(rest)?.is_empty() => Ok(file),
+            (_, rest) => Err(malformed(format!(
+                "{} bytes follow the file, which may be more files, to be read with `RZFile::frames`",
+                rest.len()
+            ))),
+        }
+    }
+    /// Parses the RZ file at the start of `buf`, returning it and the bytes
+    /// following it
+    fn parse_frame(
+        buf: &'a [u8],
+        model: Option<&C>,
+        options: &DecodeOptions,
+    ) -> Result<(Self, &'a [u8]), RzError> {
+        trace::span!("parse_rz");
+        let buf = extension::skip(buf)?;
+        if buf.len() < HEADER_LEN {
+            return Err(RzError::TruncatedHeader("header"));
+        }
+        let (header, buf) = buf.split_at(HEADER_LEN);
+        let header = Header::parse(header.try_into().unwrap())?;
 
-        let (tree, buf) = buf.split_at(tree_len as usize);
+        let mut buf = buf;
+        let sections = Sections::read_from(&mut buf, &header)?;
+        // Decrypting needs somewhere to put the payload, so can't borrow it
+        if sections.sealed.is_some() {
+            return Err(RzError::Encrypted);
+        }
+        sections.check_limits(&header, options)?;
+
+        let (tree, buf) = usize::try_from(header.tree_len)
+            .ok()
+            .filter(|tree_len| *tree_len <= buf.len())
+            .map(|tree_len| buf.split_at(tree_len))
+            .ok_or(RzError::TruncatedHeader("tree"))?;
 
-        let tree = bincode::deserialize(tree)?;
+        let (data, rest) = match sections.data_len {
+            Some(data_len) if data_len > buf.len() => {
+                return Err(malformed(format!(
+                    "the data is {} bytes long, but only {} bytes follow the tree",
+                    data_len,
+                    buf.len()
+                )))
+            }
+            Some(data_len) => buf.split_at(data_len),
+            None => (buf, &[][..]),
+        };
 
+        header.check_padding(data)?;
+        sections.check::<T>(data.len())?;
+        let tree = sections.tree(tree, model)?;
+        check_depth(&tree, options)?;
+        trace::event!(
+            tree_len = header.tree_len,
+            data_len = data.len(),
+            symbols = ?sections.len,
+            blocks = ?sections.blocks.as_ref().map(|blocks| blocks.blocks.len()),
+            "read file"
+        );
+
+        Ok((
+            sections.into_file(&header, tree, Data::Borrowed(data)),
+            rest,
+        ))
+    }
+}
+
+/// An iterator over many RZ files stored one after another, made by
+/// [`RZFile::frames`](struct.RZFile.html#method.frames)
+#[derive(Clone, Debug)]
+pub struct Frames<'a, T> {
+    buf: &'a [u8],
+    marker: PhantomData<T>,
+}
+
+impl<'a, T> Iterator for Frames<'a, T>
+where
+    T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
+{
+    type Item = Result<RZFile<'a, T>, RzError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Extensions after the last file shouldn't look like another file
+        match extension::skip(self.buf) {
+            Ok([]) => return None,
+            Ok(_) => {}
+            Err(e) => {
+                self.buf = &[];
+                return Some(Err(e));
+            }
+        }
+
+        match RZFile::parse_frame(self.buf, None, &DecodeOptions::new()) {
+            Ok((file, rest)) => {
+                self.buf = rest;
+                Some(Ok(file))
+            }
+            Err(e) => {
+                self.buf = &[];
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+/// The fixed size part of an RZ file's header
+struct Header {
+    version: u8,
+    flags: u8,
+    tree_len: u32,
+    zeros: u8,
+}
+
+impl Header {
+    /// Reads the fixed size part of the header from `reader`, skipping any
+    /// extension frames before it
+    fn read_from<R: Read>(mut reader: R) -> Result<Self, RzError> {
+        let mut header = [0; HEADER_LEN];
+        loop {
+            reader
+                .read_exact(&mut header[..4])
+                .map_err(RzError::truncated("header"))?;
+            if header[..4] != RZ_EXTENSION_MAGIC {
+                break;
+            }
+            Extension::read_after_magic(&mut reader)?;
+        }
+        reader
+            .read_exact(&mut header[4..])
+            .map_err(RzError::truncated("header"))?;
+        Self::parse(&header)
+    }
+    fn parse(header: &[u8; HEADER_LEN]) -> Result<Self, RzError> {
+        if header[0..4] != RZ_MAGIC {
+            return Err(RzError::BadMagic);
+        }
+        if header[4] == 0 || header[4] > RZ_VERSION {
+            return Err(RzError::UnsupportedVersion(header[4]));
+        }
         Ok(Self {
-            tree_len,
-            zeros,
-            tree,
-            data: buf.to_vec(),
+            version: header[4],
+            flags: header[5],
+            tree_len: u32::from_be_bytes(header[6..10].try_into().unwrap()),
+            zeros: header[10],
         })
     }
+    /// The fixed size part of the header, as it was read
+    fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&RZ_MAGIC);
+        header[4] = self.version;
+        header[5] = self.flags;
+        header[6..10].copy_from_slice(&self.tree_len.to_be_bytes());
+        header[10] = self.zeros;
+        header
+    }
+    const fn check_padding(&self, data: &[u8]) -> Result<(), RzError> {
+        if self.zeros > 7 || (self.zeros > 0 && data.is_empty()) {
+            Err(RzError::BadPadding)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The optional sections of the header, between the fixed size part and the
+/// tree
+struct Sections {
+    checksum: Option<u32>,
+    len: Option<usize>,
+    data_len: Option<usize>,
+    blocks: Option<Blocks>,
+    entries: Option<Vec<Entry>>,
+    metadata: Option<Metadata>,
+    dictionary: Option<u32>,
+    format: TreeFormat,
+    stored: bool,
+    sealed: Option<Sealed>,
+}
+
+impl Sections {
+    fn read_from<R: Read>(mut reader: R, header: &Header) -> Result<Self, RzError> {
+        let flags = header.flags;
+        let extended = Self::read_extended(&mut reader, header)?;
+
+        let checksum = if flags & FLAG_CHECKSUM == 0 {
+            None
+        } else {
+            let mut checksum = [0; 4];
+            reader
+                .read_exact(&mut checksum)
+                .map_err(RzError::truncated("checksum"))?;
+            Some(u32::from_be_bytes(checksum))
+        };
+        let len = if flags & FLAG_LEN == 0 {
+            None
+        } else {
+            let mut len = [0; 8];
+            reader
+                .read_exact(&mut len)
+                .map_err(RzError::truncated("length"))?;
+            let len = usize::try_from(u64::from_be_bytes(len))
+                .map_err(|_| malformed("the data is too long to decompress"))?;
+            Some(len)
+        };
+        let data_len = if flags & FLAG_DATA_LEN == 0 {
+            None
+        } else {
+            let mut data_len = [0; 8];
+            reader
+                .read_exact(&mut data_len)
+                .map_err(RzError::truncated("data length"))?;
+            let data_len = usize::try_from(u64::from_be_bytes(data_len))
+                .map_err(|_| malformed("the data is too long to read"))?;
+            Some(data_len)
+        };
+        let blocks = if flags & FLAG_BLOCKS == 0 {
+            None
+        } else {
+            Some(Blocks::read_from(
+                &mut reader,
+                extended & EXTENDED_BLOCK_CODERS != 0,
+            )?)
+        };
+        let entries = if flags & FLAG_ENTRIES == 0 {
+            None
+        } else {
+            Some(archive::read_entries(&mut reader)?)
+        };
+        let metadata = if flags & FLAG_METADATA == 0 {
+            None
+        } else {
+            Some(Metadata::read_from(&mut reader)?)
+        };
+        let dictionary = if flags & FLAG_DICTIONARY == 0 {
+            None
+        } else {
+            let mut id = [0; 4];
+            reader
+                .read_exact(&mut id)
+                .map_err(RzError::truncated("dictionary id"))?;
+            Some(u32::from_be_bytes(id))
+        };
+        let format = if flags & FLAG_TREE_FORMAT == 0 {
+            TreeFormat::Bincode
+        } else {
+            let mut id = [0];
+            reader
+                .read_exact(&mut id)
+                .map_err(RzError::truncated("tree format"))?;
+            TreeFormat::from_id(id[0])?
+        };
+        let sealed = if extended & EXTENDED_ENCRYPTED == 0 {
+            None
+        } else {
+            Some(Sealed::read_from(
+                &mut reader,
+                extended & EXTENDED_ENCRYPTED_TREE != 0,
+            )?)
+        };
+
+        Ok(Self {
+            checksum,
+            len,
+            data_len,
+            blocks,
+            entries,
+            metadata,
+            dictionary,
+            format,
+            stored: extended & EXTENDED_STORED != 0,
+            sealed,
+        })
+    }
+    /// Reads the extended flags, which version 1 files don't have
+    fn read_extended<R: Read>(mut reader: R, header: &Header) -> Result<u8, RzError> {
+        if header.version < 2 {
+            return Ok(0);
+        }
+        let mut extended = [0];
+        reader
+            .read_exact(&mut extended)
+            .map_err(RzError::truncated("extended flags"))?;
+        let extended = extended[0];
+
+        let known =
+            EXTENDED_STORED | EXTENDED_ENCRYPTED | EXTENDED_ENCRYPTED_TREE | EXTENDED_BLOCK_CODERS;
+        if extended & !known != 0 {
+            return Err(malformed(format!(
+                "unknown extended flags {:#04x}",
+                extended & !known
+            )));
+        }
+        if extended & EXTENDED_ENCRYPTED_TREE != 0 && extended & EXTENDED_ENCRYPTED == 0 {
+            return Err(malformed(
+                "only the tree of an encrypted file can be encrypted",
+            ));
+        }
+        if extended & EXTENDED_BLOCK_CODERS != 0 && header.flags & FLAG_BLOCKS == 0 {
+            return Err(malformed(
+                "only files split into blocks have a coder for each block",
+            ));
+        }
+        Ok(extended)
+    }
+    /// Derives the key the payload is encrypted with from `password`, if it's
+    /// encrypted
+    #[cfg(feature = "crypto")]
+    fn open(&self, password: Option<&[u8]>) -> Result<Option<Encryption>, RzError> {
+        match (&self.sealed, password) {
+            (None, _) => Ok(None),
+            (Some(sealed), Some(password)) => Ok(Some(Encryption::open(password, sealed))),
+            (Some(_), None) => Err(RzError::Encrypted),
+        }
+    }
+    /// Deserializes the tree, or if it's left out, checks `dictionary` is the
+    /// tree needed
+    fn tree<'a, T, C>(&self, tree: &'a [u8], dictionary: Option<&C>) -> Result<C, RzError>
+    where
+        T: Deserialize<'a>,
+        C: EntropyCoder<T> + Clone,
+    {
+        match (self.dictionary, dictionary) {
+            // Files written before stored data left the tree out still hold it
+            (None, _) if self.stored && tree.is_empty() => {
+                dictionary.cloned().or_else(C::empty).ok_or_else(|| {
+                    malformed("the data is stored, and no model was given to read it with")
+                })
+            }
+            (None, _) => C::deserialize_model(tree, self.format),
+            (Some(id), Some(dictionary)) if model_id::<T, _>(dictionary)? == id => {
+                Ok(dictionary.clone())
+            }
+            (Some(id), _) => Err(RzError::MissingDictionary(id)),
+        }
+    }
+    /// Checks the tree's length, and the number of symbols if it's stored,
+    /// against the limits in `options`
+    fn check_limits(&self, header: &Header, options: &DecodeOptions) -> Result<(), RzError> {
+        if let Some(limit) = options.max_tree_bytes {
+            if header.tree_len as usize > limit {
+                return Err(RzError::TreeTooLarge(limit));
+            }
+        }
+        match (self.len, options.max_output_len) {
+            (Some(len), Some(limit)) if len > limit => {
+                Err(DecodeError::OutputLimitExceeded(limit).into())
+            }
+            _ => Ok(()),
+        }
+    }
+    /// Checks the sections agree with each other, and with the `data_len`
+    /// bytes of data
+    fn check<T: 'static>(&self, data_len: usize) -> Result<(), RzError> {
+        if self.stored {
+            return match self.len {
+                _ if !is_bytes::<T>() => Err(malformed("only bytes can be stored uncompressed")),
+                _ if self.blocks.is_some() => {
+                    Err(malformed("stored data can't be split into blocks"))
+                }
+                Some(len) if len == data_len => Ok(()),
+                _ => Err(malformed(format!(
+                    "the data is stored uncompressed, so should be {data_len} symbols long"
+                ))),
+            };
+        }
+
+        let Some(blocks) = &self.blocks else {
+            return Ok(());
+        };
+        blocks.check(data_len)?;
+        if blocks.has_coders() && !is_bytes::<T>() {
+            return Err(malformed(
+                "only blocks of bytes can be coded other than with the tree",
+            ));
+        }
+        // Stored blocks hold one byte for each symbol
+        let size = blocks.size as usize;
+        let mismatched = blocks.blocks.iter().enumerate().find(|(i, block)| {
+            block.coder == BlockCoder::Stored
+                && self
+                    .len
+                    .is_some_and(|len| len.saturating_sub(i * size).min(size) != block.len as usize)
+        });
+        if let Some((i, _)) = mismatched {
+            return Err(malformed(format!(
+                "block {i} is stored, but isn't as long as the symbols it holds"
+            )));
+        }
+
+        match self.len {
+            Some(len) if len.div_ceil(blocks.size as usize) != blocks.blocks.len() => {
+                Err(malformed(format!(
+                    "{} symbols don't fit in {} blocks of {} symbols",
+                    len,
+                    blocks.blocks.len(),
+                    blocks.size
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+    fn into_file<'a, T, C>(self, header: &Header, tree: C, data: Data<'a>) -> RZFile<'a, T, C>
+    where
+        T: Serialize + Ord + Clone + 'static,
+    {
+        RZFile {
+            zeros: header.zeros,
+            checksum: self.checksum,
+            len: self.len,
+            blocks: self.blocks,
+            entries: self.entries,
+            metadata: self.metadata,
+            dictionary: self.dictionary,
+            format: self.format,
+            stored: self.stored,
+            #[cfg(feature = "crypto")]
+            encryption: None,
+            tree,
+            data,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl Blocks {
+    /// Whether any block is coded other than with the tree, so every block's
+    /// coder is written in the index
+    fn has_coders(&self) -> bool {
+        self.blocks
+            .iter()
+            .any(|block| block.coder != BlockCoder::Tree)
+    }
+    /// How many bytes each block takes up in the index
+    fn entry_len(&self) -> usize {
+        if self.has_coders() {
+            6
+        } else {
+            5
+        }
+    }
+    fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let count = u32::try_from(self.blocks.len()).map_err(io::Error::other)?;
+        let coders = self.has_coders();
+
+        writer.write_all(&self.size.to_be_bytes())?;
+        writer.write_all(&count.to_be_bytes())?;
+        for block in &self.blocks {
+            writer.write_all(&block.len.to_be_bytes())?;
+            writer.write_all(&[block.zeros])?;
+            if coders {
+                writer.write_all(&[block.coder.id()])?;
+            }
+        }
+        Ok(())
+    }
+    /// Reads the block index, with a coder for each block if `coders`
+    fn read_from<R: Read>(mut reader: R, coders: bool) -> Result<Self, RzError> {
+        let read_u32 = |reader: &mut R| -> Result<u32, RzError> {
+            let mut bytes = [0; 4];
+            reader
+                .read_exact(&mut bytes)
+                .map_err(RzError::truncated("block index"))?;
+            Ok(u32::from_be_bytes(bytes))
+        };
+
+        let size = read_u32(&mut reader)?;
+        if size == 0 {
+            return Err(malformed("the blocks are each 0 symbols long"));
+        }
+        let count = read_u32(&mut reader)?;
+
+        // Not trusting `count` enough to allocate it all upfront
+        let mut blocks = Vec::new();
+        let mut start = 0_usize;
+        for _ in 0..count {
+            let len = read_u32(&mut reader)?;
+            let mut zeros = [0];
+            reader
+                .read_exact(&mut zeros)
+                .map_err(RzError::truncated("block index"))?;
+            let coder = if coders {
+                let mut id = [0];
+                reader
+                    .read_exact(&mut id)
+                    .map_err(RzError::truncated("block index"))?;
+                BlockCoder::from_id(id[0])?
+            } else {
+                BlockCoder::Tree
+            };
+
+            blocks.push(Block {
+                start,
+                len,
+                zeros: zeros[0],
+                coder,
+            });
+            start = start.saturating_add(len as usize);
+        }
+
+        Ok(Self { size, blocks })
+    }
+    /// Checks the blocks exactly cover `data_len` bytes, and each has valid
+    /// padding
+    fn check(&self, data_len: usize) -> Result<(), RzError> {
+        let end = self
+            .blocks
+            .last()
+            .map_or(0, |block| block.start.saturating_add(block.len as usize));
+        if end != data_len {
+            return Err(malformed(format!(
+                "the blocks hold {end} bytes, but there are {data_len} bytes of data"
+            )));
+        }
+        // Only blocks coded with the tree are padded to a whole byte
+        if self.blocks.iter().any(|block| {
+            block.zeros > 7
+                || (block.zeros > 0 && (block.len == 0 || block.coder != BlockCoder::Tree))
+        }) {
+            return Err(RzError::BadPadding);
+        }
+        Ok(())
+    }
+}
+
+/// Counts the bytes written to it, discarding them
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps a copy of every byte read through it, after the bytes it starts with
+struct Recorded<R> {
+    reader: R,
+    bytes: Vec<u8>,
+}
+
+impl<R> Recorded<R> {
+    const fn new(reader: R, bytes: Vec<u8>) -> Self {
+        Self { reader, bytes }
+    }
+}
+
+impl<R: Read> Read for Recorded<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.reader.read(buf)?;
+        self.bytes.extend_from_slice(&buf[..read]);
+        Ok(read)
+    }
+}
+
+/// Packs `bits` into bytes the way the data section stores them, returning
+/// the bytes and how many zeros pad the end
+fn pack(bits: &Code) -> (Vec<u8>, u8) {
+    let mut writer = BitWriter::with_order(BitOrder::LsbFirst);
+    writer.extend(bits.to_vec().into_iter().rev());
+    writer.finish()
+}
+
+/// Unpacks bytes from the data section into bits, ready to be decoded
+fn unpack(bytes: &[u8]) -> Vec<bool> {
+    BitReader::with_order(bytes, BitOrder::LsbFirst).collect()
+}
+
+/// Whether `T` is `u8`, the only type that can be stored uncompressed
+fn is_bytes<T: 'static>() -> bool {
+    TypeId::of::<T>() == TypeId::of::<u8>()
+}
+
+/// Copies `symbols`, which must be bytes, into a `Vec<u8>`
+fn to_bytes<T: 'static>(symbols: &[T]) -> Vec<u8> {
+    symbols
+        .iter()
+        .filter_map(|symbol| (symbol as &dyn Any).downcast_ref::<u8>().copied())
+        .collect()
+}
+
+/// Copies stored bytes onto the end of `output`, which must be a `Vec<u8>`
+fn extend_stored(
+    data: &[u8],
+    output: &mut dyn Any,
+    options: &DecodeOptions,
+) -> Result<(), DecodeError> {
+    if let Some(limit) = options.max_output_len.filter(|limit| data.len() > *limit) {
+        return Err(DecodeError::OutputLimitExceeded(limit));
+    }
+    let output = output
+        .downcast_mut::<Vec<u8>>()
+        .expect("only bytes are stored uncompressed");
+    output.extend_from_slice(data);
+    Ok(())
+}
+
+/// Takes the symbols at the positions in `range` from `output`, which starts
+/// at position `offset`
+fn slice_range<T>(mut output: Vec<T>, range: Range<usize>, offset: usize) -> Vec<T> {
+    let start = range.start.saturating_sub(offset).min(output.len());
+    let end = range.end.saturating_sub(offset).clamp(start, output.len());
+
+    output.truncate(end);
+    output.drain(..start);
+    output
+}
+
+/// The id of `model` when it's left out of a file, being the CRC-32 of its
+/// bincode serialization. For a tree, this is its
+/// [`dictionary_id`](struct.Huffman.html#method.dictionary_id)
+fn model_id<T, C: EntropyCoder<T>>(model: &C) -> io::Result<u32> {
+    let mut crc = Crc32::new();
+    crc.update(&model.serialize_model(TreeFormat::Bincode)?);
+    Ok(crc.finish())
+}
+
+/// Checks the longest code in `model` against the limit in `options`
+fn check_depth<T, C: EntropyCoder<T>>(model: &C, options: &DecodeOptions) -> Result<(), RzError> {
+    match options.max_tree_depth {
+        Some(limit) if model.depth() > limit => Err(RzError::TreeTooDeep(limit)),
+        _ => Ok(()),
+    }
+}
+
+/// Deserializes a tree, checking it's well formed
+pub fn parse_tree<'a, T>(tree: &'a [u8], format: TreeFormat) -> Result<Huffman<T>, RzError>
+where
+    T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
+{
+    let tree: Huffman<T> = format.deserialize(tree)?;
+    tree.validate()
+        .map_err(|e| RzError::TreeDecode(format!("the tree is malformed, as {e}")))?;
+    Ok(tree)
+}
+
+/// Skips the RZ file at the start of `buf` without decoding it, returning the
+/// bytes following it
+pub fn skip_frame(buf: &[u8]) -> Result<&[u8], RzError> {
+    if buf.len() < HEADER_LEN {
+        return Err(RzError::TruncatedHeader("header"));
+    }
+    let (header, mut buf) = buf.split_at(HEADER_LEN);
+    let header = Header::parse(header.try_into().unwrap())?;
+    let sections = Sections::read_from(&mut buf, &header)?;
+
+    let tree_len = header.tree_len as usize;
+    match sections.data_len {
+        Some(data_len) if tree_len.saturating_add(data_len) <= buf.len() => {
+            Ok(&buf[tree_len + data_len..])
+        }
+        Some(_) => Err(malformed("the file is longer than the bytes left")),
+        None if tree_len <= buf.len() => Ok(&[]),
+        None => Err(RzError::TruncatedHeader("tree")),
+    }
+}
+
+pub fn malformed<S: Into<String>>(reason: S) -> RzError {
+    RzError::Malformed(reason.into())
+}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Returns the id an RZ file uses to reference this tree, when it's left
+    /// out with [`RZFile::with_dictionary`](struct.RZFile.html#method.with_dictionary).
+    /// This is the CRC-32 of the serialized tree
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    #[must_use]
+    pub fn dictionary_id(&self) -> u32 {
+        let mut crc = Crc32::new();
+        bincode::serialize_into(&mut crc, self).unwrap();
+        crc.finish()
+    }
+    /// Returns a 64-bit hash of the tree, which is the same for equal trees
+    /// on any platform, so can identify trees stored elsewhere, such as in a
+    /// cache of dictionaries. This is the FNV-1a hash of the serialized tree
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::from("aabbbc");
+    ///
+    /// assert_eq!(Huffman::from("aabbbc").fingerprint(), tree.fingerprint());
+    /// assert_ne!(Huffman::from("aabbcc").fingerprint(), tree.fingerprint());
+    /// ```
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    #[must_use]
+    pub fn fingerprint(&self) -> u64 {
+        let mut fnv = Fnv64::new();
+        bincode::serialize_into(&mut fnv, self).unwrap();
+        fnv.finish()
+    }
+}
+
+impl Metadata {
+    /// Reads the header of the RZ file at the start of `reader`, up to but
+    /// not including the tree, returning the file's metadata if it has any.
+    ///
+    /// As the tree isn't read, this works whatever type the symbols are, so
+    /// [`symbols`](#structfield.symbols) can say which type to read the file
+    /// as
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, Metadata, RZFile};
+    ///
+    /// let metadata = Metadata {
+    ///     symbols: Some("char".to_string()),
+    ///     ..Metadata::default()
+    /// };
+    /// let file = RZFile::compress(Huffman::from("chars"), &['c', 'h', 'a', 'r', 's']);
+    /// let mut buf = Vec::new();
+    /// file.unwrap().with_metadata(metadata.clone()).write_to(&mut buf).unwrap();
+    ///
+    /// assert_eq!(Some(metadata), Metadata::read_from_header(buf.as_slice()).unwrap());
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or the header is
+    /// malformed
+    pub fn read_from_header<R: Read>(mut reader: R) -> Result<Option<Self>, RzError> {
+        let header = Header::read_from(&mut reader)?;
+
+        Ok(Sections::read_from(&mut reader, &header)?.metadata)
+    }
+}
+
+/// The CRC-32 of every symbol, in its bincode serialized form. For bytes,
+/// this is the same as the CRC-32 of the bytes themselves
+fn checksum<T: Serialize>(symbols: &[T]) -> u32 {
+    let mut crc = Crc32::new();
+    for symbol in symbols {
+        bincode::serialize_into(&mut crc, symbol).unwrap();
+    }
+    crc.finish()
 }
@@ -0,0 +1,92 @@
+use std::iter;
+
+use super::{malformed, RzError};
+use crate::{DecodeError, DecodeOptions};
+
+/// How a block of an [`RZFile`](struct.RZFile.html) split into blocks is
+/// coded, chosen for each block by
+/// [`RZFile::new_blocked_adaptive`](struct.RZFile.html#method.new_blocked_adaptive).
+///
+/// Only blocks of bytes can be coded other than with the tree. When any
+/// block is, extended flag `0x08` is set, and each block's entry in the
+/// block index is followed by a byte holding its coder's id
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlockCoder {
+    /// With the file's tree, or its other
+    /// [`EntropyCoder`](trait.EntropyCoder.html), with id 0
+    #[default]
+    Tree,
+    /// As runs of the same byte, each as two bytes, being the length of the
+    /// run less one, then the byte, with id 1
+    Rle,
+    /// As the bytes themselves, with id 2
+    Stored,
+}
+
+impl BlockCoder {
+    /// The byte marking the coder in the block index
+    pub(crate) const fn id(self) -> u8 {
+        match self {
+            Self::Tree => 0,
+            Self::Rle => 1,
+            Self::Stored => 2,
+        }
+    }
+    pub(crate) fn from_id(id: u8) -> Result<Self, RzError> {
+        match id {
+            0 => Ok(Self::Tree),
+            1 => Ok(Self::Rle),
+            2 => Ok(Self::Stored),
+            _ => Err(malformed(format!(
+                "a block has an unknown coder, with id {id}"
+            ))),
+        }
+    }
+}
+
+/// Codes `bytes` as runs of the same byte, splitting runs longer than 256
+pub fn encode_rle(bytes: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut rest = bytes;
+    while let Some(&byte) = rest.first() {
+        let run = rest
+            .iter()
+            .take(256)
+            .take_while(|other| **other == byte)
+            .count();
+        // `run` is between 1 and 256, so this fits
+        #[allow(clippy::cast_possible_truncation)]
+        output.extend([(run - 1) as u8, byte]);
+        rest = &rest[run..];
+    }
+    output
+}
+
+/// Decodes runs written by `encode_rle` onto the end of `output`. If `len`
+/// is given, exactly that many bytes must be decoded
+pub fn extend_rle(
+    data: &[u8],
+    output: &mut Vec<u8>,
+    len: Option<usize>,
+    options: &DecodeOptions,
+) -> Result<(), DecodeError> {
+    let limit = options.max_output_len.unwrap_or(usize::MAX);
+    let mut decoded = 0_usize;
+    for run in data.chunks(2) {
+        let [count, byte] = *run else {
+            return Err(DecodeError::UnexpectedEof);
+        };
+        let count = usize::from(count) + 1;
+        if decoded + count > limit {
+            return Err(DecodeError::OutputLimitExceeded(limit));
+        }
+        output.extend(iter::repeat_n(byte, count));
+        decoded += count;
+    }
+
+    match len {
+        Some(len) if decoded < len => Err(DecodeError::UnexpectedEof),
+        Some(len) if decoded > len => Err(DecodeError::DanglingBits),
+        _ => Ok(()),
+    }
+}
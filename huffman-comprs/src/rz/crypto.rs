@@ -0,0 +1,110 @@
+use std::fmt;
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{rand_core::RngCore, Aead, KeyInit, OsRng, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+
+use super::{RzError, Sealed, NONCE_LEN, SALT_LEN};
+
+/// The length of the tag encrypting adds to the end of each payload
+pub const TAG_LEN: usize = 16;
+
+/// The key a file's payload is encrypted with, along with the salt it was
+/// derived with, and the nonce, which are stored in the header
+#[derive(Clone)]
+pub struct Encryption {
+    key: Key,
+    sealed: Sealed,
+}
+
+impl Encryption {
+    /// Derives a key from `password` with a new random salt, and picks a new
+    /// random nonce, encrypting the tree too if `tree` is set
+    pub fn new(password: &[u8], tree: bool) -> Self {
+        let mut sealed = Sealed {
+            salt: [0; SALT_LEN],
+            nonce: [0; NONCE_LEN],
+            tree,
+        };
+        OsRng.fill_bytes(&mut sealed.salt);
+        OsRng.fill_bytes(&mut sealed.nonce);
+
+        Self {
+            key: derive(password, &sealed.salt),
+            sealed,
+        }
+    }
+    /// Derives the key a file read was encrypted with from `password`, and
+    /// the salt stored in its header
+    pub fn open(password: &[u8], sealed: &Sealed) -> Self {
+        Self {
+            key: derive(password, &sealed.salt),
+            sealed: sealed.clone(),
+        }
+    }
+    /// The same key, with a new random nonce, for encrypting another payload
+    pub fn renewed(&self) -> Self {
+        let mut renewed = self.clone();
+        OsRng.fill_bytes(&mut renewed.sealed.nonce);
+        renewed
+    }
+    pub const fn sealed(&self) -> &Sealed {
+        &self.sealed
+    }
+    /// Encrypts `data`, authenticating `aad` along with it, being the header,
+    /// then the tree if it isn't encrypted
+    pub fn encrypt_data(&self, data: &[u8], aad: &[u8]) -> Vec<u8> {
+        self.cipher()
+            .encrypt(&self.nonce(0), Payload { msg: data, aad })
+            // Only fails for payloads of many gigabytes
+            .expect("the data is too long to encrypt")
+    }
+    pub fn decrypt_data(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>, RzError> {
+        self.cipher()
+            .decrypt(&self.nonce(0), Payload { msg: data, aad })
+            .map_err(|_| RzError::DecryptionFailed)
+    }
+    /// Encrypts `tree`, authenticating the header, `aad`, along with it
+    pub fn encrypt_tree(&self, tree: &[u8], aad: &[u8]) -> Vec<u8> {
+        self.cipher()
+            .encrypt(&self.nonce(1), Payload { msg: tree, aad })
+            .expect("the tree is too long to encrypt")
+    }
+    pub fn decrypt_tree(&self, tree: &[u8], aad: &[u8]) -> Result<Vec<u8>, RzError> {
+        self.cipher()
+            .decrypt(&self.nonce(1), Payload { msg: tree, aad })
+            .map_err(|_| RzError::DecryptionFailed)
+    }
+    fn cipher(&self) -> ChaCha20Poly1305 {
+        ChaCha20Poly1305::new(&self.key)
+    }
+    /// The nonce stored, with its last byte flipped by `part`, so the data
+    /// and tree are never encrypted with the same nonce
+    fn nonce(&self, part: u8) -> Nonce {
+        let mut nonce = self.sealed.nonce;
+        nonce[NONCE_LEN - 1] ^= part;
+        Nonce::clone_from_slice(&nonce)
+    }
+}
+
+/// Leaves the key out, so it isn't printed by accident
+impl fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Encryption")
+            .field("sealed", &self.sealed)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Derives a key from `password` and `salt` with Argon2id, using its default
+/// parameters
+fn derive(password: &[u8], salt: &[u8; SALT_LEN]) -> Key {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(password, salt, &mut key)
+        // Only fails for salts or keys of the wrong length, which these aren't
+        .expect("the salt and key are valid lengths");
+    key
+}
@@ -0,0 +1,124 @@
+use std::{error::Error, fmt, io};
+
+use crate::DecodeError;
+
+/// The ways reading an [`RZFile`](struct.RZFile.html) can fail
+#[derive(Debug)]
+pub enum RzError {
+    /// There was an issue reading the file
+    Io(io::Error),
+    /// The file doesn't start with [`RZ_MAGIC`](constant.RZ_MAGIC.html), so
+    /// isn't an RZ file
+    BadMagic,
+    /// The file is a version of the format that isn't supported, being newer
+    /// than [`RZ_VERSION`](constant.RZ_VERSION.html)
+    UnsupportedVersion(u8),
+    /// The file ends part way through the header or the tree. Holds the part
+    /// that's cut short, such as `"checksum"`
+    TruncatedHeader(&'static str),
+    /// The tree can't be deserialized, or isn't well formed. Holds why
+    TreeDecode(String),
+    /// The bits padding the end of the data, or of a block, can't be valid
+    BadPadding,
+    /// The data decoded, but doesn't match the checksum stored with it
+    ChecksumMismatch,
+    /// The tree is left out, and referenced by the dictionary id held, but
+    /// that dictionary wasn't given
+    MissingDictionary(u32),
+    /// The data couldn't be decoded
+    Decode(DecodeError),
+    /// The file is malformed in some other way, such as lengths that don't
+    /// agree with each other. Holds why
+    Malformed(String),
+    /// The file is encrypted, so needs a password to be read, with the
+    /// `crypto` feature
+    Encrypted,
+    /// The file couldn't be decrypted, as the password is wrong, or the file
+    /// has been changed
+    DecryptionFailed,
+    /// The tree takes up more bytes than the limit given, see
+    /// [`DecodeOptions::max_tree_bytes`](struct.DecodeOptions.html#method.max_tree_bytes)
+    TreeTooLarge(usize),
+    /// The tree has codes longer than the limit given, see
+    /// [`DecodeOptions::max_tree_depth`](struct.DecodeOptions.html#method.max_tree_depth)
+    TreeTooDeep(usize),
+}
+
+impl RzError {
+    /// Maps an error reading `part` of the file, so running out of input is
+    /// reported as [`TruncatedHeader`](#variant.TruncatedHeader)
+    pub(crate) fn truncated(part: &'static str) -> impl FnOnce(io::Error) -> Self {
+        move |e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => Self::TruncatedHeader(part),
+            _ => Self::Io(e),
+        }
+    }
+}
+
+impl fmt::Display for RzError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::BadMagic => write!(f, "the file isn't an RZ file"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "the file is version {version} of the RZ format, but only versions 1 to {} are supported",
+                super::RZ_VERSION
+            ),
+            Self::TruncatedHeader(part) => write!(f, "the file ends part way through the {part}"),
+            Self::TreeDecode(reason) | Self::Malformed(reason) => write!(f, "{reason}"),
+            Self::BadPadding => write!(f, "the data ends with invalid padding"),
+            Self::ChecksumMismatch => write!(f, "the decoded data doesn't match its checksum"),
+            Self::MissingDictionary(id) => {
+                write!(f, "the file needs dictionary {id:#010x} to be read")
+            }
+            Self::Decode(e) => write!(f, "{e}"),
+            Self::Encrypted => write!(f, "the file is encrypted, so needs a password to be read"),
+            Self::DecryptionFailed => write!(
+                f,
+                "the file couldn't be decrypted, as the password is wrong or the file has been changed"
+            ),
+            Self::TreeTooLarge(limit) => {
+                write!(f, "the tree is longer than the limit of {limit} bytes")
+            }
+            Self::TreeTooDeep(limit) => {
+                write!(f, "the tree has codes longer than the limit of {limit} bits")
+            }
+        }
+    }
+}
+
+impl Error for RzError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Decode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RzError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<DecodeError> for RzError {
+    fn from(e: DecodeError) -> Self {
+        match e {
+            DecodeError::ChecksumMismatch => Self::ChecksumMismatch,
+            DecodeError::DanglingBits => Self::BadPadding,
+            e => Self::Decode(e),
+        }
+    }
+}
+
+impl From<RzError> for io::Error {
+    fn from(e: RzError) -> Self {
+        match e {
+            RzError::Io(e) => e,
+            e => Self::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use std::{
+    convert::{TryFrom, TryInto},
+    fs::OpenOptions,
+    io::{self, BufRead, BufWriter, Read, Write},
+    path::Path,
+};
+
+use super::{malformed, RzError};
+
+/// The bytes every extension frame starts with. The first byte differs from
+/// [`RZ_MAGIC`](constant.RZ_MAGIC.html)'s, so a reader can tell which comes
+/// next from one byte
+pub const RZ_EXTENSION_MAGIC: [u8; 4] = *b"\x8aRZ\n";
+
+/// The length of an extension frame's header, before its data
+const EXTENSION_HEADER_LEN: usize = 12;
+
+/// An extension frame, holding an application's own data, which readers skip.
+///
+/// Like zstd's skippable frames, these let files be tagged with custom
+/// metadata, such as where they came from, without breaking existing readers.
+///
+/// An extension frame is [`RZ_EXTENSION_MAGIC`](constant.RZ_EXTENSION_MAGIC.html),
+/// a tag identifying what the data is as 4 bytes, the length of the data as
+/// 4 bytes, then the data. It may come before, between or after RZ files
+/// stored one after another
+/// # Examples
+/// ```
+/// use std::convert::TryFrom;
+/// use huffman_comprs::{Extension, Huffman, RZFile};
+///
+/// let input = b"Tagged with where it came from".to_vec();
+///
+/// let mut buf = Vec::new();
+/// RZFile::compress(Huffman::from(&input), &input)
+///     .unwrap()
+///     .write_to(&mut buf)
+///     .unwrap();
+/// let provenance = Extension::new(0x5052_4f56, b"built by ci #42".to_vec());
+/// provenance.write_to(&mut buf).unwrap();
+///
+/// // Readers skip the extension
+/// let file = RZFile::<u8>::try_from(buf.as_slice()).unwrap();
+/// assert_eq!(Some(input), file.decompress());
+///
+/// assert_eq!(vec![provenance], Extension::find_all(&buf).unwrap());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Extension {
+    /// What the data is, chosen by the application writing it
+    pub tag: u32,
+    pub data: Vec<u8>,
+}
+
+impl Extension {
+    /// Creates an extension holding `data`, tagged with `tag`
+    #[must_use]
+    pub const fn new(tag: u32, data: Vec<u8>) -> Self {
+        Self { tag, data }
+    }
+    /// Writes the extension frame to `writer`
+    ///
+    /// # Errors
+    /// Fails if there's any issue writing to `writer`, or the data is 4 GiB
+    /// or longer
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let len = u32::try_from(self.data.len()).map_err(io::Error::other)?;
+
+        writer.write_all(&RZ_EXTENSION_MAGIC)?;
+        writer.write_all(&self.tag.to_be_bytes())?;
+        writer.write_all(&len.to_be_bytes())?;
+        writer.write_all(&self.data)
+    }
+    /// Appends the extension frame to the file at `path`, creating it if it
+    /// doesn't exist, such as to tag an RZ file already written
+    ///
+    /// # Errors
+    /// Fails if there's any issue with writing to the file at path
+    pub fn append_to_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let file = OpenOptions::new().append(true).create(true).open(path)?;
+        let mut file = BufWriter::new(file);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+    /// Reads every extension frame at the start of `reader`, leaving it at
+    /// the start of the next RZ file, or at the end of the input
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from `reader`, or an extension
+    /// frame is cut short
+    pub fn read_all_from<R: BufRead>(mut reader: R) -> Result<Vec<Self>, RzError> {
+        let mut extensions = Vec::new();
+        while reader.fill_buf()?.first() == Some(&RZ_EXTENSION_MAGIC[0]) {
+            let mut magic = [0; 4];
+            reader
+                .read_exact(&mut magic)
+                .map_err(RzError::truncated("extension"))?;
+            if magic != RZ_EXTENSION_MAGIC {
+                return Err(RzError::BadMagic);
+            }
+            extensions.push(Self::read_after_magic(&mut reader)?);
+        }
+        Ok(extensions)
+    }
+    /// Finds every extension frame in `buf`, skipping over the RZ files
+    /// between them without decoding them
+    ///
+    /// # Errors
+    /// Fails if a frame in `buf` is malformed, or cut short
+    pub fn find_all(mut buf: &[u8]) -> Result<Vec<Self>, RzError> {
+        let mut extensions = Vec::new();
+        while !buf.is_empty() {
+            if buf.starts_with(&RZ_EXTENSION_MAGIC) {
+                let (extension, rest) = Self::parse(buf)?;
+                extensions.push(extension);
+                buf = rest;
+            } else {
+                buf = super::skip_frame(buf)?;
+            }
+        }
+        Ok(extensions)
+    }
+    /// Reads the rest of an extension frame, once its magic has been read
+    pub(super) fn read_after_magic<R: Read>(mut reader: R) -> Result<Self, RzError> {
+        let mut header = [0; EXTENSION_HEADER_LEN - 4];
+        reader
+            .read_exact(&mut header)
+            .map_err(RzError::truncated("extension"))?;
+        let tag = u32::from_be_bytes(header[0..4].try_into().unwrap());
+        let len = u32::from_be_bytes(header[4..8].try_into().unwrap());
+
+        // Not trusting `len` enough to allocate it all upfront
+        let mut data = Vec::new();
+        reader.take(u64::from(len)).read_to_end(&mut data)?;
+        if data.len() != len as usize {
+            return Err(RzError::TruncatedHeader("extension"));
+        }
+        Ok(Self { tag, data })
+    }
+    /// Parses the extension frame at the start of `buf`, returning it and the
+    /// bytes following it
+    fn parse(buf: &[u8]) -> Result<(Self, &[u8]), RzError> {
+        if buf.len() < EXTENSION_HEADER_LEN {
+            return Err(RzError::TruncatedHeader("extension"));
+        }
+        let (header, rest) = buf.split_at(EXTENSION_HEADER_LEN);
+        if header[0..4] != RZ_EXTENSION_MAGIC {
+            return Err(RzError::BadMagic);
+        }
+        let tag = u32::from_be_bytes(header[4..8].try_into().unwrap());
+        let len = u32::from_be_bytes(header[8..12].try_into().unwrap()) as usize;
+        if len > rest.len() {
+            return Err(malformed(format!(
+                "the extension is {} bytes long, but only {} bytes follow its header",
+                len,
+                rest.len()
+            )));
+        }
+
+        let (data, rest) = rest.split_at(len);
+        Ok((
+            Self {
+                tag,
+                data: data.to_vec(),
+            },
+            rest,
+        ))
+    }
+}
+
+/// Skips every extension frame at the start of `buf`, returning the bytes
+/// following them
+pub fn skip(mut buf: &[u8]) -> Result<&[u8], RzError> {
+    while buf.starts_with(&RZ_EXTENSION_MAGIC) {
+        buf = Extension::parse(buf)?.1;
+    }
+    Ok(buf)
+}
@@ -0,0 +1,238 @@
+use std::{borrow::Cow, fmt, io};
+
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+
+use super::RzError;
+use crate::{index, Huffman, Node};
+
+/// How the tree of an [`RZFile`](struct.RZFile.html) is serialized, chosen
+/// with [`RZFile::with_tree_format`](struct.RZFile.html#method.with_tree_format).
+///
+/// Trees are written in the [`Compact`](#variant.Compact) format by default.
+/// Formats other than bincode are marked by flag `0x80`, and a byte holding
+/// the format's id just before the tree, so the tree can be read from other
+/// languages with a common library. JSON, CBOR and `MessagePack` need the
+/// feature named by [`feature`](#method.feature), to be read or written
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TreeFormat {
+    /// [bincode](https://docs.rs/bincode), with id 0, marked by leaving flag
+    /// `0x80` unset. Every node and its frequency is stored
+    Bincode,
+    /// JSON, with id 1, needing the `json` feature
+    Json,
+    /// CBOR, with id 2, needing the `cbor` feature
+    Cbor,
+    /// `MessagePack`, with named fields, with id 3, needing the `msgpack`
+    /// feature
+    MessagePack,
+    /// The symbols, then the shape of the tree, with id 4. The shape is one
+    /// bit per node, most significant first, visiting each node before its
+    /// left then right child, and set for nodes with children. Both are
+    /// serialized together with bincode's varint encoding, as a sequence of
+    /// symbols in the order the leaves are visited, then a sequence of bytes.
+    ///
+    /// Frequencies aren't stored, so trees read back have a frequency of 1 at
+    /// every leaf, but the same codes
+    #[default]
+    Compact,
+}
+
+impl TreeFormat {
+    /// The feature needed to read and write the format
+    #[must_use]
+    pub const fn feature(self) -> &'static str {
+        match self {
+            Self::Bincode | Self::Compact => "rz",
+            Self::Json => "json",
+            Self::Cbor => "cbor",
+            Self::MessagePack => "msgpack",
+        }
+    }
+    /// The byte marking the format in the header
+    pub(crate) const fn id(self) -> u8 {
+        match self {
+            Self::Bincode => 0,
+            Self::Json => 1,
+            Self::Cbor => 2,
+            Self::MessagePack => 3,
+            Self::Compact => 4,
+        }
+    }
+    pub(crate) fn from_id(id: u8) -> Result<Self, RzError> {
+        match id {
+            0 => Ok(Self::Bincode),
+            1 => Ok(Self::Json),
+            2 => Ok(Self::Cbor),
+            3 => Ok(Self::MessagePack),
+            4 => Ok(Self::Compact),
+            _ => Err(RzError::TreeDecode(format!(
+                "the tree is serialized in an unknown format, with id {id}"
+            ))),
+        }
+    }
+    pub(crate) fn serialize<T>(self, tree: &Huffman<T>) -> io::Result<Vec<u8>>
+    where
+        T: Serialize + Ord + Clone + 'static,
+    {
+        match self {
+            Self::Bincode => bincode::serialize(tree).map_err(io::Error::other),
+            Self::Compact => to_compact(tree).map_err(io::Error::other),
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::to_vec(tree).map_err(io::Error::other),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => {
+                let mut buf = Vec::new();
+                ciborium::into_writer(tree, &mut buf).map_err(io::Error::other)?;
+                Ok(buf)
+            }
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::to_vec_named(tree).map_err(io::Error::other),
+            #[allow(unreachable_patterns)]
+            _ => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "writing {self:?} trees needs the `{}` feature",
+                    self.feature()
+                ),
+            )),
+        }
+    }
+    pub(crate) fn deserialize<'a, T>(self, buf: &'a [u8]) -> Result<Huffman<T>, RzError>
+    where
+        T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
+    {
+        let invalid = |e: &dyn fmt::Display| {
+            RzError::TreeDecode(format!("the tree isn't valid {self:?}, as {e}"))
+        };
+        match self {
+            Self::Bincode => bincode::deserialize(buf).map_err(|e| invalid(&e)),
+            Self::Compact => from_compact(buf).map_err(|e| invalid(&e)),
+            #[cfg(feature = "json")]
+            Self::Json => serde_json::from_slice(buf).map_err(|e| invalid(&e)),
+            #[cfg(feature = "cbor")]
+            Self::Cbor => ciborium::from_reader::<ciborium::Value, _>(buf)
+                .map_err(|e| invalid(&e))?
+                .deserialized()
+                .map_err(|e| invalid(&e)),
+            #[cfg(feature = "msgpack")]
+            Self::MessagePack => rmp_serde::from_slice(buf).map_err(|e| invalid(&e)),
+            #[allow(unreachable_patterns)]
+            _ => Err(RzError::TreeDecode(format!(
+                "the tree is serialized as {self:?}, which needs the `{}` feature",
+                self.feature()
+            ))),
+        }
+    }
+}
+
+/// The options the compact format is serialized with
+fn compact_options() -> impl Options {
+    bincode::DefaultOptions::new()
+}
+
+fn to_compact<T>(tree: &Huffman<T>) -> Result<Vec<u8>, bincode::Error>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    let mut symbols = Vec::with_capacity(tree.contents.len());
+    let mut shape: Vec<u8> = Vec::with_capacity(tree.nodes.len().div_ceil(8));
+
+    let mut stack: Vec<usize> = tree.root().into_iter().collect();
+    for bit in 0_usize.. {
+        let Some(node) = stack.pop() else {
+            break;
+        };
+        if bit % 8 == 0 {
+            shape.push(0);
+        }
+
+        if let Some([left, right]) = tree.children(node) {
+            *shape.last_mut().unwrap() |= 0x80 >> (bit % 8);
+            stack.push(right);
+            stack.push(left);
+        } else {
+            symbols.push(&tree.contents[node]);
+        }
+    }
+
+    compact_options().serialize(&(symbols, shape))
+}
+
+fn from_compact<'a, T>(buf: &'a [u8]) -> Result<Huffman<T>, Cow<'static, str>>
+where
+    T: Serialize + Ord + Deserialize<'a> + Clone + 'static,
+{
+    let (contents, shape): (Vec<T>, Vec<u8>) = compact_options()
+        .deserialize(buf)
+        .map_err(|e| e.to_string())?;
+
+    let leaves = contents.len();
+    let mut nodes = vec![
+        Node {
+            freq: 1,
+            children: None,
+        };
+        leaves
+    ];
+
+    // The left child of each node whose children are still being read, once
+    // it's been read
+    let mut open: Vec<Option<usize>> = Vec::new();
+    let mut next_leaf = 0;
+    let mut read = 0;
+    let mut complete = leaves == 0 && shape.is_empty();
+    let bits = shape
+        .iter()
+        .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1));
+
+    for has_children in bits {
+        if complete {
+            break;
+        }
+        read += 1;
+        if has_children {
+            open.push(None);
+            continue;
+        }
+
+        if next_leaf == leaves {
+            return Err("the tree's shape has more leaves than symbols".into());
+        }
+        let mut node = next_leaf;
+        next_leaf += 1;
+
+        // Finishing a node may finish its parent too
+        loop {
+            match open.last_mut() {
+                None => {
+                    complete = true;
+                    break;
+                }
+                Some(left @ None) => {
+                    *left = Some(node);
+                    break;
+                }
+                Some(Some(left)) => {
+                    let left = *left;
+                    open.pop();
+                    nodes.push(Node {
+                        freq: nodes[left].freq + nodes[node].freq,
+                        children: Some([index(left), index(node)]),
+                    });
+                    node = nodes.len() - 1;
+                }
+            }
+        }
+    }
+
+    if !complete {
+        Err("the tree's shape ends before every node is read".into())
+    } else if next_leaf != leaves {
+        Err("the tree's shape has fewer leaves than symbols".into())
+    } else if shape.len() != usize::div_ceil(read, 8) {
+        Err("bytes follow the tree's shape".into())
+    } else {
+        Ok(Huffman { contents, nodes })
+    }
+}
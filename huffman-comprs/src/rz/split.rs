@@ -0,0 +1,199 @@
+use std::{error::Error, fmt, marker::PhantomData, ops::Range};
+
+use serde::Serialize;
+
+#[cfg(feature = "crypto")]
+use super::crypto::Encryption;
+use super::{Block, Blocks, Data, RZFile};
+
+/// The ways splitting an [`RZFile`](struct.RZFile.html) into parts, or
+/// joining them back together, can fail
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitError {
+    /// Only files split into blocks can be split into parts, as a part holds
+    /// whole blocks
+    NotBlocked,
+    /// A block is too large to fit in a part of the size given. Holds the
+    /// smallest part size that would fit every block
+    PartTooSmall(usize),
+    /// The parts don't all come from the same file, in order
+    Mismatched,
+}
+
+impl fmt::Display for SplitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotBlocked => write!(f, "only files split into blocks can be split"),
+            Self::PartTooSmall(min) => {
+                write!(f, "parts must be at least {min} bytes to fit every block")
+            }
+            Self::Mismatched => write!(f, "the parts don't all come from the same file"),
+        }
+    }
+}
+
+impl Error for SplitError {}
+
+impl<T> RZFile<'_, T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    /// Splits the file into parts no more than `max_part_len` bytes long
+    /// when written, each holding some of the blocks. Every part is an RZ file
+    /// of its own, so can be decompressed independently, and the parts can be
+    /// put back together with [`join`](#method.join), or simply concatenated
+    /// and read with [`frames`](#method.frames).
+    ///
+    /// The checksum covers the whole file, so isn't kept in the parts. Any
+    /// metadata is copied into every part
+    ///
+    /// # Errors
+    /// Fails if the file isn't split into blocks, or if a block is too large
+    /// to fit in a part
+    ///
+    /// # Panics
+    /// Panics if the tree can't be serialized
+    pub fn split(&self, max_part_len: usize) -> Result<Vec<RZFile<'static, T>>, SplitError> {
+        let blocks = self.blocks.as_ref().ok_or(SplitError::NotBlocked)?;
+        let size = blocks.size as usize;
+
+        let empty = self.part(blocks, 0..0);
+        let mut overhead = Vec::new();
+        empty.write_to(&mut overhead).unwrap();
+        let overhead = overhead.len();
+
+        let entry_len = blocks.entry_len();
+        let block_len = |block: &Block| entry_len + block.len as usize;
+        if let Some(largest) = blocks.blocks.iter().map(block_len).max() {
+            if overhead + largest > max_part_len {
+                return Err(SplitError::PartTooSmall(overhead + largest));
+            }
+        }
+
+        let mut parts = Vec::new();
+        let mut start = 0;
+        while start < blocks.blocks.len() {
+            let mut end = start;
+            let mut part_len = overhead;
+            while end < blocks.blocks.len()
+                && part_len + block_len(&blocks.blocks[end]) <= max_part_len
+            {
+                part_len += block_len(&blocks.blocks[end]);
+                end += 1;
+            }
+
+            let mut part = self.part(blocks, start..end);
+            part.len = self
+                .len
+                .map(|len| (len - start * size).min((end - start) * size));
+            parts.push(part);
+            start = end;
+        }
+
+        Ok(parts)
+    }
+    /// Copies the blocks in `range` into a file of their own
+    fn part(&self, blocks: &Blocks, range: Range<usize>) -> RZFile<'static, T> {
+        let offset = blocks
+            .blocks
+            .get(range.start)
+            .map_or(0, |block| block.start);
+        let blocks = Blocks {
+            size: blocks.size,
+            blocks: blocks.blocks[range]
+                .iter()
+                .map(|block| Block {
+                    start: block.start - offset,
+                    ..*block
+                })
+                .collect(),
+        };
+        let end = blocks
+            .blocks
+            .last()
+            .map_or(offset, |block| offset + block.start + block.len as usize);
+
+        RZFile {
+            zeros: 0,
+            checksum: None,
+            len: self.len.map(|_| 0),
+            blocks: Some(blocks),
+            entries: None,
+            metadata: self.metadata.clone(),
+            dictionary: self.dictionary,
+            format: self.format,
+            stored: false,
+            #[cfg(feature = "crypto")]
+            encryption: self.encryption.as_ref().map(Encryption::renewed),
+            tree: self.tree.clone(),
+            data: Data::Owned(self.data[offset..end].to_vec()),
+            marker: PhantomData,
+        }
+    }
+    /// Joins parts made by [`split`](#method.split) back into one file. The
+    /// parts must be given in the order they were split into
+    ///
+    /// # Errors
+    /// Fails if there are no parts, or if they don't all come from the same
+    /// file, in order
+    ///
+    /// # Panics
+    /// Panics if a tree can't be serialized
+    pub fn join<'b, I>(parts: I) -> Result<RZFile<'static, T>, SplitError>
+    where
+        I: IntoIterator<Item = RZFile<'b, T>>,
+    {
+        let mut parts = parts.into_iter().peekable();
+        let first = parts.peek().ok_or(SplitError::Mismatched)?;
+        let tree = bincode::serialize(&first.tree).unwrap();
+        let mut joined = RZFile {
+            zeros: 0,
+            checksum: None,
+            len: Some(0),
+            blocks: None,
+            entries: None,
+            metadata: first.metadata.clone(),
+            dictionary: first.dictionary,
+            format: first.format,
+            stored: false,
+            #[cfg(feature = "crypto")]
+            encryption: first.encryption.as_ref().map(Encryption::renewed),
+            tree: first.tree.clone(),
+            data: Data::Owned(Vec::new()),
+            marker: PhantomData,
+        };
+
+        let mut size = None;
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        let mut full = true;
+        for part in parts {
+            let blocks = part.blocks.as_ref().ok_or(SplitError::NotBlocked)?;
+            if !full
+                || *size.get_or_insert(blocks.size) != blocks.size
+                || bincode::serialize(&part.tree).unwrap() != tree
+            {
+                return Err(SplitError::Mismatched);
+            }
+
+            // Only the last part can end with a block that isn't full
+            full = part
+                .len
+                .is_none_or(|len| len == blocks.blocks.len() * blocks.size as usize);
+            joined.len = joined.len.zip(part.len).map(|(a, b)| a + b);
+
+            index.extend(blocks.blocks.iter().map(|block| Block {
+                start: block.start + data.len(),
+                ..*block
+            }));
+            data.extend_from_slice(&part.data);
+        }
+
+        joined.blocks = Some(Blocks {
+            size: size.unwrap_or(1),
+            blocks: index,
+        });
+        joined.data = Data::Owned(data);
+        Ok(joined)
+    }
+}
@@ -0,0 +1,51 @@
+use std::{convert::TryFrom, io};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{malformed, RZFile, RzError};
+use crate::{DecodeOptions, Huffman};
+
+/// Serializes `value` with bincode, then compresses it.
+///
+/// The result is a self-contained RZ file holding its tree and a checksum, so
+/// it can be stored anywhere and read back with
+/// [`from_compressed_bytes`](fn.from_compressed_bytes.html)
+/// # Examples
+/// ```
+/// use huffman_comprs::{from_compressed_bytes, to_compressed_bytes};
+///
+/// let names = vec!["alice".to_string(), "bob".to_string(), "carol".to_string()];
+/// let compressed = to_compressed_bytes(&names).unwrap();
+///
+/// assert_eq!(names, from_compressed_bytes::<Vec<String>>(&compressed).unwrap());
+/// ```
+///
+/// # Errors
+/// Fails if `value` can't be serialized
+pub fn to_compressed_bytes<V: Serialize + ?Sized>(value: &V) -> io::Result<Vec<u8>> {
+    let bytes = bincode::serialize(value).map_err(io::Error::other)?;
+    let tree = Huffman::from(&bytes);
+
+    // The tree was built from the bytes, so every byte is in it
+    let file = RZFile::compress(tree, &bytes)
+        .ok_or_else(|| io::Error::other("a byte isn't in the tree"))?
+        .with_checksum(&bytes);
+    let mut buf = Vec::new();
+    file.write_to(&mut buf)?;
+    Ok(buf)
+}
+
+/// Decompresses a value written by
+/// [`to_compressed_bytes`](fn.to_compressed_bytes.html), then deserializes it
+/// with bincode
+///
+/// # Errors
+/// Fails if `bytes` isn't a valid RZ file, doesn't match its checksum, or
+/// doesn't hold a `V`
+pub fn from_compressed_bytes<V: DeserializeOwned>(bytes: &[u8]) -> Result<V, RzError> {
+    let file = RZFile::<u8>::try_from(bytes)?;
+    let bytes = file.decompress_with(&DecodeOptions::new())?;
+
+    bincode::deserialize(&bytes)
+        .map_err(|e| malformed(format!("the value couldn't be deserialized, as {e}")))
+}
@@ -0,0 +1,69 @@
+use std::{
+    convert::TryFrom,
+    io::{self, Read, Seek, SeekFrom},
+};
+
+use crate::{Huffman, TieBreak};
+
+/// How many bytes are read from each place sampled, so each read is cheap
+/// compared to seeking
+const SAMPLE_CHUNK: u64 = 64 * 1024;
+
+impl Huffman<u8> {
+    /// Builds a tree from a sample of about `sample_bytes` bytes of `reader`,
+    /// rather than every byte, so a huge input can be compressed in one pass.
+    ///
+    /// The sample is taken in evenly spaced chunks from `reader`'s position to
+    /// its end, which `reader` is left back at. Every byte is given a code,
+    /// even those missed by the sample, so any input can be compressed with
+    /// the tree
+    /// # Examples
+    /// ```
+    /// use std::io::Cursor;
+    ///
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let input = b"mostly a, but some b. ".repeat(10_000);
+    /// let mut reader = Cursor::new(&input);
+    ///
+    /// let tree = Huffman::from_sample(&mut reader, 4096).unwrap();
+    /// assert_eq!(0, reader.position());
+    /// assert!(tree.compress(&input).unwrap().len() < input.len() * 8);
+    /// ```
+    ///
+    /// # Errors
+    /// Fails if there's any issue reading from or seeking `reader`
+    pub fn from_sample<R: Read + Seek>(mut reader: R, sample_bytes: usize) -> io::Result<Self> {
+        let start = reader.stream_position()?;
+        let len = reader.seek(SeekFrom::End(0))?.saturating_sub(start);
+        let sample_bytes = u64::try_from(sample_bytes).unwrap_or(u64::MAX).min(len);
+
+        let chunks = sample_bytes.div_ceil(SAMPLE_CHUNK);
+        let chunk_len = sample_bytes.div_ceil(chunks.max(1));
+        let mut counts = [0_usize; 256];
+        let mut buf = Vec::new();
+        for chunk in 0..chunks {
+            // Spreads the chunks evenly, the last ending at the end
+            let offset = match chunks {
+                1 => 0,
+                _ => (len - chunk_len) / (chunks - 1) * chunk,
+            };
+            reader.seek(SeekFrom::Start(start + offset))?;
+
+            buf.clear();
+            (&mut reader).take(chunk_len).read_to_end(&mut buf)?;
+            for byte in &buf {
+                counts[usize::from(*byte)] += 1;
+            }
+        }
+        reader.seek(SeekFrom::Start(start))?;
+
+        Ok(Self::build(
+            (0..=u8::MAX)
+                .zip(counts.iter())
+                .map(|(byte, count)| (byte, count + 1))
+                .collect(),
+            TieBreak::default(),
+        ))
+    }
+}
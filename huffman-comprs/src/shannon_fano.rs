@@ -0,0 +1,218 @@
+use std::{fmt, iter::FromIterator, ops::Deref};
+
+#[cfg(feature = "rz")]
+use std::io;
+
+#[cfg(feature = "rz")]
+use serde::Deserialize;
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{count, index, trace, Huffman, Node};
+#[cfg(feature = "rz")]
+use crate::{
+    rz::parse_tree, Cancel, Code, DecodeError, DecodeOptions, EntropyCoder, RzError, TreeFormat,
+};
+
+/// A prefix code built by Shannon–Fano coding, for comparing against Huffman
+/// coding.
+///
+/// The symbols are sorted by frequency, then split in two, with as close to
+/// half the total frequency on each side as can be, giving the first half a
+/// `0` and the second a `1`, and each half is split again until every symbol
+/// has a code of its own. Building from the top down, rather than merging
+/// the rarest symbols from the bottom up as [`Huffman`](struct.Huffman.html)
+/// does, never codes the input in fewer bits than Huffman coding, and
+/// sometimes codes it in more.
+///
+/// The codes are a tree of the same shape as Huffman codes, so a
+/// `ShannonFano` dereferences to the `Huffman` holding them, with every
+/// method of one, and is an [`EntropyCoder`](trait.EntropyCoder.html), so
+/// an [`RZFile`](struct.RZFile.html) can be compressed with either
+/// # Examples
+/// ```
+/// use huffman_comprs::{Decoder, Huffman, ShannonFano};
+///
+/// let counts = vec![('a', 15), ('b', 7), ('c', 6), ('d', 6), ('e', 5)];
+/// let shannon_fano = ShannonFano::from_frequencies(counts.clone());
+/// let huffman: Huffman<char> = counts.into_iter().collect();
+///
+/// assert_eq!("00", shannon_fano.get_code('a').unwrap().to_string());
+/// assert_eq!(1, huffman.get_code('a').unwrap().len());
+///
+/// let input: Vec<char> = "abacabadae".chars().collect();
+/// let mut bytes = Vec::new();
+/// let zeros = shannon_fano.compress_into_bytes(&input, &mut bytes).unwrap();
+/// assert_eq!(Ok(input), Decoder::new(&shannon_fano).decode(&bytes, zeros));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    tree: Huffman<T>,
+}
+
+impl<T> ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    /// Builds the codes from each symbol paired with how often it appears.
+    /// Symbols as frequent as each other are kept in the order they're given
+    /// in
+    #[must_use]
+    pub fn from_frequencies(mut leaves: Vec<(T, usize)>) -> Self {
+        trace::span!("build_shannon_fano", symbols = leaves.len());
+        leaves.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+        let mut contents = Vec::with_capacity(leaves.len());
+        let mut nodes = Vec::with_capacity(leaves.len() * 2);
+        for (symbol, freq) in leaves {
+            contents.push(symbol);
+            nodes.push(Node {
+                freq,
+                children: None,
+            });
+        }
+        if !nodes.is_empty() {
+            split(&mut nodes, 0..contents.len());
+        }
+
+        Self {
+            tree: Huffman { contents, nodes },
+        }
+    }
+    /// The tree holding the codes, as a `Huffman`
+    #[must_use]
+    pub fn into_tree(self) -> Huffman<T> {
+        self.tree
+    }
+}
+
+/// Creates the node covering the leaves in `range`, which are sorted most
+/// frequent first, returning its index
+fn split(nodes: &mut Vec<Node>, range: std::ops::Range<usize>) -> usize {
+    if range.len() == 1 {
+        return range.start;
+    }
+
+    let total: usize = nodes[range.clone()].iter().map(|node| node.freq).sum();
+    // The first split leaving the two halves as close to even as they can be
+    let mut left = 0;
+    let mut best = (usize::MAX, range.start + 1);
+    for mid in range.start + 1..range.end {
+        left += nodes[mid - 1].freq;
+        let diff = (2 * left).abs_diff(total);
+        if diff < best.0 {
+            best = (diff, mid);
+        }
+    }
+    let mid = best.1;
+
+    let left = split(nodes, range.start..mid);
+    let right = split(nodes, mid..range.end);
+    nodes.push(Node {
+        freq: nodes[left].freq + nodes[right].freq,
+        children: Some([index(left), index(right)]),
+    });
+    nodes.len() - 1
+}
+
+impl<T> Deref for ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    type Target = Huffman<T>;
+
+    fn deref(&self) -> &Huffman<T> {
+        &self.tree
+    }
+}
+
+impl<T> fmt::Display for ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + fmt::Display + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tree.fmt(f)
+    }
+}
+
+impl<T> From<&Vec<T>> for ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn from(buf: &Vec<T>) -> Self {
+        Self::from_frequencies(count(buf.iter().cloned()))
+    }
+}
+
+impl<T> From<Vec<T>> for ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn from(buf: Vec<T>) -> Self {
+        Self::from(&buf)
+    }
+}
+
+impl From<&str> for ShannonFano<char> {
+    fn from(buf: &str) -> Self {
+        Self::from_frequencies(count(buf.chars()))
+    }
+}
+
+impl<T> FromIterator<T> for ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from_frequencies(count(iter))
+    }
+}
+
+/// Stored as the tree holding its codes, so a file written with a
+/// `ShannonFano` can be read back with a `Huffman`, and the other way round
+#[cfg(feature = "rz")]
+impl<T> EntropyCoder<T> for ShannonFano<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn encode(&self, input: &[T]) -> Option<Code> {
+        self.tree.compress(input)
+    }
+    fn encode_cancellable(
+        &self,
+        input: &[T],
+        cancel: &Cancel,
+    ) -> Result<Option<Code>, DecodeError> {
+        self.tree.encode_cancellable(input, cancel)
+    }
+    fn decode(
+        &self,
+        data: Vec<bool>,
+        zeros: u8,
+        len: Option<usize>,
+        output: &mut Vec<T>,
+        options: &DecodeOptions,
+    ) -> Result<(), DecodeError> {
+        EntropyCoder::decode(&self.tree, data, zeros, len, output, options)
+    }
+    fn serialize_model(&self, format: TreeFormat) -> io::Result<Vec<u8>> {
+        format.serialize(&self.tree)
+    }
+    fn deserialize_model<'de>(model: &'de [u8], format: TreeFormat) -> Result<Self, RzError>
+    where
+        T: Deserialize<'de>,
+    {
+        parse_tree(model, format).map(|tree| Self { tree })
+    }
+    fn depth(&self) -> usize {
+        self.tree.max_depth()
+    }
+    fn empty() -> Option<Self> {
+        Some(Self {
+            tree: Huffman::empty()?,
+        })
+    }
+}
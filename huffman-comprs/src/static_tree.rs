@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{tree::TreeRef, Code, DecodeError, DecodeOptions, Huffman, Node};
+
+/// A tree borrowing its symbols and nodes from `static` arrays, so it can be
+/// built at compile time, and stored in a `static` without any allocation.
+///
+/// These are made by the `include_huffman!` macro from
+/// `huffman-comprs-macros`, or by including the source written by
+/// [`codegen::static_tree`](codegen/fn.static_tree.html). Symbols can be
+/// coded and decoded with the tree as it is, while
+/// [`to_huffman`](#method.to_huffman) copies it into a
+/// [`Huffman`](struct.Huffman.html) for everything else, such as writing
+/// RZ files
+#[derive(Debug)]
+pub struct StaticHuffman<T: 'static> {
+    contents: &'static [T],
+    nodes: &'static [Node],
+}
+
+impl<T> StaticHuffman<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    /// Creates a tree directly from its parts, without any allocation. This
+    /// is what [`codegen`](codegen/index.html) emits
+    #[doc(hidden)]
+    #[must_use]
+    pub const fn from_static(contents: &'static [T], nodes: &'static [Node]) -> Self {
+        Self { contents, nodes }
+    }
+    /// Gets a reference to the tree's contents
+    #[must_use]
+    pub const fn contents(&self) -> &'static [T] {
+        self.contents
+    }
+    /// The frequency of all the symbols in the tree, being the length of the
+    /// corpus it was built from
+    #[must_use]
+    pub fn freq(&self) -> usize {
+        self.tree_ref()
+            .root()
+            .map_or(0, |root| self.nodes[root].freq)
+    }
+    /// Gets the code of a specified symbol, as
+    /// [`Huffman::get_code`](struct.Huffman.html#method.get_code) does
+    ///
+    /// # Errors
+    /// Returns `None` if no matching code is found in the tree
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)] // By value, as `Huffman::get_code` takes it
+    pub fn get_code(&self, to_get: T) -> Option<Code> {
+        self.tree_ref().get_code(&to_get)
+    }
+    /// Attempts to get the symbol associated with a given code
+    ///
+    /// # Errors
+    /// Returns `None` if no matching code is found in the tree
+    #[must_use]
+    pub fn get_char(&self, mut input: Vec<bool>) -> Option<T> {
+        input.reverse();
+        self.tree_ref().get_char_rec(&mut input)
+    }
+    /// Converts self to a map of each symbol's code, as
+    /// [`Huffman::to_btree`](struct.Huffman.html#method.to_btree) does
+    #[must_use]
+    pub fn to_btree(&self) -> BTreeMap<T, Vec<bool>> {
+        self.tree_ref().to_btree()
+    }
+    /// Compresses `input`, as [`Huffman::compress`](struct.Huffman.html#method.compress)
+    /// does
+    ///
+    /// # Errors
+    /// Returns `None` if any of the symbols in `input` are not contained in
+    /// the tree
+    #[must_use]
+    pub fn compress(&self, input: &[T]) -> Option<Code> {
+        let codes = self.to_btree();
+        let mut output = Vec::with_capacity(input.len());
+        for symbol in input {
+            output.extend_from_slice(codes.get(symbol)?);
+        }
+        Some(Code::from(output))
+    }
+    /// Reconstructs `data`, read back to front, as
+    /// [`Huffman::try_reconstruct`](struct.Huffman.html#method.try_reconstruct)
+    /// does
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding, such as if it ends
+    /// part way through a code, or the padding is wrong
+    pub fn reconstruct(&self, data: impl Into<Code>, zeros: u8) -> Result<Vec<T>, DecodeError> {
+        self.reconstruct_with(data, zeros, &DecodeOptions::new())
+    }
+    /// Reconstructs `data` as [`reconstruct`](#method.reconstruct) does,
+    /// applying the limits in `options`, as
+    /// [`Huffman::reconstruct_with`](struct.Huffman.html#method.reconstruct_with)
+    /// does
+    ///
+    /// # Errors
+    /// Returns an error if `data` isn't a valid encoding, or if decoding it
+    /// would break one of the limits in `options`
+    pub fn reconstruct_with(
+        &self,
+        data: impl Into<Code>,
+        zeros: u8,
+        options: &DecodeOptions,
+    ) -> Result<Vec<T>, DecodeError> {
+        let data = data.into();
+        let mut output = Vec::with_capacity(data.len() / 8);
+
+        self.tree_ref()
+            .decode_extend(data.to_vec(), zeros, &mut output, options)?;
+
+        Ok(output)
+    }
+    /// Copies the tree into a [`Huffman`](struct.Huffman.html), with the
+    /// same codes
+    #[must_use]
+    pub fn to_huffman(&self) -> Huffman<T> {
+        Huffman {
+            contents: self.contents.to_vec(),
+            nodes: self.nodes.to_vec(),
+        }
+    }
+    const fn tree_ref(&self) -> TreeRef<'static, T> {
+        TreeRef {
+            contents: self.contents,
+            nodes: self.nodes,
+        }
+    }
+}
+
+// Deriving would need `T: Copy`, though only references are copied
+#[allow(clippy::expl_impl_clone_on_copy)]
+impl<T: 'static> Clone for StaticHuffman<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: 'static> Copy for StaticHuffman<T> {}
+
+impl<T> From<&StaticHuffman<T>> for Huffman<T>
+where
+    T: Serialize + Ord + Clone + 'static,
+{
+    fn from(tree: &StaticHuffman<T>) -> Self {
+        tree.to_huffman()
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    mem,
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{Code, Encoder, Huffman};
+
+/// Measurements taken while compressing, returned by
+/// [`Huffman::compress_with_stats`](struct.Huffman.html#method.compress_with_stats)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CompressionStats {
+    /// How many symbols were compressed
+    pub input_symbols: usize,
+    /// How many bits they were compressed to, not counting the tree or any
+    /// padding
+    pub output_bits: usize,
+    /// The size of the output as a fraction of the input's size in memory,
+    /// with each symbol taking `size_of::<T>()` bytes, so lower is better.
+    /// For bytes, this is the ratio of the output's size to the input's. `0`
+    /// if there was no input
+    pub ratio: f64,
+    /// How long building the table of codes took, before any symbols were
+    /// encoded
+    pub table_build_time: Duration,
+}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Compresses `input` as [`compress`](#method.compress) does, measuring
+    /// how well, and how long building the table of codes took
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let input = b"aaaaaaab".to_vec();
+    /// let huffman = Huffman::from(&input);
+    ///
+    /// let (data, stats) = huffman.compress_with_stats(&input).unwrap();
+    /// assert_eq!(8, stats.input_symbols);
+    /// assert_eq!(data.len(), stats.output_bits);
+    /// assert_eq!(1.0 / 8.0, stats.ratio);
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `None` if any of the symbols in `input` are not contained in
+    /// self's tree
+    #[must_use]
+    pub fn compress_with_stats(&self, input: &[T]) -> Option<(Code, CompressionStats)> {
+        let start = Instant::now();
+        let encoder = Encoder::new(self);
+        let table_build_time = start.elapsed();
+
+        let output = encoder.encode(input)?;
+
+        let input_bits = mem::size_of_val(input) * 8;
+        // Precision is only lost beyond 2^52 bits, which is far more than
+        // fits in memory
+        #[allow(clippy::cast_precision_loss)]
+        let ratio = if input_bits == 0 {
+            0.0
+        } else {
+            output.len() as f64 / input_bits as f64
+        };
+
+        let stats = CompressionStats {
+            input_symbols: input.len(),
+            output_bits: output.len(),
+            ratio,
+            table_build_time,
+        };
+        Some((output, stats))
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::Huffman;
+
+/// How many bytes are read from the inner reader at once
+const READ_LEN: usize = 8 * 1024;
+
+/// Compresses everything written to it with a tree, writing the compressed
+/// bytes to a tokio [`AsyncWrite`].
+///
+/// Bits are packed most significant first, as
+/// [`Huffman::compress_into_bytes`](struct.Huffman.html#method.compress_into_bytes)
+/// does, with any bits of a byte not yet filled kept between writes. Shutting
+/// the writer down pads the last byte with zeros, then writes one more byte
+/// holding how many, so an [`AsyncHuffmanReader`](struct.AsyncHuffmanReader.html)
+/// with the same tree knows where the data ends
+/// # Examples
+/// ```
+/// use huffman_comprs::{AsyncHuffmanReader, AsyncHuffmanWriter, Huffman};
+/// use tokio::io::{AsyncReadExt, AsyncWriteExt};
+///
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// let tree = Huffman::from(&b"Sent without blocking".to_vec());
+///
+/// let mut writer = AsyncHuffmanWriter::new(Vec::new(), &tree);
+/// writer.write_all(b"Sent without blocking").await.unwrap();
+/// writer.shutdown().await.unwrap();
+///
+/// let compressed = writer.into_inner();
+/// let mut reader = AsyncHuffmanReader::new(compressed.as_slice(), &tree);
+/// let mut output = Vec::new();
+/// reader.read_to_end(&mut output).await.unwrap();
+///
+/// assert_eq!(b"Sent without blocking".to_vec(), output);
+/// # });
+/// ```
+#[derive(Debug)]
+pub struct AsyncHuffmanWriter<W> {
+    inner: W,
+    codes: BTreeMap<u8, Vec<bool>>,
+    /// The bits of the byte being filled, in its lowest bits
+    byte: u8,
+    filled: u8,
+    /// Compressed bytes not yet written to `inner`
+    pending: Vec<u8>,
+    /// How much of `pending` has been written
+    written: usize,
+    finished: bool,
+}
+
+impl<W> AsyncHuffmanWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    /// Creates a writer compressing with `tree`, writing to `inner`
+    ///
+    /// # Panics
+    /// Panics if `tree` has fewer than 2 symbols, as a lone symbol has an
+    /// empty code, which can't be written to a stream
+    #[must_use]
+    pub fn new(inner: W, tree: &Huffman<u8>) -> Self {
+        assert!(
+            tree.contents().len() >= 2,
+            "a tree needs at least 2 symbols to compress a stream"
+        );
+
+        Self {
+            inner,
+            codes: tree.to_btree(),
+            byte: 0,
+            filled: 0,
+            pending: Vec::new(),
+            written: 0,
+            finished: false,
+        }
+    }
+    /// Returns a reference to the inner writer
+    pub const fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    /// Returns the inner writer. Any bits not yet written are lost, unless
+    /// the writer has been shut down
+    #[allow(clippy::missing_const_for_fn)] // the codes may need dropping
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+    /// Writes as much of `pending` to `inner` as it will take, until it's all
+    /// written
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.pending.len() {
+            let written =
+                ready!(Pin::new(&mut self.inner).poll_write(cx, &self.pending[self.written..]))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written += written;
+        }
+
+        self.pending.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+    /// Adds `bits` to the byte being filled, moving it to `pending` once full
+    fn push_bits<'b, I: IntoIterator<Item = &'b bool>>(
+        byte: &mut u8,
+        filled: &mut u8,
+        pending: &mut Vec<u8>,
+        bits: I,
+    ) {
+        for bit in bits {
+            *byte = *byte << 1 | u8::from(*bit);
+            *filled += 1;
+            if *filled == 8 {
+                pending.push(*byte);
+                *byte = 0;
+                *filled = 0;
+            }
+        }
+    }
+}
+
+impl<W> AsyncWrite for AsyncHuffmanWriter<W>
+where
+    W: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(Err(io::Error::other("the writer has been shut down")));
+        }
+        ready!(this.poll_pending(cx))?;
+        if buf.iter().any(|byte| !this.codes.contains_key(byte)) {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a byte written isn't in the tree",
+            )));
+        }
+
+        let codes = &this.codes;
+        Self::push_bits(
+            &mut this.byte,
+            &mut this.filled,
+            &mut this.pending,
+            buf.iter().flat_map(|byte| &codes[byte]),
+        );
+        // Only errors matter here, the rest is written on the next call
+        if let Poll::Ready(Err(e)) = this.poll_pending(cx) {
+            return Poll::Ready(Err(e));
+        }
+        Poll::Ready(Ok(buf.len()))
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if !this.finished {
+            let zeros = (8 - this.filled) % 8;
+            Self::push_bits(
+                &mut this.byte,
+                &mut this.filled,
+                &mut this.pending,
+                &vec![false; usize::from(zeros)],
+            );
+            this.pending.push(zeros);
+            this.finished = true;
+        }
+
+        ready!(this.poll_pending(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Decompresses the bytes read from a tokio [`AsyncRead`], written by an
+/// [`AsyncHuffmanWriter`](struct.AsyncHuffmanWriter.html) with the same tree.
+///
+/// The last two bytes read are held back until the stream ends, as they may
+/// be the padded last byte, and the byte saying how much padding there is.
+/// A symbol whose code is split between reads is picked up where it left off
+#[derive(Debug)]
+pub struct AsyncHuffmanReader<R> {
+    inner: R,
+    tree: Huffman<u8>,
+    /// The node reached partway through a code, or `None` at the root
+    node: Option<usize>,
+    /// The last bytes read, which aren't decoded yet
+    held: Vec<u8>,
+    /// Decoded bytes not yet returned, and how many have been
+    decoded: Vec<u8>,
+    returned: usize,
+    done: bool,
+}
+
+impl<R> AsyncHuffmanReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    /// Creates a reader decompressing with `tree`, reading from `inner`
+    ///
+    /// # Panics
+    /// Panics if `tree` has fewer than 2 symbols, as a lone symbol has an
+    /// empty code, which can't be read from a stream
+    #[must_use]
+    pub fn new(inner: R, tree: &Huffman<u8>) -> Self {
+        assert!(
+            tree.contents().len() >= 2,
+            "a tree needs at least 2 symbols to decompress a stream"
+        );
+
+        Self {
+            inner,
+            tree: tree.clone(),
+            node: None,
+            held: Vec::with_capacity(READ_LEN + 2),
+            decoded: Vec::new(),
+            returned: 0,
+            done: false,
+        }
+    }
+    /// Returns a reference to the inner reader
+    pub const fn get_ref(&self) -> &R {
+        &self.inner
+    }
+    /// Returns the inner reader
+    #[allow(clippy::missing_const_for_fn)] // the tree may need dropping
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+    /// Decodes the first `bits` bits of `byte`, most significant first
+    fn decode(&mut self, byte: u8, bits: u8) {
+        for i in (8 - bits..8).rev() {
+            let node = match self.node {
+                Some(node) => node,
+                None => self.tree.root().unwrap(),
+            };
+            let [left, right] = self.tree.children(node).unwrap();
+            let next = if byte >> i & 1 == 1 { right } else { left };
+
+            if self.tree.children(next).is_some() {
+                self.node = Some(next);
+            } else {
+                self.decoded.push(self.tree.contents[next]);
+                self.node = None;
+            }
+        }
+    }
+    /// Decodes the bytes held back, once the stream has ended
+    fn finish(&mut self) -> io::Result<()> {
+        let truncated = |reason| io::Error::new(io::ErrorKind::UnexpectedEof, reason);
+        match *self.held.as_slice() {
+            [] => return Err(truncated("the stream ends before the padding byte")),
+            [0] => {}
+            [last, zeros] if zeros < 8 => self.decode(last, 8 - zeros),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the padding byte isn't valid",
+                ))
+            }
+        }
+        self.held.clear();
+
+        if self.node.is_some() {
+            return Err(truncated("the stream ends part way through a symbol"));
+        }
+        Ok(())
+    }
+}
+
+impl<R> AsyncRead for AsyncHuffmanReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.returned == this.decoded.len() && !this.done {
+            this.decoded.clear();
+            this.returned = 0;
+
+            let mut input = [0; READ_LEN];
+            let mut input = ReadBuf::new(&mut input);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut input))?;
+
+            if input.filled().is_empty() {
+                this.done = true;
+                this.finish()?;
+            } else {
+                this.held.extend_from_slice(input.filled());
+                let ready = this.held.len().saturating_sub(2);
+                for i in 0..ready {
+                    this.decode(this.held[i], 8);
+                }
+                this.held.drain(..ready);
+            }
+        }
+
+        let remaining = &this.decoded[this.returned..];
+        let read = remaining.len().min(buf.remaining());
+        buf.put_slice(&remaining[..read]);
+        this.returned += read;
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{bits::BitReader, Code, DecodeError, Huffman};
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Compresses `input` to bytes, followed by the code of `eos`, a reserved
+    /// end of stream symbol such as one added with
+    /// [`HuffmanBuilder::end_of_stream`](struct.HuffmanBuilder.html#method.end_of_stream).
+    /// The last byte is padded with zeros, but as the decoder stops at `eos`,
+    /// the amount of padding doesn't need to be stored alongside the bytes
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::HuffmanBuilder;
+    ///
+    /// let input: Vec<char> = "stops by itself".chars().collect();
+    /// let tree = HuffmanBuilder::new().end_of_stream('\0').build(&input);
+    ///
+    /// let mut bytes = tree.compress_terminated(&input, &'\0').unwrap();
+    /// let len = bytes.len();
+    /// // Anything can follow the stream
+    /// bytes.extend(b"trailing data");
+    ///
+    /// assert_eq!(Ok((input, len)), tree.decompress_terminated(&bytes, &'\0'));
+    /// ```
+    ///
+    /// # Errors
+    /// Returns `None` if `eos` isn't in the tree, or `input` holds `eos` or
+    /// any symbol not in the tree
+    #[must_use]
+    pub fn compress_terminated(&self, input: &[T], eos: &T) -> Option<Vec<u8>> {
+        let codes = self.to_btree();
+        let mut output = Code::with_capacity(input.len());
+
+        for symbol in input {
+            if symbol == eos {
+                return None;
+            }
+            output.extend(codes.get(symbol)?.iter().copied());
+        }
+        output.extend(codes.get(eos)?.iter().copied());
+
+        Some(output.as_bytes().to_vec())
+    }
+    /// Decompresses bytes written by
+    /// [`compress_terminated`](#method.compress_terminated), stopping at the
+    /// first code of `eos`. Returns the symbols, and how many bytes of
+    /// `bytes` they took up, so whatever follows the stream can be read next
+    ///
+    /// # Errors
+    /// Returns an error if `eos` isn't in the tree, the bytes end before a
+    /// code of `eos`, or the padding after it isn't all zeros
+    pub fn decompress_terminated(
+        &self,
+        bytes: &[u8],
+        eos: &T,
+    ) -> Result<(Vec<T>, usize), DecodeError> {
+        let root = match self.root() {
+            Some(root) if self.contents.contains(eos) => root,
+            _ => return Err(DecodeError::NoEndOfStream),
+        };
+
+        let mut reader = BitReader::new(bytes);
+        let mut output = Vec::new();
+        loop {
+            let mut node = root;
+            while let Some([left, right]) = self.children(node) {
+                let bit = reader.read_bit().ok_or(DecodeError::UnexpectedEof)?;
+                node = if bit { right } else { left };
+            }
+
+            if self.contents[node] == *eos {
+                break;
+            }
+            output.push(self.contents[node].clone());
+        }
+
+        // Every bit up to the end of the byte holding the end of the code
+        while !reader.remaining().is_multiple_of(8) {
+            if reader.read_bit() == Some(true) {
+                return Err(DecodeError::DanglingBits);
+            }
+        }
+        Ok((output, bytes.len() - reader.remaining() / 8))
+    }
+}
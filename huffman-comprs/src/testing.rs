@@ -0,0 +1,163 @@
+//! Helpers for fuzzing and property testing code built on the crate, through
+//! [`arbitrary`](https://docs.rs/arbitrary).
+//!
+//! [`Huffman<u8>`](../struct.Huffman.html) implements `Arbitrary`, and
+//! [`ValidRz`](struct.ValidRz.html) and [`InvalidRz`](struct.InvalidRz.html)
+//! generate `.rz` files that must, and mustn't, parse:
+//! ```
+//! use std::convert::TryFrom;
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use huffman_comprs::{testing::{InvalidRz, ValidRz}, RZFile};
+//!
+//! let entropy = [7; 256];
+//! let mut u = Unstructured::new(&entropy);
+//!
+//! let valid = ValidRz::arbitrary(&mut u).unwrap();
+//! let file = RZFile::<u8>::try_from(valid.buf.as_slice()).unwrap();
+//! assert_eq!(Some(valid.input), file.decompress());
+//!
+//! let invalid = InvalidRz::arbitrary(&mut u).unwrap();
+//! assert!(RZFile::<u8>::try_from(invalid.0.as_slice()).is_err());
+//! ```
+
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{rz::HEADER_LEN, Huffman, RZFile, TieBreak, TreeFormat, RZ_MAGIC, RZ_VERSION};
+
+impl<'a> Arbitrary<'a> for Huffman<u8> {
+    /// Builds a tree from an arbitrary set of bytes, each with an arbitrary,
+    /// non-zero frequency
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let frequencies: BTreeMap<u8, u16> = u.arbitrary()?;
+
+        Ok(Self::build(
+            frequencies
+                .into_iter()
+                .map(|(byte, freq)| (byte, usize::from(freq) + 1))
+                .collect(),
+            TieBreak::default(),
+        ))
+    }
+}
+
+/// A well formed `.rz` file, and the bytes it decompresses to.
+///
+/// The file is compressed with a tree built from `input`, which is stored
+/// with an arbitrary format, and may have a checksum or be split into blocks,
+/// each coded with the tree or whichever coder is smallest
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidRz {
+    /// The bytes the file decompresses to
+    pub input: Vec<u8>,
+    /// The file, as written by [`RZFile::write_to`](../struct.RZFile.html#method.write_to)
+    pub buf: Vec<u8>,
+}
+
+impl<'a> Arbitrary<'a> for ValidRz {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let input: Vec<u8> = u.arbitrary()?;
+        let tree = Huffman::from(&input);
+
+        let file = if u.arbitrary()? {
+            let block_size = u.int_in_range(1..=256)?;
+            if u.arbitrary()? {
+                RZFile::new_blocked_adaptive(tree, &input, block_size)
+            } else {
+                RZFile::new_blocked(tree, &input, block_size)
+            }
+        } else {
+            RZFile::compress(tree, &input)
+        };
+        let mut file = file
+            .expect("every byte is in the tree")
+            .with_tree_format(*u.choose(&[TreeFormat::Compact, TreeFormat::Bincode])?);
+        if u.arbitrary()? {
+            file = file.with_checksum(&input);
+        }
+
+        let mut buf = Vec::new();
+        file.write_to(&mut buf)
+            .expect("writing to a `Vec` can't fail");
+        Ok(Self { input, buf })
+    }
+}
+
+/// A `.rz` file that fails to parse.
+///
+/// A [`ValidRz`](struct.ValidRz.html) is broken in one of a few ways that
+/// every reader must reject, such as cutting it off part way through its
+/// header or tree, or claiming a version that doesn't exist
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidRz(pub Vec<u8>);
+
+impl<'a> Arbitrary<'a> for InvalidRz {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut buf = ValidRz::arbitrary(u)?.buf;
+
+        match u.int_in_range(0..=4)? {
+            // Part way through the header
+            0 => buf.truncate(u.int_in_range(0..=HEADER_LEN - 1)?),
+            1 => {
+                let first = u.int_in_range(0..=u8::MAX - 2)?;
+                // Skipping the first byte of `RZ_MAGIC`, and of extension frames
+                buf[0] = if first >= RZ_MAGIC[0] {
+                    first + 2
+                } else {
+                    first
+                };
+            }
+            // Newer than any version, or wrapping round to 0, which isn't one
+            2 => buf[4] = u.int_in_range(RZ_VERSION..=u8::MAX)?.wrapping_add(1),
+            // More padding than a byte holds
+            3 => buf[10] = u.int_in_range(8..=u8::MAX)?,
+            // Part way through the tree, which comes after the optional
+            // sections and before the data, or through the data if the tree
+            // is left out, as it is when the data is stored
+            _ => {
+                let file = RZFile::<u8>::try_from(buf.as_slice()).expect("the file is valid");
+                let tree_end = buf.len() - file.data().len();
+                let tree_start = tree_end - file.tree_len();
+                let end = if file.tree_len() == 0 {
+                    buf.len()
+                } else {
+                    tree_end
+                };
+                buf.truncate(u.int_in_range(tree_start..=end - 1)?);
+            }
+        }
+        Ok(Self(buf))
+    }
+}
+
+/// Compresses `input` to a `.rz` file, then reads it back both from a slice
+/// and from a reader, checking each decompresses to `input`. Returns the
+/// file, so it can be checked further
+/// # Examples
+/// ```
+/// use huffman_comprs::testing::roundtrip;
+///
+/// let buf = roundtrip(b"Any bytes at all");
+///
+/// assert!(!buf.is_empty());
+/// ```
+///
+/// # Panics
+/// Panics if either read doesn't give back `input`
+#[must_use]
+pub fn roundtrip(input: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    RZFile::compress(Huffman::from(&input.to_vec()), input)
+        .expect("every byte is in the tree")
+        .write_to(&mut buf)
+        .expect("writing to a `Vec` can't fail");
+
+    let parsed = RZFile::<u8>::try_from(buf.as_slice()).expect("the file should parse");
+    assert_eq!(Some(input), parsed.decompress().as_deref());
+
+    let read = RZFile::<u8>::read_from(buf.as_slice()).expect("the file should be read");
+    assert_eq!(Some(input), read.decompress().as_deref());
+
+    buf
+}
@@ -0,0 +1,166 @@
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::{count, Code, Encoder, Huffman, TieBreak};
+
+/// Splits text into the symbols a tree is built over, and compresses.
+///
+/// The tokenizers given here split text so their tokens join back together
+/// into the original, with `concat`, once decompressed. Implementing it lets
+/// text be modelled as any other kind of token, such as JSON keys
+/// # Examples
+/// ```
+/// use huffman_comprs::{Huffman, Tokenizer};
+///
+/// /// Splits text into words, each keeping any whitespace after it
+/// struct Words;
+///
+/// impl Tokenizer for Words {
+///     type Token = String;
+///
+///     fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = String> + 'a {
+///         input
+///             .split_inclusive(char::is_whitespace)
+///             .map(str::to_string)
+///     }
+/// }
+///
+/// let text = "the cat\tand the\nhat ";
+/// let tree = Huffman::from_tokens(text, &Words);
+///
+/// assert_eq!(5, tree.contents().len());
+/// assert!(tree.compress_tokens("the hat the cat\t", &Words).is_some());
+/// ```
+pub trait Tokenizer {
+    /// The type of each token, being the symbols of the tree
+    type Token: Serialize + Ord + Clone;
+
+    /// Splits `input` into tokens, in order
+    fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = Self::Token> + 'a;
+}
+
+/// Splits text into `char`s, as
+/// [`Huffman::from`](struct.Huffman.html) and
+/// [`compress_str`](struct.Huffman.html#method.compress_str) do
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Chars;
+
+impl Tokenizer for Chars {
+    type Token = char;
+
+    fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = char> + 'a {
+        input.chars()
+    }
+}
+
+/// Splits text into lines, each keeping the `'\n'` that ends it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Lines;
+
+impl Tokenizer for Lines {
+    type Token = String;
+
+    fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = String> + 'a {
+        input.split_inclusive('\n').map(str::to_string)
+    }
+}
+
+/// Splits text after each occurrence of a delimiter, each token keeping the
+/// delimiter that ends it
+/// # Examples
+/// ```
+/// use huffman_comprs::{Delimited, Tokenizer};
+///
+/// let tokens: Vec<String> = Delimited(',').tokens("a,b,,c").collect();
+///
+/// assert_eq!(vec!["a,", "b,", ",", "c"], tokens);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Delimited(pub char);
+
+impl Tokenizer for Delimited {
+    type Token = String;
+
+    fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = String> + 'a {
+        input.split_inclusive(self.0).map(str::to_string)
+    }
+}
+
+/// Splits structured logs into words, and the whitespace and punctuation
+/// between them.
+///
+/// Each run of letters, digits and underscores is a token, as is each run of
+/// the same whitespace character, and every other character is a token of
+/// its own, so the words of a log line, such as levels, keys and the parts
+/// of module paths, each become a single token
+/// # Examples
+/// ```
+/// use huffman_comprs::{LogTokens, Tokenizer};
+///
+/// let tokens: Vec<String> = LogTokens.tokens("INFO  db::pool: conn=42").collect();
+///
+/// assert_eq!(
+///     vec!["INFO", "  ", "db", ":", ":", "pool", ":", " ", "conn", "=", "42"],
+///     tokens
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LogTokens;
+
+impl Tokenizer for LogTokens {
+    type Token = String;
+
+    fn tokens<'a>(&'a self, input: &'a str) -> impl Iterator<Item = String> + 'a {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        let mut rest = input;
+        std::iter::from_fn(move || {
+            let first = rest.chars().next()?;
+            let len = if is_word(first) {
+                rest.find(|c| !is_word(c)).unwrap_or(rest.len())
+            } else if first.is_whitespace() {
+                rest.find(|c| c != first).unwrap_or(rest.len())
+            } else {
+                first.len_utf8()
+            };
+            let (token, after) = rest.split_at(len);
+            rest = after;
+            Some(token.to_string())
+        })
+    }
+}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Builds a tree from the frequency of each token `tokenizer` splits
+    /// `input` into
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::{Huffman, Lines};
+    ///
+    /// let log = "ok\nok\nretrying\nok\n";
+    /// let tree = Huffman::from_tokens(log, &Lines);
+    ///
+    /// assert_eq!(1, tree.get_code("ok\n".to_string()).unwrap().len());
+    /// ```
+    #[must_use]
+    pub fn from_tokens<K>(input: &str, tokenizer: &K) -> Self
+    where
+        K: Tokenizer<Token = T>,
+    {
+        Self::build(count(tokenizer.tokens(input)), TieBreak::default())
+    }
+    /// Compresses each token `tokenizer` splits `input` into, as
+    /// [`compress`](#method.compress) does
+    ///
+    /// # Errors
+    /// Returns `None` if any of the tokens are not contained in self's tree
+    #[must_use]
+    pub fn compress_tokens<K>(&self, input: &str, tokenizer: &K) -> Option<Code>
+    where
+        K: Tokenizer<Token = T>,
+    {
+        Encoder::new(self).encode_tokens(input, tokenizer)
+    }
+}
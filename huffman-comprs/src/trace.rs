@@ -0,0 +1,50 @@
+//! Spans and events for the `tracing` feature, which expand to nothing
+//! without it, so instrumented code needs no `cfg`s of its own
+
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
+/// Enters a debug level span, with the given name and fields, until the end
+/// of the enclosing block. How long it was entered for is recorded as it's
+/// left
+macro_rules! span {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = $crate::trace::Timed::enter(tracing::debug_span!($($args)*));
+    };
+}
+
+/// Records a debug level event, with the given fields and message
+macro_rules! event {
+    ($($args:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!($($args)*);
+    };
+}
+
+pub(crate) use {event, span};
+
+/// An entered span, which records how long it was entered for when dropped
+#[cfg(feature = "tracing")]
+pub struct Timed {
+    _span: tracing::span::EnteredSpan,
+    start: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl Timed {
+    pub fn enter(span: tracing::Span) -> Self {
+        Self {
+            _span: span.entered(),
+            start: Instant::now(),
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+impl Drop for Timed {
+    fn drop(&mut self) {
+        // The event is recorded before the span is left, so falls within it
+        tracing::debug!(elapsed_us = self.start.elapsed().as_micros(), "done");
+    }
+}
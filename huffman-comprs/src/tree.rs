@@ -0,0 +1,89 @@
+use std::collections::BTreeMap;
+
+use crate::{Code, Node};
+
+/// The symbols and nodes of a tree, borrowed from a
+/// [`Huffman`](struct.Huffman.html), or a
+/// [`StaticHuffman`](struct.StaticHuffman.html), so both walk their trees
+/// the same way
+pub struct TreeRef<'a, T> {
+    pub contents: &'a [T],
+    pub nodes: &'a [Node],
+}
+
+impl<T> TreeRef<'_, T>
+where
+    T: Ord + Clone,
+{
+    pub const fn root(&self) -> Option<usize> {
+        self.nodes.len().checked_sub(1)
+    }
+    pub fn children(&self, node: usize) -> Option<[usize; 2]> {
+        self.nodes[node]
+            .children
+            .map(|[left, right]| [left as usize, right as usize])
+    }
+    /// The code of `to_get`, or `None` if it isn't in the tree, or its code
+    /// is empty, as the only symbol of the tree
+    pub fn get_code(&self, to_get: &T) -> Option<Code> {
+        let leaf = self.contents.iter().position(|c| c == to_get)?;
+        let mut code = Code::new();
+
+        if self.path_to(self.root()?, leaf, &mut code) && !code.is_empty() {
+            Some(code)
+        } else {
+            None
+        }
+    }
+    fn path_to(&self, node: usize, leaf: usize, code: &mut Code) -> bool {
+        if node == leaf {
+            return true;
+        }
+        if let Some(children) = self.children(node) {
+            for (bit, child) in [false, true].iter().zip(children.iter()) {
+                code.push(*bit);
+                if self.path_to(*child, leaf, code) {
+                    return true;
+                }
+                code.pop();
+            }
+        }
+        false
+    }
+    /// Decodes a single symbol from the back of `input`, or `None` if it
+    /// ends part way through a code
+    pub fn get_char_rec(&self, input: &mut Vec<bool>) -> Option<T> {
+        let mut node = self.root()?;
+
+        while let Some([left, right]) = self.children(node) {
+            node = if input.pop()? { right } else { left };
+        }
+
+        Some(self.contents[node].clone())
+    }
+    pub fn to_btree(&self) -> BTreeMap<T, Vec<bool>> {
+        let mut b_tree = BTreeMap::new();
+
+        if let Some(root) = self.root() {
+            self.to_btree_rec(root, &mut b_tree, Vec::new());
+        }
+
+        b_tree
+    }
+    fn to_btree_rec(&self, node: usize, b_tree: &mut BTreeMap<T, Vec<bool>>, path: Vec<bool>) {
+        match self.children(node) {
+            Some([left, right]) => {
+                let mut left_path = path.clone();
+                left_path.push(false);
+                self.to_btree_rec(left, b_tree, left_path);
+
+                let mut right_path = path;
+                right_path.push(true);
+                self.to_btree_rec(right, b_tree, right_path);
+            }
+            None => {
+                b_tree.insert(self.contents[node].clone(), path);
+            }
+        }
+    }
+}
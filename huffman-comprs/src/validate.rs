@@ -0,0 +1,147 @@
+use std::{error::Error, fmt};
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::Huffman;
+
+/// The ways a [`Huffman`](struct.Huffman.html) tree can be malformed, as found
+/// by [`Huffman::validate`](struct.Huffman.html#method.validate). Each holds
+/// the index of the offending node
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeError {
+    /// There are more symbols than nodes to hold them
+    MissingLeaves,
+    /// A node that should be a leaf, holding a symbol, has children
+    LeafWithChildren(usize),
+    /// A node past the leaves, which must be internal, has no children
+    InternalWithoutChildren(usize),
+    /// A node has a child at or after its own position, which could form a
+    /// cycle
+    ForwardChild(usize),
+    /// A node is the child of more than one parent
+    SharedNode(usize),
+    /// A node other than the root isn't the child of any node
+    Unreachable(usize),
+    /// The symbol at this index also appears earlier in the tree
+    DuplicateSymbol(usize),
+    /// A node's frequency isn't the sum of its children's
+    FrequencyMismatch(usize),
+    /// The sum of `2^-len` over every code length is over 1, so the codes
+    /// can't form a prefix code
+    KraftViolation,
+    /// The sum of `2^-len` over every code length is under 1, so some codes
+    /// are left unused, and the codes can't form a full tree
+    IncompleteCode,
+    /// The code length at this index is too long, over 127 bits, or over 16
+    /// bits for a JPEG table
+    CodeTooLong(usize),
+    /// The weight at this index is negative, infinite or NaN
+    InvalidWeight(usize),
+    /// The counts of codes of each length add up to this many codes, which
+    /// isn't the number of symbols given
+    WrongSymbolCount(usize),
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingLeaves => write!(f, "there are more symbols than nodes"),
+            Self::LeafWithChildren(n) => write!(f, "leaf node {n} has children"),
+            Self::InternalWithoutChildren(n) => write!(f, "internal node {n} has no children"),
+            Self::ForwardChild(n) => write!(f, "node {n} has a child that isn't before it"),
+            Self::SharedNode(n) => write!(f, "node {n} has more than one parent"),
+            Self::Unreachable(n) => write!(f, "node {n} isn't reachable from the root"),
+            Self::DuplicateSymbol(n) => write!(f, "symbol {n} appears more than once"),
+            Self::FrequencyMismatch(n) => {
+                write!(f, "node {n}'s frequency isn't the sum of its children's")
+            }
+            Self::KraftViolation => write!(f, "the code lengths break the Kraft inequality"),
+            Self::IncompleteCode => write!(f, "the code lengths leave some codes unused"),
+            Self::CodeTooLong(n) => write!(f, "code length {n} is too long"),
+            Self::InvalidWeight(n) => write!(f, "weight {n} isn't a finite, positive number"),
+            Self::WrongSymbolCount(n) => {
+                write!(f, "there are {n} codes, but a different number of symbols")
+            }
+        }
+    }
+}
+
+impl Error for TreeError {}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Checks that the tree is well formed, and so describes a proper prefix
+    /// code. Trees built by this crate are always valid, but trees read from
+    /// untrusted sources, such as `.rz` files, should be checked before use,
+    /// as decoding with a malformed tree may panic or never finish.
+    ///
+    /// # Errors
+    /// Returns the first problem found with the tree
+    pub fn validate(&self) -> Result<(), TreeError> {
+        let leaves = self.contents.len();
+        if leaves > self.nodes.len() {
+            return Err(TreeError::MissingLeaves);
+        }
+
+        let mut parents = vec![0_u8; self.nodes.len()];
+        for (i, node) in self.nodes.iter().enumerate() {
+            match node.children {
+                Some(_) if i < leaves => return Err(TreeError::LeafWithChildren(i)),
+                None if i >= leaves => return Err(TreeError::InternalWithoutChildren(i)),
+                None => {}
+                Some(children) => {
+                    let mut freq: usize = 0;
+                    for child in children.iter().map(|c| *c as usize) {
+                        if child >= i {
+                            return Err(TreeError::ForwardChild(i));
+                        }
+                        if parents[child] > 0 {
+                            return Err(TreeError::SharedNode(child));
+                        }
+                        parents[child] += 1;
+                        freq = freq.saturating_add(self.nodes[child].freq);
+                    }
+                    if freq != node.freq {
+                        return Err(TreeError::FrequencyMismatch(i));
+                    }
+                }
+            }
+        }
+        if let Some(unreachable) = parents.iter().rev().skip(1).position(|p| *p == 0) {
+            return Err(TreeError::Unreachable(self.nodes.len() - 2 - unreachable));
+        }
+
+        let mut sorted: Vec<(&T, usize)> = self.contents.iter().zip(0..).collect();
+        sorted.sort();
+        if let Some(pair) = sorted.windows(2).find(|pair| pair[0].0 == pair[1].0) {
+            return Err(TreeError::DuplicateSymbol(pair[1].1));
+        }
+
+        self.check_kraft()
+    }
+    /// Checks `sum(2^-len) <= 1` over every leaf exactly, without needing
+    /// arbitrarily large integers, by carrying leaves up a level at a time
+    fn check_kraft(&self) -> Result<(), TreeError> {
+        let depths = self.depths();
+
+        let mut per_depth = vec![0_usize; depths.iter().max().map_or(0, |d| d + 1)];
+        for depth in &depths[..self.contents.len()] {
+            per_depth[*depth] += 1;
+        }
+
+        // Rounding up at each level makes the final count `ceil(sum)`
+        let mut carry = 0;
+        for count in per_depth.iter().skip(1).rev() {
+            carry = (count + carry).div_ceil(2);
+        }
+
+        if per_depth.first().copied().unwrap_or(0) + carry <= 1 {
+            Ok(())
+        } else {
+            Err(TreeError::KraftViolation)
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::fmt;
+
+#[cfg(feature = "serde_support")]
+use serde::Serialize;
+
+use crate::Huffman;
+
+/// A node of a tree, borrowed from it, as yielded by
+/// [`Huffman::iter_nodes`](struct.Huffman.html#method.iter_nodes)
+pub struct NodeRef<'a, T>
+where
+    T: Serialize + Ord + Clone,
+{
+    tree: &'a Huffman<T>,
+    index: usize,
+}
+
+impl<'a, T> NodeRef<'a, T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// The node's position in the tree, counting from the first leaf. Every
+    /// node comes after its children, so the root is last
+    #[must_use]
+    pub const fn index(&self) -> usize {
+        self.index
+    }
+    /// The frequency of the node, being the sum of its children's if it has
+    /// any
+    #[must_use]
+    pub fn freq(&self) -> usize {
+        self.tree.nodes[self.index].freq
+    }
+    /// The child reached by a `0`, then the child reached by a `1`, or `None`
+    /// if the node is a leaf
+    #[must_use]
+    pub fn children(&self) -> Option<[Self; 2]> {
+        self.tree.children(self.index).map(|[left, right]| {
+            [left, right].map(|index| Self {
+                tree: self.tree,
+                index,
+            })
+        })
+    }
+    /// Whether the node is a leaf, so has a symbol rather than children
+    #[must_use]
+    pub fn is_leaf(&self) -> bool {
+        self.tree.children(self.index).is_none()
+    }
+    /// The node's symbol if it's a leaf, or `None` if it has children
+    #[must_use]
+    pub fn contents(&self) -> Option<&'a T> {
+        let tree = self.tree;
+        tree.children(self.index)
+            .is_none()
+            .then(|| &tree.contents[self.index])
+    }
+}
+
+// Deriving would need `T: Copy`, though only a reference is copied
+#[allow(clippy::expl_impl_clone_on_copy)]
+impl<T> Clone for NodeRef<'_, T>
+where
+    T: Serialize + Ord + Clone,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for NodeRef<'_, T> where T: Serialize + Ord + Clone {}
+
+impl<T> fmt::Debug for NodeRef<'_, T>
+where
+    T: Serialize + Ord + Clone + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NodeRef")
+            .field("index", &self.index)
+            .field("freq", &self.freq())
+            .field(
+                "children",
+                &self
+                    .children()
+                    .map(|children| children.map(|child| child.index)),
+            )
+            .field("contents", &self.contents())
+            .finish()
+    }
+}
+
+/// A node of a tree, as seen by the visitor passed to
+/// [`Huffman::walk`](struct.Huffman.html#method.walk)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeView<'a, T> {
+    /// How many edges are between the node and the root, which is at depth 0
+    pub depth: usize,
+    /// The bits leading from the root to the node, being its code if it's a
+    /// leaf
+    pub path: &'a [bool],
+    /// The frequency of the node, being the sum of its children's if it has
+    /// any
+    pub freq: usize,
+    /// The node's symbol if it's a leaf, or `None` if it has children
+    pub symbol: Option<&'a T>,
+}
+
+impl<T> Huffman<T>
+where
+    T: Serialize + Ord + Clone,
+{
+    /// Iterates over every node in the tree, leaves first, with each node
+    /// after its children and the root last. The root is also given by
+    /// [`root_node`](#method.root_node), to follow its children from
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::from("aabc");
+    ///
+    /// let leaves: Vec<char> = tree.iter_nodes().filter_map(|node| node.contents().copied()).collect();
+    /// assert_eq!(vec!['a', 'b', 'c'], leaves);
+    ///
+    /// let root = tree.iter_nodes().last().unwrap();
+    /// assert_eq!(4, root.freq());
+    /// assert_eq!(Some(root.index()), tree.root_node().map(|root| root.index()));
+    /// ```
+    #[must_use]
+    pub fn iter_nodes(
+        &self,
+    ) -> impl DoubleEndedIterator<Item = NodeRef<'_, T>> + ExactSizeIterator {
+        (0..self.nodes.len()).map(move |index| NodeRef { tree: self, index })
+    }
+    /// The root of the tree, or `None` if the tree is empty
+    #[must_use]
+    pub fn root_node(&self) -> Option<NodeRef<'_, T>> {
+        self.root().map(|index| NodeRef { tree: self, index })
+    }
+    /// Calls `visitor` with every node in the tree, each before its children,
+    /// and the child reached by a `0` before the child reached by a `1`. This
+    /// is enough to export the tree in any format, such as CSV or HTML
+    /// # Examples
+    /// ```
+    /// use huffman_comprs::Huffman;
+    ///
+    /// let tree = Huffman::from("aabc");
+    ///
+    /// let mut csv = String::new();
+    /// tree.walk(|node| {
+    ///     if let Some(symbol) = node.symbol {
+    ///         let code: String = node.path.iter().map(|&bit| if bit { '1' } else { '0' }).collect();
+    ///         csv.push_str(&format!("{},{},{}\n", symbol, code, node.freq));
+    ///     }
+    /// });
+    ///
+    /// assert_eq!("c,00,1\nb,01,1\na,1,2\n", csv);
+    /// ```
+    pub fn walk<F>(&self, mut visitor: F)
+    where
+        F: FnMut(NodeView<'_, T>),
+    {
+        let mut path = Vec::new();
+        // Each node, its depth, and the bit leading to it from its parent
+        let mut stack: Vec<(usize, usize, bool)> = self
+            .root()
+            .map(|root| (root, 0, false))
+            .into_iter()
+            .collect();
+
+        while let Some((node, depth, bit)) = stack.pop() {
+            path.truncate(depth.saturating_sub(1));
+            if depth > 0 {
+                path.push(bit);
+            }
+
+            let children = self.children(node);
+            visitor(NodeView {
+                depth,
+                path: &path,
+                freq: self.nodes[node].freq,
+                symbol: children.is_none().then(|| &self.contents[node]),
+            });
+
+            if let Some([left, right]) = children {
+                stack.push((right, depth + 1, true));
+                stack.push((left, depth + 1, false));
+            }
+        }
+    }
+}
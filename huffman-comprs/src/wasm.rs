@@ -0,0 +1,98 @@
+//! Bindings for using the crate from JavaScript, through
+//! [`wasm-bindgen`](https://docs.rs/wasm-bindgen).
+//!
+//! Byte slices are taken and returned as `Uint8Array`s, and errors are thrown
+//! as JavaScript `Error`s. Compressed data is always a whole `.rz` file, so
+//! assets compressed by the CLI can be decompressed in the browser:
+//! ```js
+//! import { compress_bytes, decompress_bytes } from "huffman_comprs";
+//!
+//! const response = await fetch("/assets/data.json.rz");
+//! const compressed = new Uint8Array(await response.arrayBuffer());
+//! const json = new TextDecoder().decode(decompress_bytes(compressed));
+//! ```
+
+use std::convert::TryFrom;
+
+use wasm_bindgen::prelude::*;
+
+use crate::{DecodeOptions, Huffman, RZFile};
+
+/// Compresses `input` into an `.rz` file, with a tree built from `input`
+/// itself and a checksum
+#[wasm_bindgen]
+#[must_use]
+#[allow(clippy::missing_panics_doc)] // every byte is in the tree, so it can't
+pub fn compress_bytes(input: &[u8]) -> Vec<u8> {
+    write(
+        RZFile::compress(Huffman::from(input.to_vec()), input).unwrap(),
+        input,
+    )
+}
+
+/// Decompresses an `.rz` file, or many of them one after another, checking
+/// any checksums
+///
+/// # Errors
+/// Throws if `input` isn't an `.rz` file, or the data is corrupt
+#[wasm_bindgen]
+pub fn decompress_bytes(input: &[u8]) -> Result<Vec<u8>, JsError> {
+    let mut output = Vec::new();
+    for file in RZFile::<u8>::frames(input) {
+        output.extend(file?.decompress_with(&DecodeOptions::new())?);
+    }
+    Ok(output)
+}
+
+/// A tree built once, to compress many inputs with, exported to JavaScript
+/// as `HuffmanTree`
+#[wasm_bindgen(js_name = HuffmanTree)]
+#[derive(Clone, Debug)]
+pub struct WasmTree {
+    tree: Huffman<u8>,
+}
+
+#[wasm_bindgen(js_class = HuffmanTree)]
+impl WasmTree {
+    /// Builds a tree from the frequency of each byte in `sample`
+    #[wasm_bindgen(constructor)]
+    #[must_use]
+    pub fn new(sample: &[u8]) -> Self {
+        Self {
+            tree: Huffman::from(sample.to_vec()),
+        }
+    }
+    /// Reads the tree out of an `.rz` file, so other inputs can be compressed
+    /// the same way
+    ///
+    /// # Errors
+    /// Throws if `file` isn't an `.rz` file
+    #[wasm_bindgen(js_name = fromFile)]
+    pub fn from_file(file: &[u8]) -> Result<Self, JsError> {
+        let file: RZFile<u8> = RZFile::try_from(file)?;
+        Ok(Self { tree: file.tree })
+    }
+    /// How many different bytes the tree holds
+    #[wasm_bindgen(getter)]
+    #[must_use]
+    pub fn symbols(&self) -> usize {
+        self.tree.contents().len()
+    }
+    /// Compresses `input` into an `.rz` file, with this tree and a checksum
+    ///
+    /// # Errors
+    /// Throws if a byte of `input` isn't in the tree
+    pub fn compress(&self, input: &[u8]) -> Result<Vec<u8>, JsError> {
+        let file = RZFile::compress(self.tree.clone(), input)
+            .ok_or_else(|| JsError::new("a byte of the input isn't in the tree"))?;
+        Ok(write(file, input))
+    }
+}
+
+/// Adds a checksum of `input` to `file`, then writes it out
+fn write(file: RZFile<u8>, input: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    // Writing to a `Vec` can't fail
+    file.with_checksum(input).write_to(&mut buf).unwrap();
+    buf
+}
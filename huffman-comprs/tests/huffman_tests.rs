@@ -24,3 +24,1085 @@ fn serde_testing() {
 
     assert_eq!(huffman, huffman_de);
 }
+
+#[test]
+fn from_large_alphabet() {
+    let input: Vec<u16> = (0..4096).map(|i| i % 1500).collect();
+
+    let huffman = Huffman::from(&input);
+
+    for symbol in 0..1500 {
+        assert!(huffman.get_code(symbol).is_some());
+    }
+
+    // `reconstruct` reads the bitstream back to front, as laid out by `RZFile`
+    let mut data = huffman.compress(&input).unwrap();
+    data.reverse();
+
+    assert_eq!(input, huffman.reconstruct(data, 0).unwrap());
+}
+
+#[test]
+fn collect_symbols_or_counts() {
+    let input = "Symbols straight off an iterator";
+
+    let from_symbols: Huffman<char> = input.chars().collect();
+    assert_eq!(Huffman::from(input), from_symbols);
+
+    let counts: Vec<(char, usize)> = from_symbols.frequencies().map(|(c, f)| (*c, f)).collect();
+    let from_counts: Huffman<char> = counts.into_iter().collect();
+    assert_eq!(from_symbols.to_btree(), from_counts.to_btree());
+}
+
+#[test]
+fn count_large_alphabets() {
+    let words: Vec<String> = (0..20_000)
+        .map(|i| format!("word{}", (i * 7919) % 5000))
+        .collect();
+    let tree = Huffman::from(&words);
+
+    assert_eq!(5000, tree.frequencies().count());
+    assert!(tree.frequencies().all(|(_, freq)| freq == 4));
+    // Symbols keep the order they first appeared in
+    let first: Vec<&String> = tree.frequencies().map(|(word, _)| word).take(3).collect();
+    assert_eq!(vec!["word0", "word2919", "word838"], first);
+}
+
+#[test]
+fn extend_folds_in_batches() {
+    let batches: [&[u8]; 3] = [b"first batch", b"then another", b"and a last one"];
+
+    let mut tree = Huffman::from(&batches[0].to_vec());
+    tree.extend(batches[1].iter().copied());
+    tree.extend(vec![(b'~', 3), (b'a', 1)]);
+    tree.extend(batches[2].iter().copied());
+
+    let mut all = batches.concat();
+    all.extend(b"~~~a");
+    let expected: std::collections::BTreeMap<u8, usize> = Huffman::from(&all)
+        .frequencies()
+        .map(|(s, f)| (*s, f))
+        .collect();
+    let extended: std::collections::BTreeMap<u8, usize> =
+        tree.frequencies().map(|(s, f)| (*s, f)).collect();
+    assert_eq!(expected, extended);
+    assert_eq!(all.len(), tree.freq());
+}
+
+#[test]
+fn from_reader_matches_whole_input() {
+    // Longer than one chunk, so counts carry over between them
+    let input: Vec<u8> = (0..200_000_u32)
+        .map(|i| (i % 251 * (i % 13) % 251) as u8)
+        .collect();
+
+    let tree = Huffman::from_reader(input.as_slice()).unwrap();
+
+    assert_eq!(Huffman::from(&input), tree);
+    assert_eq!(Huffman::from(&input).to_btree(), tree.to_btree());
+    assert!(Huffman::from_reader(&[][..]).unwrap().contents().is_empty());
+}
+
+#[test]
+fn codes_match_symbols() {
+    let script = "A slightly longer script, with a few more distinct characters!";
+
+    let huffman = Huffman::from(script);
+    let encoder = Encoder::new(&huffman);
+
+    for (symbol, code) in huffman.to_btree() {
+        assert_eq!(Some(Code::from(code.clone())), huffman.get_code(symbol));
+        assert_eq!(Some(code.as_slice()), encoder.code_of(&symbol));
+        assert_eq!(Some(symbol), huffman.get_char(code));
+    }
+    assert_eq!(None, encoder.code_of(&'~'));
+    assert_eq!(script.len(), huffman.freq());
+}
+
+#[test]
+fn builder_limits_depth() {
+    // Fibonacci frequencies give the deepest possible tree
+    let mut input = Vec::new();
+    let (mut a, mut b) = (1, 1);
+    for symbol in 0..20_u8 {
+        input.extend(std::iter::repeat_n(symbol, a));
+        let next = a + b;
+        a = b;
+        b = next;
+    }
+
+    let plain = HuffmanBuilder::new().build(&input);
+    let limited = HuffmanBuilder::new().max_depth(8).build(&input);
+
+    assert_eq!(plain.to_btree(), Huffman::from(&input).to_btree());
+    assert!(plain.to_btree().values().any(|code| code.len() > 8));
+    assert!(limited.to_btree().values().all(|code| code.len() <= 8));
+    assert_eq!(input.len(), limited.freq());
+}
+
+#[test]
+fn builder_canonical_codes() {
+    let input: Vec<char> = "a canonical tree counts upwards".chars().collect();
+
+    let huffman = HuffmanBuilder::new().canonical(true).build(&input);
+
+    let mut codes: Vec<(char, Vec<bool>)> = huffman.to_btree().into_iter().collect();
+    codes.sort_by(|(a, a_code), (b, b_code)| a_code.len().cmp(&b_code.len()).then(a.cmp(b)));
+
+    for pair in codes.windows(2) {
+        let value = |code: &[bool]| code.iter().fold(0_u64, |v, b| v << 1 | u64::from(*b));
+        let (prev, next) = (&pair[0].1, &pair[1].1);
+        assert_eq!((value(prev) + 1) << (next.len() - prev.len()), value(next));
+    }
+}
+
+#[cfg(feature = "serde_support")]
+#[test]
+fn validate_rejects_malformed_trees() {
+    let huffman = Huffman::from("A perfectly normal tree");
+    assert_eq!(Ok(()), huffman.validate());
+
+    // Trees serialize as their symbols, then each node's frequency and children
+    let malformed = |nodes: Vec<(usize, Option<[u32; 2]>)>| {
+        let bytes = bincode::serialize(&(vec!['a', 'b'], nodes)).unwrap();
+        bincode::deserialize::<Huffman<char>>(&bytes)
+            .unwrap()
+            .validate()
+    };
+
+    assert_eq!(
+        Err(TreeError::SharedNode(0)),
+        malformed(vec![(1, None), (1, None), (2, Some([0, 0]))])
+    );
+    assert_eq!(
+        Err(TreeError::ForwardChild(2)),
+        malformed(vec![(1, None), (1, None), (2, Some([0, 2]))])
+    );
+    assert_eq!(
+        Err(TreeError::InternalWithoutChildren(2)),
+        malformed(vec![(1, None), (1, None), (2, None)])
+    );
+    assert_eq!(
+        Err(TreeError::Unreachable(0)),
+        malformed(vec![(1, None), (1, None)])
+    );
+}
+
+#[test]
+fn compress_into_reuses_buffers() {
+    let input: Vec<char> = "Messages compressed into the same buffer".chars().collect();
+    let huffman = Huffman::from(&input);
+
+    let mut bits = vec![true];
+    huffman.compress_into(&input, &mut bits).unwrap();
+    assert_eq!(bits[1..], huffman.compress(&input).unwrap().to_vec()[..]);
+
+    assert!(huffman.compress_into(&['?'], &mut bits).is_none());
+    assert_eq!(huffman.compress(&input).unwrap().len() + 1, bits.len());
+
+    let mut bytes = Vec::new();
+    let zeros = huffman.compress_into_bytes(&input, &mut bytes).unwrap();
+
+    let unpacked: Vec<bool> = bits::BitReader::new(&bytes).with_zeros(zeros).collect();
+    assert_eq!(bits[1..], unpacked[..]);
+}
+
+#[test]
+fn reconstruct_into_existing_collections() {
+    let input = "Decoded straight into a String";
+    let huffman = Huffman::from(input);
+
+    let mut data = huffman.compress_str(input).unwrap();
+    data.reverse();
+
+    let mut output = String::from(">");
+    huffman
+        .reconstruct_extend(data.clone(), 0, &mut output)
+        .unwrap();
+    assert_eq!(format!(">{}", input), output);
+
+    let mut output = vec!['>'];
+    huffman.reconstruct_into(data, 0, &mut output).unwrap();
+    assert_eq!(output.len(), input.len() + 1);
+}
+
+#[test]
+fn strict_decoding_errors() {
+    // 'a' is `1`, 'b' is `01` and 'c' is `00`
+    let huffman = Huffman::from("aabc");
+
+    // Read back to front, so this is `1`, then the start of another code
+    assert_eq!(
+        Err(DecodeError::UnexpectedEof),
+        huffman.try_reconstruct(vec![false, true], 0)
+    );
+    assert_eq!(
+        Err(DecodeError::DanglingBits),
+        huffman.try_reconstruct(vec![true, true], 1)
+    );
+    assert_eq!(
+        Err(DecodeError::DanglingBits),
+        huffman.try_reconstruct(vec![false; 8], 8)
+    );
+    assert_eq!(Ok(vec!['a']), huffman.try_reconstruct(vec![true, false], 1));
+    assert_eq!(
+        Err(DecodeError::DanglingBits),
+        Huffman::from("aaa").try_reconstruct(vec![true], 0)
+    );
+}
+
+#[cfg(feature = "prebuilt")]
+#[test]
+fn prebuilt_trees() {
+    let inputs: [(Huffman<u8>, &[u8]); 4] = [
+        (
+            Huffman::english(),
+            b"It was the best of times, it was the worst of times.",
+        ),
+        (
+            Huffman::json(),
+            br#"{"id":1024,"tags":["a","b"],"ok":true}"#,
+        ),
+        (Huffman::base64(), b"SGVsbG8sIFdvcmxkIQ=="),
+        (
+            Huffman::url(),
+            b"https://example.com/search?q=huffman%20trees&page=2",
+        ),
+    ];
+
+    for (tree, input) in &inputs {
+        assert!(tree.validate().is_ok());
+
+        let input = input.to_vec();
+        let compressed = tree.compress(&input).unwrap();
+        assert!(compressed.len() < input.len() * 8);
+
+        let mut reversed = compressed;
+        reversed.reverse();
+        assert_eq!(Some(input), tree.reconstruct(reversed, 0));
+    }
+
+    // Every byte is in every tree
+    let every_byte: Vec<u8> = (0..=u8::MAX).collect();
+    assert!(Huffman::english().compress(&every_byte).is_some());
+}
+
+#[cfg(feature = "prebuilt")]
+#[test]
+fn deflate_fixed_codes() {
+    let literals = Huffman::deflate_literals();
+    assert!(literals.validate().is_ok());
+    assert_eq!(
+        Huffman::from_code_lengths(
+            &(0..)
+                .zip(deflate::FIXED_LITERAL_LENGTHS)
+                .collect::<Vec<_>>()
+        ),
+        Ok(literals.clone())
+    );
+
+    let code = |tree: &Huffman<u16>, symbol: u16| tree.get_code(symbol).unwrap().to_string();
+    // The ranges listed in RFC 1951, section 3.2.6
+    assert_eq!("10111111", code(&literals, 143));
+    assert_eq!("111111111", code(&literals, 255));
+    assert_eq!("0010111", code(&literals, 279));
+    assert_eq!("11000000", code(&literals, 280));
+    assert_eq!("11000111", code(&literals, 287));
+    assert_eq!("0000000", code(&literals, deflate::END_OF_BLOCK));
+
+    let distances = Huffman::deflate_distances();
+    assert!(distances.validate().is_ok());
+    assert_eq!("11111", code(&distances, 31));
+}
+
+#[cfg(feature = "prebuilt")]
+#[test]
+fn hpack_string_literals() {
+    // From RFC 7541, appendices C.4 and C.6
+    let examples: [(&[u8], &[u8]); 4] = [
+        (b"no-cache", &[0xa8, 0xeb, 0x10, 0x64, 0x9c, 0xbf]),
+        (
+            b"custom-value",
+            &[0x25, 0xa8, 0x49, 0xe9, 0x5b, 0xb8, 0xe8, 0xb4, 0xbf],
+        ),
+        (b"302", &[0x64, 0x02]),
+        (
+            b"https://www.example.com",
+            &[
+                0x9d, 0x29, 0xad, 0x17, 0x18, 0x63, 0xc7, 0x8f, 0x0b, 0x97, 0xc8, 0xe9, 0xae, 0x82,
+                0xae, 0x43, 0xd3,
+            ],
+        ),
+    ];
+    for (input, encoded) in &examples {
+        assert_eq!(encoded.to_vec(), hpack::encode(input));
+        assert_eq!(encoded.len(), hpack::encoded_len(input));
+        assert_eq!(Ok(input.to_vec()), hpack::decode(encoded));
+    }
+
+    let tree = Huffman::hpack();
+    assert!(tree.validate().is_ok());
+    let every_byte: Vec<u8> = (0..=u8::MAX).collect();
+    assert_eq!(
+        Ok(every_byte.clone()),
+        hpack::decode(&hpack::encode(&every_byte))
+    );
+
+    // "a" is 00011, so must be padded with 111
+    assert_eq!(Ok(b"a".to_vec()), hpack::decode(&[0x1f]));
+    assert_eq!(Err(DecodeError::DanglingBits), hpack::decode(&[0x18]));
+    // Padding of a byte or more
+    assert_eq!(Err(DecodeError::DanglingBits), hpack::decode(&[0x1f, 0xff]));
+    // The end of string symbol, padded with 11
+    assert_eq!(Err(DecodeError::EndOfString), hpack::decode(&[0xff; 4]));
+}
+
+#[test]
+fn jpeg_tables() {
+    use huffman_comprs::jpeg::{DhtError, DhtTable, TableClass};
+
+    let code = |tree: &Huffman<u8>, symbol: u8| tree.get_code(symbol).unwrap().to_string();
+
+    // The start of the luminance AC table suggested by the JPEG standard,
+    // whose symbols aren't in order of their value
+    let mut counts = [0; 16];
+    counts[1] = 2;
+    counts[2] = 1;
+    counts[3] = 3;
+    let values = vec![0x01, 0x02, 0x03, 0x00, 0x04, 0x11];
+    let tree = Huffman::from_jpeg_table(&counts, &values).unwrap();
+    assert!(tree.validate().is_ok());
+    assert_eq!("100", code(&tree, 0x03));
+    assert_eq!("1010", code(&tree, 0x00));
+    assert_eq!("1100", code(&tree, 0x11));
+    assert_eq!(Ok((counts, values.clone())), tree.to_jpeg_table());
+
+    let tables = vec![
+        DhtTable::from_huffman(TableClass::Ac, 1, &tree).unwrap(),
+        DhtTable::from_huffman(TableClass::Dc, 0, &Huffman::from(&b"aab".to_vec())).unwrap(),
+    ];
+    let mut segment = Vec::new();
+    DhtTable::write_segment(&tables, &mut segment).unwrap();
+    assert_eq!(Ok(tables), DhtTable::parse_segment(&segment));
+
+    assert_eq!(
+        Err(DhtError::Truncated),
+        DhtTable::parse_segment(&segment[..20])
+    );
+    segment[4] = 0x24;
+    assert_eq!(
+        Err(DhtError::BadTable(0x24)),
+        DhtTable::parse_segment(&segment)
+    );
+    segment[1] = 0xc0;
+    assert_eq!(Err(DhtError::BadMarker), DhtTable::parse_segment(&segment));
+
+    // Codes are limited to 16 bits, and the code of all ones is left unused
+    let mut fibonacci = vec![1, 1];
+    while fibonacci.len() < 24 {
+        fibonacci.push(fibonacci[fibonacci.len() - 1] + fibonacci[fibonacci.len() - 2]);
+    }
+    let input: Vec<u8> = (0..24_u8)
+        .zip(fibonacci)
+        .flat_map(|(byte, freq)| vec![byte; freq])
+        .collect();
+    let deep = Huffman::from(&input);
+    assert!(deep.code_lengths().iter().any(|(_, len)| *len > 16));
+    let (counts, values) = deep.to_jpeg_table().unwrap();
+    assert_eq!(24, values.len());
+    let limited = Huffman::from_jpeg_table(&counts, &values).unwrap();
+    assert!(limited.compress(&input).is_some());
+    assert!(limited.code_lengths().iter().all(|(_, len)| *len <= 16));
+    // Filled by a byte missing from the table
+    assert_eq!(Ok((counts, values)), limited.to_jpeg_table());
+
+    let mut counts = [0; 16];
+    counts[0] = 2;
+    assert_eq!(
+        Err(TreeError::WrongSymbolCount(2)),
+        Huffman::from_jpeg_table(&counts, &[0])
+    );
+    assert_eq!(
+        Err(TreeError::DuplicateSymbol(1)),
+        Huffman::from_jpeg_table(&counts, &[0, 0])
+    );
+    counts[0] = 3;
+    assert_eq!(
+        Err(TreeError::KraftViolation),
+        Huffman::from_jpeg_table(&counts, &[0, 1, 2])
+    );
+}
+
+#[cfg(all(feature = "json", feature = "cbor", feature = "msgpack"))]
+#[test]
+fn interchange_formats() {
+    let input = b"Codebooks for other languages".to_vec();
+    let tree = Huffman::from(&input);
+    let codes =
+        |tree: &Huffman<u8>| -> Vec<_> { input.iter().map(|b| tree.get_code(*b)).collect() };
+
+    let json: Huffman<u8> = Huffman::from_json(&tree.to_json().unwrap()).unwrap();
+    let cbor: Huffman<u8> = Huffman::from_cbor(&tree.to_cbor().unwrap()).unwrap();
+    let msgpack: Huffman<u8> = Huffman::from_msgpack(&tree.to_msgpack().unwrap()).unwrap();
+
+    for read in &[json, cbor, msgpack] {
+        assert!(read.validate().is_ok());
+        assert_eq!(codes(&tree), codes(read));
+    }
+}
+
+#[test]
+fn from_code_lengths() {
+    // The fixed literal/length code of DEFLATE, from RFC 1951
+    let lengths: Vec<(u16, u8)> = (0..288_u16)
+        .map(|symbol| match symbol {
+            0..=143 => (symbol, 8),
+            144..=255 => (symbol, 9),
+            256..=279 => (symbol, 7),
+            _ => (symbol, 8),
+        })
+        .collect();
+    let huffman = Huffman::from_code_lengths(&lengths).unwrap();
+    assert!(huffman.validate().is_ok());
+
+    let code = |bits: &str| -> Code { bits.chars().map(|c| c == '1').collect() };
+    assert_eq!(Some(code("00110000")), huffman.get_code(0));
+    assert_eq!(Some(code("110010000")), huffman.get_code(144));
+    assert_eq!(Some(code("0000000")), huffman.get_code(256));
+    assert_eq!(Some(code("11000111")), huffman.get_code(287));
+
+    // Unused symbols are left out
+    let huffman = Huffman::from_code_lengths(&[('a', 1), ('b', 0), ('c', 1)]).unwrap();
+    assert!(huffman.get_code('b').is_none());
+
+    assert_eq!(
+        Err(TreeError::KraftViolation),
+        Huffman::from_code_lengths(&[('a', 1), ('b', 1), ('c', 1)]).map(|_| ())
+    );
+    assert_eq!(
+        Err(TreeError::IncompleteCode),
+        Huffman::from_code_lengths(&[('a', 1), ('b', 2)]).map(|_| ())
+    );
+    assert_eq!(
+        Err(TreeError::DuplicateSymbol(1)),
+        Huffman::from_code_lengths(&[('a', 1), ('a', 1)]).map(|_| ())
+    );
+    assert_eq!(
+        Err(TreeError::CodeTooLong(0)),
+        Huffman::from_code_lengths(&[('a', 200)]).map(|_| ())
+    );
+}
+
+#[test]
+fn code_lengths_round_trip() {
+    let input: Vec<char> = "lengths are all another decoder needs".chars().collect();
+
+    let huffman = HuffmanBuilder::new().canonical(true).build(&input);
+    let lengths = huffman.code_lengths();
+    assert_eq!(huffman.contents().len(), lengths.len());
+    assert!(lengths.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+    let rebuilt = Huffman::from_code_lengths(&lengths).unwrap();
+    assert_eq!(huffman.to_btree(), rebuilt.to_btree());
+
+    // Any tree's lengths give a tree with codes of the same lengths
+    let huffman = Huffman::from(&input);
+    let rebuilt = Huffman::from_code_lengths(&huffman.code_lengths()).unwrap();
+    assert_eq!(huffman.code_lengths(), rebuilt.code_lengths());
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_adapters() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let input: Vec<u8> = (0..5000_u32)
+        .map(|i| b"websocket frame"[(i % 15) as usize])
+        .collect();
+    let tree = Huffman::from(&input);
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let output = runtime.block_on(async {
+        // A tiny pipe, so both sides often have to wait for the other
+        let (client, server) = tokio::io::duplex(16);
+
+        let write = async {
+            let mut writer = AsyncHuffmanWriter::new(client, &tree);
+            for chunk in input.chunks(7) {
+                writer.write_all(chunk).await.unwrap();
+            }
+            writer.shutdown().await.unwrap();
+        };
+        let read = async {
+            let mut reader = AsyncHuffmanReader::new(server, &tree);
+            let mut output = Vec::new();
+            let mut buf = [0; 5];
+            loop {
+                match reader.read(&mut buf).await.unwrap() {
+                    0 => break output,
+                    read => output.extend_from_slice(&buf[..read]),
+                }
+            }
+        };
+
+        tokio::join!(write, read).1
+    });
+    assert_eq!(input, output);
+
+    runtime.block_on(async {
+        let mut writer = AsyncHuffmanWriter::new(Vec::new(), &tree);
+        assert!(writer.write_all(b"?").await.is_err());
+
+        // Cut off before the padding byte
+        let mut reader = AsyncHuffmanReader::new(&[][..], &tree);
+        assert!(reader.read_to_end(&mut Vec::new()).await.is_err());
+    });
+}
+
+#[test]
+fn compression_stats() {
+    let input: Vec<char> = "a fairly ordinary sentence, to be measured"
+        .chars()
+        .collect();
+    let huffman = Huffman::from(&input);
+
+    let (data, stats) = huffman.compress_with_stats(&input).unwrap();
+    assert_eq!(huffman.compress(&input).unwrap(), data);
+    assert_eq!(input.len(), stats.input_symbols);
+    assert_eq!(data.len(), stats.output_bits);
+    // Each `char` takes 32 bits in memory
+    assert_eq!(data.len() as f64 / (input.len() * 32) as f64, stats.ratio);
+
+    let (_, empty) = huffman.compress_with_stats(&[]).unwrap();
+    assert_eq!(0, empty.output_bits);
+    assert_eq!(0.0, empty.ratio);
+
+    assert!(huffman.compress_with_stats(&['?']).is_none());
+}
+
+#[test]
+fn reusable_encoder_and_decoder() {
+    // Enough symbols for some codes to be longer than a byte
+    let input: Vec<u16> = (0..2000_u16).map(|i| i % 300 + i % 7 * 300).collect();
+    let tree = Huffman::from(&input);
+    let encoder = Encoder::new(&tree);
+    let decoder = Decoder::new(&tree);
+    assert!(tree.code_lengths().iter().any(|(_, len)| *len > 8));
+
+    for message in input.chunks(333) {
+        assert_eq!(tree.compress(message), encoder.encode(message));
+
+        let mut bytes = Vec::new();
+        let zeros = encoder.encode_into_bytes(message, &mut bytes).unwrap();
+        assert_eq!(Ok(message.to_vec()), decoder.decode(&bytes, zeros));
+    }
+    assert!(encoder.encode(&[5000]).is_none());
+
+    let mut bytes = Vec::new();
+    let zeros = encoder.encode_into_bytes(&input, &mut bytes).unwrap();
+    assert_eq!(
+        Err(DecodeError::OutputLimitExceeded(10)),
+        decoder.decode_with(&bytes, zeros, &DecodeOptions::new().max_output_len(10))
+    );
+    assert_eq!(
+        Err(DecodeError::UnexpectedEof),
+        decoder.decode(&bytes[..bytes.len() - 1], 1)
+    );
+    assert_eq!(Err(DecodeError::DanglingBits), decoder.decode(&[0xff], 1));
+    assert_eq!(Ok(Vec::new()), decoder.decode(&[], 0));
+
+    let lone = Decoder::new(&Huffman::from(&vec![1_u8]));
+    assert_eq!(Err(DecodeError::DanglingBits), lone.decode(&[0], 0));
+}
+
+#[test]
+fn decoder_fed_in_pieces() {
+    let input: Vec<u16> = (0..3000_u16).map(|i| i % 400 + i % 5 * 400).collect();
+    let tree = Huffman::from(&input);
+    let mut bytes = Vec::new();
+    let zeros = Encoder::new(&tree)
+        .encode_into_bytes(&input, &mut bytes)
+        .unwrap();
+
+    let mut decoder = Decoder::new(&tree);
+    for piece_len in [1, 2, 7, 100, bytes.len()] {
+        let mut output = Vec::new();
+        for piece in bytes.chunks(piece_len) {
+            output.extend(decoder.feed(piece));
+            output.extend(decoder.feed(&[]));
+        }
+        output.extend(decoder.finish(zeros).unwrap());
+        assert_eq!(input, output);
+    }
+
+    // Ending part way through a code fails, but leaves the decoder reusable
+    decoder.feed(&bytes[..bytes.len() / 2]);
+    assert_eq!(Err(DecodeError::UnexpectedEof), decoder.finish(0));
+    let mut output = decoder.feed(&bytes);
+    output.extend(decoder.finish(zeros).unwrap());
+    assert_eq!(input, output);
+
+    assert_eq!(Err(DecodeError::DanglingBits), decoder.finish(1));
+    assert_eq!(Ok(Vec::new()), decoder.finish(0));
+
+    let mut lone = Decoder::new(&Huffman::from(&vec![1_u8]));
+    assert!(lone.feed(&[0, 0]).is_empty());
+    assert_eq!(Err(DecodeError::DanglingBits), lone.finish(0));
+}
+
+#[test]
+fn bit_reader_and_writer() {
+    let bits: Vec<bool> = (0..21).map(|i| i % 3 == 0).collect();
+
+    for order in [bits::BitOrder::MsbFirst, bits::BitOrder::LsbFirst] {
+        let mut writer = bits::BitWriter::with_order(order);
+        writer.write_bits(&bits);
+        assert_eq!(21, writer.len());
+        let (bytes, zeros) = writer.finish();
+        assert_eq!((3, 3), (bytes.len(), zeros));
+
+        let reader = bits::BitReader::with_order(&bytes, order).with_zeros(zeros);
+        assert_eq!(21, reader.len());
+        assert_eq!(bits, reader.collect::<Vec<_>>());
+    }
+
+    let mut writer = bits::BitWriter::new();
+    writer.write_bit(true);
+    assert_eq!((vec![0x80], 7), writer.finish());
+    let mut writer = bits::BitWriter::with_order(bits::BitOrder::LsbFirst);
+    writer.write_bit(true);
+    assert_eq!((vec![0x01], 7), writer.finish());
+    assert_eq!((Vec::new(), 0), bits::BitWriter::new().finish());
+}
+
+#[test]
+fn bulk_bit_conversions() {
+    let bytes = [0x01, 0x80, 0xA5];
+    let bits = bytes_to_bits(&bytes);
+    assert_eq!(
+        bytes
+            .iter()
+            .flat_map(|byte| u8_to_bits(*byte))
+            .collect::<Vec<_>>(),
+        bits
+    );
+    assert_eq!((bytes.to_vec(), 0), bits_to_bytes(&bits));
+
+    assert_eq!((vec![0b101], 5), bits_to_bytes(&[true, false, true]));
+    assert_eq!((Vec::new(), 0), bits_to_bytes(&[]));
+}
+
+#[test]
+fn packed_codes() {
+    let input = "Codes packed into bytes".chars().collect::<Vec<_>>();
+    let huffman = Huffman::from(&input);
+
+    let mut code = huffman.compress(&input).unwrap();
+    let bits = code.to_vec();
+    assert_eq!(bits.len(), code.len());
+    assert_eq!(bits.len().div_ceil(8), code.as_bytes().len());
+    assert_eq!(Code::from(bits.clone()), code);
+
+    code.reverse();
+    assert_eq!(Some(input.clone()), huffman.reconstruct(code, 0));
+
+    let mut bits = bits;
+    bits.reverse();
+    assert_eq!(Some(input), huffman.reconstruct(bits, 0));
+
+    let mut code = Code::from(vec![true, false, true]);
+    assert_eq!(5, code.zeros());
+    assert_eq!([0b1010_0000], code.as_bytes());
+    assert_eq!(Some(true), code.pop());
+    assert_eq!(Code::from(vec![true, false]), code);
+    assert_eq!(None, code.get(2));
+}
+
+#[cfg(feature = "rz")]
+#[test]
+fn structural_equality() {
+    use std::collections::HashSet;
+
+    // Both have a total frequency of 4, but different symbols
+    let a = Huffman::from("aabc");
+    let b = Huffman::from("xxyz");
+    assert_ne!(a, b);
+    assert_ne!(a.fingerprint(), b.fingerprint());
+
+    assert_eq!(a, Huffman::from("aabc"));
+    assert_eq!(a.fingerprint(), Huffman::from("abca").fingerprint());
+
+    let trees: HashSet<_> = vec![a.clone(), b, a].into_iter().collect();
+    assert_eq!(2, trees.len());
+}
+
+#[test]
+fn symbol_frequencies() {
+    let input = b"mississippi".to_vec();
+    let huffman = Huffman::from(&input);
+
+    let frequencies: std::collections::BTreeMap<_, _> = huffman.frequencies().collect();
+    assert_eq!(Some(&4), frequencies.get(&b's'));
+    assert_eq!(Some(&1), frequencies.get(&b'm'));
+    assert_eq!(4, frequencies.len());
+    assert_eq!(huffman.freq(), frequencies.values().sum::<usize>());
+
+    assert_eq!(0, Huffman::<u8>::default().frequencies().count());
+}
+
+#[test]
+fn trees_from_weights() {
+    let huffman = Huffman::from_weights(vec![(1_u8, 0.1), (2, 0.6), (3, 0.3), (4, 0.0)]).unwrap();
+    assert!(huffman.validate().is_ok());
+    assert_eq!(1, huffman.get_code(2).unwrap().len());
+    assert!(huffman.get_code(4).is_some());
+
+    // Only relative weights matter
+    let scaled = Huffman::from_weights(vec![(1_u8, 1e300), (2, 6e300), (3, 3e300), (4, 0.0)]);
+    assert_eq!(huffman.to_btree(), scaled.unwrap().to_btree());
+
+    assert_eq!(
+        Err(TreeError::InvalidWeight(1)),
+        Huffman::from_weights(vec![('a', 1.0), ('b', -1.0)])
+    );
+    assert_eq!(
+        Err(TreeError::InvalidWeight(0)),
+        Huffman::from_weights(vec![('a', f64::NAN)])
+    );
+    assert!(Huffman::from_weights(vec![('a', 0.0), ('b', 0.0)]).is_ok());
+}
+
+#[cfg(feature = "unicode")]
+#[test]
+fn grapheme_symbols() {
+    let text = "family: 👨‍👩‍👧, flag: 🇳🇿, accent: q\u{303}, family again: 👨‍👩‍👧";
+    let tree = Huffman::from_graphemes(text);
+
+    assert!(tree.get_code("👨‍👩‍👧".to_string()).is_some());
+    assert!(tree.get_code("q\u{303}".to_string()).is_some());
+    assert!(tree.get_code("q".to_string()).is_none());
+
+    let mut data = tree.compress_graphemes(text).unwrap();
+    data.reverse();
+    assert_eq!(text, tree.reconstruct(data, 0).unwrap().concat());
+
+    assert!(tree.compress_graphemes("👋").is_none());
+}
+
+#[test]
+fn custom_tokenizers() {
+    let csv = "id,name\n1,ada\n2,alan\n3,ada\n";
+
+    let tree = Huffman::from_tokens(csv, &Delimited(','));
+    assert!(tree.get_code("ada\n2,".to_string()).is_some());
+    assert!(tree.get_code("ada\n3,".to_string()).is_none());
+    let mut data = tree.compress_tokens(csv, &Delimited(',')).unwrap();
+    data.reverse();
+    assert_eq!(csv, tree.reconstruct(data, 0).unwrap().concat());
+
+    let lines = Huffman::from_tokens(csv, &Lines);
+    assert_eq!(4, lines.contents().len());
+    assert!(lines.compress_tokens("3,ada\n1,ada\n", &Lines).is_some());
+    assert!(lines.compress_tokens("4,ada\n", &Lines).is_none());
+
+    let chars = Huffman::from_tokens(csv, &Chars);
+    assert_eq!(
+        chars.compress_str(csv),
+        Encoder::new(&chars).encode_tokens(csv, &Chars)
+    );
+}
+
+#[test]
+fn trees_from_samples() {
+    use std::io::{Cursor, Seek, SeekFrom};
+
+    // The first half is all 'a', the second all 'b', so a sample from only
+    // the start would miss 'b'
+    let mut input = vec![b'a'; 500_000];
+    input.extend(vec![b'b'; 500_000]);
+    input.push(b'c');
+
+    let mut reader = Cursor::new(&input);
+    reader.seek(SeekFrom::Start(10)).unwrap();
+    let tree = Huffman::from_sample(&mut reader, 256 * 1024).unwrap();
+    assert_eq!(10, reader.position());
+
+    assert!(tree.get_code(b'a').unwrap().len() <= 2);
+    assert!(tree.get_code(b'b').unwrap().len() <= 2);
+    assert_eq!(256, tree.contents().len());
+    assert!(tree.compress(&(0..=u8::MAX).collect::<Vec<_>>()).is_some());
+
+    // Samples larger than the input read all of it
+    let tree = Huffman::from_sample(Cursor::new(b"aab"), 1000).unwrap();
+    let frequencies: std::collections::BTreeMap<_, _> = tree.frequencies().collect();
+    assert_eq!(Some(&3), frequencies.get(&b'a'));
+    assert_eq!(Some(&1), frequencies.get(&b'z'));
+}
+
+#[test]
+fn escaped_literals() {
+    let training = b"only lowercase letters and spaces".to_vec();
+    let tree = HuffmanBuilder::new().escape(0_u8).build(&training);
+
+    let input = b"NEW bytes, \0 and \xff".to_vec();
+    assert!(tree.compress(&input).is_none());
+    let mut reversed = tree.compress_escaped(&input, &0).unwrap();
+    reversed.reverse();
+    assert_eq!(Ok(input.clone()), tree.reconstruct_escaped(reversed, 0, &0));
+
+    // Symbols with codes cost no more than usual
+    let known = b"letters".to_vec();
+    assert_eq!(tree.compress(&known), tree.compress_escaped(&known, &0));
+
+    // An escape needs to be in the tree
+    assert!(Huffman::from(&training)
+        .compress_escaped(&input, &0)
+        .is_none());
+
+    // Surrogates aren't chars
+    let tree = HuffmanBuilder::new().escape('\0').build(&['a', 'b']);
+    let mut data = tree.get_code('\0').unwrap();
+    data.extend((0..21).rev().map(|i| 0xD800 >> i & 1 == 1));
+    data.reverse();
+    assert_eq!(
+        Err(DecodeError::InvalidLiteral),
+        tree.reconstruct_escaped(data, 0, &'\0')
+    );
+}
+
+#[test]
+fn terminated_streams() {
+    let input = b"ends with its own marker".to_vec();
+    let tree = HuffmanBuilder::new().end_of_stream(0_u8).build(&input);
+
+    let bytes = tree.compress_terminated(&input, &0).unwrap();
+    assert_eq!(
+        Ok((input.clone(), bytes.len())),
+        tree.decompress_terminated(&bytes, &0)
+    );
+
+    // Streams can be read back to back, without their lengths
+    let mut both = bytes.clone();
+    both.extend(tree.compress_terminated(b"marker", &0).unwrap());
+    let (first, used) = tree.decompress_terminated(&both, &0).unwrap();
+    assert_eq!(input, first);
+    assert_eq!(
+        Ok((b"marker".to_vec(), both.len() - used)),
+        tree.decompress_terminated(&both[used..], &0)
+    );
+
+    // Only the end of stream symbol ends the stream
+    assert!(tree.compress_terminated(b"\0", &0).is_none());
+    assert_eq!(
+        Err(DecodeError::UnexpectedEof),
+        tree.decompress_terminated(&bytes[..bytes.len() - 1], &0)
+    );
+    assert_eq!(
+        Err(DecodeError::NoEndOfStream),
+        Huffman::from(&input).decompress_terminated(&bytes, &0)
+    );
+
+    // Nothing to write but the end of the stream
+    let tree = HuffmanBuilder::new().end_of_stream(0_u8).build(&[]);
+    assert_eq!(Some(Vec::new()), tree.compress_terminated(&[], &0));
+    assert_eq!(Ok((Vec::new(), 0)), tree.decompress_terminated(&[], &0));
+}
+
+#[test]
+fn golomb_codes() {
+    use huffman_comprs::{
+        bits::{BitReader, BitWriter},
+        golomb::{self, Golomb},
+    };
+
+    let values: Vec<u64> = (0..500).map(|i| (i * 37) % 101).collect();
+    for divisor in [1, 2, 3, 5, 16, 100, 1 << 40] {
+        let code = Golomb::new(divisor).unwrap();
+        let bytes = code.encode(&values);
+        assert_eq!(
+            code.encoded_len(&values).div_ceil(8),
+            bytes.len() as u64,
+            "divisor {}",
+            divisor
+        );
+        assert_eq!(Ok(values.clone()), code.decode(&bytes, values.len()));
+    }
+
+    // Truncated binary gives the first remainders a bit less
+    let code = Golomb::new(3).unwrap();
+    assert_eq!(vec![2, 3, 3, 3], [0, 1, 2, 3].map(|v| code.code_len(v)));
+    assert_eq!(None, Golomb::new(0));
+    assert_eq!(None, Golomb::rice(64));
+
+    // Mixed with other bits, and with the largest values
+    let huge = Golomb::new(u64::MAX).unwrap();
+    let mut writer = BitWriter::new();
+    huge.write(&mut writer, u64::MAX);
+    writer.write_bit(true);
+    huge.write(&mut writer, 12345);
+    let (bytes, zeros) = writer.finish();
+    let mut reader = BitReader::new(&bytes).with_zeros(zeros);
+    assert_eq!(Ok(u64::MAX), huge.read(&mut reader));
+    assert_eq!(Some(true), reader.read_bit());
+    assert_eq!(Ok(12345), huge.read(&mut reader));
+    assert_eq!(0, reader.remaining());
+
+    let code = Golomb::rice(2).unwrap();
+    assert_eq!(Err(DecodeError::UnexpectedEof), code.decode(&[0xff], 1));
+    assert_eq!(
+        Err(DecodeError::ValueOverflow),
+        Golomb::rice(62)
+            .unwrap()
+            .decode(&[0xf0, 0, 0, 0, 0, 0, 0, 0, 0], 1)
+    );
+
+    for value in [0, 1, -1, 1000, -1000, i64::MAX, i64::MIN] {
+        assert_eq!(value, golomb::unzigzag(golomb::zigzag(value)));
+    }
+    assert_eq!([0, 1, 2, 3, 4], [0, -1, 1, -2, 2].map(golomb::zigzag));
+}
+
+#[test]
+fn elias_codes() {
+    use huffman_comprs::{
+        bits::{BitReader, BitWriter},
+        elias::Elias,
+    };
+
+    let values: Vec<u64> = (0..64)
+        .flat_map(|shift| [(1 << shift) - 1, 1 << shift, (1 << shift) + 1])
+        .chain([u64::MAX - 1, u64::MAX])
+        .collect();
+    for code in [Elias::Gamma, Elias::Delta] {
+        let bytes = code.encode(&values);
+        assert_eq!(code.encoded_len(&values).div_ceil(8), bytes.len() as u64);
+        assert_eq!(Ok(values.clone()), code.decode(&bytes, values.len()));
+    }
+
+    // From the tables of codes for 1 to 4, each coding one less
+    let codes: Vec<String> = (0..4)
+        .map(|value| {
+            let mut writer = BitWriter::new();
+            Elias::Delta.write(&mut writer, value);
+            let (bytes, zeros) = writer.finish();
+            BitReader::new(&bytes)
+                .with_zeros(zeros)
+                .map(|bit| if bit { '1' } else { '0' })
+                .collect()
+        })
+        .collect();
+    assert_eq!(vec!["1", "0100", "0101", "01100"], codes);
+    assert_eq!([1, 3, 3, 5], [0, 1, 2, 3].map(|v| Elias::Gamma.code_len(v)));
+
+    assert_eq!(
+        Err(DecodeError::UnexpectedEof),
+        Elias::Gamma.decode(&[0], 1)
+    );
+    // 65 zeros, then a 1, for a number of 66 bits
+    let mut too_long = vec![0; 8];
+    too_long.extend([0x40, 0, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(
+        Err(DecodeError::ValueOverflow),
+        Elias::Gamma.decode(&too_long, 1)
+    );
+    // A number of 65 bits, above `u64::MAX + 1`
+    let mut too_large = vec![0; 8];
+    too_large.extend([0xff; 9]);
+    assert_eq!(
+        Err(DecodeError::ValueOverflow),
+        Elias::Gamma.decode(&too_large, 1)
+    );
+}
+
+#[test]
+fn shannon_fano_against_huffman() {
+    let script = "Shannon-Fano codes are built from the top down, splitting symbols in half";
+    let input: Vec<char> = script.chars().collect();
+    let shannon_fano = ShannonFano::from(script);
+    let huffman = Huffman::from(script);
+
+    let mut bytes = Vec::new();
+    let zeros = shannon_fano
+        .compress_into_bytes(&input, &mut bytes)
+        .unwrap();
+    assert_eq!(
+        Ok(input.clone()),
+        Decoder::new(&shannon_fano).decode(&bytes, zeros)
+    );
+    assert!(
+        huffman.compress(&input).unwrap().len() <= shannon_fano.compress(&input).unwrap().len()
+    );
+
+    // Where the most frequent symbol takes less than half, Shannon-Fano pairs
+    // it with another, which Huffman coding doesn't
+    let counts = vec![('a', 35), ('b', 17), ('c', 17), ('d', 16), ('e', 15)];
+    let shannon_fano = ShannonFano::from_frequencies(counts.clone());
+    let huffman: Huffman<char> = counts.into_iter().collect();
+    let bits = |tree: &Huffman<char>| -> usize {
+        tree.frequencies()
+            .map(|(c, freq)| freq * tree.get_code(*c).unwrap().len())
+            .sum()
+    };
+    assert_eq!(231, bits(&shannon_fano));
+    assert_eq!(230, bits(&huffman));
+
+    // Equally frequent symbols keep their order
+    let codes: Vec<String> = ['x', 'y', 'z']
+        .iter()
+        .map(|c| ShannonFano::from("xyz").get_code(*c).unwrap().to_string())
+        .collect();
+    assert_eq!(vec!["0", "10", "11"], codes);
+    assert!(ShannonFano::<u8>::from(Vec::new()).contents().is_empty());
+}
+
+#[test]
+fn walk_visits_every_node() {
+    let script = "A tree walked from the root down";
+    let huffman = Huffman::from(script);
+
+    let mut leaves = std::collections::BTreeMap::new();
+    let mut nodes = 0;
+    huffman.walk(|node| {
+        nodes += 1;
+        assert_eq!(node.depth, node.path.len());
+        if let Some(symbol) = node.symbol {
+            leaves.insert(*symbol, node.path.to_vec());
+        }
+        if node.depth == 0 {
+            assert_eq!(script.len(), node.freq);
+        }
+    });
+
+    assert_eq!(huffman.to_btree(), leaves);
+    assert_eq!(leaves.len() * 2 - 1, nodes);
+
+    let mut visited = false;
+    Huffman::<u8>::from(&Vec::new()).walk(|_| visited = true);
+    assert!(!visited);
+}
+
+#[test]
+fn iter_nodes_exposes_structure() {
+    let huffman = Huffman::from("A tree inspected node by node");
+
+    let nodes: Vec<_> = huffman.iter_nodes().collect();
+    assert_eq!(huffman.contents().len() * 2 - 1, nodes.len());
+
+    for node in &nodes {
+        match node.children() {
+            Some([left, right]) => {
+                assert!(!node.is_leaf() && node.contents().is_none());
+                assert!(left.index() < node.index() && right.index() < node.index());
+                assert_eq!(node.freq(), left.freq() + right.freq());
+            }
+            None => assert_eq!(Some(&huffman.contents()[node.index()]), node.contents()),
+        }
+    }
+
+    let root = huffman.root_node().unwrap();
+    assert_eq!(nodes.last().unwrap().index(), root.index());
+    assert_eq!(huffman.freq(), root.freq());
+    assert!(Huffman::<u8>::from(&Vec::new()).root_node().is_none());
+}
@@ -1,6 +1,17 @@
-use std::convert::TryFrom;
+use std::{
+    convert::TryFrom,
+    io::{Read, Seek, SeekFrom},
+};
 
-use huffman_comprs::{Huffman, RZFile};
+use huffman_comprs::{
+    analysis::{self, CodecChoice},
+    bits::{BitOrder, BitReader},
+    copy_decode, copy_decode_with_progress, copy_encode, copy_encode_retraining,
+    copy_encode_retraining_with_progress, copy_encode_with_progress, from_compressed_bytes,
+    to_compressed_bytes, ArchiveBuilder, BlockCoder, Cancel, Code, DecodeError, DecodeOptions,
+    EntropyCoder, Extension, Huffman, Metadata, MultiHuffman, Pipeline, Progress, RZArchive,
+    RZFile, Retrain, RzError, RzReader, ShannonFano, SplitError, TreeFormat, RZ_MAGIC, RZ_VERSION,
+};
 
 #[test]
 fn from_nonsense() {
@@ -40,3 +51,1917 @@ fn from_nonsense() {
 
     assert_eq!(new_input.as_str(), input);
 }
+
+#[test]
+fn bit_reader_reads_file_data() {
+    let input = "abcdefg";
+    let tree = Huffman::from(input);
+    let file = RZFile::new(tree.clone(), tree.compress_str(input).unwrap());
+
+    let mut data = Vec::new();
+    for a in file.data() {
+        data.append(&mut huffman_comprs::u8_to_bits(*a));
+    }
+    let read: Vec<bool> = BitReader::with_order(file.data(), BitOrder::LsbFirst).collect();
+    assert_eq!(data, read);
+
+    let new_input: String = tree
+        .reconstruct(read, file.zeros())
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(input, new_input);
+}
+
+#[test]
+fn bytes_to_bits_reads_file_data() {
+    let input = "abcdefg";
+    let tree = Huffman::from(input);
+    let file = RZFile::new(tree.clone(), tree.compress_str(input).unwrap());
+
+    let mut data = Vec::new();
+    for a in file.data() {
+        data.append(&mut huffman_comprs::u8_to_bits(*a));
+    }
+    let bits = huffman_comprs::bytes_to_bits(file.data());
+    assert_eq!(data, bits);
+    assert_eq!(
+        (file.data().to_vec(), 0),
+        huffman_comprs::bits_to_bytes(&bits)
+    );
+
+    let new_input: String = tree
+        .reconstruct(bits, file.zeros())
+        .unwrap()
+        .into_iter()
+        .collect();
+    assert_eq!(input, new_input);
+}
+
+#[test]
+fn borrows_data_section() {
+    let input = "The data section shouldn't be copied out of the buffer";
+
+    let tree = Huffman::from(input);
+    let data = tree.compress_str(input).unwrap();
+
+    let path = "borrowed.rz";
+
+    RZFile::new(tree, data).save_to_file(path).unwrap();
+    let buf = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let file: RZFile<char> = RZFile::try_from(buf.as_slice()).unwrap();
+
+    let range = buf.as_ptr_range();
+    assert!(range.contains(&file.data().as_ptr()));
+    assert_eq!(file.clone().into_owned().data(), file.data());
+}
+
+#[test]
+fn verified_compression() {
+    let input: Vec<u8> = b"Verified data decompresses back to itself".to_vec();
+
+    let tree = Huffman::from(&input);
+    let data = tree.compress_verified(&input).unwrap();
+
+    assert_eq!(Some(data.clone()), tree.compress(&input));
+    assert_eq!(Some(input), RZFile::new(tree, data).decompress());
+}
+
+#[test]
+fn decompress_with_output_limit() {
+    let input = vec![0_u8; 4096];
+
+    let tree = Huffman::from(&[input.clone(), vec![1]].concat());
+    let file = RZFile::new(tree.clone(), tree.compress(&input).unwrap());
+
+    let options = DecodeOptions::new().max_output_len(1024);
+    assert_eq!(
+        Err(DecodeError::OutputLimitExceeded(1024)),
+        file.decompress_with(&options)
+    );
+
+    let options = DecodeOptions::new().max_output_len(4096);
+    assert_eq!(Ok(input), file.decompress_with(&options));
+}
+
+#[test]
+fn decompress_with_inflated_len() {
+    let input = b"The length is read from the header".repeat(4);
+    let mut buf = Vec::new();
+    RZFile::compress(Huffman::from(&input), &input)
+        .unwrap()
+        .write_to(&mut buf)
+        .unwrap();
+
+    // Without a checksum, the length follows the first 8 bytes after the
+    // magic number
+    let len = RZ_MAGIC.len() + 8;
+    buf[len..len + 8].copy_from_slice(&(1_u64 << 40).to_be_bytes());
+    let file = RZFile::<u8>::read_from(buf.as_slice()).unwrap();
+    assert_eq!(
+        Err(DecodeError::UnexpectedEof),
+        file.decompress_with(&DecodeOptions::new())
+    );
+}
+
+#[test]
+fn parse_with_limits() {
+    // Doubling frequencies make a tree as deep as it has symbols, less one
+    let input: Vec<u8> = (0..12_u8)
+        .flat_map(|i| std::iter::repeat_n(i, 1 << i))
+        .collect();
+
+    let mut buf = Vec::new();
+    RZFile::compress(Huffman::from(&input), &input)
+        .unwrap()
+        .write_to(&mut buf)
+        .unwrap();
+
+    let parse = |options: &DecodeOptions| RZFile::<u8>::parse_with_options(&buf, options);
+    let read = |options: &DecodeOptions| RZFile::<u8>::read_from_with_options(&buf[..], options);
+
+    let options = DecodeOptions::new().max_tree_bytes(8);
+    assert!(matches!(parse(&options), Err(RzError::TreeTooLarge(8))));
+    assert!(matches!(read(&options), Err(RzError::TreeTooLarge(8))));
+
+    let options = DecodeOptions::new().max_tree_depth(8);
+    assert!(matches!(parse(&options), Err(RzError::TreeTooDeep(8))));
+    assert!(matches!(read(&options), Err(RzError::TreeTooDeep(8))));
+
+    let options = DecodeOptions::new().max_output_len(100);
+    assert!(matches!(
+        parse(&options),
+        Err(RzError::Decode(DecodeError::OutputLimitExceeded(100)))
+    ));
+    assert!(matches!(
+        read(&options),
+        Err(RzError::Decode(DecodeError::OutputLimitExceeded(100)))
+    ));
+
+    let options = DecodeOptions::new()
+        .max_tree_bytes(buf.len())
+        .max_tree_depth(11)
+        .max_output_len(input.len());
+    assert_eq!(Some(input.clone()), parse(&options).unwrap().decompress());
+    assert_eq!(Some(input), read(&options).unwrap().decompress());
+}
+
+#[test]
+fn rejects_truncated_files() {
+    let input = "Every prefix of this file should fail to parse, not panic";
+    let path = "truncated.rz";
+
+    let tree = Huffman::from(input);
+    let data = tree.compress_str(input).unwrap();
+    RZFile::new(tree, data).save_to_file(path).unwrap();
+    let buf = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let tree_len = u32::from_be_bytes([buf[6], buf[7], buf[8], buf[9]]) as usize;
+    for len in 0..11 + tree_len {
+        assert!(RZFile::<char>::try_from(&buf[..len]).is_err());
+    }
+
+    let mut bad_padding = buf.clone();
+    bad_padding[10] = 8;
+    assert!(RZFile::<char>::try_from(bad_padding.as_slice()).is_err());
+
+    let mut bad_tree_len = buf.clone();
+    bad_tree_len[6] = 0xff;
+    assert!(RZFile::<char>::try_from(bad_tree_len.as_slice()).is_err());
+}
+
+#[test]
+fn rejects_unknown_formats() {
+    let input = "Only RZ files of a known version should be read";
+    let path = "versioned.rz";
+
+    let tree = Huffman::from(input);
+    let data = tree.compress_str(input).unwrap();
+    RZFile::new(tree, data).save_to_file(path).unwrap();
+    let buf = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(RZ_MAGIC, buf[0..4]);
+    assert_eq!(RZ_VERSION, buf[4]);
+
+    let mut bad_magic = buf.clone();
+    bad_magic[1] = b'Z';
+    assert!(RZFile::<char>::try_from(bad_magic.as_slice()).is_err());
+
+    let mut future_version = buf.clone();
+    future_version[4] = RZ_VERSION + 1;
+    assert!(RZFile::<char>::try_from(future_version.as_slice()).is_err());
+
+    let mut unknown_flags = buf;
+    unknown_flags[5] = 0x80;
+    assert!(RZFile::<char>::try_from(unknown_flags.as_slice()).is_err());
+}
+
+#[test]
+fn parse_errors_say_why() {
+    let input = b"Each way of being malformed has its own error. ".repeat(4);
+    let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let parse = |buf: &[u8]| RZFile::<u8>::try_from(buf).map(|_| ());
+
+    let mut bad_magic = buf.clone();
+    bad_magic[0] = 0;
+    assert!(matches!(parse(&bad_magic), Err(RzError::BadMagic)));
+
+    let mut future_version = buf.clone();
+    future_version[4] = RZ_VERSION + 1;
+    assert!(matches!(
+        parse(&future_version),
+        Err(RzError::UnsupportedVersion(v)) if v == RZ_VERSION + 1
+    ));
+
+    assert!(matches!(
+        parse(&buf[..5]),
+        Err(RzError::TruncatedHeader("header"))
+    ));
+    assert!(matches!(
+        parse(&buf[..11]),
+        Err(RzError::TruncatedHeader("extended flags"))
+    ));
+    assert!(matches!(
+        parse(&buf[..12]),
+        Err(RzError::TruncatedHeader("length"))
+    ));
+
+    let mut bad_padding = buf.clone();
+    bad_padding[10] = 8;
+    assert!(matches!(parse(&bad_padding), Err(RzError::BadPadding)));
+
+    let tree_len = u32::from_be_bytes([buf[6], buf[7], buf[8], buf[9]]) as usize;
+    let tree_start = buf.len() - file.data().len() - tree_len;
+    let mut bad_tree = buf.clone();
+    bad_tree[tree_start..tree_start + tree_len].fill(0xff);
+    assert!(matches!(parse(&bad_tree), Err(RzError::TreeDecode(_))));
+
+    // Decoding errors convert too, so both can be handled with `?`
+    let checked = file.with_checksum(b"a different input");
+    let error = RzError::from(checked.verify().unwrap_err());
+    assert!(matches!(error, RzError::ChecksumMismatch));
+    assert_eq!(
+        "the decoded data doesn't match its checksum",
+        error.to_string()
+    );
+}
+
+#[test]
+fn checksum_detects_corruption() {
+    let input = b"123456789".to_vec();
+    let path = "checksummed.rz";
+
+    let tree = Huffman::from(&input);
+    let data = tree.compress(&input).unwrap();
+    let file = RZFile::new(tree, data).with_checksum(&input);
+
+    // The standard CRC-32 check value
+    assert_eq!(Some(0xCBF4_3926), file.checksum());
+    assert_eq!(Ok(()), file.verify());
+
+    file.save_to_file(path).unwrap();
+    let mut buf = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(
+        Some(input),
+        RZFile::try_from(buf.as_slice()).unwrap().decompress()
+    );
+
+    // The checksum comes straight after the fixed size header and extended
+    // flags
+    buf[12] ^= 1;
+    let file: RZFile<u8> = RZFile::try_from(buf.as_slice()).unwrap();
+    assert_eq!(Err(DecodeError::ChecksumMismatch), file.verify());
+}
+
+#[test]
+fn write_to_any_writer() {
+    let input = "Written straight into a Vec, rather than a file";
+
+    let tree = Huffman::from(input);
+    let data = tree.compress_str(input).unwrap();
+    let file = RZFile::new(tree, data);
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+
+    let path = "written.rz";
+    file.save_to_file(path).unwrap();
+    assert_eq!(std::fs::read(path).unwrap(), buf);
+    std::fs::remove_file(path).unwrap();
+
+    let read: RZFile<char> = RZFile::try_from(buf.as_slice()).unwrap();
+    assert_eq!(
+        input,
+        read.decompress().unwrap().into_iter().collect::<String>()
+    );
+}
+
+#[test]
+fn read_from_any_reader() {
+    let input = b"Read piece by piece, from anything implementing Read".to_vec();
+
+    let tree = Huffman::from(&input);
+    let data = tree.compress(&input).unwrap();
+
+    let mut buf = Vec::new();
+    RZFile::new(tree, data)
+        .with_checksum(&input)
+        .write_to(&mut buf)
+        .unwrap();
+
+    let file: RZFile<u8> = RZFile::read_from(buf.as_slice()).unwrap();
+    assert_eq!(Some(input), file.decompress());
+
+    for len in 0..buf.len() - file.data().len() {
+        assert!(RZFile::<u8>::read_from(&buf[..len]).is_err());
+    }
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn open_memory_mapped() {
+    let input = b"Decoded straight out of a memory mapped file".to_vec();
+    let path = "mapped.rz";
+
+    let tree = Huffman::from(&input);
+    let data = tree.compress(&input).unwrap();
+    RZFile::new(tree, data)
+        .with_checksum(&input)
+        .save_to_file(path)
+        .unwrap();
+
+    let file: RZFile<u8> = RZFile::open_mmap(path).unwrap();
+    let decompressed = file.clone().into_owned().decompress();
+    drop(file);
+
+    // Extensions after the file are left out of the data
+    Extension::new(1, b"after".to_vec())
+        .append_to_file(path)
+        .unwrap();
+    let file: RZFile<u8> = RZFile::open_mmap(path).unwrap();
+    let extended = file.decompress();
+    drop(file);
+    std::fs::remove_file(path).unwrap();
+
+    assert_eq!(Some(input.clone()), decompressed);
+    assert_eq!(Some(input), extended);
+}
+
+#[test]
+fn blocked_range_decompression() {
+    let input: Vec<u8> = (0..1000_u32).map(|i| (i * 7 % 26) as u8 + b'a').collect();
+
+    let tree = Huffman::from(&input);
+    let file = RZFile::new_blocked(tree, &input, 64)
+        .unwrap()
+        .with_checksum(&input);
+
+    assert_eq!(Some(64), file.block_size());
+    assert_eq!(16, file.block_count());
+    assert_eq!(Ok(input[64..128].to_vec()), file.decompress_block(1));
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let read: RZFile<u8> = RZFile::try_from(buf.as_slice()).unwrap();
+    let streamed: RZFile<u8> = RZFile::read_from(buf.as_slice()).unwrap();
+
+    for file in &[read, streamed] {
+        assert_eq!(Some(input.clone()), file.decompress());
+        assert_eq!(
+            Ok(input[100..300].to_vec()),
+            file.decompress_range(100..300)
+        );
+        assert_eq!(Ok(input[990..].to_vec()), file.decompress_range(990..2000));
+        assert_eq!(Ok(Vec::new()), file.decompress_range(1500..2000));
+    }
+
+    let options = DecodeOptions::new().max_output_len(100);
+    assert_eq!(
+        Err(DecodeError::OutputLimitExceeded(100)),
+        file.decompress_with(&options)
+    );
+
+    // Cutting off the last byte leaves the index pointing past the data
+    assert!(RZFile::<u8>::try_from(&buf[..buf.len() - 1]).is_err());
+}
+
+#[test]
+fn seekable_reader() {
+    let input: Vec<u8> = (0..1000_u32).map(|i| (i * 13 % 31) as u8).collect();
+
+    let tree = Huffman::from(&input);
+    for file in [
+        RZFile::new_blocked(tree.clone(), &input, 100).unwrap(),
+        RZFile::new(tree.clone(), tree.compress(&input).unwrap()),
+    ] {
+        let mut reader = RzReader::new(file);
+        assert_eq!(1000, reader.len().unwrap());
+
+        let mut buf = [0; 50];
+        assert_eq!(750, reader.seek(SeekFrom::Start(750)).unwrap());
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(input[750..800], buf);
+
+        assert_eq!(180, reader.seek(SeekFrom::Current(-620)).unwrap());
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(input[180..230], buf);
+
+        assert_eq!(990, reader.seek(SeekFrom::End(-10)).unwrap());
+        let mut rest = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(input[990..], rest[..]);
+
+        assert!(reader.seek(SeekFrom::Current(-2000)).is_err());
+
+        reader.seek(SeekFrom::Start(0)).unwrap();
+        let mut all = Vec::new();
+        reader.read_to_end(&mut all).unwrap();
+        assert_eq!(input, all);
+    }
+}
+
+#[test]
+fn archive_many_files() {
+    let first = b"The first file in the archive".to_vec();
+    let second = vec![b'z'; 300];
+
+    let archive = ArchiveBuilder::new()
+        .block_size(32)
+        .add("first.txt", first.clone())
+        .add("nested/second.txt", second.clone())
+        .add("empty", Vec::new())
+        .build();
+
+    let mut buf = Vec::new();
+    archive.write_to(&mut buf).unwrap();
+    let read = RZArchive::try_from(buf.as_slice()).unwrap();
+
+    let names: Vec<&str> = read.entries().iter().map(|e| e.name()).collect();
+    assert_eq!(vec!["first.txt", "nested/second.txt", "empty"], names);
+    assert_eq!(Some(Ok(second.clone())), read.extract("nested/second.txt"));
+    assert_eq!(Some(Ok(Vec::new())), read.extract("empty"));
+    assert_eq!(None, read.extract("missing"));
+
+    let added = read
+        .into_builder()
+        .unwrap()
+        .add("third", b"Added later".to_vec())
+        .build();
+    assert_eq!(4, added.entries().len());
+    assert_eq!(Some(Ok(first.clone())), added.extract("first.txt"));
+
+    let dir = std::env::temp_dir().join("huffman-comprs-archive-test");
+    added.extract_to(&dir).unwrap();
+    assert_eq!(first, std::fs::read(dir.join("first.txt")).unwrap());
+    assert_eq!(
+        second,
+        std::fs::read(dir.join("nested/second.txt")).unwrap()
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let unsafe_name = ArchiveBuilder::new().add("../escape", first).build();
+    assert!(unsafe_name.extract_to(&dir).is_err());
+
+    let plain = RZFile::new(Huffman::from(&b"abc".to_vec()), Vec::new());
+    assert!(RZArchive::try_from(plain).is_err());
+}
+
+#[test]
+fn stores_file_metadata() {
+    let input = b"Restored with its original name and timestamp".to_vec();
+    let path = "metadata-source.txt";
+    std::fs::write(path, &input).unwrap();
+
+    let modified = std::time::UNIX_EPOCH + std::time::Duration::new(1_600_000_000, 5);
+    let metadata = Metadata {
+        modified: Some(modified),
+        ..Metadata::from_file(path).unwrap()
+    };
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(Some("metadata-source.txt"), metadata.file_name());
+
+    let tree = Huffman::from(&input);
+    let data = tree.compress(&input).unwrap();
+    let file = RZFile::new(tree, data).with_metadata(metadata.clone());
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let read: RZFile<u8> = RZFile::try_from(buf.as_slice()).unwrap();
+    assert_eq!(Some(&metadata), read.metadata());
+    assert_eq!(Some(input), read.decompress());
+
+    let before_epoch = Metadata {
+        name: Some("../../etc/passwd".to_string()),
+        modified: Some(std::time::UNIX_EPOCH - std::time::Duration::new(10, 3)),
+        permissions: Some(0o600),
+        symbols: Some("word".to_string()),
+    };
+    let file = RZFile::new(Huffman::from("a"), Vec::new()).with_metadata(before_epoch.clone());
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let read: RZFile<char> = RZFile::read_from(buf.as_slice()).unwrap();
+    assert_eq!(Some(&before_epoch), read.metadata());
+    assert_eq!(Some("passwd"), before_epoch.file_name());
+}
+
+#[test]
+fn stores_exact_length() {
+    // A single symbol has an empty code, so only the length says how many
+    let input = vec![b'a'; 100];
+
+    let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    assert_eq!(Some(100), file.len());
+    assert_eq!(Some(input.clone()), file.decompress());
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let read: RZFile<u8> = RZFile::try_from(buf.as_slice()).unwrap();
+    assert_eq!(Some(input), read.decompress());
+
+    let input = b"Knowing the length catches truncated data".to_vec();
+    let tree = Huffman::from(&input);
+    let file = RZFile::compress(tree.clone(), &input).unwrap();
+    assert_eq!(
+        Err(DecodeError::OutputLimitExceeded(10)),
+        file.decompress_with(&DecodeOptions::new().max_output_len(10))
+    );
+
+    let mut data = tree.compress(&input).unwrap();
+    data.reverse();
+    assert_eq!(
+        Ok(input.clone()),
+        tree.reconstruct_exact(data.clone(), 0, input.len())
+    );
+    assert_eq!(
+        Err(DecodeError::DanglingBits),
+        tree.reconstruct_exact(data.clone(), 0, input.len() - 1)
+    );
+    assert_eq!(
+        Err(DecodeError::UnexpectedEof),
+        tree.reconstruct_exact(data, 0, input.len() + 1)
+    );
+}
+
+#[test]
+fn concatenated_frames() {
+    let path = "appended.rz";
+    let _ = std::fs::remove_file(path);
+
+    let lines = [
+        "The first line of a log\n",
+        "Appended later\n",
+        "And later still\n",
+    ];
+    for line in &lines {
+        let line = line.as_bytes().to_vec();
+        let file = RZFile::compress(Huffman::from(&line), &line).unwrap();
+        file.append_to_file(path).unwrap();
+    }
+
+    let buf = std::fs::read(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+
+    let mut decompressed = Vec::new();
+    for file in RZFile::<u8>::frames(&buf) {
+        decompressed.append(&mut file.unwrap().decompress().unwrap());
+    }
+    assert_eq!(lines.concat().into_bytes(), decompressed);
+
+    let mut reader = buf.as_slice();
+    for line in &lines {
+        let file: RZFile<u8> = RZFile::read_from(&mut reader).unwrap();
+        assert_eq!(Some(line.as_bytes().to_vec()), file.decompress());
+    }
+    assert!(reader.is_empty());
+
+    // A single file can't have anything following it
+    assert!(RZFile::<u8>::try_from(buf.as_slice()).is_err());
+
+    let mut truncated = RZFile::<u8>::frames(&buf[..buf.len() - 1]);
+    assert!(truncated.next().unwrap().is_ok());
+    assert!(truncated.next().unwrap().is_ok());
+    assert!(truncated.next().unwrap().is_err());
+    assert!(truncated.next().is_none());
+}
+
+#[test]
+fn split_and_join() {
+    let input: Vec<u8> = (0..5000_u32).map(|i| (i * i % 251) as u8).collect();
+
+    let file = RZFile::new_blocked(Huffman::from(&input), &input, 256).unwrap();
+    let mut whole = Vec::new();
+    file.write_to(&mut whole).unwrap();
+
+    let parts = file.split(whole.len() / 2).unwrap();
+    assert!(parts.len() > 2);
+
+    let mut decompressed = Vec::new();
+    for part in &parts {
+        let mut buf = Vec::new();
+        part.write_to(&mut buf).unwrap();
+        assert!(buf.len() <= whole.len() / 2);
+
+        let part: RZFile<u8> = RZFile::try_from(buf.as_slice()).unwrap();
+        decompressed.append(&mut part.decompress().unwrap());
+    }
+    assert_eq!(input, decompressed);
+
+    let joined = RZFile::join(parts.clone()).unwrap();
+    assert_eq!(Some(input.len()), joined.len());
+    assert_eq!(Some(input.clone()), joined.decompress());
+
+    let mut reversed = parts;
+    reversed.reverse();
+    assert_eq!(
+        Err(SplitError::Mismatched),
+        RZFile::join(reversed).map(|_| ())
+    );
+
+    assert!(matches!(file.split(10), Err(SplitError::PartTooSmall(_))));
+
+    let unblocked = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    assert_eq!(
+        Err(SplitError::NotBlocked),
+        unblocked.split(1000).map(|_| ())
+    );
+}
+
+#[test]
+fn shared_dictionary() {
+    let samples: Vec<Vec<u8>> = (0..200)
+        .map(|i| {
+            format!(
+                r#"{{"id":{},"name":"user{}","active":{}}}"#,
+                i,
+                i * 7,
+                i % 2 == 0
+            )
+            .into_bytes()
+        })
+        .collect();
+    let tree = Huffman::train(samples.iter().map(Vec::as_slice));
+
+    let message = br#"{"id":42,"name":"user1234","active":true}"#.to_vec();
+    let embedded = RZFile::compress(tree.clone(), &message).unwrap();
+    let mut with_tree = Vec::new();
+    embedded.write_to(&mut with_tree).unwrap();
+
+    let file = embedded.with_dictionary();
+    assert_eq!(Some(tree.dictionary_id()), file.dictionary_id());
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    assert!(buf.len() < with_tree.len());
+
+    let read = RZFile::parse_with_dictionary(&buf, &tree).unwrap();
+    assert_eq!(Some(message.clone()), read.decompress());
+    let read = RZFile::read_from_with_dictionary(buf.as_slice(), &tree).unwrap();
+    assert_eq!(Some(message), read.decompress());
+
+    let id = tree.dictionary_id();
+    assert!(matches!(
+        RZFile::<u8>::try_from(buf.as_slice()),
+        Err(RzError::MissingDictionary(missing)) if missing == id
+    ));
+    let other = Huffman::from(&b"a different tree".to_vec());
+    assert!(RZFile::parse_with_dictionary(&buf, &other).is_err());
+}
+
+#[test]
+fn tree_formats() {
+    let input = b"A tree other languages can read".to_vec();
+    // Made directly, as the input is too short for compressing it to be
+    // worth writing the tree
+    let tree = Huffman::from(&input);
+    let file = RZFile::new(tree.clone(), tree.compress(&input).unwrap());
+
+    assert_eq!(TreeFormat::Compact, file.tree_format());
+    let written_len = |format| {
+        let mut buf = Vec::new();
+        file.clone()
+            .with_tree_format(format)
+            .write_to(&mut buf)
+            .unwrap();
+        buf.len()
+    };
+    assert!(written_len(TreeFormat::Compact) * 4 < written_len(TreeFormat::Bincode));
+
+    let mut formats = vec![TreeFormat::Compact, TreeFormat::Bincode];
+    if cfg!(feature = "json") {
+        formats.push(TreeFormat::Json);
+    }
+    if cfg!(feature = "cbor") {
+        formats.push(TreeFormat::Cbor);
+    }
+    if cfg!(feature = "msgpack") {
+        formats.push(TreeFormat::MessagePack);
+    }
+
+    for format in formats {
+        let file = file.clone().with_tree_format(format);
+        let mut buf = Vec::new();
+        file.write_to(&mut buf).unwrap();
+
+        let read: RZFile<u8> = RZFile::try_from(buf.as_slice()).unwrap();
+        assert_eq!(format, read.tree_format());
+        assert_eq!(Some(input.clone()), read.decompress());
+    }
+
+    if !cfg!(feature = "json") {
+        let file = file.with_tree_format(TreeFormat::Json);
+        assert!(file.write_to(Vec::new()).is_err());
+    }
+}
+
+#[test]
+fn copy_between_streams() {
+    let input: Vec<u8> = (0..1_300_000_u64)
+        .map(|i| b"streamed"[(i * i % 8) as usize])
+        .collect();
+    let tree = Huffman::from(&b"adeemrst".to_vec());
+
+    let mut compressed = Vec::new();
+    let read = copy_encode(input.as_slice(), &mut compressed, &tree).unwrap();
+    assert_eq!(input.len() as u64, read);
+    assert!(RZFile::<u8>::frames(&compressed).count() > 1);
+
+    let mut output = Vec::new();
+    let written = copy_decode(compressed.as_slice(), &mut output).unwrap();
+    assert_eq!(input.len() as u64, written);
+    assert_eq!(input, output);
+
+    let mut empty = Vec::new();
+    assert_eq!(0, copy_encode(&[][..], &mut empty, &tree).unwrap());
+    assert_eq!(0, copy_decode(empty.as_slice(), &mut Vec::new()).unwrap());
+
+    assert!(copy_encode(&b"not in the tree"[..], &mut Vec::new(), &tree).is_err());
+    compressed.truncate(compressed.len() - 1);
+    assert!(copy_decode(compressed.as_slice(), &mut Vec::new()).is_err());
+}
+
+#[test]
+fn pipelined_compression() {
+    let input: Vec<u8> = (0..300_000_u64)
+        .map(|i| b"pipelined"[(i * i % 9) as usize])
+        .collect();
+
+    let mut compressed = Vec::new();
+    let pipeline = Pipeline::new().chunk_len(64 * 1024).queue_len(1);
+    let read = pipeline.run(input.as_slice(), &mut compressed).unwrap();
+    assert_eq!(input.len() as u64, read);
+    assert!(compressed.len() < input.len());
+    assert_eq!(5, RZFile::<u8>::frames(&compressed).count());
+    // Each chunk holds its checksum
+    for frame in RZFile::<u8>::frames(&compressed) {
+        assert!(frame.unwrap().checksum().is_some());
+    }
+
+    let mut output = Vec::new();
+    copy_decode(compressed.as_slice(), &mut output).unwrap();
+    assert_eq!(input, output);
+
+    // Stages hand each chunk straight on
+    let mut unqueued = Vec::new();
+    Pipeline::new()
+        .chunk_len(64 * 1024)
+        .queue_len(0)
+        .run(input.as_slice(), &mut unqueued)
+        .unwrap();
+    assert_eq!(compressed, unqueued);
+
+    let mut empty = Vec::new();
+    assert_eq!(0, Pipeline::new().run(&[][..], &mut empty).unwrap());
+    assert!(empty.is_empty());
+
+    // Errors reading or writing stop every stage
+    struct Failing;
+    impl std::io::Read for Failing {
+        fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("failed to read"))
+        }
+    }
+    impl std::io::Write for Failing {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::Error::other("failed to write"))
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let error = Pipeline::new().run(Failing, Vec::new()).unwrap_err();
+    assert_eq!("failed to read", error.to_string());
+    let error = pipeline.run(input.as_slice(), Failing).unwrap_err();
+    assert_eq!("failed to write", error.to_string());
+}
+
+#[test]
+fn progress_callbacks() {
+    let input: Vec<u8> = (0..2_500_000_u64)
+        .map(|i| b"progress"[(i * i % 8) as usize])
+        .collect();
+    let tree = Huffman::from(&b"egoprs".to_vec());
+
+    let mut reports = Vec::new();
+    let mut compressed = Vec::new();
+    copy_encode_with_progress(input.as_slice(), &mut compressed, &tree, |progress| {
+        reports.push(progress)
+    })
+    .unwrap();
+    assert_eq!(3, reports.len());
+    assert_eq!(1024 * 1024, reports[0].consumed);
+    assert!(reports
+        .windows(2)
+        .all(|pair| pair[0].consumed < pair[1].consumed && pair[0].produced < pair[1].produced));
+    assert_eq!(
+        Progress {
+            consumed: input.len() as u64,
+            produced: compressed.len() as u64,
+        },
+        reports[2]
+    );
+
+    let mut reports = Vec::new();
+    let mut retrained = Vec::new();
+    copy_encode_retraining_with_progress(
+        input.as_slice(),
+        &mut retrained,
+        &Retrain::new(),
+        |progress| reports.push(progress),
+    )
+    .unwrap();
+    assert_eq!(3, reports.len());
+    assert_eq!(retrained.len() as u64, reports[2].produced);
+
+    // Decoding reports each file as it's read, not what's been buffered
+    let mut reports = Vec::new();
+    let mut output = Vec::new();
+    copy_decode_with_progress(compressed.as_slice(), &mut output, |progress| {
+        reports.push(progress)
+    })
+    .unwrap();
+    assert_eq!(input, output);
+    let frames: Vec<u64> = RZFile::<u8>::frames(&compressed)
+        .map(|frame| {
+            let mut buf = Vec::new();
+            frame.unwrap().write_to(&mut buf).unwrap();
+            buf.len() as u64
+        })
+        .collect();
+    assert_eq!(frames[0], reports[0].consumed);
+    assert_eq!(1024 * 1024, reports[0].produced);
+    assert_eq!(
+        Progress {
+            consumed: compressed.len() as u64,
+            produced: input.len() as u64,
+        },
+        reports[2]
+    );
+
+    let mut reports = Vec::new();
+    let mut pipelined = Vec::new();
+    Pipeline::new()
+        .run_with_progress(input.as_slice(), &mut pipelined, |progress| {
+            reports.push(progress)
+        })
+        .unwrap();
+    assert_eq!(3, reports.len());
+    assert_eq!(pipelined.len() as u64, reports[2].produced);
+
+    // A file in blocks reports each block, and one without only once
+    let file = RZFile::new_blocked(tree.clone(), &input[..1000], 300).unwrap();
+    let mut reports = Vec::new();
+    file.decompress_with_progress(&DecodeOptions::new(), |progress| reports.push(progress))
+        .unwrap();
+    let produced: Vec<u64> = reports.iter().map(|progress| progress.produced).collect();
+    assert_eq!(vec![300, 600, 900, 1000], produced);
+    assert_eq!(file.data().len() as u64, reports[3].consumed);
+
+    let file = RZFile::compress(tree, &input[..1000]).unwrap();
+    let mut reports = Vec::new();
+    file.decompress_with_progress(&DecodeOptions::new(), |progress| reports.push(progress))
+        .unwrap();
+    assert_eq!(
+        vec![Progress {
+            consumed: file.data().len() as u64,
+            produced: 1000,
+        }],
+        reports
+    );
+}
+
+#[test]
+fn cancelling() {
+    let input: Vec<u8> = (0..2_500_000_u64)
+        .map(|i| b"cancelled"[(i * i % 9) as usize])
+        .collect();
+    let tree = Huffman::from(&input);
+
+    // Blocks are checked as they're decoded
+    let file = RZFile::new_blocked(tree.clone(), &input[..1000], 300).unwrap();
+    let cancel = Cancel::new();
+    let options = DecodeOptions::new().cancel(cancel.clone());
+    assert_eq!(Ok(input[..1000].to_vec()), file.decompress_with(&options));
+    let mut decoded = Vec::new();
+    let result = file.decompress_with_progress(&options, |progress| {
+        decoded.push(progress.produced);
+        cancel.cancel();
+    });
+    assert_eq!(Err(DecodeError::Cancelled), result);
+    assert_eq!(vec![300], decoded);
+    assert!(cancel.is_cancelled());
+    assert_eq!(Err(DecodeError::Cancelled), file.decompress_with(&options));
+
+    // Streams stop at the next read or write
+    let mut compressed = Vec::new();
+    copy_encode(input.as_slice(), &mut compressed, &tree).unwrap();
+    let cancel = Cancel::new();
+    let mut output = Vec::new();
+    let error = copy_decode_with_progress(cancel.wrap(compressed.as_slice()), &mut output, |_| {
+        cancel.cancel()
+    })
+    .unwrap_err();
+    assert_eq!(Some(DecodeError::Cancelled), DecodeError::from_io(&error));
+    assert_eq!(input[..1024 * 1024], output);
+
+    let cancel = Cancel::new();
+    let mut written = 0;
+    let error = Pipeline::new()
+        .chunk_len(64 * 1024)
+        .run_with_progress(input.as_slice(), cancel.wrap(Vec::new()), |progress| {
+            written = progress.produced;
+            cancel.cancel();
+        })
+        .unwrap_err();
+    assert_eq!(Some(DecodeError::Cancelled), DecodeError::from_io(&error));
+    assert!(written > 0);
+
+    // Other errors hold no `DecodeError`
+    let error = copy_decode(&b"not an rz file"[..], Vec::new()).unwrap_err();
+    assert_eq!(None, DecodeError::from_io(&error));
+}
+
+#[test]
+fn cancelling_a_single_block() {
+    let input: Vec<u8> = (0..2_500_000_u64)
+        .map(|i| b"cancelled"[(i * i % 9) as usize])
+        .collect();
+    let tree = Huffman::from(&input);
+
+    // Symbols are checked as they're decoded, so a file that isn't split
+    // into blocks stops part way through
+    let file = RZFile::compress(tree.clone(), &input).unwrap();
+    assert_eq!(1, file.block_count());
+    let cancel = Cancel::new();
+    let options = DecodeOptions::new().cancel(cancel.clone());
+    let decoding = std::thread::spawn(move || file.decompress_with(&options));
+    cancel.cancel();
+    assert_eq!(Err(DecodeError::Cancelled), decoding.join().unwrap());
+
+    let options = DecodeOptions::new().cancel(cancel.clone());
+    let code = tree.compress(&input).unwrap();
+    assert_eq!(
+        Err(DecodeError::Cancelled),
+        tree.reconstruct_with(code, 0, &options)
+    );
+    let multi = MultiHuffman::train(&input[..10_000], 2, 50);
+    let mut output = Vec::new();
+    let result = multi.decode(
+        multi.encode(&input).unwrap().to_vec(),
+        0,
+        Some(input.len()),
+        &mut output,
+        &options,
+    );
+    assert_eq!(Err(DecodeError::Cancelled), result);
+    assert!(output.is_empty());
+
+    // And as they're encoded
+    let file = RZFile::compress_with(tree.clone(), &input, &Cancel::new())
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        RZFile::compress(tree.clone(), &input).unwrap().data(),
+        file.data()
+    );
+    assert_eq!(
+        Some(DecodeError::Cancelled),
+        RZFile::compress_with(tree.clone(), &input, &cancel).err()
+    );
+    assert_eq!(
+        Some(DecodeError::Cancelled),
+        RZFile::compress_with(multi, &input, &cancel).err()
+    );
+    let missing = RZFile::compress_with(tree, b"not in the tree", &Cancel::new());
+    assert!(matches!(missing, Ok(None)));
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn arrow_columns() {
+    use arrow_array::{types::Utf8Type, Array, BinaryArray, LargeStringArray, StringArray};
+    use huffman_comprs::arrow::ArrowColumn;
+
+    let statuses = ["ok", "ok", "failed", "ok", "pending", "ok", "failed", "ok"];
+    let array: StringArray = (0..1000)
+        .map(|i| (i % 13 != 0).then_some(statuses[i % statuses.len()]))
+        .collect();
+    assert_eq!(77, array.null_count());
+
+    let column = ArrowColumn::encode(&array);
+    assert_eq!(3, column.tree().contents().len());
+    let mut bytes = Vec::new();
+    column.write_to(&mut bytes).unwrap();
+    assert!(bytes.len() < array.value_data().len() / 4);
+
+    let read = ArrowColumn::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(1000, read.len());
+    let decoded: StringArray = read.decode().unwrap();
+    assert_eq!(array, decoded);
+
+    // A slice of an array, with offsets and nulls not starting at 0, decodes
+    // to an array whose offsets do, and nulls to empty values
+    let sliced = array.slice(25, 10);
+    let decoded: StringArray = ArrowColumn::encode(&sliced).decode().unwrap();
+    assert_eq!(sliced, decoded);
+    assert_eq!(0, decoded.value_offsets()[0]);
+    assert!(decoded.is_null(1));
+    assert_eq!("ok", decoded.value(2));
+
+    // Binary arrays, and arrays with `i64` offsets
+    let binary = BinaryArray::from(vec![Some(&b"\xff\x00"[..]), None, Some(b"")]);
+    let decoded: BinaryArray = ArrowColumn::encode(&binary).decode().unwrap();
+    assert_eq!(binary, decoded);
+    let large = LargeStringArray::from(vec!["large"; 10]);
+    let decoded: LargeStringArray = ArrowColumn::encode(&large).decode().unwrap();
+    assert_eq!(large, decoded);
+
+    // Columns of one value, and of none
+    for values in [vec![Some("same"); 10], vec![], vec![None, None]] {
+        let array = StringArray::from(values);
+        let mut bytes = Vec::new();
+        ArrowColumn::encode(&array).write_to(&mut bytes).unwrap();
+        let read = ArrowColumn::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(array, read.decode().unwrap());
+    }
+
+    // Values that aren't UTF-8 can't be decoded as strings
+    let column = ArrowColumn::encode(&binary);
+    assert!(matches!(
+        column.decode::<Utf8Type>(),
+        Err(RzError::Malformed(_))
+    ));
+
+    let mut flag = bytes.clone();
+    flag[8] = 2;
+    assert!(matches!(
+        ArrowColumn::read_from(flag.as_slice()),
+        Err(RzError::Malformed(_))
+    ));
+    assert!(matches!(
+        ArrowColumn::read_from(&bytes[..9]),
+        Err(RzError::TruncatedHeader("validity bitmap"))
+    ));
+
+    // Without a bitmap, the length isn't bounded by the bytes read, so a
+    // malformed one mustn't be allocated for
+    let mut bytes = Vec::new();
+    ArrowColumn::encode(&StringArray::from(vec!["same"; 10]))
+        .write_to(&mut bytes)
+        .unwrap();
+    for len in [u64::MAX / 4, u64::MAX] {
+        let mut long = bytes.clone();
+        long[..8].copy_from_slice(&len.to_be_bytes());
+        let read = ArrowColumn::read_from(long.as_slice()).unwrap();
+        assert!(matches!(
+            read.decode::<Utf8Type>(),
+            Err(RzError::Decode(DecodeError::UnexpectedEof))
+        ));
+    }
+}
+
+#[test]
+fn packing() {
+    use huffman_comprs::packing::Packed;
+
+    // A sequence with repeats, and a few runs of unknown bases
+    let mut sequence = Vec::new();
+    for i in 0..2000_usize {
+        sequence.extend_from_slice(b"GATTACA");
+        sequence.push(b"ACGT"[i * 7 % 4]);
+        if i % 250 == 0 {
+            sequence.extend_from_slice(b"NNNN");
+        }
+    }
+
+    let packed = Packed::pack(&sequence).unwrap();
+    assert_eq!(b"ACGT", packed.alphabet());
+    assert_eq!(2, packed.bits());
+    assert_eq!(8, packed.exceptions());
+    let mut bytes = Vec::new();
+    packed.write_to(&mut bytes).unwrap();
+
+    let unpacked = RZFile::compress(Huffman::from(&sequence), &sequence).unwrap();
+    assert!(bytes.len() < unpacked.compressed_len() / 2);
+    let read = Packed::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(sequence.len(), read.len());
+    assert_eq!(Ok(sequence.clone()), read.unpack());
+
+    // Alphabets of each size, with lengths that don't fill the last byte
+    for (alphabet, bits) in [(&b"01"[..], 1), (b"ACG", 2), (b"0123456789abcdef", 4)] {
+        let input: Vec<u8> = (0..1001)
+            .map(|i| alphabet[i * 5 % alphabet.len()])
+            .collect();
+        let packed = Packed::with_alphabet(&input, alphabet).unwrap();
+        assert_eq!(bits, packed.bits());
+        assert_eq!(0, packed.exceptions());
+        assert_eq!(Ok(input), packed.unpack());
+    }
+    let empty = Packed::with_alphabet(&[], b"ACGT").unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(Ok(vec![]), empty.unpack());
+
+    assert!(Packed::with_alphabet(&sequence, b"").is_none());
+    assert!(Packed::with_alphabet(&sequence, b"AACG").is_none());
+    assert!(Packed::with_alphabet(&sequence, &[0; 17]).is_none());
+    assert!(Packed::pack(b"Text uses far too many distinct bytes to pack").is_none());
+
+    let mut alphabet = bytes.clone();
+    alphabet[8] = 17;
+    assert!(matches!(
+        Packed::read_from(alphabet.as_slice()),
+        Err(RzError::Malformed(_))
+    ));
+    // The first run starts past the end of the input
+    let mut run = bytes.clone();
+    run[21] = 0xff;
+    assert!(matches!(
+        Packed::read_from(run.as_slice()),
+        Err(RzError::Malformed(_))
+    ));
+    assert!(matches!(
+        Packed::read_from(&bytes[..20]),
+        Err(RzError::TruncatedHeader("run count"))
+    ));
+}
+
+#[test]
+fn log_model() {
+    use huffman_comprs::logs::{LogFile, LogModel};
+
+    let levels = ["INFO", "INFO", "DEBUG", "WARN", "INFO"];
+    let log: String = (0..500_u64)
+        .map(|i| {
+            format!(
+                "2024-03-{:02}T12:{:02}:{:02}Z {} http::server: request_id={:x} path=/api/v1/users status={}\n",
+                i % 28 + 1,
+                i % 60,
+                i * 7 % 60,
+                levels[i as usize % levels.len()],
+                i * 2_654_435_761 % 1_000_003,
+                if i % 9 == 0 { 404 } else { 200 }
+            )
+        })
+        .collect();
+
+    let file = LogFile::compress(&log);
+    let mut bytes = Vec::new();
+    file.write_to(&mut bytes).unwrap();
+    let chars: Vec<char> = log.chars().collect();
+    let by_char = RZFile::compress(Huffman::from(&chars), &chars).unwrap();
+    assert!(bytes.len() < by_char.compressed_len() / 2);
+    assert!(file
+        .tokens()
+        .get_code(Some("request_id".to_string()))
+        .is_some());
+    assert!(file.tokens().get_code(None).is_some());
+
+    let read = LogFile::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(Ok(log.clone()), read.decompress());
+
+    // A model trained on one log compresses another, escaping the tokens it
+    // hasn't seen, even if they aren't ASCII
+    let model = LogModel::train(&log, 16);
+    assert_eq!(17, model.tokens().contents().len());
+    let unseen = "ERROR  ünïcödé::module: panicked at 'oh no' status=500\n";
+    assert_eq!(Ok(unseen.to_string()), model.compress(unseen).decompress());
+    assert_eq!(Ok(String::new()), model.compress("").decompress());
+    assert_eq!(
+        Ok("once".to_string()),
+        LogFile::compress("once").decompress()
+    );
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn wasm_bindings() {
+    use huffman_comprs::wasm::{compress_bytes, decompress_bytes, WasmTree};
+
+    let input = b"decompressed in the browser, ".repeat(4);
+    let compressed = compress_bytes(&input);
+    assert_eq!(input, decompress_bytes(&compressed).unwrap());
+
+    // A file written by the rest of the crate reads the same
+    let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    assert_eq!(input, decompress_bytes(&buf).unwrap());
+
+    let tree = WasmTree::from_file(&compressed).unwrap();
+    assert_eq!(tree.symbols(), WasmTree::new(&input).symbols());
+    let mut twice = tree.compress(b"browser").unwrap();
+    twice.extend(tree.compress(b"decompressed").unwrap());
+    assert_eq!(
+        b"browserdecompressed".to_vec(),
+        decompress_bytes(&twice).unwrap()
+    );
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn ffi_round_trip() {
+    use huffman_comprs::ffi::*;
+    use std::{ffi::CStr, ptr};
+
+    let input = b"linked from C";
+    unsafe {
+        let tree = huffman_tree_new(input.as_ptr(), input.len());
+        let (mut compressed, mut compressed_len) = (ptr::null_mut(), 0);
+        assert_eq!(
+            0,
+            huffman_compress(
+                tree,
+                input.as_ptr(),
+                input.len(),
+                &mut compressed,
+                &mut compressed_len
+            )
+        );
+
+        let (mut output, mut output_len) = (ptr::null_mut(), 0);
+        assert_eq!(
+            0,
+            huffman_decompress(compressed, compressed_len, &mut output, &mut output_len)
+        );
+        assert_eq!(input, std::slice::from_raw_parts(output, output_len));
+        huffman_buffer_free(output, output_len);
+
+        // Corrupting the data fails, with a reason
+        *compressed.add(compressed_len - 1) ^= 0xFF;
+        assert_eq!(
+            -1,
+            huffman_decompress(compressed, compressed_len, &mut output, &mut output_len)
+        );
+        assert!(!CStr::from_ptr(huffman_last_error()).to_bytes().is_empty());
+        huffman_buffer_free(compressed, compressed_len);
+
+        assert_eq!(
+            -1,
+            huffman_compress(tree, b"?".as_ptr(), 1, &mut compressed, &mut compressed_len)
+        );
+        huffman_tree_free(tree);
+    }
+}
+
+#[cfg(feature = "python")]
+#[test]
+fn python_bindings() {
+    use huffman_comprs::python::{compress, decompress, PyHuffman, PyRZFile};
+
+    let data = b"read by the data science team, ".repeat(4);
+    let tree = PyHuffman::new(&data);
+    let compressed = tree.compress(&data).unwrap();
+    assert_eq!(data, *tree.decompress(&compressed).unwrap());
+    assert_eq!(data, *decompress(&compress(&data)).unwrap());
+    assert!(tree.compress(b"?").is_err());
+
+    // Files written from Rust read the same
+    let file = RZFile::compress(Huffman::from(&data), &data)
+        .unwrap()
+        .with_checksum(&data);
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let file = PyRZFile::new(&buf).unwrap();
+    assert_eq!(data, *file.decompress().unwrap());
+    assert_eq!(Some(data.len()), file.stored_len());
+    assert!(file.checksum().is_some());
+    assert_eq!(tree.symbols(), file.tree().symbols());
+
+    let file = PyRZFile::compress(&data, Some(&tree)).unwrap();
+    assert_eq!(buf.len(), file.to_bytes().unwrap().len());
+}
+
+/// Stores each byte as its own 8 bits, with no model
+#[derive(Clone, Debug)]
+struct Raw;
+
+impl EntropyCoder<u8> for Raw {
+    fn encode(&self, input: &[u8]) -> Option<Code> {
+        Some(
+            input
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |i| byte >> i & 1 == 1))
+                .collect(),
+        )
+    }
+    fn decode(
+        &self,
+        mut data: Vec<bool>,
+        zeros: u8,
+        _len: Option<usize>,
+        output: &mut Vec<u8>,
+        _options: &DecodeOptions,
+    ) -> Result<(), DecodeError> {
+        // The padding is read last, so comes at the end
+        data.truncate(data.len() - usize::from(zeros));
+        while !data.is_empty() {
+            let mut byte = 0;
+            for _ in 0..8 {
+                byte = byte << 1 | u8::from(data.pop().ok_or(DecodeError::DanglingBits)?);
+            }
+            output.push(byte);
+        }
+        Ok(())
+    }
+    fn serialize_model(&self, _format: TreeFormat) -> std::io::Result<Vec<u8>> {
+        Ok(b"raw".to_vec())
+    }
+    fn deserialize_model<'de>(model: &'de [u8], _format: TreeFormat) -> Result<Self, RzError>
+    where
+        u8: serde::Deserialize<'de>,
+    {
+        if model == b"raw" {
+            Ok(Raw)
+        } else {
+            Err(RzError::TreeDecode("not a raw model".to_string()))
+        }
+    }
+    fn empty() -> Option<Self> {
+        Some(Raw)
+    }
+}
+
+#[test]
+fn other_entropy_coders() {
+    let input = b"Stored without a tree".to_vec();
+
+    let file = RZFile::compress(Raw, &input).unwrap().with_checksum(&input);
+    assert_eq!(input.len(), file.data().len());
+    assert_eq!(Some(input.clone()), file.decompress());
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let read = RZFile::<u8, Raw>::read_from_with_model(buf.as_slice(), None).unwrap();
+    assert_eq!(Some(input.clone()), read.decompress());
+    let parsed = RZFile::<u8, Raw>::parse_with_model(&buf, None).unwrap();
+    assert_eq!(Some(input.clone()), parsed.decompress());
+
+    // The same calls work with a tree
+    let input = input.repeat(4);
+    let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    assert!(RZFile::<u8, Raw>::parse_with_model(&buf, None).is_err());
+    let parsed = RZFile::<u8, Huffman<u8>>::parse_with_model(&buf, None).unwrap();
+    assert_eq!(Some(input), parsed.decompress());
+}
+
+#[test]
+fn many_trees_selected_per_group() {
+    // Prose with base64 embedded in it, which codes best with a tree of its own
+    let prose = b"The quick brown fox jumps over the lazy dog, and then rests. ";
+    let base64 = b"VGhlIHF1aWNrIGJyb3duIGZveCBqdW1wcyBvdmVyIHRoZSBsYXp5IGRvZy4K";
+    let mut input = Vec::new();
+    for _ in 0..8 {
+        input.extend(prose.repeat(20));
+        input.extend(base64.repeat(20));
+    }
+
+    let coder = MultiHuffman::train(&input, 4, 50);
+    assert!(coder.trees().len() > 1);
+    let multi = RZFile::compress(coder, &input).unwrap();
+    let single = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    assert!(multi.compressed_len() < single.compressed_len());
+
+    let mut buf = Vec::new();
+    multi.write_to(&mut buf).unwrap();
+    let read = RZFile::<u8, MultiHuffman<u8>>::read_from_with_model(buf.as_slice(), None).unwrap();
+    assert_eq!(Some(input.clone()), read.decompress());
+
+    // Each block is split into groups of its own
+    let blocked = RZFile::new_blocked(MultiHuffman::train(&input, 2, 64), &input, 1000).unwrap();
+    assert_eq!(
+        Ok(input[1500..2500].to_vec()),
+        blocked.decompress_range(1500..2500)
+    );
+}
+
+#[test]
+fn incompressible_data_is_stored() {
+    // Every byte value equally often, so nothing can be given a shorter code
+    let input: Vec<u8> = (0..4096_u32).map(|i| (i * 167 % 256) as u8).collect();
+
+    let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+    assert!(file.is_stored());
+    assert_eq!(input.as_slice(), file.data());
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    let parsed = RZFile::<u8>::try_from(buf.as_slice()).unwrap();
+    assert!(parsed.is_stored());
+    assert_eq!(Some(input.clone()), parsed.decompress());
+    assert!(matches!(
+        parsed.decompress_with(&DecodeOptions::new().max_output_len(100)),
+        Err(DecodeError::OutputLimitExceeded(100))
+    ));
+
+    let text = b"Text compresses well enough to not be stored, tree and all. ".repeat(8);
+    let file = RZFile::compress(Huffman::from(&text), &text).unwrap();
+    assert!(!file.is_stored());
+
+    // Version 1 files have no extended flags, and are still read
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    buf[4] = 1;
+    buf.remove(11);
+    assert_eq!(
+        Some(text),
+        RZFile::<u8>::try_from(buf.as_slice()).unwrap().decompress()
+    );
+
+    // Only bytes can be stored
+    let chars = "abc".chars().collect::<Vec<_>>();
+    let mut buf = Vec::new();
+    RZFile::compress(Huffman::from(&chars), &chars)
+        .unwrap()
+        .write_to(&mut buf)
+        .unwrap();
+    buf[11] = 0x01;
+    assert!(matches!(
+        RZFile::<char>::try_from(buf.as_slice()),
+        Err(RzError::Malformed(_))
+    ));
+}
+
+#[test]
+fn stored_files_leave_out_the_tree() {
+    // Magic, version, flags, tree length, zeros, extended flags, the number
+    // of symbols, the data's length, and the tree's format
+    const HEADER_LEN: usize = 29;
+
+    let every_byte: Vec<u8> = (0..=255).collect();
+    // The data alone is smaller, but not once the tree is written with it
+    let skewed: Vec<u8> = (0..=255).chain(std::iter::repeat_n(0, 64)).collect();
+    for input in [every_byte, skewed] {
+        let file = RZFile::compress(Huffman::from(&input), &input).unwrap();
+        assert!(file.is_stored());
+        assert_eq!(0, file.tree_len());
+
+        let mut written = Vec::new();
+        file.write_to(&mut written).unwrap();
+        assert!(
+            written.len() <= input.len() + HEADER_LEN,
+            "{} {}",
+            written.len(),
+            input.len()
+        );
+        assert_eq!([0; 4], written[6..10]);
+
+        let read = RZFile::<u8>::read_from(written.as_slice()).unwrap();
+        assert!(read.is_stored());
+        assert_eq!(Some(input.clone()), read.decompress());
+        let parsed = RZFile::<u8>::try_from(written.as_slice()).unwrap();
+        assert_eq!(Some(input.clone()), parsed.decompress());
+    }
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encrypted_stored_files_leave_out_the_tree() {
+    let input: Vec<u8> = (0..=255).collect();
+    let file = RZFile::compress(Huffman::from(&input), &input)
+        .unwrap()
+        .with_encryption(b"hunter2", true);
+    assert_eq!(0, file.tree_len());
+
+    let mut written = Vec::new();
+    file.write_to(&mut written).unwrap();
+    let read = RZFile::<u8>::read_from_with_password(written.as_slice(), b"hunter2").unwrap();
+    assert_eq!(Some(input), read.decompress());
+}
+
+#[test]
+fn retraining_follows_drifting_input() {
+    // Two megabytes of one alphabet, then two of another
+    let input: Vec<u8> = (0..4 * 1024 * 1024_u32)
+        .map(|i| {
+            let base = if i < 2 * 1024 * 1024 { b'a' } else { b'0' };
+            base + (i % 7) as u8
+        })
+        .collect();
+
+    let mut fixed = Vec::new();
+    copy_encode_retraining(input.as_slice(), &mut fixed, &Retrain::new()).unwrap();
+    let mut every = Vec::new();
+    let retrain = Retrain::new().every(2 * 1024 * 1024);
+    copy_encode_retraining(input.as_slice(), &mut every, &retrain).unwrap();
+    let mut lossy = Vec::new();
+    let retrain = Retrain::new().max_loss(0.1);
+    copy_encode_retraining(input.as_slice(), &mut lossy, &retrain).unwrap();
+
+    // A tree trained on the first alphabet gives the second long codes
+    assert!(every.len() < fixed.len());
+    assert!(lossy.len() < fixed.len());
+    for compressed in [fixed, every, lossy] {
+        let mut output = Vec::new();
+        copy_decode(compressed.as_slice(), &mut output).unwrap();
+        assert_eq!(input, output);
+    }
+}
+
+#[test]
+fn compressed_values() {
+    let lines: Vec<String> = (0..100).map(|i| format!("line {i} of the log")).collect();
+    let compressed = to_compressed_bytes(&lines).unwrap();
+
+    assert!(compressed.len() < bincode::serialize(&lines).unwrap().len());
+    assert_eq!(
+        lines,
+        from_compressed_bytes::<Vec<String>>(&compressed).unwrap()
+    );
+
+    // Values of one repeated byte, or no bytes at all, still round trip
+    let zero = to_compressed_bytes(&0_u64).unwrap();
+    assert_eq!(0_u64, from_compressed_bytes::<u64>(&zero).unwrap());
+    let unit = to_compressed_bytes(&()).unwrap();
+    from_compressed_bytes::<()>(&unit).unwrap();
+
+    // Reading a type needing more bytes than there are is an error, rather
+    // than a panic
+    assert!(matches!(
+        from_compressed_bytes::<u128>(&zero),
+        Err(RzError::Malformed(_))
+    ));
+}
+
+#[test]
+fn sizes_and_flags() {
+    let input = b"Sized and flagged, without reparsing the header".to_vec();
+    let file = RZFile::compress(Huffman::from(&input), &input)
+        .unwrap()
+        .with_checksum(&input);
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    assert_eq!(buf.len(), file.compressed_len());
+    assert_eq!(Some(input.len()), file.uncompressed_len());
+    assert_eq!(Some(buf.len() as f64 / input.len() as f64), file.ratio());
+
+    // The flags and tree length are those written in the header
+    assert_eq!(buf[5], file.flags());
+    assert_eq!(buf[11], file.extended_flags());
+    let tree_len = u32::from_be_bytes([buf[6], buf[7], buf[8], buf[9]]) as usize;
+    assert_eq!(tree_len, file.tree_len());
+
+    let parsed = RZFile::<u8>::try_from(buf.as_slice()).unwrap();
+    assert_eq!(file.flags(), parsed.flags());
+    assert_eq!(file.compressed_len(), parsed.compressed_len());
+
+    // A tree left out takes no bytes
+    assert_eq!(0, file.with_dictionary().tree_len());
+    assert_eq!(
+        None,
+        RZFile::compress(Huffman::from(&input), &[])
+            .unwrap()
+            .ratio()
+    );
+}
+
+#[test]
+fn extensions_are_skipped() {
+    let first = b"first file, first file".to_vec();
+    let second = b"second file".to_vec();
+    let before = Extension::new(1, b"before".to_vec());
+    let between = Extension::new(2, Vec::new());
+    let after = Extension::new(3, b"after".to_vec());
+
+    let mut buf = Vec::new();
+    before.write_to(&mut buf).unwrap();
+    let file = RZFile::compress(Huffman::from(&first), &first).unwrap();
+    file.write_to(&mut buf).unwrap();
+    between.write_to(&mut buf).unwrap();
+    let file = RZFile::compress(Huffman::from(&second), &second).unwrap();
+    file.write_to(&mut buf).unwrap();
+    after.write_to(&mut buf).unwrap();
+
+    assert_eq!(
+        vec![before.clone(), between, after.clone()],
+        Extension::find_all(&buf).unwrap()
+    );
+
+    let files: Vec<RZFile<u8>> = RZFile::frames(&buf).map(Result::unwrap).collect();
+    assert_eq!(2, files.len());
+    assert_eq!(Some(first.clone()), files[0].decompress());
+    assert_eq!(Some(second.clone()), files[1].decompress());
+
+    let mut decompressed = Vec::new();
+    copy_decode(buf.as_slice(), &mut decompressed).unwrap();
+    assert_eq!([first.as_slice(), &second].concat(), decompressed);
+
+    // A single file may have extensions either side of it
+    let mut single = Vec::new();
+    before.write_to(&mut single).unwrap();
+    file.write_to(&mut single).unwrap();
+    after.write_to(&mut single).unwrap();
+    let parsed = RZFile::<u8>::try_from(single.as_slice()).unwrap();
+    assert_eq!(Some(second.clone()), parsed.decompress());
+    let read = RZFile::<u8>::read_from(single.as_slice()).unwrap();
+    assert_eq!(Some(second), read.decompress());
+
+    // An extension cut short is an error, not skipped
+    single.truncate(single.len() - 1);
+    assert!(matches!(
+        RZFile::<u8>::try_from(single.as_slice()),
+        Err(RzError::Malformed(_))
+    ));
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encrypted_payloads() {
+    let input = b"A secret, repeated: a secret, repeated".to_vec();
+    let tree = Huffman::from(&input);
+    let file = RZFile::compress(tree, &input)
+        .unwrap()
+        .with_checksum(&input)
+        .with_encryption(b"correct horse", true);
+    assert!(file.is_encrypted());
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    assert_eq!(buf.len(), file.compressed_len());
+
+    // Reading needs the password, and borrowing can't decrypt
+    assert!(matches!(
+        RZFile::<u8>::read_from(buf.as_slice()),
+        Err(RzError::Encrypted)
+    ));
+    assert!(matches!(
+        RZFile::<u8>::try_from(buf.as_slice()),
+        Err(RzError::Encrypted)
+    ));
+
+    let read = RZFile::<u8>::read_from_with_password(buf.as_slice(), b"correct horse").unwrap();
+    assert!(!read.is_encrypted());
+    assert_eq!(Some(input.clone()), read.decompress());
+
+    // Any change to the payload is caught
+    let mut tampered = buf.clone();
+    *tampered.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        RZFile::<u8>::read_from_with_password(tampered.as_slice(), b"correct horse"),
+        Err(RzError::DecryptionFailed)
+    ));
+}
+
+#[cfg(feature = "crypto")]
+#[test]
+fn encryption_authenticates_the_header() {
+    let input = b"A secret, repeated: a secret, repeated. ".repeat(4);
+    let read = |buf: &[u8]| RZFile::<u8>::read_from_with_password(buf, b"correct horse");
+
+    for encrypt_tree in [true, false] {
+        let file = RZFile::compress(Huffman::from(&input), &input)
+            .unwrap()
+            .with_checksum(&input)
+            .with_encryption(b"correct horse", encrypt_tree);
+        assert!(!file.is_stored());
+        let mut buf = Vec::new();
+        file.write_to(&mut buf).unwrap();
+        assert_eq!(Some(input.clone()), read(&buf).unwrap().decompress());
+
+        // The padding, in the fixed size part of the header, then the
+        // checksum, which follows it
+        for i in [10, 13] {
+            let mut tampered = buf.clone();
+            tampered[i] ^= 1;
+            assert!(matches!(read(&tampered), Err(RzError::DecryptionFailed)));
+        }
+
+        // A tree left in the clear is authenticated with the data, and one
+        // that's encrypted by its own tag
+        let tree_end = buf.len() - file.data().len() - 16;
+        let mut tampered = buf.clone();
+        tampered[tree_end - 1] ^= 1;
+        assert!(matches!(read(&tampered), Err(RzError::DecryptionFailed)));
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn arbitrary_files() {
+    use arbitrary::{Arbitrary, Unstructured};
+    use huffman_comprs::testing::{roundtrip, InvalidRz, ValidRz};
+
+    // A xorshift generator, so every run checks the same files
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut entropy = || {
+        (0..512)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state.to_be_bytes()[0]
+            })
+            .collect::<Vec<u8>>()
+    };
+
+    for _ in 0..200 {
+        let bytes = entropy();
+        let mut u = Unstructured::new(&bytes);
+
+        let tree = Huffman::<u8>::arbitrary(&mut u).unwrap();
+        assert!(tree.validate().is_ok());
+
+        let valid = ValidRz::arbitrary(&mut u).unwrap();
+        let file = RZFile::<u8>::try_from(valid.buf.as_slice()).unwrap();
+        assert_eq!(Some(valid.input.clone()), file.decompress());
+        assert!(!roundtrip(&valid.input).is_empty());
+
+        let invalid = InvalidRz::arbitrary(&mut u).unwrap();
+        assert!(RZFile::<u8>::try_from(invalid.0.as_slice()).is_err());
+        assert!(RZFile::<u8>::read_from(invalid.0.as_slice()).is_err());
+    }
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn traced_spans() {
+    use std::sync::{Arc, Mutex};
+    use tracing::{span, Event, Metadata, Subscriber};
+
+    // Records the name of every span created
+    struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Subscriber for Spans {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+            let mut spans = self.0.lock().unwrap();
+            spans.push(span.metadata().name());
+            span::Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+        fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, _: &span::Id) {}
+        fn exit(&self, _: &span::Id) {}
+    }
+
+    let spans = Arc::new(Mutex::new(Vec::new()));
+    tracing::subscriber::with_default(Spans(Arc::clone(&spans)), || {
+        let input = b"Where does the time go?".to_vec();
+        let mut buf = Vec::new();
+        RZFile::compress(Huffman::from(&input), &input)
+            .unwrap()
+            .write_to(&mut buf)
+            .unwrap();
+
+        let file = RZFile::<u8>::try_from(buf.as_slice()).unwrap();
+        assert_eq!(Some(input), file.decompress());
+    });
+
+    let spans = spans.lock().unwrap();
+    for name in ["build_tree", "encode", "parse_rz", "decompress"] {
+        assert!(spans.contains(&name), "no {} span in {:?}", name, spans);
+    }
+}
+
+#[test]
+fn shannon_fano_files() {
+    let input = b"Compressed with Shannon-Fano codes, read back as a tree".repeat(10);
+
+    let file = RZFile::compress(ShannonFano::from(&input), &input).unwrap();
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+
+    let read = RZFile::<u8, ShannonFano<u8>>::read_from_with_model(buf.as_slice(), None).unwrap();
+    assert_eq!(Some(input.clone()), read.decompress());
+    // The model is the tree, so either can read it
+    assert_eq!(
+        Some(input),
+        RZFile::<u8>::try_from(buf.as_slice()).unwrap().decompress()
+    );
+}
+
+#[test]
+fn adaptive_blocks() {
+    let mut input = b"A line of text, then a run of zeros\n".repeat(30);
+    input.extend([0; 1000]);
+    input.extend((0..1000_u32).map(|i| (i * 7919 % 256) as u8));
+    input.extend(b"and text again\n".repeat(40));
+
+    let tree = Huffman::from(&input);
+    let file = RZFile::new_blocked_adaptive(tree.clone(), &input, 500).unwrap();
+    let coders: Vec<BlockCoder> = (0..file.block_count())
+        .map(|i| file.block_coder(i))
+        .collect();
+    assert!(coders.contains(&BlockCoder::Tree));
+    assert!(coders.contains(&BlockCoder::Rle));
+    assert!(coders.contains(&BlockCoder::Stored));
+    let plain = RZFile::new_blocked(tree.clone(), &input, 500).unwrap();
+    assert!(file.compressed_len() < plain.compressed_len());
+    assert_eq!(0, plain.extended_flags() & 0x08);
+
+    let mut buf = Vec::new();
+    file.write_to(&mut buf).unwrap();
+    assert_eq!(0x08, file.extended_flags() & 0x08);
+    let read = RZFile::<u8>::try_from(buf.as_slice()).unwrap();
+    assert_eq!(coders[3], read.block_coder(3));
+    assert_eq!(Some(input.clone()), read.decompress());
+    assert_eq!(
+        Ok(input[1400..2600].to_vec()),
+        read.decompress_range(1400..2600)
+    );
+
+    // Parts keep the coder of each block
+    let parts = file.split(buf.len() / 3).unwrap();
+    assert_eq!(
+        Some(input.clone()),
+        RZFile::join(parts).unwrap().decompress()
+    );
+
+    // The first block's coder follows its length and zeros in the index
+    let mut unknown = buf.clone();
+    unknown[41] = 3;
+    assert!(matches!(
+        RZFile::<u8>::try_from(unknown.as_slice()),
+        Err(RzError::Malformed(_))
+    ));
+
+    // Symbols other than bytes are always coded with the tree
+    let chars: Vec<char> = "aaaaaaaaaaaaaaaaaaaab".chars().collect();
+    let file = RZFile::new_blocked_adaptive(Huffman::from(&chars), &chars, 8).unwrap();
+    assert_eq!(BlockCoder::Tree, file.block_coder(0));
+    assert_eq!(Some(chars), file.decompress());
+}
+
+#[test]
+fn compare_codecs() {
+    let text = "the cat sat on the mat\nthe dog sat on the log\n".repeat(40);
+    let choices = [
+        CodecChoice::Huffman,
+        CodecChoice::ShannonFano,
+        CodecChoice::MultiHuffman {
+            trees: 2,
+            group_size: 50,
+        },
+        CodecChoice::Adaptive { block_size: 256 },
+        CodecChoice::Chars,
+        CodecChoice::Lines,
+        CodecChoice::Delimited(' '),
+    ];
+
+    let reports = analysis::compare(text.as_bytes(), &choices);
+    let measured: Vec<CodecChoice> = reports.iter().map(|report| report.choice).collect();
+    assert_eq!(choices.to_vec(), measured);
+    for report in &reports {
+        assert!(
+            report.ratio < 1.0,
+            "{} didn't compress the text",
+            report.choice
+        );
+    }
+    // Only two different lines, so coding whole lines wins
+    let best = reports
+        .iter()
+        .min_by_key(|report| report.compressed_len)
+        .unwrap();
+    assert_eq!(CodecChoice::Lines, best.choice);
+
+    // Text choices are left out for bytes that aren't UTF-8, as are invalid
+    // parameters
+    let bytes: Vec<u8> = (0..2000_u32).map(|i| (i % 7 * 40) as u8).collect();
+    let reports = analysis::compare(
+        &bytes,
+        &[
+            CodecChoice::Chars,
+            CodecChoice::Adaptive { block_size: 0 },
+            CodecChoice::Huffman,
+        ],
+    );
+    assert_eq!(1, reports.len());
+    assert_eq!(CodecChoice::Huffman, reports[0].choice);
+
+    assert_eq!(
+        0.0,
+        analysis::compare(&[], &[CodecChoice::Huffman])[0].ratio
+    );
+}